@@ -6,20 +6,41 @@
 //! The blacklist exists for this purpose. A unit that is on it will never be scheduled. In
 //! addition, the scheduler will continue the search past its dependents as if the unit was already
 //! mastered. Courses, lessons, and exercises can be added to the blacklist.
+//!
+//! Entries can optionally record why the unit was blacklisted and a time after which the entry
+//! expires. Expired entries are ignored, and the scheduler removes them so that the units are
+//! scheduled again. This allows students to pause a course for a while without having to remember
+//! to resume it.
 
 use anyhow::Result;
+use chrono::Utc;
 use parking_lot::{Mutex, RwLock};
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, Row, params};
 use rusqlite_migration::{M, Migrations};
+use std::sync::Arc;
 use ustr::{Ustr, UstrMap};
 
-use crate::{error::BlacklistError, utils};
+use crate::{
+    data::{BlacklistEntry, BlacklistReason},
+    error::BlacklistError,
+    utils,
+};
 
 /// An interface to store and read the list of units which should be skipped during scheduling.
 pub trait Blacklist {
-    /// Adds the given unit to the blacklist.
+    /// Adds the given unit to the blacklist. Do nothing if the unit is already in the list.
     fn add_to_blacklist(&mut self, unit_id: Ustr) -> Result<(), BlacklistError>;
 
+    /// Adds the given unit to the blacklist along with the reason for doing so and an optional
+    /// timestamp after which the unit should be scheduled again. If the unit is already in the
+    /// list, its details are replaced.
+    fn add_to_blacklist_with_details(
+        &mut self,
+        unit_id: Ustr,
+        reason: BlacklistReason,
+        expires_at: Option<i64>,
+    ) -> Result<(), BlacklistError>;
+
     /// Removes the given unit from the blacklist. Do nothing if the unit is not already in the
     /// list.
     fn remove_from_blacklist(&mut self, unit_id: Ustr) -> Result<(), BlacklistError>;
@@ -27,11 +48,26 @@ pub trait Blacklist {
     /// Removes all the units that match the given prefix from the blacklist.
     fn remove_prefix_from_blacklist(&mut self, prefix: &str) -> Result<(), BlacklistError>;
 
+    /// Moves the blacklist entry of the unit with ID `old_id` to the unit with ID `new_id`. Used
+    /// when a unit is renamed. If the new unit is already in the blacklist, its entry is kept.
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), BlacklistError>;
+
+    /// Removes all the entries that have expired by the given timestamp from the blacklist and
+    /// returns the IDs of the removed units.
+    fn remove_expired_from_blacklist(&mut self, now: i64) -> Result<Vec<Ustr>, BlacklistError>;
+
     /// Returns whether the given unit is in the blacklist and should be skipped during scheduling.
+    /// Expired entries are not considered to be in the blacklist.
     fn blacklisted(&self, unit_id: Ustr) -> Result<bool, BlacklistError>;
 
-    /// Returns all the entries in the blacklist.
+    /// Returns all the entries in the blacklist that have not expired.
     fn get_blacklist_entries(&self) -> Result<Vec<Ustr>, BlacklistError>;
+
+    /// Returns the details of the entry for the given unit, whether it has expired or not.
+    fn get_blacklist_entry(&self, unit_id: Ustr) -> Result<Option<BlacklistEntry>, BlacklistError>;
+
+    /// Returns the details of all the entries in the blacklist, including the expired ones.
+    fn get_detailed_blacklist_entries(&self) -> Result<Vec<BlacklistEntry>, BlacklistError>;
 }
 
 /// An implementation of [Blacklist] backed by `SQLite`.
@@ -39,8 +75,11 @@ pub struct LocalBlacklist {
     /// A cache of the blacklist entries used to avoid unnecessary queries to the database.
     cache: RwLock<UstrMap<bool>>,
 
+    /// A cache of the expiry times of the entries that have one.
+    expirations: RwLock<UstrMap<i64>>,
+
     /// A connection to the database.
-    connection: Mutex<Connection>,
+    connection: Arc<Mutex<Connection>>,
 }
 
 impl LocalBlacklist {
@@ -53,6 +92,16 @@ impl LocalBlacklist {
             // Create an index of the blacklisted unit IDs.
             M::up("CREATE INDEX unit_id_index ON blacklist (unit_id);")
                 .down("DROP INDEX unit_id_index"),
+            // Record when each unit was added to the blacklist. Existing entries get a value of
+            // zero.
+            M::up("ALTER TABLE blacklist ADD COLUMN added_at INTEGER NOT NULL DEFAULT 0;")
+                .down("ALTER TABLE blacklist DROP COLUMN added_at;"),
+            // Record the reason why each unit was added to the blacklist.
+            M::up("ALTER TABLE blacklist ADD COLUMN reason TEXT;")
+                .down("ALTER TABLE blacklist DROP COLUMN reason;"),
+            // Record the time after which each entry expires.
+            M::up("ALTER TABLE blacklist ADD COLUMN expires_at INTEGER;")
+                .down("ALTER TABLE blacklist DROP COLUMN expires_at;"),
         ])
     }
 
@@ -69,15 +118,35 @@ impl LocalBlacklist {
     fn new(connection: Connection) -> Result<LocalBlacklist> {
         let mut blacklist = LocalBlacklist {
             cache: RwLock::new(UstrMap::default()),
-            connection: Mutex::new(connection),
+            expirations: RwLock::new(UstrMap::default()),
+            connection: Arc::new(Mutex::new(connection)),
         };
         blacklist.init()?;
+        blacklist.init_cache()?;
+        Ok(blacklist)
+    }
 
-        // Initialize the cache with the existing blacklist entries.
-        for unit_id in blacklist.get_blacklist_entries()? {
-            blacklist.cache.write().insert(unit_id, true);
+    /// Initializes the caches with the existing blacklist entries.
+    fn init_cache(&self) -> Result<()> {
+        for entry in self.detailed_blacklist_entries_helper()? {
+            self.cache.write().insert(entry.unit_id, true);
+            if let Some(expires_at) = entry.expires_at {
+                self.expirations.write().insert(entry.unit_id, expires_at);
+            }
         }
+        Ok(())
+    }
 
+    /// Creates a new instance that shares the given connection with the other stores in the
+    /// consolidated user database. The schema is managed by
+    /// [`UserDatabase`](crate::user_database::UserDatabase), so no migrations are run.
+    pub(crate) fn new_shared(connection: Arc<Mutex<Connection>>) -> Result<LocalBlacklist> {
+        let blacklist = LocalBlacklist {
+            cache: RwLock::new(UstrMap::default()),
+            expirations: RwLock::new(UstrMap::default()),
+            connection,
+        };
+        blacklist.init_cache()?;
         Ok(blacklist)
     }
 
@@ -87,11 +156,25 @@ impl LocalBlacklist {
         Self::new(connection)
     }
 
-    /// Returns whether there's an entry for the given unit in the blacklist.
+    /// A constructor for a database that is kept in memory and discarded when dropped. Useful to
+    /// run Trane without writing any user data to the file system.
+    pub fn new_in_memory() -> Result<LocalBlacklist> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    /// Returns whether there's an entry for the given unit in the blacklist that has not expired
+    /// by the given timestamp.
     #[inline]
-    fn has_entry(&self, unit_id: Ustr) -> bool {
+    fn has_active_entry(&self, unit_id: Ustr, now: i64) -> bool {
         if let Some(has_entry) = self.cache.read().get(&unit_id) {
-            return *has_entry;
+            if !*has_entry {
+                return false;
+            }
+            return self
+                .expirations
+                .read()
+                .get(&unit_id)
+                .is_none_or(|expires_at| *expires_at > now);
         }
         // Because the cache was initialized with all the entries in the blacklist, and it's
         // kept updated, it's safe to assume that the entry is not in the blacklist and update
@@ -100,21 +183,67 @@ impl LocalBlacklist {
         false
     }
 
+    /// Converts a row from the blacklist table into a blacklist entry.
+    fn row_to_entry(row: &Row) -> Result<BlacklistEntry> {
+        let unit_id: String = row.get(0)?;
+        let reason: Option<String> = row.get(2)?;
+        Ok(BlacklistEntry {
+            unit_id: Ustr::from(&unit_id),
+            added_at: row.get(1)?,
+            reason: reason.map(|reason| reason.parse()).transpose()?,
+            expires_at: row.get(3)?,
+        })
+    }
+
     /// Helper function to add a unit to the blacklist.
     fn add_to_blacklist_helper(&mut self, unit_id: Ustr) -> Result<()> {
         // Check the cache first to avoid unnecessary queries.
-        let has_entry = self.has_entry(unit_id);
+        let now = Utc::now().timestamp();
+        let has_entry = self.has_active_entry(unit_id, now);
         if has_entry {
             return Ok(());
         }
 
-        // Add the entry to the database.
+        // Add the entry to the database. An expired entry for the same unit might still exist, so
+        // it's replaced.
         let connection = self.connection.lock();
-        let mut stmt = connection.prepare_cached("INSERT INTO blacklist (unit_id) VALUES (?1)")?;
-        stmt.execute(params![unit_id.as_str()])?;
+        let mut stmt = connection.prepare_cached(
+            "INSERT OR REPLACE INTO blacklist (unit_id, added_at) VALUES (?1, ?2)",
+        )?;
+        stmt.execute(params![unit_id.as_str(), now])?;
 
-        // Update the cache.
+        // Update the caches.
         self.cache.write().insert(unit_id, true);
+        self.expirations.write().remove(&unit_id);
+        Ok(())
+    }
+
+    /// Helper function to add a unit to the blacklist with the given details.
+    fn add_to_blacklist_with_details_helper(
+        &mut self,
+        unit_id: Ustr,
+        reason: BlacklistReason,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        // Add or replace the entry in the database.
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "INSERT OR REPLACE INTO blacklist (unit_id, added_at, reason, expires_at) \
+            VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        stmt.execute(params![
+            unit_id.as_str(),
+            Utc::now().timestamp(),
+            reason.to_string(),
+            expires_at
+        ])?;
+
+        // Update the caches.
+        self.cache.write().insert(unit_id, true);
+        match expires_at {
+            Some(expires_at) => self.expirations.write().insert(unit_id, expires_at),
+            None => self.expirations.write().remove(&unit_id),
+        };
         Ok(())
     }
 
@@ -125,8 +254,9 @@ impl LocalBlacklist {
         let mut stmt = connection.prepare_cached("DELETE FROM blacklist WHERE unit_id = $1")?;
         stmt.execute(params![unit_id.as_str()])?;
 
-        // Update the cache.
+        // Update the caches.
         self.cache.write().insert(unit_id, false);
+        self.expirations.write().remove(&unit_id);
         Ok(())
     }
 
@@ -141,12 +271,15 @@ impl LocalBlacklist {
         // Remove all the entries with the given prefix.
         let mut stmt = connection.prepare_cached("DELETE FROM blacklist WHERE unit_id = $1")?;
         let mut cache = self.cache.write();
+        let mut expirations = self.expirations.write();
         while let Some(row) = rows.next()? {
             let unit_id: String = row.get(0)?;
             stmt.execute(params![unit_id])?;
 
-            // Update the cache.
-            cache.insert(unit_id.into(), false);
+            // Update the caches.
+            let unit_id = Ustr::from(&unit_id);
+            cache.insert(unit_id, false);
+            expirations.remove(&unit_id);
         }
 
         // Call the `VACUUM` command to reclaim the space freed by the deleted entries.
@@ -154,12 +287,69 @@ impl LocalBlacklist {
         Ok(())
     }
 
-    /// Helper function to retrieve all the entries in the blacklist.
+    /// Helper function to remove all the expired entries from the blacklist.
+    fn remove_expired_from_blacklist_helper(&mut self, now: i64) -> Result<Vec<Ustr>> {
+        // Use the cache to find the expired entries and avoid unnecessary queries.
+        let expired: Vec<Ustr> = self
+            .expirations
+            .read()
+            .iter()
+            .filter(|(_, expires_at)| **expires_at <= now)
+            .map(|(unit_id, _)| *unit_id)
+            .collect();
+        if expired.is_empty() {
+            return Ok(expired);
+        }
+
+        // Remove the expired entries from the database and update the caches.
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached("DELETE FROM blacklist WHERE unit_id = $1")?;
+        let mut cache = self.cache.write();
+        let mut expirations = self.expirations.write();
+        for unit_id in &expired {
+            stmt.execute(params![unit_id.as_str()])?;
+            cache.insert(*unit_id, false);
+            expirations.remove(unit_id);
+        }
+        Ok(expired)
+    }
+
+    /// Helper function to move the entry of a unit in the blacklist to a new ID.
+    fn rename_unit_helper(&mut self, old_id: Ustr, new_id: Ustr) -> Result<()> {
+        // Update the entry unless the new unit already has one, in which case the old entry is
+        // deleted.
+        let mut connection = self.connection.lock();
+        let tx = connection.savepoint()?;
+        tx.execute(
+            "UPDATE OR IGNORE blacklist SET unit_id = ?2 WHERE unit_id = ?1;",
+            params![old_id.as_str(), new_id.as_str()],
+        )?;
+        tx.execute(
+            "DELETE FROM blacklist WHERE unit_id = ?1;",
+            params![old_id.as_str()],
+        )?;
+        tx.commit()?;
+
+        // Update the caches with the entry of the new unit.
+        let mut cache = self.cache.write();
+        let mut expirations = self.expirations.write();
+        if cache.remove(&old_id).unwrap_or(false) {
+            cache.insert(new_id, true);
+            if let Some(expires_at) = expirations.remove(&old_id) {
+                expirations.entry(new_id).or_insert(expires_at);
+            }
+        }
+        Ok(())
+    }
+
+    /// Helper function to retrieve all the entries in the blacklist that have not expired.
     fn all_blacklist_entries_helper(&self) -> Result<Vec<Ustr>> {
-        // Get all the entries from the database.
+        // Get all the active entries from the database.
         let connection = self.connection.lock();
-        let mut stmt = connection.prepare_cached("SELECT unit_id from blacklist;")?;
-        let mut rows = stmt.query(params![])?;
+        let mut stmt = connection.prepare_cached(
+            "SELECT unit_id from blacklist WHERE expires_at IS NULL OR expires_at > ?1;",
+        )?;
+        let mut rows = stmt.query(params![Utc::now().timestamp()])?;
 
         // Convert the rows into a vector of `Ustr` values.
         let mut entries = Vec::new();
@@ -169,6 +359,33 @@ impl LocalBlacklist {
         }
         Ok(entries)
     }
+
+    /// Helper function to retrieve the details of the entry for the given unit.
+    fn blacklist_entry_helper(&self, unit_id: Ustr) -> Result<Option<BlacklistEntry>> {
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT unit_id, added_at, reason, expires_at from blacklist WHERE unit_id = ?1;",
+        )?;
+        let mut rows = stmt.query(params![unit_id.as_str()])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::row_to_entry(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Helper function to retrieve the details of all the entries in the blacklist.
+    fn detailed_blacklist_entries_helper(&self) -> Result<Vec<BlacklistEntry>> {
+        let connection = self.connection.lock();
+        let mut stmt = connection
+            .prepare_cached("SELECT unit_id, added_at, reason, expires_at from blacklist;")?;
+        let mut rows = stmt.query(params![])?;
+
+        let mut entries = Vec::new();
+        while let Some(row) = rows.next()? {
+            entries.push(Self::row_to_entry(row)?);
+        }
+        Ok(entries)
+    }
 }
 
 impl Blacklist for LocalBlacklist {
@@ -177,6 +394,16 @@ impl Blacklist for LocalBlacklist {
             .map_err(|e| BlacklistError::AddUnit(unit_id, e))
     }
 
+    fn add_to_blacklist_with_details(
+        &mut self,
+        unit_id: Ustr,
+        reason: BlacklistReason,
+        expires_at: Option<i64>,
+    ) -> Result<(), BlacklistError> {
+        self.add_to_blacklist_with_details_helper(unit_id, reason, expires_at)
+            .map_err(|e| BlacklistError::AddUnit(unit_id, e))
+    }
+
     fn remove_from_blacklist(&mut self, unit_id: Ustr) -> Result<(), BlacklistError> {
         self.remove_from_blacklist_helper(unit_id)
             .map_err(|e| BlacklistError::RemoveUnit(unit_id, e))
@@ -187,26 +414,51 @@ impl Blacklist for LocalBlacklist {
             .map_err(|e| BlacklistError::RemovePrefix(prefix.into(), e))
     }
 
+    fn remove_expired_from_blacklist(&mut self, now: i64) -> Result<Vec<Ustr>, BlacklistError> {
+        self.remove_expired_from_blacklist_helper(now)
+            .map_err(BlacklistError::RemoveExpired)
+    }
+
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), BlacklistError> {
+        self.rename_unit_helper(old_id, new_id)
+            .map_err(|e| BlacklistError::RenameUnit(old_id, new_id, e))
+    }
+
     #[inline]
     fn blacklisted(&self, unit_id: Ustr) -> Result<bool, BlacklistError> {
-        Ok(self.has_entry(unit_id))
+        Ok(self.has_active_entry(unit_id, Utc::now().timestamp()))
     }
 
     fn get_blacklist_entries(&self) -> Result<Vec<Ustr>, BlacklistError> {
         self.all_blacklist_entries_helper()
             .map_err(BlacklistError::GetEntries)
     }
+
+    fn get_blacklist_entry(&self, unit_id: Ustr) -> Result<Option<BlacklistEntry>, BlacklistError> {
+        self.blacklist_entry_helper(unit_id)
+            .map_err(|e| BlacklistError::GetEntry(unit_id, e))
+    }
+
+    fn get_detailed_blacklist_entries(&self) -> Result<Vec<BlacklistEntry>, BlacklistError> {
+        self.detailed_blacklist_entries_helper()
+            .map_err(BlacklistError::GetEntries)
+    }
 }
 
 #[cfg(test)]
 #[cfg_attr(coverage, coverage(off))]
 mod test {
     use anyhow::Result;
-    use rusqlite::Connection;
+    use chrono::Utc;
+    use rusqlite::{Connection, params};
+    use rusqlite_migration::{M, Migrations};
     use tempfile::tempdir;
     use ustr::Ustr;
 
-    use crate::blacklist::{Blacklist, LocalBlacklist};
+    use crate::{
+        blacklist::{Blacklist, LocalBlacklist},
+        data::BlacklistReason,
+    };
 
     fn new_test_blacklist() -> Result<Box<dyn Blacklist>> {
         let connection = Connection::open_in_memory()?;
@@ -322,4 +574,177 @@ mod test {
         assert!(new_blacklist.blacklisted(unit_id)?);
         Ok(())
     }
+
+    /// Verifies adding an entry with details and retrieving them.
+    #[test]
+    fn add_with_details() -> Result<()> {
+        let mut blacklist = new_test_blacklist()?;
+        let unit_id = Ustr::from("unit_id");
+        let expires_at = Utc::now().timestamp() + 3600;
+        blacklist.add_to_blacklist_with_details(
+            unit_id,
+            BlacklistReason::TemporarilyPaused,
+            Some(expires_at),
+        )?;
+        assert!(blacklist.blacklisted(unit_id)?);
+
+        let entry = blacklist.get_blacklist_entry(unit_id)?.unwrap();
+        assert_eq!(entry.unit_id, unit_id);
+        assert_eq!(entry.reason, Some(BlacklistReason::TemporarilyPaused));
+        assert_eq!(entry.expires_at, Some(expires_at));
+        assert!(entry.added_at > 0);
+        assert_eq!(blacklist.get_detailed_blacklist_entries()?, vec![entry]);
+
+        // Replacing the details removes the expiry time.
+        blacklist.add_to_blacklist_with_details(unit_id, BlacklistReason::NotInterested, None)?;
+        let entry = blacklist.get_blacklist_entry(unit_id)?.unwrap();
+        assert_eq!(entry.reason, Some(BlacklistReason::NotInterested));
+        assert_eq!(entry.expires_at, None);
+        assert!(
+            blacklist
+                .get_blacklist_entry(Ustr::from("other"))?
+                .is_none()
+        );
+        Ok(())
+    }
+
+    /// Verifies that expired entries are ignored and can be removed.
+    #[test]
+    fn expired_entries() -> Result<()> {
+        let mut blacklist = new_test_blacklist()?;
+        let now = Utc::now().timestamp();
+        let expired_id = Ustr::from("expired");
+        let active_id = Ustr::from("active");
+        let permanent_id = Ustr::from("permanent");
+        blacklist.add_to_blacklist_with_details(
+            expired_id,
+            BlacklistReason::TemporarilyPaused,
+            Some(now - 10),
+        )?;
+        blacklist.add_to_blacklist_with_details(
+            active_id,
+            BlacklistReason::TemporarilyPaused,
+            Some(now + 3600),
+        )?;
+        blacklist.add_to_blacklist(permanent_id)?;
+
+        // The expired entry is not considered to be in the blacklist.
+        assert!(!blacklist.blacklisted(expired_id)?);
+        assert!(blacklist.blacklisted(active_id)?);
+        assert!(blacklist.blacklisted(permanent_id)?);
+        assert_eq!(
+            blacklist.get_blacklist_entries()?,
+            vec![active_id, permanent_id]
+        );
+        assert_eq!(blacklist.get_detailed_blacklist_entries()?.len(), 3);
+
+        // Remove the expired entries.
+        assert_eq!(
+            blacklist.remove_expired_from_blacklist(now)?,
+            vec![expired_id]
+        );
+        assert!(blacklist.get_blacklist_entry(expired_id)?.is_none());
+        assert!(blacklist.remove_expired_from_blacklist(now)?.is_empty());
+        assert_eq!(blacklist.get_detailed_blacklist_entries()?.len(), 2);
+
+        // Re-adding the unit after the entry expired works.
+        blacklist.add_to_blacklist_with_details(
+            expired_id,
+            BlacklistReason::TemporarilyPaused,
+            Some(now - 10),
+        )?;
+        blacklist.add_to_blacklist(expired_id)?;
+        assert!(blacklist.blacklisted(expired_id)?);
+        assert_eq!(
+            blacklist.get_blacklist_entry(expired_id)?.unwrap().reason,
+            None
+        );
+        Ok(())
+    }
+
+    /// Verifies that entries created before the details were recorded are migrated.
+    #[test]
+    fn migrate_old_entries() -> Result<()> {
+        // Create a database with the original schema and add an entry to it.
+        let mut connection = Connection::open_in_memory()?;
+        Migrations::new(vec![
+            M::up("CREATE TABLE blacklist(unit_id TEXT NOT NULL UNIQUE);"),
+            M::up("CREATE INDEX unit_id_index ON blacklist (unit_id);"),
+        ])
+        .to_latest(&mut connection)?;
+        connection.execute(
+            "INSERT INTO blacklist (unit_id) VALUES (?1)",
+            params!["unit_id"],
+        )?;
+
+        // Open the blacklist and verify the entry has default details.
+        let blacklist = LocalBlacklist::new(connection)?;
+        let unit_id = Ustr::from("unit_id");
+        assert!(blacklist.blacklisted(unit_id)?);
+        let entry = blacklist.get_blacklist_entry(unit_id)?.unwrap();
+        assert_eq!(entry.added_at, 0);
+        assert_eq!(entry.reason, None);
+        assert_eq!(entry.expires_at, None);
+        Ok(())
+    }
+
+    /// Verifies that the details of the entries are preserved after re-opening the database.
+    #[test]
+    fn reopen_blacklist_with_details() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("blacklist.db");
+        let mut blacklist = LocalBlacklist::new_from_disk(db_path.to_str().unwrap())?;
+        let unit_id = Ustr::from("unit_id");
+        let expires_at = Utc::now().timestamp() - 10;
+        blacklist.add_to_blacklist_with_details(
+            unit_id,
+            BlacklistReason::MasteredElsewhere,
+            Some(expires_at),
+        )?;
+
+        // The expiry time is loaded into the cache when the database is re-opened.
+        let mut new_blacklist = LocalBlacklist::new_from_disk(db_path.to_str().unwrap())?;
+        assert!(!new_blacklist.blacklisted(unit_id)?);
+        assert_eq!(
+            new_blacklist.get_blacklist_entry(unit_id)?.unwrap().reason,
+            Some(BlacklistReason::MasteredElsewhere)
+        );
+        assert_eq!(
+            new_blacklist.remove_expired_from_blacklist(Utc::now().timestamp())?,
+            vec![unit_id]
+        );
+        Ok(())
+    }
+
+    /// Verifies moving a blacklist entry to a new ID.
+    #[test]
+    fn rename_unit() -> Result<()> {
+        let mut blacklist = new_test_blacklist()?;
+        let old_id = Ustr::from("old_unit");
+        let new_id = Ustr::from("new_unit");
+        blacklist.add_to_blacklist_with_details(
+            old_id,
+            BlacklistReason::TemporarilyPaused,
+            Some(i64::MAX),
+        )?;
+
+        // The entry and its details are moved to the new ID.
+        blacklist.rename_unit(old_id, new_id)?;
+        assert!(!blacklist.blacklisted(old_id)?);
+        assert!(blacklist.blacklisted(new_id)?);
+        let entry = blacklist.get_blacklist_entry(new_id)?.unwrap();
+        assert_eq!(entry.reason, Some(BlacklistReason::TemporarilyPaused));
+        assert_eq!(entry.expires_at, Some(i64::MAX));
+
+        // If the new unit already has an entry, it's kept.
+        let other_id = Ustr::from("other_unit");
+        blacklist.add_to_blacklist(other_id)?;
+        blacklist.rename_unit(new_id, other_id)?;
+        assert!(!blacklist.blacklisted(new_id)?);
+        assert!(blacklist.blacklisted(other_id)?);
+        let entry = blacklist.get_blacklist_entry(other_id)?.unwrap();
+        assert_eq!(entry.reason, None);
+        assert_eq!(blacklist.get_blacklist_entries()?, vec![other_id]);
+        Ok(())
+    }
 }
@@ -293,6 +293,7 @@ mod test {
             directory_name: "course1".to_string(),
             course_manifest: CourseManifest {
                 id: "course1".into(),
+                renamed_from: vec![],
                 name: "Course 1".into(),
                 dependencies: vec![],
                 encompassed: vec![],
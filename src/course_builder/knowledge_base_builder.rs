@@ -65,6 +65,24 @@ impl ExerciseBuilder {
             let mut exercise_type_file = File::create(exercise_type_path)?;
             exercise_type_file.write_all(exercise_type_json.as_bytes())?;
         }
+        if !self.exercise.renamed_from.is_empty() {
+            let renamed_from_json = serde_json::to_string_pretty(&self.exercise.renamed_from)?;
+            let renamed_from_path = lesson_directory.join(format!(
+                "{}{}",
+                self.exercise.short_id, EXERCISE_RENAMED_FROM_SUFFIX
+            ));
+            let mut renamed_from_file = File::create(renamed_from_path)?;
+            renamed_from_file.write_all(renamed_from_json.as_bytes())?;
+        }
+        if let Some(reverse) = &self.exercise.reverse {
+            let reverse_json = serde_json::to_string_pretty(reverse)?;
+            let reverse_path = lesson_directory.join(format!(
+                "{}{}",
+                self.exercise.short_id, EXERCISE_REVERSE_SUFFIX
+            ));
+            let mut reverse_file = File::create(reverse_path)?;
+            reverse_file.write_all(reverse_json.as_bytes())?;
+        }
         Ok(())
     }
 }
@@ -140,6 +158,18 @@ impl LessonBuilder {
             let mut type_file = File::create(type_path)?;
             type_file.write_all(type_json.as_bytes())?;
         }
+        if !self.lesson.renamed_from.is_empty() {
+            let renamed_from_json = serde_json::to_string_pretty(&self.lesson.renamed_from)?;
+            let renamed_from_path = lesson_directory.join(LESSON_RENAMED_FROM_FILE);
+            let mut renamed_from_file = File::create(renamed_from_path)?;
+            renamed_from_file.write_all(renamed_from_json.as_bytes())?;
+        }
+        if self.lesson.reverse {
+            let reverse_json = serde_json::to_string_pretty(&self.lesson.reverse)?;
+            let reverse_path = lesson_directory.join(LESSON_REVERSE_FILE);
+            let mut reverse_file = File::create(reverse_path)?;
+            reverse_file.write_all(reverse_json.as_bytes())?;
+        }
         Ok(())
     }
 }
@@ -256,6 +286,11 @@ impl SimpleKnowledgeBaseExercise {
                 name: None,
                 description: None,
                 exercise_type: None,
+                inline_content: None,
+                multiple_choice: None,
+                cloze: None,
+                reverse: None,
+                renamed_from: vec![],
             },
             asset_builders,
         })
@@ -346,6 +381,8 @@ impl SimpleKnowledgeBaseLesson {
                 has_instructions,
                 has_material,
                 default_exercise_type: None,
+                reverse: false,
+                renamed_from: vec![],
             },
             exercises,
             asset_builders: self.additional_files.clone(),
@@ -454,6 +491,11 @@ mod test {
                 name: Some("Exercise 1".to_string()),
                 description: Some("Exercise 1 description".to_string()),
                 exercise_type: Some(ExerciseType::Procedural),
+                inline_content: None,
+                multiple_choice: None,
+                cloze: None,
+                reverse: None,
+                renamed_from: vec![],
             },
             asset_builders: vec![
                 AssetBuilder {
@@ -482,6 +524,8 @@ mod test {
                 has_instructions: true,
                 has_material: true,
                 default_exercise_type: Some(ExerciseType::Declarative),
+                reverse: false,
+                renamed_from: vec![],
             },
             exercises: vec![exercise_builder],
             asset_builders: vec![
@@ -505,6 +549,7 @@ mod test {
             directory_name: "course1".into(),
             manifest: CourseManifest {
                 id: "course1".into(),
+                renamed_from: vec![],
                 name: "Course 1".into(),
                 dependencies: vec![],
                 encompassed: vec![],
@@ -621,6 +666,7 @@ mod test {
         let simple_course = SimpleKnowledgeBaseCourse {
             manifest: CourseManifest {
                 id: "course1".into(),
+                renamed_from: vec![],
                 name: "Course 1".into(),
                 dependencies: vec![],
                 encompassed: vec![],
@@ -805,6 +851,7 @@ mod test {
         let simple_course = SimpleKnowledgeBaseCourse {
             manifest: CourseManifest {
                 id: "course1".into(),
+                renamed_from: vec![],
                 name: "Course 1".into(),
                 dependencies: vec![],
                 encompassed: vec![],
@@ -860,6 +907,7 @@ mod test {
         let simple_course = SimpleKnowledgeBaseCourse {
             manifest: CourseManifest {
                 id: "course1".into(),
+                renamed_from: vec![],
                 name: "Course 1".into(),
                 dependencies: vec![],
                 superseded: vec![],
@@ -907,6 +955,7 @@ mod test {
         let simple_course = SimpleKnowledgeBaseCourse {
             manifest: CourseManifest {
                 id: "course1".into(),
+                renamed_from: vec![],
                 name: "Course 1".into(),
                 dependencies: vec![],
                 encompassed: vec![],
@@ -947,6 +996,7 @@ mod test {
         let simple_course = SimpleKnowledgeBaseCourse {
             manifest: CourseManifest {
                 id: "course1".into(),
+                renamed_from: vec![],
                 name: "Course 1".into(),
                 dependencies: vec![],
                 encompassed: vec![],
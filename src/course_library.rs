@@ -4,14 +4,12 @@
 //! courses that the student wishes to practice together. Courses, lessons, and exercises are
 //! defined by their manifest files (see [data](crate::data)).
 
-use anyhow::{Context, Result, anyhow, ensure};
+use anyhow::{Result, anyhow, bail, ensure};
 use parking_lot::RwLock;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::{
-    fs::File,
-    io::BufReader,
     path::{self, Path, PathBuf},
     sync::Arc,
 };
@@ -22,6 +20,7 @@ use crate::{
     data::{
         CourseManifest, ExerciseManifest, GenerateManifests, LessonManifest, NormalizePaths,
         UnitType, UserPreferences,
+        manifest_format::{ManifestFormat, open_file},
     },
     graph::{InMemoryUnitGraph, UnitGraph},
 };
@@ -35,6 +34,16 @@ pub const LESSON_MANIFEST_FILENAME: &str = "lesson_manifest.json";
 /// The file name for all exercise manifests.
 pub const EXERCISE_MANIFEST_FILENAME: &str = "exercise_manifest.json";
 
+/// The file name of all course manifests without the extension. Manifests written by hand can use
+/// any of the formats in [`ManifestFormat`].
+pub const COURSE_MANIFEST_STEM: &str = "course_manifest";
+
+/// The file name of all lesson manifests without the extension.
+pub const LESSON_MANIFEST_STEM: &str = "lesson_manifest";
+
+/// The file name of all exercise manifests without the extension.
+pub const EXERCISE_MANIFEST_STEM: &str = "exercise_manifest";
+
 /// A trait that manages a course library, its corresponding manifest files, and provides basic
 /// operations to retrieve the courses, lessons in a course, and exercises in a lesson.
 pub trait CourseLibrary {
@@ -62,6 +71,10 @@ pub trait CourseLibrary {
     /// Returns the set of units whose ID starts with the given prefix and are of the given type.
     /// If `unit_type` is `None`, then all unit types are considered.
     fn get_matching_prefix(&self, prefix: &str, unit_type: Option<UnitType>) -> UstrSet;
+
+    /// Returns a map of the previous IDs of the units that have been renamed to their current IDs,
+    /// as declared in the `renamed_from` field of their manifests.
+    fn get_renamed_units(&self) -> UstrMap<Ustr>;
 }
 
 /// A trait that retrieves the unit graph generated after reading a course library.
@@ -153,6 +166,9 @@ struct OpenCourseResult {
 ///   ...
 /// ```
 ///
+/// The manifests can also be written in YAML or TOML by using the corresponding extension, such as
+/// `course_manifest.yaml` or `lesson_manifest.toml`. Each directory can only contain one of them.
+///
 /// The directory can also contain asset files referenced by the manifests. For example, a basic
 /// flashcard with a front and back can be stored using two markdown files.
 pub struct LocalCourseLibrary {
@@ -168,17 +184,35 @@ pub struct LocalCourseLibrary {
     /// A mapping of exercise ID to its corresponding exercise manifest.
     pub exercise_map: UstrMap<Arc<ExerciseManifest>>,
 
+    /// A mapping of the previous IDs of renamed units to their current IDs.
+    pub renamed_units: UstrMap<Ustr>,
+
     /// The user preferences.
     pub user_preferences: UserPreferences,
 }
 
 impl LocalCourseLibrary {
-    /// Opens the course, lesson, or exercise manifest located at the given path.
-    fn open_manifest<T: DeserializeOwned>(path: &Path) -> Result<T> {
-        let display = path.display();
-        let file = File::open(path).context(format!("cannot open manifest file {display}"))?;
-        let reader = BufReader::new(file);
-        serde_json::from_reader(reader).context(format!("cannot parse manifest file {display}"))
+    /// Opens the course, lesson, or exercise manifest located at the given path. The format of the
+    /// manifest is determined by its extension.
+    fn open_manifest<T: DeserializeOwned + 'static>(path: &Path) -> Result<T> {
+        open_file(path, "manifest file")
+    }
+
+    /// Returns whether the file at the given path is a manifest with the given file name, written
+    /// in any of the supported formats. Returns an error if the directory contains the same
+    /// manifest in more than one format, since it would be ambiguous which one to use.
+    fn is_manifest(path: &Path, stem: &str) -> Result<bool> {
+        let file_name = Self::get_file_name(path)?;
+        if ManifestFormat::from_file_name(&file_name, stem).is_none() {
+            return Ok(false);
+        }
+        let directory = path.parent().unwrap_or(Path::new(""));
+        ensure!(
+            ManifestFormat::find_files(directory, stem).len() == 1,
+            "found {stem} files in multiple formats in directory {}",
+            directory.display(),
+        );
+        Ok(true)
     }
 
     /// Returns the file name of the given path.
@@ -254,12 +288,11 @@ impl LocalCourseLibrary {
             .into_iter()
             .flatten()
         {
-            // Ignore any entries that are not files named `exercise_manifest.json`.
+            // Ignore any entries that are not exercise manifests.
             if entry.path().is_dir() {
                 continue; // grcov-excl-line
             }
-            let file_name = Self::get_file_name(entry.path())?;
-            if file_name != EXERCISE_MANIFEST_FILENAME {
+            if !Self::is_manifest(entry.path(), EXERCISE_MANIFEST_STEM)? {
                 continue;
             }
 
@@ -325,9 +358,8 @@ impl LocalCourseLibrary {
                 continue;
             }
 
-            // Ignore any files which are not named `lesson_manifest.json`.
-            let file_name = Self::get_file_name(entry.path())?;
-            if file_name != LESSON_MANIFEST_FILENAME {
+            // Ignore any files which are not lesson manifests.
+            if !Self::is_manifest(entry.path(), LESSON_MANIFEST_STEM)? {
                 continue;
             }
 
@@ -423,12 +455,117 @@ impl LocalCourseLibrary {
         Ok(())
     }
 
+    /// Collects the previous IDs of all the renamed units in the library, including the descendants
+    /// of renamed courses and lessons. A previous ID declared in a manifest cannot be the ID of an
+    /// existing unit, nor can it be claimed by more than one unit.
+    fn collect_renamed_units(&self) -> Result<UstrMap<Ustr>> {
+        let renamed_from = self
+            .course_map
+            .values()
+            .map(|manifest| (manifest.id, &manifest.renamed_from))
+            .chain(
+                self.lesson_map
+                    .values()
+                    .map(|manifest| (manifest.id, &manifest.renamed_from)),
+            )
+            .chain(
+                self.exercise_map
+                    .values()
+                    .map(|manifest| (manifest.id, &manifest.renamed_from)),
+            );
+
+        let mut renamed_units = UstrMap::default();
+        for (unit_id, old_ids) in renamed_from {
+            for old_id in old_ids {
+                ensure!(
+                    !self.course_map.contains_key(old_id)
+                        && !self.lesson_map.contains_key(old_id)
+                        && !self.exercise_map.contains_key(old_id),
+                    "unit {unit_id} was renamed from {old_id}, which is the ID of an existing unit",
+                );
+                if let Some(other_id) = renamed_units.insert(*old_id, unit_id) {
+                    bail!("units {other_id} and {unit_id} were both renamed from {old_id}");
+                }
+            }
+        }
+
+        // Add the renames of the descendants of renamed courses and lessons. Renames declared
+        // explicitly take precedence, and previous IDs that belong to existing units are skipped.
+        for (old_id, unit_id) in self.descendant_renames() {
+            if !self.course_map.contains_key(&old_id)
+                && !self.lesson_map.contains_key(&old_id)
+                && !self.exercise_map.contains_key(&old_id)
+            {
+                renamed_units.entry(old_id).or_insert(unit_id);
+            }
+        }
+        Ok(renamed_units)
+    }
+
+    /// Returns the previous IDs of the lessons and exercises inside renamed courses and lessons,
+    /// sorted by previous ID. When a course or lesson is renamed, the IDs of its descendants that
+    /// start with its ID are renamed as well, by replacing that prefix with each of the previous
+    /// IDs.
+    fn descendant_renames(&self) -> Vec<(Ustr, Ustr)> {
+        // Collect the descendants of each renamed course and lesson.
+        let renamed_parents = self
+            .course_map
+            .values()
+            .filter(|course| !course.renamed_from.is_empty())
+            .map(|course| {
+                let descendants = self
+                    .lesson_map
+                    .values()
+                    .filter(|lesson| lesson.course_id == course.id)
+                    .map(|lesson| lesson.id)
+                    .chain(
+                        self.exercise_map
+                            .values()
+                            .filter(|exercise| exercise.course_id == course.id)
+                            .map(|exercise| exercise.id),
+                    )
+                    .collect::<Vec<_>>();
+                (course.id, &course.renamed_from, descendants)
+            })
+            .chain(
+                self.lesson_map
+                    .values()
+                    .filter(|lesson| !lesson.renamed_from.is_empty())
+                    .map(|lesson| {
+                        let descendants = self
+                            .exercise_map
+                            .values()
+                            .filter(|exercise| exercise.lesson_id == lesson.id)
+                            .map(|exercise| exercise.id)
+                            .collect::<Vec<_>>();
+                        (lesson.id, &lesson.renamed_from, descendants)
+                    }),
+            );
+
+        // Replace the prefix of each descendant with the previous IDs of its parent.
+        let mut renames: Vec<(Ustr, Ustr)> = vec![];
+        for (parent_id, old_parent_ids, descendants) in renamed_parents {
+            let prefix = format!("{parent_id}::");
+            for descendant_id in descendants {
+                let Some(suffix) = descendant_id.strip_prefix(&prefix) else {
+                    continue;
+                };
+                for old_parent_id in old_parent_ids {
+                    renames.push((format!("{old_parent_id}::{suffix}").into(), descendant_id));
+                }
+            }
+        }
+        renames.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        renames
+    }
+
     /// A constructor taking the path to the root of the library.
     pub fn new(library_root: &Path, user_preferences: UserPreferences) -> Result<Self> {
         let mut library = LocalCourseLibrary {
             course_map: UstrMap::default(),
             lesson_map: UstrMap::default(),
             exercise_map: UstrMap::default(),
+            renamed_units: UstrMap::default(),
             user_preferences,
             unit_graph: Arc::new(RwLock::new(InMemoryUnitGraph::default())),
         };
@@ -460,9 +597,8 @@ impl LocalCourseLibrary {
                 continue;
             }
 
-            // Ignore any files which are not named `course_manifest.json`.
-            let file_name = Self::get_file_name(entry.path())?;
-            if file_name != COURSE_MANIFEST_FILENAME {
+            // Ignore any files which are not course manifests.
+            if !Self::is_manifest(entry.path(), COURSE_MANIFEST_STEM)? {
                 continue;
             }
 
@@ -492,6 +628,7 @@ impl LocalCourseLibrary {
             })
             .collect::<Result<Vec<_>>>()?;
         library.process_results(course_results)?;
+        library.renamed_units = library.collect_renamed_units()?;
         Ok(library)
     }
 
@@ -500,7 +637,7 @@ impl LocalCourseLibrary {
         serialized_library: SerializedCourseLibrary,
         user_preferences: UserPreferences,
     ) -> Result<Self> {
-        Ok(LocalCourseLibrary {
+        let mut library = LocalCourseLibrary {
             course_map: serialized_library
                 .course_map
                 .into_iter()
@@ -516,9 +653,12 @@ impl LocalCourseLibrary {
                 .into_iter()
                 .map(|(k, v)| (k, Arc::new(v)))
                 .collect(),
+            renamed_units: UstrMap::default(),
             user_preferences,
             unit_graph: Arc::new(RwLock::new(serialized_library.unit_graph)),
-        })
+        };
+        library.renamed_units = library.collect_renamed_units()?;
+        Ok(library)
     }
 }
 
@@ -650,6 +790,10 @@ impl CourseLibrary for LocalCourseLibrary {
                 .collect(),
         }
     }
+
+    fn get_renamed_units(&self) -> UstrMap<Ustr> {
+        self.renamed_units.clone()
+    }
 }
 
 impl GetUnitGraph for LocalCourseLibrary {
@@ -0,0 +1,718 @@
+//! Defines the `.trane-course` package format used to distribute courses and the operations to
+//! install, upgrade, and uninstall packages in a course library.
+//!
+//! A package is a zip archive that contains the course directory under the `course/` prefix and a
+//! `trane_package.json` file with the version of the format, the ID of the course, the name of the
+//! directory in which the course is installed, and the SHA-256 hash of every file. The hashes are
+//! verified before a package is installed, and a copy of the package manifest is kept in the
+//! installed directory so that the course can be upgraded or uninstalled later.
+//!
+//! Upgrading or uninstalling a course can change the IDs of its units. The practice data of units
+//! that are renamed is migrated when the library is opened, but the data of units that are removed
+//! is orphaned. Each operation reports these changes so that the student can review them.
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+use tempfile::TempDir;
+use ustr::{Ustr, UstrSet};
+use walkdir::WalkDir;
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    course_library::{COURSE_MANIFEST_STEM, LocalCourseLibrary},
+    data::{
+        CourseManifest, UserPreferences,
+        manifest_format::{ManifestFormat, open_file},
+    },
+    error::CoursePackageError,
+};
+
+/// The extension of course packages.
+pub const PACKAGE_EXTENSION: &str = "trane-course";
+
+/// The version of the package format written by this version of Trane. Packages with a newer
+/// version are rejected.
+pub const PACKAGE_FORMAT_VERSION: u32 = 1;
+
+/// The name of the package manifest, both inside the archive and in the installed directory.
+pub const PACKAGE_MANIFEST_FILE: &str = "trane_package.json";
+
+/// The prefix of the course files inside the archive.
+const COURSE_PREFIX: &str = "course/";
+
+/// The manifest of a course package.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PackageManifest {
+    /// The version of the package format.
+    pub format_version: u32,
+
+    /// The ID of the course in the package.
+    pub course_id: Ustr,
+
+    /// The name of the directory, directly under the library root, in which the course is
+    /// installed.
+    pub directory_name: String,
+
+    /// A map of the paths of all the files in the course, relative to the course directory and
+    /// using `/` as the separator, to their SHA-256 hashes.
+    pub files: BTreeMap<String, String>,
+}
+
+/// The changes to the IDs of the units in a course caused by installing, upgrading, or
+/// uninstalling a package.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct UnitIdChanges {
+    /// The IDs of the units that did not exist before the operation, sorted alphabetically.
+    pub added: Vec<Ustr>,
+
+    /// The IDs of the units that no longer exist after the operation, sorted alphabetically. Their
+    /// practice data is orphaned.
+    pub removed: Vec<Ustr>,
+
+    /// The previous and current IDs of the units renamed by the operation, sorted by the previous
+    /// ID. Their practice data is migrated the next time the library is opened.
+    pub renamed: Vec<(Ustr, Ustr)>,
+}
+
+/// The result of installing, upgrading, or uninstalling a package.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PackageReport {
+    /// The ID of the course.
+    pub course_id: Ustr,
+
+    /// The directory in which the course is or was installed.
+    pub course_directory: PathBuf,
+
+    /// The number of files in the course.
+    pub num_files: usize,
+
+    /// The changes to the IDs of the units in the course.
+    pub id_changes: UnitIdChanges,
+}
+
+/// The units in a course, used to compute the changes between two versions.
+#[derive(Default)]
+struct CourseUnits {
+    /// The IDs of all the units in the course.
+    ids: UstrSet,
+
+    /// The previous and current IDs of all the renamed units in the course.
+    renamed: Vec<(Ustr, Ustr)>,
+}
+
+/// Returns the SHA-256 hash of the given reader as a hexadecimal string.
+fn hash_reader(mut reader: impl Read) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        }))
+}
+
+/// Verifies that the given path is a relative path without any components that could escape the
+/// directory in which it is extracted.
+fn verify_relative_path(path: &str) -> Result<()> {
+    ensure!(!path.is_empty(), "empty path in package");
+    ensure!(
+        Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_))),
+        "invalid path {path} in package"
+    );
+    Ok(())
+}
+
+/// Reads the manifest of the course in the given directory, written in any of the supported
+/// formats.
+fn read_course_manifest(course_directory: &Path) -> Result<CourseManifest> {
+    let manifests = ManifestFormat::find_files(course_directory, COURSE_MANIFEST_STEM);
+    match manifests.as_slice() {
+        [path] => open_file(path, "manifest file"),
+        [] => bail!("no course manifest in {}", course_directory.display()),
+        _ => bail!(
+            "multiple course manifests in {}",
+            course_directory.display()
+        ),
+    }
+}
+
+/// Returns the units in the course at the given directory. The course is opened as if it were the
+/// only course in its parent directory.
+fn read_course_units(course_directory: &Path) -> Result<CourseUnits> {
+    // Ignore all the other entries in the parent directory.
+    let parent = course_directory
+        .parent()
+        .ok_or_else(|| anyhow!("course directory has no parent"))?;
+    let directory_name = course_directory.file_name().unwrap_or_default();
+    let ignored_paths = fs::read_dir(parent)?
+        .flatten()
+        .map(|entry| entry.file_name())
+        .filter(|name| name != directory_name)
+        .map(|name| name.to_string_lossy().to_string())
+        .collect();
+    let library = LocalCourseLibrary::new(
+        parent,
+        UserPreferences {
+            ignored_paths,
+            ..Default::default()
+        },
+    )?;
+
+    // Collect the IDs of all the units in the course.
+    let mut ids = UstrSet::default();
+    ids.extend(library.course_map.keys().copied());
+    ids.extend(library.lesson_map.keys().copied());
+    ids.extend(library.exercise_map.keys().copied());
+    let renamed = library
+        .renamed_units
+        .iter()
+        .map(|(previous, current)| (*previous, *current))
+        .collect();
+    Ok(CourseUnits { ids, renamed })
+}
+
+/// Computes the changes to the IDs of the units between two versions of a course.
+fn compute_id_changes(before: &CourseUnits, after: &CourseUnits) -> UnitIdChanges {
+    let mut renamed: Vec<(Ustr, Ustr)> = after
+        .renamed
+        .iter()
+        .filter(|(previous, _)| before.ids.contains(previous))
+        .copied()
+        .collect();
+    let mut added: Vec<Ustr> = after
+        .ids
+        .iter()
+        .filter(|id| !before.ids.contains(*id) && !renamed.iter().any(|(_, c)| c == *id))
+        .copied()
+        .collect();
+    let mut removed: Vec<Ustr> = before
+        .ids
+        .iter()
+        .filter(|id| !after.ids.contains(*id) && !renamed.iter().any(|(p, _)| p == *id))
+        .copied()
+        .collect();
+    added.sort();
+    removed.sort();
+    renamed.sort();
+    UnitIdChanges {
+        added,
+        removed,
+        renamed,
+    }
+}
+
+/// Returns the directories and manifests of all the packages installed under the library root.
+fn installed_packages(library_root: &Path) -> Result<Vec<(PathBuf, PackageManifest)>> {
+    let mut packages = vec![];
+    for entry in fs::read_dir(library_root)?.flatten() {
+        let manifest_path = entry.path().join(PACKAGE_MANIFEST_FILE);
+        if manifest_path.is_file() {
+            packages.push((entry.path(), open_file(&manifest_path, "package manifest")?));
+        }
+    }
+    Ok(packages)
+}
+
+/// Returns the directory and manifest of the installed package with the given course ID.
+fn find_installed_package(
+    library_root: &Path,
+    course_id: Ustr,
+) -> Result<Option<(PathBuf, PackageManifest)>> {
+    Ok(installed_packages(library_root)?
+        .into_iter()
+        .find(|(_, manifest)| manifest.course_id == course_id))
+}
+
+/// Helper function to create a package.
+fn create_package_helper(course_directory: &Path, package_path: &Path) -> Result<PackageManifest> {
+    // Collect the files in the course and their hashes. A package manifest left by a previous
+    // installation is not part of the course.
+    let course_manifest = read_course_manifest(course_directory)?;
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(course_directory).min_depth(1) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative_path = entry
+            .path()
+            .strip_prefix(course_directory)?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if relative_path == PACKAGE_MANIFEST_FILE {
+            continue;
+        }
+        let hash = hash_reader(File::open(entry.path())?)?;
+        files.insert(relative_path, hash);
+    }
+    let manifest = PackageManifest {
+        format_version: PACKAGE_FORMAT_VERSION,
+        course_id: course_manifest.id,
+        directory_name: course_directory
+            .file_name()
+            .ok_or_else(|| anyhow!("invalid course directory"))?
+            .to_string_lossy()
+            .to_string(),
+        files,
+    };
+
+    // Write the package manifest followed by all the files.
+    let mut writer = ZipWriter::new(File::create(package_path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    writer.start_file(PACKAGE_MANIFEST_FILE, options)?;
+    writer.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    for relative_path in manifest.files.keys() {
+        writer.start_file(format!("{COURSE_PREFIX}{relative_path}"), options)?;
+        io::copy(
+            &mut File::open(course_directory.join(relative_path))?,
+            &mut writer,
+        )?;
+    }
+    writer.finish()?;
+    Ok(manifest)
+}
+
+/// Creates a package with the course in the given directory and writes it to the given path.
+pub fn create_package(
+    course_directory: &Path,
+    package_path: &Path,
+) -> Result<PackageManifest, CoursePackageError> {
+    create_package_helper(course_directory, package_path)
+        .map_err(|e| CoursePackageError::Create(course_directory.display().to_string(), e))
+}
+
+/// Reads and validates the manifest of the package at the given path.
+fn open_package(package_path: &Path) -> Result<(PackageManifest, ZipArchive<File>)> {
+    let mut archive =
+        ZipArchive::new(File::open(package_path)?).context("invalid package archive")?;
+    let manifest: PackageManifest = serde_json::from_reader(
+        archive
+            .by_name(PACKAGE_MANIFEST_FILE)
+            .context("missing package manifest")?,
+    )
+    .context("invalid package manifest")?;
+    ensure!(
+        manifest.format_version <= PACKAGE_FORMAT_VERSION,
+        "unsupported package format version {}",
+        manifest.format_version
+    );
+    ensure!(
+        !manifest.directory_name.contains(['/', '\\']),
+        "invalid directory name {}",
+        manifest.directory_name
+    );
+    verify_relative_path(&manifest.directory_name)?;
+    for relative_path in manifest.files.keys() {
+        verify_relative_path(relative_path)?;
+    }
+    Ok((manifest, archive))
+}
+
+/// Extracts the course in the package into the given directory, verifying the hash of every file,
+/// and writes a copy of the package manifest into it.
+fn extract_package(
+    manifest: &PackageManifest,
+    archive: &mut ZipArchive<File>,
+    course_directory: &Path,
+) -> Result<()> {
+    for (relative_path, expected_hash) in &manifest.files {
+        // Extract the file and verify its hash.
+        let mut file = archive
+            .by_name(&format!("{COURSE_PREFIX}{relative_path}"))
+            .with_context(|| format!("missing file {relative_path}"))?;
+        let path = course_directory.join(relative_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut file, &mut File::create(&path)?)?;
+        let hash = hash_reader(File::open(&path)?)?;
+        ensure!(
+            &hash == expected_hash,
+            "hash mismatch for file {relative_path}"
+        );
+    }
+
+    // Reject any files that are not listed in the manifest.
+    for name in archive.file_names() {
+        if let Some(relative_path) = name.strip_prefix(COURSE_PREFIX)
+            && !relative_path.is_empty()
+            && !relative_path.ends_with('/')
+        {
+            ensure!(
+                manifest.files.contains_key(relative_path),
+                "file {relative_path} is not listed in the package manifest"
+            );
+        }
+    }
+
+    let manifest_file = File::create(course_directory.join(PACKAGE_MANIFEST_FILE))?;
+    serde_json::to_writer_pretty(manifest_file, manifest)?;
+    Ok(())
+}
+
+/// Helper function to install a package.
+fn install_package_helper(package_path: &Path, library_root: &Path) -> Result<PackageReport> {
+    // Verify that the course is not already installed.
+    let (manifest, mut archive) = open_package(package_path)?;
+    ensure!(
+        find_installed_package(library_root, manifest.course_id)?.is_none(),
+        "course {} is already installed, upgrade it instead",
+        manifest.course_id
+    );
+    let course_directory = library_root.join(&manifest.directory_name);
+    ensure!(
+        !course_directory.exists(),
+        "directory {} already exists",
+        course_directory.display()
+    );
+
+    // Extract the package into a staging directory and move it into place once it has been
+    // verified, so that a bad package does not leave a partial course in the library.
+    let staging = TempDir::new_in(library_root)?;
+    let staged_directory = staging.path().join(&manifest.directory_name);
+    extract_package(&manifest, &mut archive, &staged_directory)?;
+    let units = read_course_units(&staged_directory)?;
+    fs::rename(&staged_directory, &course_directory)?;
+
+    Ok(PackageReport {
+        course_id: manifest.course_id,
+        course_directory,
+        num_files: manifest.files.len(),
+        id_changes: compute_id_changes(&CourseUnits::default(), &units),
+    })
+}
+
+/// Installs the package at the given path into a new directory directly under the library root.
+pub fn install_package(
+    package_path: &Path,
+    library_root: &Path,
+) -> Result<PackageReport, CoursePackageError> {
+    install_package_helper(package_path, library_root)
+        .map_err(|e| CoursePackageError::Install(package_path.display().to_string(), e))
+}
+
+/// A package that has been extracted next to the installed version of its course.
+struct StagedUpgrade {
+    /// The staging directory, which is deleted when dropped.
+    staging: TempDir,
+
+    /// The directory of the installed version of the course.
+    installed_directory: PathBuf,
+
+    /// The directory in which the new version of the course has been extracted.
+    staged_directory: PathBuf,
+
+    /// The manifest of the new package.
+    manifest: PackageManifest,
+
+    /// The changes between the installed version and the new version.
+    id_changes: UnitIdChanges,
+}
+
+/// Extracts the package into a staging directory and compares it with the installed version.
+fn stage_upgrade(package_path: &Path, library_root: &Path) -> Result<StagedUpgrade> {
+    let (manifest, mut archive) = open_package(package_path)?;
+    let (installed_directory, _) = find_installed_package(library_root, manifest.course_id)?
+        .ok_or_else(|| anyhow!("course {} is not installed", manifest.course_id))?;
+    let target_directory = library_root.join(&manifest.directory_name);
+    ensure!(
+        target_directory == installed_directory || !target_directory.exists(),
+        "directory {} already exists",
+        target_directory.display()
+    );
+
+    let staging = TempDir::new_in(library_root)?;
+    let staged_directory = staging.path().join("new").join(&manifest.directory_name);
+    extract_package(&manifest, &mut archive, &staged_directory)?;
+    let before = read_course_units(&installed_directory)?;
+    let after = read_course_units(&staged_directory)?;
+    Ok(StagedUpgrade {
+        staging,
+        installed_directory,
+        staged_directory,
+        manifest,
+        id_changes: compute_id_changes(&before, &after),
+    })
+}
+
+/// Returns the changes to the IDs of the units that upgrading the installed course with the package
+/// at the given path would cause, without modifying the library.
+pub fn preview_upgrade(
+    package_path: &Path,
+    library_root: &Path,
+) -> Result<UnitIdChanges, CoursePackageError> {
+    stage_upgrade(package_path, library_root)
+        .map(|staged| staged.id_changes)
+        .map_err(|e| CoursePackageError::Upgrade(package_path.display().to_string(), e))
+}
+
+/// Helper function to upgrade a package.
+fn upgrade_package_helper(package_path: &Path, library_root: &Path) -> Result<PackageReport> {
+    // Move the installed version into the staging directory and the new version into its place.
+    // Restore the installed version if the latter fails.
+    let staged = stage_upgrade(package_path, library_root)?;
+    let course_directory = library_root.join(&staged.manifest.directory_name);
+    let old_directory = staged.staging.path().join("old");
+    fs::rename(&staged.installed_directory, &old_directory)?;
+    if let Err(e) = fs::rename(&staged.staged_directory, &course_directory) {
+        fs::rename(&old_directory, &staged.installed_directory)?;
+        return Err(e.into());
+    }
+
+    Ok(PackageReport {
+        course_id: staged.manifest.course_id,
+        course_directory,
+        num_files: staged.manifest.files.len(),
+        id_changes: staged.id_changes,
+    })
+}
+
+/// Replaces the installed version of a course with the one in the package at the given path.
+pub fn upgrade_package(
+    package_path: &Path,
+    library_root: &Path,
+) -> Result<PackageReport, CoursePackageError> {
+    upgrade_package_helper(package_path, library_root)
+        .map_err(|e| CoursePackageError::Upgrade(package_path.display().to_string(), e))
+}
+
+/// Helper function to uninstall a package.
+fn uninstall_package_helper(course_id: Ustr, library_root: &Path) -> Result<PackageReport> {
+    let (course_directory, manifest) = find_installed_package(library_root, course_id)?
+        .ok_or_else(|| anyhow!("course is not installed from a package"))?;
+    let units = read_course_units(&course_directory)?;
+    fs::remove_dir_all(&course_directory)?;
+    Ok(PackageReport {
+        course_id,
+        course_directory,
+        num_files: manifest.files.len(),
+        id_changes: compute_id_changes(&units, &CourseUnits::default()),
+    })
+}
+
+/// Removes the course with the given ID, which must have been installed from a package, from the
+/// library.
+pub fn uninstall_package(
+    course_id: Ustr,
+    library_root: &Path,
+) -> Result<PackageReport, CoursePackageError> {
+    uninstall_package_helper(course_id, library_root)
+        .map_err(|e| CoursePackageError::Uninstall(course_id, e))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use anyhow::Result;
+    use std::{collections::BTreeMap, fs, io::Write, path::Path};
+    use tempfile::TempDir;
+    use ustr::Ustr;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use crate::course_package::*;
+
+    /// Writes a course with one lesson and the given exercises, each of them with an optional
+    /// previous ID.
+    fn write_course(course_directory: &Path, exercises: &[(&str, Option<&str>)]) -> Result<()> {
+        let lesson_directory = course_directory.join("lesson");
+        fs::create_dir_all(&lesson_directory)?;
+        fs::write(
+            course_directory.join("course_manifest.yaml"),
+            "id: course\nname: Course\n",
+        )?;
+        fs::write(
+            lesson_directory.join("lesson_manifest.yaml"),
+            "id: course::lesson\ncourse_id: course\n",
+        )?;
+        for (short_id, renamed_from) in exercises {
+            let exercise_directory = lesson_directory.join(short_id);
+            fs::create_dir_all(&exercise_directory)?;
+            let renamed_from = renamed_from
+                .map(|id| format!("renamed_from: [course::lesson::{id}]\n"))
+                .unwrap_or_default();
+            fs::write(
+                exercise_directory.join("exercise_manifest.yaml"),
+                format!(
+                    "id: course::lesson::{short_id}\nlesson_id: course::lesson\n\
+                    course_id: course\n{renamed_from}exercise_asset:\n  \
+                    InlineFlashcardAsset:\n    front_content: {short_id}\n"
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Verifies creating and installing a package.
+    #[test]
+    fn install() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let course_directory = temp_dir.path().join("source").join("my_course");
+        write_course(&course_directory, &[("a", None)])?;
+        let package_path = temp_dir.path().join(format!("course.{PACKAGE_EXTENSION}"));
+        let manifest = create_package(&course_directory, &package_path)?;
+        assert_eq!(manifest.course_id, "course");
+        assert_eq!(manifest.directory_name, "my_course");
+        assert_eq!(manifest.files.len(), 3);
+        assert!(
+            manifest
+                .files
+                .contains_key("lesson/a/exercise_manifest.yaml")
+        );
+
+        // Install the package and verify the course can be opened.
+        let library_root = temp_dir.path().join("library");
+        fs::create_dir(&library_root)?;
+        let report = install_package(&package_path, &library_root)?;
+        assert_eq!(report.course_directory, library_root.join("my_course"));
+        assert_eq!(report.num_files, 3);
+        assert_eq!(
+            report.id_changes.added,
+            vec![
+                Ustr::from("course"),
+                Ustr::from("course::lesson"),
+                Ustr::from("course::lesson::a")
+            ]
+        );
+        assert!(report.id_changes.removed.is_empty());
+        assert!(
+            library_root
+                .join("my_course")
+                .join(PACKAGE_MANIFEST_FILE)
+                .exists()
+        );
+        let library = LocalCourseLibrary::new(&library_root, UserPreferences::default())?;
+        assert!(library.course_map.contains_key(&Ustr::from("course")));
+
+        // The same course cannot be installed twice. Packaging the installed course does not
+        // include the installed package manifest.
+        assert!(install_package(&package_path, &library_root).is_err());
+        let repackaged = create_package(
+            &library_root.join("my_course"),
+            &temp_dir.path().join("repackaged.trane-course"),
+        )?;
+        assert_eq!(repackaged, manifest);
+        Ok(())
+    }
+
+    /// Verifies that packages that fail the integrity checks are rejected.
+    #[test]
+    fn reject_invalid_packages() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let library_root = temp_dir.path().join("library");
+        fs::create_dir(&library_root)?;
+
+        // Writes a package with the given manifest and files.
+        let write_package = |manifest: &PackageManifest, files: &[(&str, &str)]| -> Result<_> {
+            let path = temp_dir.path().join("bad.trane-course");
+            let mut writer = ZipWriter::new(fs::File::create(&path)?);
+            writer.start_file(PACKAGE_MANIFEST_FILE, SimpleFileOptions::default())?;
+            writer.write_all(&serde_json::to_vec(manifest)?)?;
+            for (name, contents) in files {
+                writer.start_file(format!("course/{name}"), SimpleFileOptions::default())?;
+                writer.write_all(contents.as_bytes())?;
+            }
+            writer.finish()?;
+            Ok(path)
+        };
+        let valid = PackageManifest {
+            format_version: PACKAGE_FORMAT_VERSION,
+            course_id: Ustr::from("course"),
+            directory_name: "course".into(),
+            files: BTreeMap::from([(
+                "course_manifest.yaml".to_string(),
+                hash_reader("id: course\n".as_bytes())?,
+            )]),
+        };
+
+        // A file whose contents do not match the hash.
+        let path = write_package(&valid, &[("course_manifest.yaml", "id: other\n")])?;
+        let error = install_package(&path, &library_root).unwrap_err();
+        assert!(error.to_string().contains("hash mismatch"));
+
+        // A file that is not listed in the manifest.
+        let path = write_package(
+            &valid,
+            &[("course_manifest.yaml", "id: course\n"), ("extra.md", "")],
+        )?;
+        assert!(install_package(&path, &library_root).is_err());
+
+        // A newer version of the format.
+        let mut manifest = valid.clone();
+        manifest.format_version = PACKAGE_FORMAT_VERSION + 1;
+        let path = write_package(&manifest, &[("course_manifest.yaml", "id: course\n")])?;
+        assert!(install_package(&path, &library_root).is_err());
+
+        // A path that escapes the course directory.
+        let mut manifest = valid.clone();
+        manifest.directory_name = "..".into();
+        let path = write_package(&manifest, &[("course_manifest.yaml", "id: course\n")])?;
+        assert!(install_package(&path, &library_root).is_err());
+
+        // None of the failed installations left files behind.
+        assert_eq!(fs::read_dir(&library_root)?.count(), 0);
+        Ok(())
+    }
+
+    /// Verifies upgrading and uninstalling a package.
+    #[test]
+    fn upgrade_and_uninstall() -> Result<()> {
+        // Install the first version of the course.
+        let temp_dir = TempDir::new()?;
+        let library_root = temp_dir.path().join("library");
+        fs::create_dir(&library_root)?;
+        let v1_directory = temp_dir.path().join("v1").join("course");
+        write_course(&v1_directory, &[("a", None), ("b", None)])?;
+        let v1_package = temp_dir.path().join("v1.trane-course");
+        create_package(&v1_directory, &v1_package)?;
+        install_package(&v1_package, &library_root)?;
+        assert!(upgrade_package(&v1_package, &temp_dir.path().join("v1")).is_err());
+
+        // Create a second version that removes `a`, renames `b` to `c`, and adds `d`.
+        let v2_directory = temp_dir.path().join("v2").join("course");
+        write_course(&v2_directory, &[("c", Some("b")), ("d", None)])?;
+        let v2_package = temp_dir.path().join("v2.trane-course");
+        create_package(&v2_directory, &v2_package)?;
+        let expected_changes = UnitIdChanges {
+            added: vec![Ustr::from("course::lesson::d")],
+            removed: vec![Ustr::from("course::lesson::a")],
+            renamed: vec![(
+                Ustr::from("course::lesson::b"),
+                Ustr::from("course::lesson::c"),
+            )],
+        };
+
+        // Preview the upgrade without changing the library, then apply it.
+        assert_eq!(
+            preview_upgrade(&v2_package, &library_root)?,
+            expected_changes
+        );
+        assert!(library_root.join("course/lesson/a").exists());
+        let report = upgrade_package(&v2_package, &library_root)?;
+        assert_eq!(report.id_changes, expected_changes);
+        assert!(!library_root.join("course/lesson/a").exists());
+        assert!(library_root.join("course/lesson/d").exists());
+        assert_eq!(fs::read_dir(&library_root)?.count(), 1);
+
+        // Uninstall the course.
+        let report = uninstall_package(Ustr::from("course"), &library_root)?;
+        assert_eq!(report.id_changes.removed.len(), 4);
+        assert!(report.id_changes.added.is_empty());
+        assert_eq!(fs::read_dir(&library_root)?.count(), 0);
+        assert!(uninstall_package(Ustr::from("course"), &library_root).is_err());
+        Ok(())
+    }
+}
@@ -2,13 +2,16 @@
 //! store the results of a student's attempt at mastering an exercise, the options avaialble to
 //! control the behavior of the scheduler, among other things.
 
+pub mod cloze;
 pub mod course_generator;
 pub mod filter;
+pub mod manifest_format;
 
 use anyhow::{Result, bail};
 use derive_builder::Builder;
 use serde::{Deserialize, Serialize};
 use std::{collections::BTreeMap, path::Path};
+use strum::{Display, EnumString};
 use ustr::Ustr;
 
 use crate::data::course_generator::{
@@ -112,6 +115,27 @@ pub struct ExerciseTrial {
 }
 //>@lp-example-4
 
+/// A position in the history of exercise trials, used to retrieve the history one page at a time.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TrialHistoryCursor {
+    /// The timestamp of the last trial in the previous page.
+    pub timestamp: i64,
+
+    /// The internal ID of the last trial in the previous page, used to break ties between trials
+    /// with the same timestamp.
+    pub id: i64,
+}
+
+/// A page of the history of exercise trials.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TrialHistoryPage {
+    /// The trials in the page, sorted in ascending order by timestamp.
+    pub trials: Vec<ExerciseTrial>,
+
+    /// The cursor to retrieve the next page, or `None` if this is the last page.
+    pub next: Option<TrialHistoryCursor>,
+}
+
 /// The delta between the predicted and actual score for a single exercise trial.
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct ExerciseDelta {
@@ -143,6 +167,110 @@ pub struct UnitReward {
     pub timestamp: i64,
 }
 
+/// The reason why a unit was added to the blacklist.
+#[derive(Clone, Copy, Debug, Deserialize, Display, EnumString, Eq, PartialEq, Serialize)]
+#[strum(serialize_all = "snake_case")]
+pub enum BlacklistReason {
+    /// The student already mastered the material outside of Trane.
+    MasteredElsewhere,
+
+    /// The student is not interested in practicing the material.
+    NotInterested,
+
+    /// The student wants to stop practicing the material for a while, for example, during an
+    /// injury or a vacation.
+    TemporarilyPaused,
+}
+
+/// A single entry in the blacklist, along with the details of when and why it was added.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BlacklistEntry {
+    /// The ID of the blacklisted unit.
+    pub unit_id: Ustr,
+
+    /// The timestamp at which the unit was added to the blacklist. Entries created before this
+    /// information was recorded have a value of zero.
+    pub added_at: i64,
+
+    /// The reason why the unit was added to the blacklist, if one was given.
+    pub reason: Option<BlacklistReason>,
+
+    /// The timestamp after which the entry expires and the unit is scheduled again. Entries
+    /// without an expiry time remain in the blacklist until they are removed.
+    pub expires_at: Option<i64>,
+}
+
+impl BlacklistEntry {
+    /// Returns whether the entry has expired at the given timestamp.
+    #[must_use]
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// A single entry in the review list, along with the details used to schedule it and decide when
+/// it should be removed.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReviewListEntry {
+    /// The ID of the unit in the review list.
+    pub unit_id: Ustr,
+
+    /// The priority of the entry. Exercises from entries with higher priority are more likely to
+    /// be scheduled when practicing from the review list.
+    pub priority: u32,
+
+    /// The timestamp at which the unit was added to the review list. Entries created before this
+    /// information was recorded have a value of zero.
+    pub added_at: i64,
+
+    /// The number of successful reviews after which the entry is removed from the review list.
+    /// Entries without a value remain in the list until they are removed.
+    pub remove_after: Option<u32>,
+
+    /// The number of successful reviews of exercises from this unit since it was added to the
+    /// review list.
+    pub successful_reviews: u32,
+}
+
+/// The reason why a unit was automatically added to the review list.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AutoReviewReason {
+    /// The exercise had been mastered, but it received a score below the lapse score.
+    Lapse {
+        /// The score of the exercise before the trial.
+        previous_score: f32,
+
+        /// The score assigned to the exercise in the trial that caused the lapse.
+        score: f32,
+    },
+
+    /// The exercise was failed repeatedly within the failure window.
+    RepeatedFailures {
+        /// The number of failures within the window.
+        num_failures: usize,
+
+        /// The length of the window in seconds.
+        window: i64,
+    },
+}
+
+/// A record of a unit that was automatically added to the review list.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AutoReviewAddition {
+    /// The ID of the unit added to the review list. Depending on the policy, it's either the ID of
+    /// the exercise that lapsed or the ID of its lesson.
+    pub unit_id: Ustr,
+
+    /// The ID of the exercise that lapsed.
+    pub exercise_id: Ustr,
+
+    /// The reason why the unit was added.
+    pub reason: AutoReviewReason,
+
+    /// The timestamp of the trial that caused the unit to be added.
+    pub timestamp: i64,
+}
+
 /// The type of the units stored in the dependency graph.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum UnitType {
@@ -357,6 +485,13 @@ pub struct CourseManifest {
     #[builder(setter(into))]
     pub id: Ustr,
 
+    /// The IDs this course had in previous versions of the library. When the library is opened, the
+    /// data recorded under these IDs (scores, rewards, blacklist and review list entries, etc.)
+    /// is moved to the current ID so that renaming a course does not discard its history.
+    #[builder(default)]
+    #[serde(default)]
+    pub renamed_from: Vec<Ustr>,
+
     /// The name of the course to be presented to the user.
     ///
     /// For example, "Basic Jazz Chords on Guitar".
@@ -484,6 +619,13 @@ pub struct LessonManifest {
     #[builder(setter(into))]
     pub id: Ustr,
 
+    /// The IDs this lesson had in previous versions of the course. When the library is opened, the
+    /// data recorded under these IDs (scores, rewards, blacklist and review list entries, etc.)
+    /// is moved to the current ID so that renaming a lesson does not discard its history.
+    #[builder(default)]
+    #[serde(default)]
+    pub renamed_from: Vec<Ustr>,
+
     /// The IDs of all dependencies of this lesson.
     #[builder(default)]
     #[serde(default)]
@@ -584,12 +726,91 @@ impl GetUnitType for LessonManifest {
     }
 }
 
+/// One of the options of a multiple-choice question.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MultipleChoiceOption {
+    /// The text of the option.
+    pub text: String,
+
+    /// Whether selecting this option is part of the correct answer.
+    #[serde(default)]
+    pub correct: bool,
+
+    /// An optional explanation of why the option is correct or incorrect, to show to the student
+    /// after they answer.
+    #[serde(default)]
+    pub explanation: Option<String>,
+}
+
+/// A multiple-choice question, which Trane can grade automatically. Questions with more than one
+/// correct option require the student to select all of them.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MultipleChoice {
+    /// The prompt of the question.
+    pub prompt: String,
+
+    /// The options from which the student selects their answer.
+    pub options: Vec<MultipleChoiceOption>,
+
+    /// An optional explanation of the answer as a whole, to show to the student after they answer.
+    #[serde(default)]
+    pub explanation: Option<String>,
+
+    /// The time in seconds in which a student who has mastered the exercise is expected to answer.
+    /// Correct answers that take longer receive a lower score. If not set, a default value is used.
+    #[serde(default)]
+    pub expected_response_time: Option<f32>,
+}
+
+/// An exercise whose answer the student types, which Trane can grade automatically by comparing it
+/// with the accepted answers.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TypedAnswer {
+    /// The prompt of the exercise.
+    pub prompt: String,
+
+    /// The answers that are accepted as correct.
+    pub accepted_answers: Vec<String>,
+
+    /// Whether differences in case make an answer incorrect.
+    #[serde(default)]
+    pub case_sensitive: bool,
+
+    /// Whether differences in diacritics, such as accents, are ignored when comparing the answers.
+    #[serde(default)]
+    pub ignore_diacritics: bool,
+
+    /// The maximum number of edits (insertions, deletions, or substitutions of a single character)
+    /// for an answer to be considered a typo of an accepted answer rather than a wrong answer.
+    #[serde(default)]
+    pub tolerance: usize,
+
+    /// The time in seconds in which a student who has mastered the exercise is expected to answer.
+    /// Correct answers that take longer receive a lower score. If not set, a default value is used.
+    #[serde(default)]
+    pub expected_response_time: Option<f32>,
+}
+
 /// The asset storing the material of a particular exercise.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum ExerciseAsset {
     /// A basic asset storing the material of the exercise.
     BasicAsset(BasicAsset),
 
+    /// An asset representing one of the exercises generated from a note with cloze deletions. The
+    /// exercise hides the deletions with the given index. See the [`cloze`] module for the syntax.
+    ClozeAsset {
+        /// The ID of the note from which the exercise was generated. All the exercises generated
+        /// from the same note share this ID.
+        note_id: Ustr,
+
+        /// The text of the note, including the cloze deletions.
+        note: String,
+
+        /// The index of the deletions hidden in this exercise.
+        index: u32,
+    },
+
     /// An asset representing a flashcard with a front and back each stored in a markdown file.
     FlashcardAsset {
         /// The path to the file containing the front of the flashcard.
@@ -632,6 +853,10 @@ pub enum ExerciseAsset {
         exceptions: Vec<(String, Option<String>)>,
     },
 
+    /// An asset representing a multiple-choice question, which is graded automatically based on
+    /// the options selected by the student.
+    MultipleChoiceAsset(MultipleChoice),
+
     /// An asset which stores a link to a SoundSlice.
     SoundSliceAsset {
         /// The link to the SoundSlice asset.
@@ -648,6 +873,10 @@ pub enum ExerciseAsset {
         backup: Option<String>,
     },
 
+    /// An asset representing an exercise whose answer the student types, which is graded
+    /// automatically.
+    TypedAnswerAsset(TypedAnswer),
+
     /// A transcription asset, containing an exercise's content and an optional external link to the
     /// audio for the exercise.
     TranscriptionAsset {
@@ -685,7 +914,11 @@ impl NormalizePaths for ExerciseAsset {
                 })
             }
             ExerciseAsset::InlineFlashcardAsset { .. } => Ok(self.clone()), // grcov-excl-line
-            ExerciseAsset::LiteracyAsset { .. } | ExerciseAsset::TranscriptionAsset { .. } => {
+            ExerciseAsset::ClozeAsset { .. }
+            | ExerciseAsset::LiteracyAsset { .. }
+            | ExerciseAsset::MultipleChoiceAsset(..)
+            | ExerciseAsset::TranscriptionAsset { .. }
+            | ExerciseAsset::TypedAnswerAsset(..) => {
                 Ok(self.clone()) // grcov-excl-line
             }
             ExerciseAsset::SoundSliceAsset {
@@ -725,10 +958,12 @@ impl VerifyPaths for ExerciseAsset {
                     Ok(front_abs_path.exists())
                 }
             }
-            ExerciseAsset::InlineFlashcardAsset { .. } => Ok(true),
-            ExerciseAsset::LiteracyAsset { .. } | ExerciseAsset::TranscriptionAsset { .. } => {
-                Ok(true)
-            }
+            ExerciseAsset::ClozeAsset { .. }
+            | ExerciseAsset::InlineFlashcardAsset { .. }
+            | ExerciseAsset::LiteracyAsset { .. }
+            | ExerciseAsset::MultipleChoiceAsset(..)
+            | ExerciseAsset::TranscriptionAsset { .. }
+            | ExerciseAsset::TypedAnswerAsset(..) => Ok(true),
             ExerciseAsset::SoundSliceAsset { backup, .. } => match backup {
                 None => Ok(true),
                 Some(path) => {
@@ -750,6 +985,13 @@ pub struct ExerciseManifest {
     #[builder(setter(into))]
     pub id: Ustr,
 
+    /// The IDs this exercise had in previous versions of the course. When the library is opened, the
+    /// data recorded under these IDs (scores, rewards, blacklist and review list entries, etc.)
+    /// is moved to the current ID so that renaming an exercise does not discard its history.
+    #[builder(default)]
+    #[serde(default)]
+    pub renamed_from: Vec<Ustr>,
+
     /// The ID of the lesson to which this exercise belongs.
     #[builder(setter(into))]
     pub lesson_id: Ustr,
@@ -775,6 +1017,14 @@ pub struct ExerciseManifest {
     #[serde(default)]
     pub exercise_type: ExerciseType,
 
+    /// The ID of the group of sibling exercises to which this exercise belongs. Siblings are
+    /// exercises generated from the same source, such as the cloze deletions of a single note, and
+    /// they give each other away when practiced together. At most one exercise of each group is
+    /// scheduled in the same batch. Exercises without a group have no siblings.
+    #[builder(default)]
+    #[serde(default)]
+    pub sibling_group: Option<Ustr>,
+
     /// The asset containing the exercise itself.
     pub exercise_asset: ExerciseAsset,
 }
@@ -907,6 +1157,64 @@ impl MasteryWindow {
     }
 }
 
+/// A policy to automatically add exercises, or the lessons to which they belong, to the review list
+/// when the student's performance lapses. An exercise lapses when it receives a low score after it
+/// had been mastered, or when it's failed repeatedly within a short window of time.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AutoReviewPolicy {
+    /// Whether to add the lesson of the lapsed exercise to the review list instead of the exercise
+    /// itself.
+    #[serde(default)]
+    pub add_lessons: bool,
+
+    /// The score at or above which an exercise is considered to be mastered.
+    pub mastered_score: f32,
+
+    /// A mastered exercise lapses when it receives a score below this value.
+    pub lapse_score: f32,
+
+    /// The score at or below which a trial is considered to be a failure.
+    pub failure_score: f32,
+
+    /// The number of failures within the failure window after which the exercise is added to the
+    /// review list.
+    pub max_failures: usize,
+
+    /// The length of the failure window in seconds.
+    pub failure_window: i64,
+}
+
+impl Default for AutoReviewPolicy {
+    fn default() -> Self {
+        AutoReviewPolicy {
+            add_lessons: false,
+            mastered_score: 4.0,
+            lapse_score: 3.0,
+            failure_score: 2.0,
+            max_failures: 3,
+            failure_window: 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl AutoReviewPolicy {
+    /// Verifies that the policy is valid.
+    pub fn verify(&self) -> Result<()> {
+        if self.lapse_score > self.mastered_score {
+            bail!(
+                "invalid auto review policy: lapse_score must not be greater than mastered_score"
+            );
+        }
+        if self.max_failures == 0 {
+            bail!("invalid auto review policy: max_failures must be greater than 0");
+        }
+        if self.failure_window <= 0 {
+            bail!("invalid auto review policy: failure_window must be greater than 0");
+        }
+        Ok(())
+    }
+}
+
 /// Options to control how the scheduler selects exercises.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct SchedulerOptions {
@@ -958,9 +1266,26 @@ pub struct SchedulerOptions {
     /// it's not been seen before or its score is below the target window's range. The limit
     /// prevents the student from splitting attention between too many new or difficult lessons.
     pub max_lessons_in_progress: usize,
+
+    /// The policy used to automatically add lapsed exercises to the review list. If missing,
+    /// exercises are never added automatically.
+    #[serde(default)]
+    pub auto_review: Option<AutoReviewPolicy>,
+
+    /// The number of seconds after which the saved state of a study session is considered stale
+    /// and discarded instead of being restored. The state includes the relearn pile, the number of
+    /// times each exercise was scheduled, and the success rate of the session.
+    #[serde(default = "SchedulerOptions::default_session_staleness_window")]
+    pub session_staleness_window: i64,
 }
 
 impl SchedulerOptions {
+    /// Returns the default value of the session staleness window, which is set to six hours.
+    #[must_use]
+    fn default_session_staleness_window() -> i64 {
+        6 * 60 * 60
+    }
+
     #[must_use]
     fn float_equals(f1: f32, f2: f32) -> bool {
         (f1 - f2).abs() < f32::EPSILON
@@ -1025,6 +1350,16 @@ impl SchedulerOptions {
             bail!("invalid scheduler options: max_lessons_in_progress must be greater than 0");
         }
 
+        // The session staleness window cannot be negative.
+        if self.session_staleness_window < 0 {
+            bail!("invalid scheduler options: session_staleness_window must not be negative");
+        }
+
+        // The auto review policy, if any, must be valid.
+        if let Some(auto_review) = &self.auto_review {
+            auto_review.verify()?;
+        }
+
         Ok(())
     }
 }
@@ -1064,6 +1399,8 @@ impl Default for SchedulerOptions {
             num_deltas: 10,
             num_rewards: 10,
             max_lessons_in_progress: 10,
+            auto_review: None,
+            session_staleness_window: Self::default_session_staleness_window(),
         }
     }
 }
@@ -1074,6 +1411,81 @@ pub struct SchedulerPreferences {
     /// The maximum number of candidates to return each time the scheduler is called.
     #[serde(default)]
     pub batch_size: Option<usize>,
+
+    /// The policy used to automatically add lapsed exercises to the review list.
+    #[serde(default)]
+    pub auto_review: Option<AutoReviewPolicy>,
+
+    /// The number of seconds after which the saved state of a study session is discarded.
+    #[serde(default)]
+    pub session_staleness_window: Option<i64>,
+}
+
+/// A policy to limit the growth of the stored trials, rewards, and deltas based on their age.
+/// Everything newer than the retention window is kept. Older entries are downsampled so that only
+/// the last entry of each unit in each downsampling interval is kept, except for the first entry of
+/// each unit and the lapses, which are always kept.
+///
+/// As in the [`AutoReviewPolicy`], a lapse is a trial with a low score after the exercise had been
+/// mastered. The trial before each lapse is kept as well so that the lapse can still be identified
+/// after the policy is applied again.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RetentionPolicy {
+    /// The number of seconds during which all the entries are kept.
+    pub retention_window: i64,
+
+    /// The length in seconds of the intervals used to downsample older entries. If zero, all older
+    /// entries are removed except for the first entry of each unit and the lapses.
+    pub downsample_interval: i64,
+
+    /// The score at or above which a trial is considered to master the exercise.
+    pub mastered_score: f32,
+
+    /// A trial with a score below this value is a lapse if the previous trial of the exercise had a
+    /// score at or above the mastered score.
+    pub lapse_score: f32,
+
+    /// Whether to compact the databases after removing the entries to reclaim the freed space.
+    #[serde(default)]
+    pub compact: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            retention_window: 365 * 24 * 60 * 60,
+            downsample_interval: 7 * 24 * 60 * 60,
+            mastered_score: 4.0,
+            lapse_score: 3.0,
+            compact: true,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Verifies that the policy is valid.
+    pub fn verify(&self) -> Result<()> {
+        if self.retention_window < 0 {
+            bail!("retention window must be non-negative");
+        }
+        if self.downsample_interval < 0 {
+            bail!("downsample interval must be non-negative");
+        }
+        Ok(())
+    }
+}
+
+/// The number of entries removed after applying a retention policy.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RetentionSummary {
+    /// The number of trials removed from the practice stats.
+    pub trials: usize,
+
+    /// The number of rewards removed from the practice rewards.
+    pub rewards: usize,
+
+    /// The number of deltas removed from the practice deltas.
+    pub deltas: usize,
 }
 
 /// Represents a repository containing Trane courses.
@@ -1103,6 +1515,17 @@ pub struct UserPreferences {
     /// "foo/bar" will ignore any courses in "foo/bar" or any of its subdirectories.
     #[serde(default)]
     pub ignored_paths: Vec<String>,
+
+    /// The name of the time zone of the user from the IANA time zone database, for example
+    /// "America/New_York". It's used to compute the day boundaries in the practice statistics. If
+    /// it's not set, UTC is used.
+    #[serde(default)]
+    pub time_zone: Option<String>,
+
+    /// The policy used to limit the growth of the stored practice history. If it's not set, the
+    /// default policy is used.
+    #[serde(default)]
+    pub retention: Option<RetentionPolicy>,
 }
 //>@user-preferences
 
@@ -1454,6 +1877,53 @@ mod test {
         assert!(options.verify().is_err());
     }
 
+    /// Verifies that scheduler options with a negative session staleness window fail verification.
+    #[test]
+    fn verify_scheduler_options_negative_staleness_window() {
+        let options = SchedulerOptions {
+            session_staleness_window: -1,
+            ..Default::default()
+        };
+        assert!(options.verify().is_err());
+    }
+
+    /// Verifies that scheduler options with an invalid auto review policy fail verification.
+    #[test]
+    fn verify_scheduler_options_auto_review() {
+        let options = SchedulerOptions {
+            auto_review: Some(AutoReviewPolicy::default()),
+            ..Default::default()
+        };
+        assert!(options.verify().is_ok());
+
+        let options = SchedulerOptions {
+            auto_review: Some(AutoReviewPolicy {
+                lapse_score: 4.5,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(options.verify().is_err());
+
+        let options = SchedulerOptions {
+            auto_review: Some(AutoReviewPolicy {
+                max_failures: 0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(options.verify().is_err());
+
+        let options = SchedulerOptions {
+            auto_review: Some(AutoReviewPolicy {
+                failure_window: 0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(options.verify().is_err());
+    }
+
     /// Verifies that the default exercise type is Procedural. Written to satisfy code coverage.
     #[test]
     fn default_exercise_type() {
@@ -1484,8 +1954,12 @@ mod test {
             }),
             scheduler: Some(SchedulerPreferences {
                 batch_size: Some(10),
+                auto_review: None,
+                session_staleness_window: None,
             }),
             ignored_paths: vec!["courses/".to_owned()],
+            time_zone: None,
+            retention: None,
         };
         assert_eq!(preferences, preferences.clone());
     }
@@ -0,0 +1,197 @@
+//! Contains the logic to parse and render notes with cloze deletions.
+//!
+//! A cloze deletion hides part of a note, and the student must recall the hidden text. Deletions
+//! are written as `{{c<INDEX>::<TEXT>}}` or `{{c<INDEX>::<TEXT>::<HINT>}}`, where the index is a
+//! positive number. A single note generates one exercise per distinct index, and all the deletions
+//! with the same index are hidden together.
+
+use anyhow::{Result, anyhow, bail, ensure};
+use std::collections::BTreeSet;
+
+/// The string that opens a cloze deletion.
+pub const CLOZE_OPEN: &str = "{{c";
+
+/// The string that closes a cloze deletion.
+pub const CLOZE_CLOSE: &str = "}}";
+
+/// The separator between the index, the text, and the hint of a cloze deletion.
+pub const CLOZE_SEPARATOR: &str = "::";
+
+/// The text shown in place of a hidden deletion without a hint.
+pub const CLOZE_PLACEHOLDER: &str = "[...]";
+
+/// A single cloze deletion in a note.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClozeDeletion {
+    /// The index of the deletion. Deletions with the same index are hidden together.
+    pub index: u32,
+
+    /// The hidden text.
+    pub text: String,
+
+    /// An optional hint shown in place of the hidden text.
+    pub hint: Option<String>,
+}
+
+/// A part of a note, which is either plain text or a cloze deletion.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClozePart {
+    /// Plain text that is always shown.
+    Text(String),
+
+    /// A cloze deletion.
+    Deletion(ClozeDeletion),
+}
+
+/// Parses the body of a deletion, that is, the contents between the opening and closing strings
+/// without the leading `c`.
+fn parse_deletion(body: &str) -> Result<ClozeDeletion> {
+    let mut parts = body.splitn(3, CLOZE_SEPARATOR);
+    let index = parts.next().unwrap_or_default();
+    let index: u32 = index
+        .parse()
+        .map_err(|_| anyhow!("invalid cloze index {index}"))?;
+    ensure!(index > 0, "cloze indices must be positive");
+    let text = parts
+        .next()
+        .ok_or_else(|| anyhow!("cloze deletion c{index} has no text"))?;
+    ensure!(!text.is_empty(), "cloze deletion c{index} has no text");
+    Ok(ClozeDeletion {
+        index,
+        text: text.to_string(),
+        hint: parts.next().map(ToString::to_string),
+    })
+}
+
+/// Splits the note into plain text and cloze deletions.
+pub fn parse_note(note: &str) -> Result<Vec<ClozePart>> {
+    let mut parts = vec![];
+    let mut rest = note;
+    while let Some(start) = rest.find(CLOZE_OPEN) {
+        // Add the text before the deletion.
+        if start > 0 {
+            parts.push(ClozePart::Text(rest[..start].to_string()));
+        }
+
+        // Parse the deletion and continue after it.
+        let body_start = start + CLOZE_OPEN.len();
+        let Some(length) = rest[body_start..].find(CLOZE_CLOSE) else {
+            bail!("unclosed cloze deletion in note");
+        };
+        let body = &rest[body_start..body_start + length];
+        ensure!(
+            !body.contains(CLOZE_OPEN),
+            "nested cloze deletions are not supported"
+        );
+        parts.push(ClozePart::Deletion(parse_deletion(body)?));
+        rest = &rest[body_start + length + CLOZE_CLOSE.len()..];
+    }
+    if !rest.is_empty() {
+        parts.push(ClozePart::Text(rest.to_string()));
+    }
+    Ok(parts)
+}
+
+/// Returns the distinct indices of the deletions in the note, in ascending order.
+pub fn cloze_indices(note: &str) -> Result<BTreeSet<u32>> {
+    Ok(parse_note(note)?
+        .into_iter()
+        .filter_map(|part| match part {
+            ClozePart::Deletion(deletion) => Some(deletion.index),
+            ClozePart::Text(_) => None,
+        })
+        .collect())
+}
+
+/// Renders the note using the given functions to render the deletions with the given index and
+/// the rest of the deletions.
+fn render(
+    note: &str,
+    index: u32,
+    render_hidden: impl Fn(&ClozeDeletion) -> String,
+) -> Result<String> {
+    let parts = parse_note(note)?;
+    ensure!(
+        parts
+            .iter()
+            .any(|part| matches!(part, ClozePart::Deletion(deletion) if deletion.index == index)),
+        "the note has no cloze deletion with index {index}",
+    );
+    Ok(parts
+        .into_iter()
+        .map(|part| match part {
+            ClozePart::Text(text) => text,
+            ClozePart::Deletion(deletion) if deletion.index == index => render_hidden(&deletion),
+            ClozePart::Deletion(deletion) => deletion.text,
+        })
+        .collect())
+}
+
+/// Renders the front of the exercise for the deletion with the given index. The deletions with
+/// that index are replaced with their hint or a placeholder, and the rest show their text.
+pub fn render_front(note: &str, index: u32) -> Result<String> {
+    render(note, index, |deletion| match &deletion.hint {
+        Some(hint) => format!("[{hint}]"),
+        None => CLOZE_PLACEHOLDER.to_string(),
+    })
+}
+
+/// Renders the back of the exercise for the deletion with the given index. The full note is shown,
+/// with the text of the deletions with that index in bold.
+pub fn render_back(note: &str, index: u32) -> Result<String> {
+    render(note, index, |deletion| format!("**{}**", deletion.text))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use anyhow::Result;
+    use std::collections::BTreeSet;
+
+    use crate::data::cloze::*;
+
+    /// Verifies parsing notes with cloze deletions.
+    #[test]
+    fn parse_notes() -> Result<()> {
+        let parts = parse_note("The {{c1::capital}} of {{c2::France::country}} is {{c1::Paris}}.")?;
+        assert_eq!(parts.len(), 7);
+        assert_eq!(parts[0], ClozePart::Text("The ".to_string()));
+        assert_eq!(
+            parts[3],
+            ClozePart::Deletion(ClozeDeletion {
+                index: 2,
+                text: "France".to_string(),
+                hint: Some("country".to_string()),
+            })
+        );
+        assert_eq!(
+            cloze_indices("{{c3::a}} {{c1::b}} {{c3::c}}")?,
+            BTreeSet::from([1, 3])
+        );
+        assert!(cloze_indices("No deletions")?.is_empty());
+
+        // Invalid deletions are rejected.
+        assert!(parse_note("{{c1::unclosed").is_err());
+        assert!(parse_note("{{c0::zero}}").is_err());
+        assert!(parse_note("{{cx::letter}}").is_err());
+        assert!(parse_note("{{c1::}}").is_err());
+        assert!(parse_note("{{c1}}").is_err());
+        assert!(parse_note("{{c1::a {{c2::b}}}}").is_err());
+        Ok(())
+    }
+
+    /// Verifies rendering the front and back of the exercises generated from a note.
+    #[test]
+    fn render_notes() -> Result<()> {
+        let note = "The {{c1::capital}} of {{c2::France::country}} is {{c1::Paris}}.";
+        assert_eq!(render_front(note, 1)?, "The [...] of France is [...].");
+        assert_eq!(render_front(note, 2)?, "The capital of [country] is Paris.");
+        assert_eq!(
+            render_back(note, 1)?,
+            "The **capital** of France is **Paris**."
+        );
+        assert_eq!(render_back(note, 2)?, "The capital of **France** is Paris.");
+        assert!(render_front(note, 3).is_err());
+        Ok(())
+    }
+}
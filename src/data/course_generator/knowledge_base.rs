@@ -1,19 +1,23 @@
 //! Contains the logic to generate a Trane course based on a knowledge base of markdown files
 //! representing the front and back of flashcard exercises.
 
-use anyhow::{Context, Error, Result, anyhow};
+use anyhow::{Context, Error, Result, anyhow, ensure};
+use noyalib::compat::serde_yaml;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
-    fs::{File, read_dir, read_to_string},
-    io::BufReader,
-    path::Path,
+    fmt::Write,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
 };
 use ustr::{Ustr, UstrMap};
 
 use crate::data::{
     BasicAsset, CourseManifest, ExerciseAsset, ExerciseManifest, ExerciseType, GenerateManifests,
-    GeneratedCourse, LessonManifest, UserPreferences,
+    GeneratedCourse, LessonManifest, MultipleChoice, UserPreferences,
+    cloze::cloze_indices,
+    manifest_format::{ManifestFormat, open_file},
 };
 
 /// The suffix used to recognize a directory as a knowledge base lesson.
@@ -46,6 +50,32 @@ pub const LESSON_MATERIAL_FILE: &str = "lesson.material.md";
 /// The name of the file containing the default exercise type for exercises in the lesson.
 pub const LESSON_DEFAULT_EXERCISE_TYPE_FILE: &str = "lesson.default_exercise_type.json";
 
+/// The name of the file containing the previous IDs of the lesson.
+pub const LESSON_RENAMED_FROM_FILE: &str = "lesson.renamed_from.json";
+
+/// The name of the file indicating whether the lesson generates reversed exercises.
+pub const LESSON_REVERSE_FILE: &str = "lesson.reverse.json";
+
+/// The suffix appended to the short ID of a lesson to generate the short ID of the lesson containing
+/// its reversed exercises.
+pub const REVERSED_LESSON_SUFFIX: &str = "_reversed";
+
+/// The name of the markdown file containing the properties and the flashcards of the lesson in a
+/// single file.
+pub const LESSON_MARKDOWN_FILE: &str = "lesson.md";
+
+/// The line that opens and closes the YAML front matter at the start of a `lesson.md` file. Inside
+/// a card, the same line separates the front of the flashcard from the back.
+pub const FRONT_MATTER_DELIMITER: &str = "---";
+
+/// The line that separates two cards in a `lesson.md` file when they are not introduced by a
+/// heading.
+pub const CARD_DELIMITER: &str = "===";
+
+/// The prefix of the heading that introduces a card in a `lesson.md` file. The rest of the heading
+/// is the short ID of the exercise.
+pub const CARD_HEADING_PREFIX: &str = "## ";
+
 /// The suffix of the file containing the front of the flashcard for an exercise.
 pub const EXERCISE_FRONT_SUFFIX: &str = ".front.md";
 
@@ -61,6 +91,18 @@ pub const EXERCISE_DESCRIPTION_SUFFIX: &str = ".description.json";
 /// The suffix of the file containing the metadata of an exercise.
 pub const EXERCISE_TYPE_SUFFIX: &str = ".type.json";
 
+/// The suffix of the file containing the multiple-choice question of an exercise.
+pub const EXERCISE_MULTIPLE_CHOICE_SUFFIX: &str = ".multiple_choice.json";
+
+/// The suffix of the markdown file containing a note with cloze deletions.
+pub const EXERCISE_CLOZE_SUFFIX: &str = ".cloze.md";
+
+/// The suffix of the file containing the previous IDs of an exercise.
+pub const EXERCISE_RENAMED_FROM_SUFFIX: &str = ".renamed_from.json";
+
+/// The suffix of the file indicating whether a reversed exercise is generated for an exercise.
+pub const EXERCISE_REVERSE_SUFFIX: &str = ".reverse.json";
+
 /// An enum representing a type of file that can be found in a knowledge base lesson directory.
 #[derive(Debug, Eq, PartialEq)]
 pub enum KnowledgeBaseFile {
@@ -91,6 +133,15 @@ pub enum KnowledgeBaseFile {
     /// The file containing the default exercise type for exercises in the lesson.
     LessonDefaultExerciseType,
 
+    /// The file containing the previous IDs of the lesson.
+    LessonRenamedFrom,
+
+    /// The file indicating whether the lesson generates reversed exercises.
+    LessonReverse,
+
+    /// The markdown file containing the properties and flashcards of the lesson.
+    LessonMarkdown,
+
     /// The file containing the front of the flashcard for the exercise with the given short ID.
     ExerciseFront(String),
 
@@ -105,25 +156,60 @@ pub enum KnowledgeBaseFile {
 
     /// The file containing the type of the exercise with the given short ID.
     ExerciseType(String),
+
+    /// The file containing the multiple-choice question of the exercise with the given short ID.
+    ExerciseMultipleChoice(String),
+
+    /// The file containing the note with cloze deletions with the given short ID.
+    ExerciseCloze(String),
+
+    /// The file indicating whether a reversed exercise is generated for the exercise with the given
+    /// short ID.
+    ExerciseReverse(String),
+
+    /// The file containing the previous IDs of the exercise with the given short ID.
+    ExerciseRenamedFrom(String),
 }
 
 impl KnowledgeBaseFile {
-    /// Opens the knowledge base file at the given path and deserializes its contents.
-    pub fn open<T: DeserializeOwned>(path: &Path) -> Result<T> {
-        let display = path.display();
-        let file =
-            File::open(path).context(format!("cannot open knowledge base file {display}"))?;
-        let reader = BufReader::new(file);
-        serde_json::from_reader(reader)
-            .context(format!("cannot parse knowledge base file {display}"))
+    /// Opens the knowledge base file at the given path and deserializes its contents. Files with a
+    /// YAML extension are read as YAML and all other files as JSON.
+    pub fn open<T: DeserializeOwned + 'static>(path: &Path) -> Result<T> {
+        open_file(path, "knowledge base file")
+    }
+
+    /// Returns the path of the knowledge base file with the given name inside the lesson directory.
+    /// If the JSON file does not exist but a YAML file with the same name does, the path to the
+    /// YAML file is returned instead.
+    #[must_use]
+    pub fn resolve_path(lesson_root: &Path, file_name: &str) -> PathBuf {
+        let path = lesson_root.join(file_name);
+        if path.exists() {
+            return path;
+        }
+        let stem = file_name.strip_suffix(".json").unwrap_or(file_name);
+        ManifestFormat::Yaml
+            .extensions()
+            .iter()
+            .map(|extension| lesson_root.join(format!("{stem}.{extension}")))
+            .find(|yaml_path| yaml_path.exists())
+            .unwrap_or(path)
     }
 }
 
 impl TryFrom<&str> for KnowledgeBaseFile {
     type Error = Error;
 
-    /// Converts a file name to a `KnowledgeBaseFile` variant.
+    /// Converts a file name to a `KnowledgeBaseFile` variant. Files written in YAML are recognized
+    /// by the name of their JSON counterpart.
     fn try_from(file_name: &str) -> Result<Self> {
+        let json_name = match ManifestFormat::from_path(Path::new(file_name)) {
+            Some(ManifestFormat::Yaml) => file_name
+                .rsplit_once('.')
+                .map(|(stem, _)| format!("{stem}.json")),
+            _ => None,
+        };
+        let file_name = json_name.as_deref().unwrap_or(file_name);
         match file_name {
             LESSON_DEPENDENCIES_FILE => Ok(KnowledgeBaseFile::LessonDependencies),
             LESSON_SUPERSEDED_FILE => Ok(KnowledgeBaseFile::LessonSuperseded),
@@ -134,6 +220,9 @@ impl TryFrom<&str> for KnowledgeBaseFile {
             LESSON_MATERIAL_FILE => Ok(KnowledgeBaseFile::LessonMaterial),
             LESSON_INSTRUCTIONS_FILE => Ok(KnowledgeBaseFile::LessonInstructions),
             LESSON_DEFAULT_EXERCISE_TYPE_FILE => Ok(KnowledgeBaseFile::LessonDefaultExerciseType),
+            LESSON_RENAMED_FROM_FILE => Ok(KnowledgeBaseFile::LessonRenamedFrom),
+            LESSON_REVERSE_FILE => Ok(KnowledgeBaseFile::LessonReverse),
+            LESSON_MARKDOWN_FILE => Ok(KnowledgeBaseFile::LessonMarkdown),
             file_name if file_name.ends_with(EXERCISE_FRONT_SUFFIX) => {
                 let short_id = file_name.strip_suffix(EXERCISE_FRONT_SUFFIX).unwrap();
                 Ok(KnowledgeBaseFile::ExerciseFront(short_id.to_string()))
@@ -154,11 +243,61 @@ impl TryFrom<&str> for KnowledgeBaseFile {
                 let short_id = file_name.strip_suffix(EXERCISE_TYPE_SUFFIX).unwrap();
                 Ok(KnowledgeBaseFile::ExerciseType(short_id.to_string()))
             }
+            file_name if file_name.ends_with(EXERCISE_MULTIPLE_CHOICE_SUFFIX) => {
+                let short_id = file_name
+                    .strip_suffix(EXERCISE_MULTIPLE_CHOICE_SUFFIX)
+                    .unwrap();
+                Ok(KnowledgeBaseFile::ExerciseMultipleChoice(
+                    short_id.to_string(),
+                ))
+            }
+            file_name if file_name.ends_with(EXERCISE_CLOZE_SUFFIX) => {
+                let short_id = file_name.strip_suffix(EXERCISE_CLOZE_SUFFIX).unwrap();
+                Ok(KnowledgeBaseFile::ExerciseCloze(short_id.to_string()))
+            }
+            file_name if file_name.ends_with(EXERCISE_REVERSE_SUFFIX) => {
+                let short_id = file_name.strip_suffix(EXERCISE_REVERSE_SUFFIX).unwrap();
+                Ok(KnowledgeBaseFile::ExerciseReverse(short_id.to_string()))
+            }
+            file_name if file_name.ends_with(EXERCISE_RENAMED_FROM_SUFFIX) => {
+                let short_id = file_name
+                    .strip_suffix(EXERCISE_RENAMED_FROM_SUFFIX)
+                    .unwrap();
+                Ok(KnowledgeBaseFile::ExerciseRenamedFrom(short_id.to_string()))
+            }
             _ => Err(anyhow!("Not a valid knowledge base file name: {file_name}")),
         }
     }
 }
 
+/// Returns the short ID of the exercise generated for the cloze deletions with the given index in
+/// the note with the given short ID. The ID only depends on the index, so that the progress of each
+/// exercise is kept when the note is edited.
+#[must_use]
+pub fn cloze_short_id(note_short_id: &str, index: u32) -> String {
+    format!("{note_short_id}_c{index}")
+}
+
+/// Returns the short ID of the lesson containing the reversed exercises generated from the lesson
+/// with the given short ID.
+#[must_use]
+pub fn reversed_lesson_short_id(short_lesson_id: &str) -> Ustr {
+    format!("{short_lesson_id}{REVERSED_LESSON_SUFFIX}").into()
+}
+
+/// The cloze deletion hidden by a knowledge base exercise generated from a note.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KnowledgeBaseCloze {
+    /// The short ID of the note from which the exercise was generated.
+    pub note_short_id: String,
+
+    /// The text of the note, including the cloze deletions.
+    pub note: String,
+
+    /// The index of the deletions hidden by the exercise.
+    pub index: u32,
+}
+
 //@<knowledge-base-exercise
 /// Represents a knowledge base exercise.
 ///
@@ -175,6 +314,23 @@ impl TryFrom<&str> for KnowledgeBaseFile {
 /// `ex1`, one would write a file named `ex1.name.json` containing a JSON string with the desired
 /// name.
 ///
+/// An exercise can be written as a multiple-choice question instead of a flashcard by writing a
+/// file named `<SHORT_EXERCISE_ID>.multiple_choice.json` containing a serialized
+/// [`MultipleChoice`]. Such exercises do not need a front file, and they are graded automatically.
+///
+/// A file named `<SHORT_NOTE_ID>.cloze.md` contains a note with cloze deletions, written as
+/// `{{c1::text}}` (see the [`cloze`](crate::data::cloze) module for the full syntax). The note
+/// generates one exercise for each distinct deletion index, with short ID `<SHORT_NOTE_ID>_c<INDEX>`.
+/// The name, description, and type files of the note apply to all of its exercises.
+///
+/// A file named `<SHORT_EXERCISE_ID>.renamed_from.json` contains the full IDs the exercise had
+/// before it was renamed, so that its data is moved to the new ID. For a cloze note, these are the
+/// previous IDs of the note, and each generated exercise is renamed from them with the suffix
+/// `_c<INDEX>`. The cards in a `lesson.md` file cannot declare previous IDs.
+///
+/// A file named `<SHORT_EXERCISE_ID>.reverse.json` containing a boolean overrides whether the
+/// lesson generates a reversed exercise for the flashcard (see [`KnowledgeBaseLesson`]).
+///
 /// Trane will ignore any markdown files that do not match the exercise name pattern or that do not
 /// have a matching pair of front and back files.
 #[derive(Clone)]
@@ -206,10 +362,75 @@ pub struct KnowledgeBaseExercise {
     /// between the types of exercises, but that will likely change in the future. The option to set
     /// the type is provided, but most users should not need to use it.
     pub exercise_type: Option<ExerciseType>,
+
+    /// The front and optional back of the flashcard, for exercises whose content is not stored in
+    /// their own files, such as the cards in a `lesson.md` file. If set, the files are not read and
+    /// the exercise always uses an inline flashcard asset.
+    pub inline_content: Option<(String, Option<String>)>,
+
+    /// The multiple-choice question of the exercise. If set, the exercise uses a multiple-choice
+    /// asset instead of a flashcard.
+    pub multiple_choice: Option<MultipleChoice>,
+
+    /// The cloze deletion hidden by the exercise, if it was generated from a note. If set, the
+    /// exercise uses a cloze asset instead of a flashcard.
+    pub cloze: Option<KnowledgeBaseCloze>,
+
+    /// Whether to generate a reversed exercise for this flashcard. If not set, the value set for
+    /// the lesson is used.
+    pub reverse: Option<bool>,
+
+    /// The full IDs the exercise had before it was renamed.
+    pub renamed_from: Vec<Ustr>,
 }
 //>@knowledge-base-exercise
 
 impl KnowledgeBaseExercise {
+    /// Returns the full ID of the exercise.
+    fn exercise_id(&self) -> Ustr {
+        format!(
+            "{}::{}::{}",
+            self.course_id, self.short_lesson_id, self.short_id
+        )
+        .into()
+    }
+
+    /// Returns the reversed version of this exercise, which shows the back of the flashcard and
+    /// asks for the front, if one should be generated. Only flashcards with a back are reversed.
+    /// The reversed exercise keeps the same short ID and belongs to the reversed lesson, so its ID
+    /// is stable.
+    fn reversed(&self, lesson_reverse: bool) -> Option<Self> {
+        if !self.reverse.unwrap_or(lesson_reverse)
+            || self.cloze.is_some()
+            || self.multiple_choice.is_some()
+        {
+            return None;
+        }
+        let inline_content = match &self.inline_content {
+            Some((front, Some(back))) => Some((back.clone(), Some(front.clone()))),
+            Some((_, None)) => return None,
+            None => None,
+        };
+        let reversed_lesson_id = reversed_lesson_short_id(&self.short_lesson_id);
+        let lesson_prefix = format!("{}::{}::", self.course_id, self.short_lesson_id);
+        let renamed_from = self
+            .renamed_from
+            .iter()
+            .filter_map(|old_id| old_id.strip_prefix(&lesson_prefix))
+            .map(|short_id| format!("{}::{reversed_lesson_id}::{short_id}", self.course_id).into())
+            .collect();
+        Some(Self {
+            short_lesson_id: reversed_lesson_id,
+            front_file: self.back_file.clone()?,
+            back_file: Some(self.front_file.clone()),
+            name: self.name.as_ref().map(|name| format!("{name} (Reversed)")),
+            inline_content,
+            reverse: Some(false),
+            renamed_from,
+            ..self.clone()
+        })
+    }
+
     /// Generates the exercise manifest, using the provided default exercise type from the lesson
     /// if the exercise does not have its own type set. Falls back to `Procedural` if neither is
     /// set.
@@ -218,7 +439,29 @@ impl KnowledgeBaseExercise {
         default_exercise_type: Option<ExerciseType>,
         inlined: bool,
     ) -> Result<ExerciseManifest> {
-        let exercise_asset = if inlined {
+        // The exercises generated from the same cloze note are siblings.
+        let note_id: Option<Ustr> = self.cloze.as_ref().map(|cloze| {
+            format!(
+                "{}::{}::{}",
+                self.course_id, self.short_lesson_id, cloze.note_short_id
+            )
+            .into()
+        });
+
+        let exercise_asset = if let (Some(cloze), Some(note_id)) = (&self.cloze, note_id) {
+            ExerciseAsset::ClozeAsset {
+                note_id,
+                note: cloze.note.clone(),
+                index: cloze.index,
+            }
+        } else if let Some(multiple_choice) = &self.multiple_choice {
+            ExerciseAsset::MultipleChoiceAsset(multiple_choice.clone())
+        } else if let Some((front_content, back_content)) = &self.inline_content {
+            ExerciseAsset::InlineFlashcardAsset {
+                front_content: front_content.clone(),
+                back_content: back_content.clone(),
+            }
+        } else if inlined {
             let front_content = read_to_string(&self.front_file).context(format!(
                 "failed to read exercise front file {}",
                 self.front_file
@@ -243,11 +486,8 @@ impl KnowledgeBaseExercise {
         };
 
         Ok(ExerciseManifest {
-            id: format!(
-                "{}::{}::{}",
-                self.course_id, self.short_lesson_id, self.short_id
-            )
-            .into(),
+            id: self.exercise_id(),
+            renamed_from: self.renamed_from.clone(),
             lesson_id: format!("{}::{}", self.course_id, self.short_lesson_id).into(),
             course_id: self.course_id,
             name: self
@@ -259,18 +499,21 @@ impl KnowledgeBaseExercise {
                 .exercise_type
                 .clone()
                 .unwrap_or(default_exercise_type.unwrap_or(ExerciseType::Procedural)),
+            sibling_group: note_id,
             exercise_asset,
         })
     }
 
-    /// Generates the exercise from a list of knowledge base files.
-    fn create_exercise(
+    /// Generates the exercises from a list of knowledge base files. A single exercise is generated
+    /// unless the files contain a note with cloze deletions, in which case one exercise is
+    /// generated for each deletion index.
+    fn create_exercises(
         lesson_root: &Path,
         short_id: &str,
         short_lesson_id: Ustr,
         course_manifest: &CourseManifest,
         files: &[KnowledgeBaseFile],
-    ) -> Result<Self> {
+    ) -> Result<Vec<Self>> {
         // Check if the exercise has a back file and create it accordingly.
         let has_back_file = files.iter().any(|file| match file {
             KnowledgeBaseFile::ExerciseBack(id) => id == short_id,
@@ -302,6 +545,11 @@ impl KnowledgeBaseExercise {
             name: None,
             description: None,
             exercise_type: None,
+            inline_content: None,
+            multiple_choice: None,
+            cloze: None,
+            reverse: None,
+            renamed_from: vec![],
         };
 
         // Iterate through the exercise files found in the lesson directory and set the
@@ -310,21 +558,94 @@ impl KnowledgeBaseExercise {
         for exercise_file in files {
             match exercise_file {
                 KnowledgeBaseFile::ExerciseName(..) => {
-                    let path = lesson_root.join(format!("{short_id}{EXERCISE_NAME_SUFFIX}"));
+                    let path = KnowledgeBaseFile::resolve_path(
+                        lesson_root,
+                        &format!("{short_id}{EXERCISE_NAME_SUFFIX}"),
+                    );
                     exercise.name = Some(KnowledgeBaseFile::open(&path)?);
                 }
                 KnowledgeBaseFile::ExerciseDescription(..) => {
-                    let path = lesson_root.join(format!("{short_id}{EXERCISE_DESCRIPTION_SUFFIX}"));
+                    let path = KnowledgeBaseFile::resolve_path(
+                        lesson_root,
+                        &format!("{short_id}{EXERCISE_DESCRIPTION_SUFFIX}"),
+                    );
                     exercise.description = Some(KnowledgeBaseFile::open(&path)?);
                 }
                 KnowledgeBaseFile::ExerciseType(..) => {
-                    let path = lesson_root.join(format!("{short_id}{EXERCISE_TYPE_SUFFIX}"));
+                    let path = KnowledgeBaseFile::resolve_path(
+                        lesson_root,
+                        &format!("{short_id}{EXERCISE_TYPE_SUFFIX}"),
+                    );
                     exercise.exercise_type = Some(KnowledgeBaseFile::open(&path)?);
                 }
+                KnowledgeBaseFile::ExerciseMultipleChoice(..) => {
+                    let path = KnowledgeBaseFile::resolve_path(
+                        lesson_root,
+                        &format!("{short_id}{EXERCISE_MULTIPLE_CHOICE_SUFFIX}"),
+                    );
+                    let multiple_choice: MultipleChoice = KnowledgeBaseFile::open(&path)?;
+                    multiple_choice.verify().context(format!(
+                        "invalid multiple-choice question in {}",
+                        path.display()
+                    ))?;
+                    exercise.multiple_choice = Some(multiple_choice);
+                }
+                KnowledgeBaseFile::ExerciseReverse(..) => {
+                    let path = KnowledgeBaseFile::resolve_path(
+                        lesson_root,
+                        &format!("{short_id}{EXERCISE_REVERSE_SUFFIX}"),
+                    );
+                    exercise.reverse = Some(KnowledgeBaseFile::open(&path)?);
+                }
+                KnowledgeBaseFile::ExerciseRenamedFrom(..) => {
+                    let path = KnowledgeBaseFile::resolve_path(
+                        lesson_root,
+                        &format!("{short_id}{EXERCISE_RENAMED_FROM_SUFFIX}"),
+                    );
+                    exercise.renamed_from = KnowledgeBaseFile::open(&path)?;
+                }
                 _ => {}
             }
         }
-        Ok(exercise)
+
+        // Generate one exercise for each deletion index if the exercise is a cloze note.
+        if !files
+            .iter()
+            .any(|file| matches!(file, KnowledgeBaseFile::ExerciseCloze(_)))
+        {
+            return Ok(vec![exercise]);
+        }
+        let path = lesson_root.join(format!("{short_id}{EXERCISE_CLOZE_SUFFIX}"));
+        let note = read_to_string(&path)
+            .context(format!("failed to read cloze note {}", path.display()))?;
+        let indices =
+            cloze_indices(&note).context(format!("invalid cloze note {}", path.display()))?;
+        ensure!(
+            !indices.is_empty(),
+            "cloze note {} has no deletions",
+            path.display()
+        );
+        Ok(indices
+            .into_iter()
+            .map(|index| KnowledgeBaseExercise {
+                short_id: cloze_short_id(short_id, index),
+                name: exercise
+                    .name
+                    .as_ref()
+                    .map(|name| format!("{name} (c{index})")),
+                cloze: Some(KnowledgeBaseCloze {
+                    note_short_id: short_id.to_string(),
+                    note: note.clone(),
+                    index,
+                }),
+                renamed_from: exercise
+                    .renamed_from
+                    .iter()
+                    .map(|old_id| cloze_short_id(old_id, index).into())
+                    .collect(),
+                ..exercise.clone()
+            })
+            .collect())
     }
 }
 
@@ -349,6 +670,21 @@ impl KnowledgeBaseExercise {
 /// `lesson.instructoins.md` and `lesson.material.md` contain the instructions and material of the
 /// lesson.
 ///
+/// Instead of writing one file per property and two files per flashcard, the whole lesson can be
+/// written in a single `lesson.md` file. See [`LessonMarkdown`] for the details of the format. The
+/// properties and exercises in this file are combined with those defined in separate files, with
+/// the values in `lesson.md` taking precedence.
+///
+/// Writing `true` to the `lesson.reverse.json` file makes the lesson generate a reversed exercise,
+/// which shows the back of the flashcard and asks for the front, for every flashcard with a back.
+/// The reversed exercises are placed in a separate lesson with short ID
+/// `<SHORT_LESSON_ID>_reversed`, which depends on the original lesson. Each reversed exercise keeps
+/// the short ID of the original, and both exercises are siblings, so they are never scheduled in the
+/// same batch.
+///
+/// When a lesson directory is renamed, writing the previous full ID of the lesson to the
+/// `lesson.renamed_from.json` file moves the data of the lesson and its exercises to their new IDs.
+///
 /// None of the `<SHORT_LESSON_ID>.lesson` directories should contain a `lesson_manifest.json` file,
 /// as that file would indicate to Trane that this is a regular lesson and not a generated lesson.
 #[derive(Clone)]
@@ -394,18 +730,30 @@ pub struct KnowledgeBaseLesson {
 
     /// The default exercise type for exercises in this lesson.
     pub default_exercise_type: Option<ExerciseType>,
+
+    /// Whether to generate a reversed exercise for each flashcard with a back in this lesson.
+    pub reverse: bool,
+
+    /// The full IDs the lesson had before it was renamed. The exercises in the lesson are renamed
+    /// along with it.
+    pub renamed_from: Vec<Ustr>,
 }
 //>@knowledge-base-lesson
 
 impl KnowledgeBaseLesson {
-    // Filters out exercises that don't have both a front file. Exercises without a back file are
-    // allowed, as it is not required to have one.
+    // Filters out exercises that don't have a front file or a multiple-choice question. Exercises
+    // without a back file are allowed, as it is not required to have one.
     fn filter_matching_exercises(exercise_files: &mut HashMap<String, Vec<KnowledgeBaseFile>>) {
         let mut to_remove = Vec::new();
         for (short_id, files) in &*exercise_files {
-            let has_front = files
-                .iter()
-                .any(|file| matches!(file, KnowledgeBaseFile::ExerciseFront(_)));
+            let has_front = files.iter().any(|file| {
+                matches!(
+                    file,
+                    KnowledgeBaseFile::ExerciseFront(_)
+                        | KnowledgeBaseFile::ExerciseMultipleChoice(_)
+                        | KnowledgeBaseFile::ExerciseCloze(_)
+                )
+            });
             if !has_front {
                 to_remove.push(short_id.clone());
             }
@@ -435,6 +783,8 @@ impl KnowledgeBaseLesson {
             has_instructions: false,
             has_material: false,
             default_exercise_type: None,
+            reverse: false,
+            renamed_from: vec![],
         };
 
         // Iterate through the lesson files found in the lesson directory and set the corresponding
@@ -442,35 +792,50 @@ impl KnowledgeBaseLesson {
         for lesson_file in files {
             match lesson_file {
                 KnowledgeBaseFile::LessonDependencies => {
-                    let path = lesson_root.join(LESSON_DEPENDENCIES_FILE);
+                    let path =
+                        KnowledgeBaseFile::resolve_path(lesson_root, LESSON_DEPENDENCIES_FILE);
                     lesson.dependencies = KnowledgeBaseFile::open(&path)?;
                 }
                 KnowledgeBaseFile::LessonEncompassed => {
-                    let path = lesson_root.join(LESSON_ENCOMPASSED_FILE);
+                    let path =
+                        KnowledgeBaseFile::resolve_path(lesson_root, LESSON_ENCOMPASSED_FILE);
                     lesson.encompassed = KnowledgeBaseFile::open(&path)?;
                 }
                 KnowledgeBaseFile::LessonSuperseded => {
-                    let path = lesson_root.join(LESSON_SUPERSEDED_FILE);
+                    let path = KnowledgeBaseFile::resolve_path(lesson_root, LESSON_SUPERSEDED_FILE);
                     lesson.superseded = KnowledgeBaseFile::open(&path)?;
                 }
                 KnowledgeBaseFile::LessonName => {
-                    let path = lesson_root.join(LESSON_NAME_FILE);
+                    let path = KnowledgeBaseFile::resolve_path(lesson_root, LESSON_NAME_FILE);
                     lesson.name = Some(KnowledgeBaseFile::open(&path)?);
                 }
                 KnowledgeBaseFile::LessonDescription => {
-                    let path = lesson_root.join(LESSON_DESCRIPTION_FILE);
+                    let path =
+                        KnowledgeBaseFile::resolve_path(lesson_root, LESSON_DESCRIPTION_FILE);
                     lesson.description = Some(KnowledgeBaseFile::open(&path)?);
                 }
                 KnowledgeBaseFile::LessonMetadata => {
-                    let path = lesson_root.join(LESSON_METADATA_FILE);
+                    let path = KnowledgeBaseFile::resolve_path(lesson_root, LESSON_METADATA_FILE);
                     lesson.metadata = Some(KnowledgeBaseFile::open(&path)?);
                 }
                 KnowledgeBaseFile::LessonInstructions => lesson.has_instructions = true,
                 KnowledgeBaseFile::LessonMaterial => lesson.has_material = true,
                 KnowledgeBaseFile::LessonDefaultExerciseType => {
-                    let path = lesson_root.join(LESSON_DEFAULT_EXERCISE_TYPE_FILE);
+                    let path = KnowledgeBaseFile::resolve_path(
+                        lesson_root,
+                        LESSON_DEFAULT_EXERCISE_TYPE_FILE,
+                    );
                     lesson.default_exercise_type = Some(KnowledgeBaseFile::open(&path)?);
                 }
+                KnowledgeBaseFile::LessonRenamedFrom => {
+                    let path =
+                        KnowledgeBaseFile::resolve_path(lesson_root, LESSON_RENAMED_FROM_FILE);
+                    lesson.renamed_from = KnowledgeBaseFile::open(&path)?;
+                }
+                KnowledgeBaseFile::LessonReverse => {
+                    let path = KnowledgeBaseFile::resolve_path(lesson_root, LESSON_REVERSE_FILE);
+                    lesson.reverse = KnowledgeBaseFile::open(&path)?;
+                }
                 _ => {} // grcov-excl-line
             }
         }
@@ -498,7 +863,11 @@ impl KnowledgeBaseLesson {
                 | KnowledgeBaseFile::ExerciseBack(ref short_id)
                 | KnowledgeBaseFile::ExerciseName(ref short_id)
                 | KnowledgeBaseFile::ExerciseDescription(ref short_id)
-                | KnowledgeBaseFile::ExerciseType(ref short_id) => {
+                | KnowledgeBaseFile::ExerciseType(ref short_id)
+                | KnowledgeBaseFile::ExerciseMultipleChoice(ref short_id)
+                | KnowledgeBaseFile::ExerciseCloze(ref short_id)
+                | KnowledgeBaseFile::ExerciseReverse(ref short_id)
+                | KnowledgeBaseFile::ExerciseRenamedFrom(ref short_id) => {
                     exercise_files
                         .entry(short_id.clone())
                         .or_insert_with(Vec::new)
@@ -509,7 +878,7 @@ impl KnowledgeBaseLesson {
         }
 
         // Create the knowledge base lesson.
-        let lesson =
+        let mut lesson =
             Self::create_lesson(lesson_root, short_lesson_id, course_manifest, &lesson_files)?;
 
         // Remove exercises for the empty short ID. This can happen if the user has a file named
@@ -519,10 +888,10 @@ impl KnowledgeBaseLesson {
         // Filter out exercises that don't have both a front and back file and create the knowledge
         // base exercises.
         Self::filter_matching_exercises(&mut exercise_files);
-        let exercises = exercise_files
+        let mut exercises = exercise_files
             .into_iter()
             .map(|(short_id, files)| {
-                KnowledgeBaseExercise::create_exercise(
+                KnowledgeBaseExercise::create_exercises(
                     lesson_root,
                     &short_id,
                     short_lesson_id,
@@ -530,9 +899,325 @@ impl KnowledgeBaseLesson {
                     &files,
                 )
             })
-            .collect::<Result<Vec<_>>>()?;
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        // The exercises generated from cloze notes cannot have the same ID as other exercises.
+        let mut short_ids = HashSet::new();
+        for exercise in &exercises {
+            ensure!(
+                short_ids.insert(exercise.short_id.clone()),
+                "exercise {} in lesson {} is defined more than once",
+                exercise.short_id,
+                lesson_root.display()
+            );
+        }
+
+        // Add the properties and exercises declared in the `lesson.md` file, if it exists.
+        if lesson_files.contains(&KnowledgeBaseFile::LessonMarkdown) {
+            let path = lesson_root.join(LESSON_MARKDOWN_FILE);
+            let lesson_markdown = LessonMarkdown::open(&path)?;
+            lesson.apply_front_matter(lesson_markdown.front_matter);
+            for card in lesson_markdown.cards {
+                if exercises.iter().any(|e| e.short_id == card.short_id) {
+                    return Err(anyhow!(
+                        "exercise {} in {} is also defined in separate files",
+                        card.short_id,
+                        path.display()
+                    ));
+                }
+                exercises.push(KnowledgeBaseExercise {
+                    short_id: card.short_id,
+                    short_lesson_id,
+                    course_id: course_manifest.id,
+                    front_file: path.to_str().unwrap_or_default().to_string(),
+                    back_file: card
+                        .back
+                        .as_ref()
+                        .map(|_| path.to_str().unwrap_or_default().to_string()),
+                    name: None,
+                    description: None,
+                    exercise_type: None,
+                    inline_content: Some((card.front, card.back)),
+                    multiple_choice: None,
+                    cloze: None,
+                    reverse: None,
+                    renamed_from: vec![],
+                });
+            }
+        }
         Ok((lesson, exercises))
     }
+
+    /// Overrides the properties of the lesson with the values set in the front matter of the
+    /// `lesson.md` file.
+    fn apply_front_matter(&mut self, front_matter: LessonFrontMatter) {
+        if let Some(name) = front_matter.name {
+            self.name = Some(name);
+        }
+        if let Some(description) = front_matter.description {
+            self.description = Some(description);
+        }
+        if let Some(dependencies) = front_matter.dependencies {
+            self.dependencies = dependencies;
+        }
+        if let Some(encompassed) = front_matter.encompassed {
+            self.encompassed = encompassed;
+        }
+        if let Some(superseded) = front_matter.superseded {
+            self.superseded = superseded;
+        }
+        if let Some(metadata) = front_matter.metadata {
+            self.metadata = Some(metadata);
+        }
+        if let Some(default_exercise_type) = front_matter.default_exercise_type {
+            self.default_exercise_type = Some(default_exercise_type);
+        }
+        if let Some(reverse) = front_matter.reverse {
+            self.reverse = reverse;
+        }
+        if let Some(renamed_from) = front_matter.renamed_from {
+            self.renamed_from = renamed_from;
+        }
+    }
+
+    /// Returns the lesson containing the reversed exercises generated from this lesson and its
+    /// exercises, or `None` if no reversed exercises are generated. The reversed lesson depends on
+    /// this lesson.
+    fn reversed(
+        &self,
+        exercises: &[KnowledgeBaseExercise],
+    ) -> Option<(KnowledgeBaseLesson, Vec<KnowledgeBaseExercise>)> {
+        let reversed_exercises = exercises
+            .iter()
+            .filter_map(|exercise| exercise.reversed(self.reverse))
+            .collect::<Vec<_>>();
+        if reversed_exercises.is_empty() {
+            return None;
+        }
+        let lesson = KnowledgeBaseLesson {
+            short_id: reversed_lesson_short_id(&self.short_id),
+            course_id: self.course_id,
+            dependencies: vec![self.short_id],
+            encompassed: vec![],
+            superseded: vec![],
+            name: self.name.as_ref().map(|name| format!("{name} (Reversed)")),
+            description: self.description.clone(),
+            metadata: self.metadata.clone(),
+            has_instructions: false,
+            has_material: false,
+            default_exercise_type: self.default_exercise_type.clone(),
+            reverse: false,
+            renamed_from: self
+                .renamed_from
+                .iter()
+                .map(|old_id| format!("{old_id}{REVERSED_LESSON_SUFFIX}").into())
+                .collect(),
+        };
+        Some((lesson, reversed_exercises))
+    }
+}
+
+/// The properties of a lesson that can be set in the YAML front matter of a `lesson.md` file. Each
+/// of them mirrors one of the fields of [`KnowledgeBaseLesson`].
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LessonFrontMatter {
+    /// The name of the lesson.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// The description of the lesson.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// The dependencies of the lesson, either as full IDs or as short IDs of other lessons in the
+    /// course.
+    #[serde(default)]
+    pub dependencies: Option<Vec<Ustr>>,
+
+    /// The courses or lessons encompassed by the lesson and their respective weights.
+    #[serde(default)]
+    pub encompassed: Option<Vec<(Ustr, f32)>>,
+
+    /// The courses or lessons superseded by the lesson.
+    #[serde(default)]
+    pub superseded: Option<Vec<Ustr>>,
+
+    /// The metadata of the lesson.
+    #[serde(default)]
+    pub metadata: Option<BTreeMap<String, Vec<String>>>,
+
+    /// The default exercise type for the exercises in the lesson.
+    #[serde(default)]
+    pub default_exercise_type: Option<ExerciseType>,
+
+    /// Whether to generate reversed exercises for the flashcards in the lesson.
+    #[serde(default)]
+    pub reverse: Option<bool>,
+
+    /// The full IDs the lesson had before it was renamed.
+    #[serde(default)]
+    pub renamed_from: Option<Vec<Ustr>>,
+}
+
+/// A single flashcard read from a `lesson.md` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LessonMarkdownCard {
+    /// The short ID of the exercise.
+    pub short_id: String,
+
+    /// The front of the flashcard.
+    pub front: String,
+
+    /// The back of the flashcard, if any.
+    pub back: Option<String>,
+}
+
+/// The contents of a `lesson.md` file, which contains an entire knowledge base lesson.
+///
+/// The file optionally starts with a YAML front matter, enclosed between two `---` lines, that sets
+/// the properties of the lesson in [`LessonFrontMatter`]. The rest of the file contains the cards,
+/// which are introduced by a heading of the form `## <SHORT_EXERCISE_ID>` or separated by a line
+/// containing only `===`. Cards without a heading are assigned the short ID `card_<HASH>`, where
+/// `HASH` is made of the first eight hexadecimal digits of the SHA-256 hash of the card's front, so
+/// that the ID does not change when cards are inserted or reordered. Editing the front of such a
+/// card changes its ID, so cards whose front might change should be given a heading. Inside each
+/// card, a line containing only `---` separates the front of the flashcard from the back. For
+/// example:
+///
+/// ```markdown
+/// ---
+/// name: Numbers
+/// dependencies: [greetings]
+/// ---
+///
+/// ## one
+/// uno
+/// ---
+/// one
+///
+/// ## two
+/// dos
+/// ---
+/// two
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LessonMarkdown {
+    /// The properties of the lesson declared in the front matter.
+    pub front_matter: LessonFrontMatter,
+
+    /// The cards in the lesson, in the order in which they appear in the file.
+    pub cards: Vec<LessonMarkdownCard>,
+}
+
+impl LessonMarkdown {
+    /// Returns the short ID of a card without a heading, derived from the hash of its front.
+    fn content_id(front: &str) -> String {
+        Sha256::digest(front.as_bytes())
+            .iter()
+            .take(4)
+            .fold("card_".to_string(), |mut id, byte| {
+                let _ = write!(id, "{byte:02x}");
+                id
+            })
+    }
+
+    /// Adds the card whose lines have been collected to the list of cards. Cards without a heading
+    /// and without any content are the result of blank space before a delimiter and are ignored.
+    fn push_card(
+        &mut self,
+        heading: Option<String>,
+        front: &[&str],
+        back: Option<&[&str]>,
+    ) -> Result<()> {
+        let front = front.join("\n").trim().to_string();
+        let back = back
+            .map(|back| back.join("\n").trim().to_string())
+            .filter(|back| !back.is_empty());
+        if heading.is_none() && front.is_empty() && back.is_none() {
+            return Ok(());
+        }
+
+        let short_id = heading.unwrap_or_else(|| Self::content_id(&front));
+        if front.is_empty() {
+            return Err(anyhow!("card {short_id} has an empty front"));
+        }
+        if self.cards.iter().any(|card| card.short_id == short_id) {
+            return Err(anyhow!("duplicate card {short_id}"));
+        }
+        self.cards.push(LessonMarkdownCard {
+            short_id,
+            front,
+            back,
+        });
+        Ok(())
+    }
+
+    /// Parses the contents of a `lesson.md` file.
+    pub fn parse(contents: &str) -> Result<Self> {
+        // Read the front matter, if the file starts with one.
+        let mut lesson_markdown = Self::default();
+        let mut lines = contents.trim_start_matches('\u{feff}').lines().peekable();
+        if lines
+            .peek()
+            .is_some_and(|line| line.trim_end() == FRONT_MATTER_DELIMITER)
+        {
+            lines.next();
+            let mut yaml = vec![];
+            loop {
+                match lines.next() {
+                    Some(line) if line.trim_end() == FRONT_MATTER_DELIMITER => break,
+                    Some(line) => yaml.push(line),
+                    None => return Err(anyhow!("the front matter is not closed")),
+                }
+            }
+            let yaml = yaml.join("\n");
+            if !yaml.trim().is_empty() {
+                lesson_markdown.front_matter =
+                    serde_yaml::from_str(&yaml).context("cannot parse the front matter")?;
+            }
+        }
+
+        // Split the rest of the file into cards and each card into its front and back.
+        let mut heading = None;
+        let mut front = vec![];
+        let mut back: Option<Vec<&str>> = None;
+        for line in lines {
+            if let Some(short_id) = line.strip_prefix(CARD_HEADING_PREFIX) {
+                lesson_markdown.push_card(heading.take(), &front, back.as_deref())?;
+                let short_id = short_id.trim();
+                if short_id.is_empty() || short_id.contains(char::is_whitespace) {
+                    return Err(anyhow!("invalid card heading \"{}\"", line.trim()));
+                }
+                heading = Some(short_id.to_string());
+                front.clear();
+                back = None;
+            } else if line.trim_end() == CARD_DELIMITER {
+                lesson_markdown.push_card(heading.take(), &front, back.as_deref())?;
+                front.clear();
+                back = None;
+            } else if line.trim_end() == FRONT_MATTER_DELIMITER && back.is_none() {
+                back = Some(vec![]);
+            } else if let Some(back) = &mut back {
+                back.push(line);
+            } else {
+                front.push(line);
+            }
+        }
+        lesson_markdown.push_card(heading, &front, back.as_deref())?;
+        Ok(lesson_markdown)
+    }
+
+    /// Reads and parses the `lesson.md` file at the given path.
+    pub fn open(path: &Path) -> Result<Self> {
+        let display = path.display();
+        let contents =
+            read_to_string(path).context(format!("cannot open knowledge base file {display}"))?;
+        Self::parse(&contents).context(format!("cannot parse knowledge base file {display}"))
+    }
 }
 
 impl From<KnowledgeBaseLesson> for LessonManifest {
@@ -540,6 +1225,7 @@ impl From<KnowledgeBaseLesson> for LessonManifest {
     fn from(lesson: KnowledgeBaseLesson) -> Self {
         Self {
             id: format!("{}::{}", lesson.course_id, lesson.short_id).into(),
+            renamed_from: lesson.renamed_from,
             course_id: lesson.course_id,
             dependencies: lesson.dependencies,
             encompassed: lesson.encompassed,
@@ -664,6 +1350,34 @@ impl GenerateManifests for KnowledgeBaseConfig {
             }
         }
 
+        // Generate the lessons with the reversed exercises. Each reversed exercise is a sibling of
+        // the exercise from which it was generated.
+        let mut sibling_groups = UstrMap::default();
+        let reversed_lessons = lessons
+            .values()
+            .filter_map(|(lesson, exercises)| lesson.reversed(exercises))
+            .collect::<Vec<_>>();
+        for (reversed_lesson, reversed_exercises) in reversed_lessons {
+            ensure!(
+                !lessons.contains_key(&reversed_lesson.short_id),
+                "cannot generate reversed lesson {} because a lesson with the same ID exists",
+                reversed_lesson.short_id
+            );
+            for exercise in &reversed_exercises {
+                let forward_id: Ustr = format!(
+                    "{}::{}::{}",
+                    exercise.course_id, reversed_lesson.dependencies[0], exercise.short_id
+                )
+                .into();
+                sibling_groups.insert(forward_id, forward_id);
+                sibling_groups.insert(exercise.exercise_id(), forward_id);
+            }
+            lessons.insert(
+                reversed_lesson.short_id,
+                (reversed_lesson, reversed_exercises),
+            );
+        }
+
         // Convert all the dependencies to full lesson IDs.
         let short_ids: HashSet<Ustr> = lessons.keys().copied().collect();
         KnowledgeBaseConfig::convert_to_full_ids(course_manifest, &short_ids, &mut lessons);
@@ -676,7 +1390,14 @@ impl GenerateManifests for KnowledgeBaseConfig {
                 let exercise_manifests = exercises
                     .into_iter()
                     .map(|e| {
-                        e.to_exercise_manifest(lesson.default_exercise_type.clone(), self.inlined)
+                        let mut manifest = e.to_exercise_manifest(
+                            lesson.default_exercise_type.clone(),
+                            self.inlined,
+                        )?;
+                        if let Some(sibling_group) = sibling_groups.get(&manifest.id) {
+                            manifest.sibling_group = Some(*sibling_group);
+                        }
+                        Ok(manifest)
                     })
                     .collect::<Result<Vec<_>>>()?;
                 Ok((lesson_manifest, exercise_manifests))
@@ -696,7 +1417,7 @@ impl GenerateManifests for KnowledgeBaseConfig {
 mod test {
     use anyhow::Result;
     use std::{
-        fs::{self, Permissions},
+        fs::{self, File, Permissions},
         io::{BufWriter, Write},
         os::unix::prelude::PermissionsExt,
     };
@@ -757,6 +1478,18 @@ mod test {
             KnowledgeBaseFile::LessonSuperseded,
             KnowledgeBaseFile::try_from(LESSON_SUPERSEDED_FILE).unwrap(),
         );
+        assert_eq!(
+            KnowledgeBaseFile::LessonMarkdown,
+            KnowledgeBaseFile::try_from(LESSON_MARKDOWN_FILE).unwrap(),
+        );
+        assert_eq!(
+            KnowledgeBaseFile::LessonName,
+            KnowledgeBaseFile::try_from("lesson.name.yaml").unwrap(),
+        );
+        assert_eq!(
+            KnowledgeBaseFile::ExerciseType("ex1".to_string()),
+            KnowledgeBaseFile::try_from("ex1.type.yml").unwrap(),
+        );
         assert_eq!(
             KnowledgeBaseFile::LessonEncompassed,
             KnowledgeBaseFile::try_from(LESSON_ENCOMPASSED_FILE).unwrap(),
@@ -806,6 +1539,30 @@ mod test {
             KnowledgeBaseFile::try_from(format!("{}{}", "ex1", EXERCISE_TYPE_SUFFIX).as_str())
                 .unwrap(),
         );
+        assert_eq!(
+            KnowledgeBaseFile::ExerciseMultipleChoice("ex1".to_string()),
+            KnowledgeBaseFile::try_from("ex1.multiple_choice.yaml").unwrap(),
+        );
+        assert_eq!(
+            KnowledgeBaseFile::ExerciseCloze("ex1".to_string()),
+            KnowledgeBaseFile::try_from("ex1.cloze.md").unwrap(),
+        );
+        assert_eq!(
+            KnowledgeBaseFile::ExerciseReverse("ex1".to_string()),
+            KnowledgeBaseFile::try_from("ex1.reverse.json").unwrap(),
+        );
+        assert_eq!(
+            KnowledgeBaseFile::LessonReverse,
+            KnowledgeBaseFile::try_from(LESSON_REVERSE_FILE).unwrap(),
+        );
+        assert_eq!(
+            KnowledgeBaseFile::LessonRenamedFrom,
+            KnowledgeBaseFile::try_from(LESSON_RENAMED_FROM_FILE).unwrap(),
+        );
+        assert_eq!(
+            KnowledgeBaseFile::ExerciseRenamedFrom("ex1".to_string()),
+            KnowledgeBaseFile::try_from("ex1.renamed_from.json").unwrap(),
+        );
 
         // Parse exercise file names with invalid exercise names.
         assert!(KnowledgeBaseFile::try_from("ex1").is_err());
@@ -826,9 +1583,12 @@ mod test {
             has_instructions: true,
             has_material: true,
             default_exercise_type: Some(ExerciseType::Declarative),
+            reverse: false,
+            renamed_from: vec![],
         };
         let expected_manifest = LessonManifest {
             id: "course1::lesson1".into(),
+            renamed_from: vec![],
             course_id: "course1".into(),
             name: "Name".into(),
             description: Some("Description".into()),
@@ -859,14 +1619,21 @@ mod test {
             name: Some("Name".into()),
             description: Some("Description".into()),
             exercise_type: Some(ExerciseType::Procedural),
+            inline_content: None,
+            multiple_choice: None,
+            cloze: None,
+            reverse: None,
+            renamed_from: vec![],
         };
         let expected_manifest = ExerciseManifest {
             id: "course1::lesson1::ex1".into(),
+            renamed_from: vec![],
             lesson_id: "course1::lesson1".into(),
             course_id: "course1".into(),
             name: "Name".into(),
             description: Some("Description".into()),
             exercise_type: ExerciseType::Procedural,
+            sibling_group: None,
             exercise_asset: ExerciseAsset::FlashcardAsset {
                 front_path: "ex1.front.md".into(),
                 back_path: Some("ex1.back.md".into()),
@@ -894,6 +1661,11 @@ mod test {
             name: Some("Name".into()),
             description: Some("Description".into()),
             exercise_type: Some(ExerciseType::Procedural),
+            inline_content: None,
+            multiple_choice: None,
+            cloze: None,
+            reverse: None,
+            renamed_from: vec![],
         };
         let manifest = exercise.to_exercise_manifest(None, true)?;
         assert_eq!(
@@ -918,6 +1690,11 @@ mod test {
             name: Some("Name".into()),
             description: Some("Description".into()),
             exercise_type: Some(ExerciseType::Procedural),
+            inline_content: None,
+            multiple_choice: None,
+            cloze: None,
+            reverse: None,
+            renamed_from: vec![],
         };
         let manifest = exercise.to_exercise_manifest(None, true);
         assert!(manifest.is_err());
@@ -935,6 +1712,11 @@ mod test {
             name: Some("Name".into()),
             description: Some("Description".into()),
             exercise_type: None,
+            inline_content: None,
+            multiple_choice: None,
+            cloze: None,
+            reverse: None,
+            renamed_from: vec![],
         };
 
         // Exercise has its own type, ignore lesson default.
@@ -969,6 +1751,7 @@ mod test {
         // Create an example course manifest.
         let course_manifest = CourseManifest {
             id: "course1".into(),
+            renamed_from: vec![],
             name: "Course 1".into(),
             dependencies: vec![],
             encompassed: vec![],
@@ -996,6 +1779,8 @@ mod test {
             has_instructions: false,
             has_material: false,
             default_exercise_type: None,
+            reverse: false,
+            renamed_from: vec![],
         };
         let exercise = KnowledgeBaseExercise {
             short_id: "ex1".into(),
@@ -1006,6 +1791,11 @@ mod test {
             name: Some("Name".into()),
             description: Some("Description".into()),
             exercise_type: Some(ExerciseType::Procedural),
+            inline_content: None,
+            multiple_choice: None,
+            cloze: None,
+            reverse: None,
+            renamed_from: vec![],
         };
         let mut lesson_map = UstrMap::default();
         lesson_map.insert("lesson1".into(), (lesson, vec![exercise]));
@@ -1147,6 +1937,7 @@ mod test {
         // Create a test course manifest.
         let course_manifest = CourseManifest {
             id: "course1".into(),
+            renamed_from: vec![],
             name: "Course 1".into(),
             dependencies: vec![],
             encompassed: vec![],
@@ -1194,4 +1985,535 @@ mod test {
         );
         Ok(())
     }
+
+    /// Verifies parsing a `lesson.md` file with front matter and both styles of cards.
+    #[test]
+    fn parse_lesson_markdown() -> Result<()> {
+        let lesson_markdown = LessonMarkdown::parse(indoc::indoc! {"
+            ---
+            name: Numbers
+            dependencies: [greetings, other::lesson]
+            encompassed: [[greetings, 0.5]]
+            metadata:
+              tags: [spanish]
+            default_exercise_type: Declarative
+            ---
+
+            ## one
+            uno
+            ---
+            one
+
+            with two paragraphs
+            ## two
+            dos
+            ===
+            tres
+            ---
+            three
+            ---
+            still three
+            ===
+            ===
+        "})?;
+        assert_eq!(
+            lesson_markdown.front_matter,
+            LessonFrontMatter {
+                name: Some("Numbers".into()),
+                dependencies: Some(vec!["greetings".into(), "other::lesson".into()]),
+                encompassed: Some(vec![("greetings".into(), 0.5)]),
+                metadata: Some(BTreeMap::from([("tags".into(), vec!["spanish".into()])])),
+                default_exercise_type: Some(ExerciseType::Declarative),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            lesson_markdown.cards,
+            vec![
+                LessonMarkdownCard {
+                    short_id: "one".into(),
+                    front: "uno".into(),
+                    back: Some("one\n\nwith two paragraphs".into()),
+                },
+                LessonMarkdownCard {
+                    short_id: "two".into(),
+                    front: "dos".into(),
+                    back: None,
+                },
+                LessonMarkdownCard {
+                    short_id: "card_3d5f1d09".into(),
+                    front: "tres".into(),
+                    back: Some("three\n---\nstill three".into()),
+                },
+            ]
+        );
+
+        // A file without front matter only contains cards.
+        let lesson_markdown = LessonMarkdown::parse("uno\n---\none\n===\ndos\n")?;
+        assert_eq!(lesson_markdown.front_matter, LessonFrontMatter::default());
+        assert_eq!(lesson_markdown.cards.len(), 2);
+        assert_eq!(lesson_markdown.cards[1].short_id, "card_c1299854");
+
+        // The IDs of cards without a heading do not depend on their position.
+        let lesson_markdown = LessonMarkdown::parse("cero\n===\nuno\n---\none\n===\ndos\n")?;
+        assert_eq!(lesson_markdown.cards[2].short_id, "card_c1299854");
+        Ok(())
+    }
+
+    /// Verifies that invalid `lesson.md` files are rejected.
+    #[test]
+    fn parse_invalid_lesson_markdown() {
+        assert!(LessonMarkdown::parse("---\nname: Numbers\n").is_err());
+        assert!(LessonMarkdown::parse("---\nunknown: field\n---\n").is_err());
+        assert!(LessonMarkdown::parse("## one\nuno\n## one\none\n").is_err());
+        assert!(LessonMarkdown::parse("## one\n---\none\n").is_err());
+        assert!(LessonMarkdown::parse("## two words\ndos\n").is_err());
+        assert!(LessonMarkdown::parse("##  \ndos\n").is_err());
+        assert!(LessonMarkdown::parse("dos\n===\ndos\n").is_err());
+    }
+
+    /// Verifies opening a lesson directory with a `lesson.md` file.
+    #[test]
+    fn open_lesson_dir_with_markdown() -> Result<()> {
+        // Create a lesson with a name file, an exercise in separate files, and a `lesson.md` file
+        // that overrides the name.
+        let course_dir = tempfile::tempdir()?;
+        let lesson_dir = course_dir.path().join("lesson1.lesson");
+        fs::create_dir(&lesson_dir)?;
+        write_json(&"Old name", &lesson_dir.join(LESSON_NAME_FILE))?;
+        write_json(&"Description", &lesson_dir.join(LESSON_DESCRIPTION_FILE))?;
+        fs::write(lesson_dir.join("ex1.front.md"), "Front 1")?;
+        let markdown_path = lesson_dir.join(LESSON_MARKDOWN_FILE);
+        fs::write(
+            &markdown_path,
+            "---\nname: New name\ndependencies: [lesson2]\n---\n## ex2\nFront 2\n---\nBack 2\n",
+        )?;
+        let course_manifest = CourseManifest {
+            id: "course1".into(),
+            renamed_from: vec![],
+            name: "Course 1".into(),
+            dependencies: vec![],
+            encompassed: vec![],
+            superseded: vec![],
+            description: None,
+            authors: None,
+            metadata: None,
+            course_instructions: None,
+            course_material: None,
+            generator_config: None,
+        };
+
+        // Verify the lesson and the exercises.
+        let (lesson, mut exercises) =
+            KnowledgeBaseLesson::open_lesson(&lesson_dir, &course_manifest, "lesson1".into())?;
+        assert_eq!(lesson.name, Some("New name".into()));
+        assert_eq!(lesson.description, Some("Description".into()));
+        assert_eq!(lesson.dependencies, vec![Ustr::from("lesson2")]);
+        exercises.sort_by(|a, b| a.short_id.cmp(&b.short_id));
+        assert_eq!(exercises.len(), 2);
+        assert_eq!(exercises[0].inline_content, None);
+        let manifest = exercises[1].to_exercise_manifest(None, false)?;
+        assert_eq!(manifest.id, "course1::lesson1::ex2");
+        assert_eq!(
+            manifest.exercise_asset,
+            ExerciseAsset::InlineFlashcardAsset {
+                front_content: "Front 2".into(),
+                back_content: Some("Back 2".into()),
+            }
+        );
+
+        // An exercise cannot be defined both in `lesson.md` and in separate files.
+        fs::write(&markdown_path, "## ex1\nFront 1\n")?;
+        assert!(
+            KnowledgeBaseLesson::open_lesson(&lesson_dir, &course_manifest, "lesson1".into())
+                .is_err()
+        );
+        Ok(())
+    }
+
+    /// Verifies opening a lesson directory whose property files are written in YAML.
+    #[test]
+    fn open_lesson_dir_with_yaml_files() -> Result<()> {
+        let course_dir = tempfile::tempdir()?;
+        let lesson_dir = course_dir.path().join("lesson1.lesson");
+        fs::create_dir(&lesson_dir)?;
+        fs::write(lesson_dir.join("lesson.name.yaml"), "Name # A comment.\n")?;
+        fs::write(
+            lesson_dir.join("lesson.dependencies.yml"),
+            "- lesson2\n- lesson3\n",
+        )?;
+        fs::write(lesson_dir.join("ex1.front.md"), "Front")?;
+        fs::write(lesson_dir.join("ex1.type.yaml"), "Declarative")?;
+        let course_manifest = CourseManifest {
+            id: "course1".into(),
+            renamed_from: vec![],
+            name: "Course 1".into(),
+            dependencies: vec![],
+            encompassed: vec![],
+            superseded: vec![],
+            description: None,
+            authors: None,
+            metadata: None,
+            course_instructions: None,
+            course_material: None,
+            generator_config: None,
+        };
+
+        let (lesson, exercises) =
+            KnowledgeBaseLesson::open_lesson(&lesson_dir, &course_manifest, "lesson1".into())?;
+        assert_eq!(lesson.name, Some("Name".into()));
+        assert_eq!(
+            lesson.dependencies,
+            vec![Ustr::from("lesson2"), Ustr::from("lesson3")]
+        );
+        assert_eq!(exercises.len(), 1);
+        assert_eq!(exercises[0].exercise_type, Some(ExerciseType::Declarative));
+        Ok(())
+    }
+
+    /// Verifies opening a lesson directory with a multiple-choice exercise.
+    #[test]
+    fn open_lesson_dir_with_multiple_choice() -> Result<()> {
+        let course_dir = tempfile::tempdir()?;
+        let lesson_dir = course_dir.path().join("lesson1.lesson");
+        fs::create_dir(&lesson_dir)?;
+        fs::write(
+            lesson_dir.join("ex1.multiple_choice.yaml"),
+            "prompt: What is 2 + 2?\noptions:\n  - text: \"3\"\n  - text: \"4\"\n    \
+            correct: true\n    explanation: Two plus two is four.\n",
+        )?;
+        fs::write(
+            lesson_dir.join("ex2.multiple_choice.json"),
+            r#"{"prompt": "Pick one", "options": [{"text": "A"}]}"#,
+        )?;
+        let course_manifest = CourseManifest {
+            id: "course1".into(),
+            renamed_from: vec![],
+            name: "Course 1".into(),
+            dependencies: vec![],
+            encompassed: vec![],
+            superseded: vec![],
+            description: None,
+            authors: None,
+            metadata: None,
+            course_instructions: None,
+            course_material: None,
+            generator_config: None,
+        };
+
+        // Questions without a correct option are rejected.
+        assert!(
+            KnowledgeBaseLesson::open_lesson(&lesson_dir, &course_manifest, "lesson1".into())
+                .is_err()
+        );
+        fs::remove_file(lesson_dir.join("ex2.multiple_choice.json"))?;
+
+        // The exercise does not need a front file and generates a multiple-choice asset.
+        let (_, exercises) =
+            KnowledgeBaseLesson::open_lesson(&lesson_dir, &course_manifest, "lesson1".into())?;
+        assert_eq!(exercises.len(), 1);
+        let manifest = exercises[0].to_exercise_manifest(None, false)?;
+        let ExerciseAsset::MultipleChoiceAsset(question) = manifest.exercise_asset else {
+            panic!("expected a multiple-choice asset");
+        };
+        assert_eq!(question.prompt, "What is 2 + 2?");
+        assert_eq!(question.options.len(), 2);
+        assert!(question.options[1].correct);
+        assert_eq!(
+            question.options[1].explanation.as_deref(),
+            Some("Two plus two is four.")
+        );
+        Ok(())
+    }
+
+    /// Verifies opening a lesson directory with a note with cloze deletions.
+    #[test]
+    fn open_lesson_dir_with_cloze() -> Result<()> {
+        let course_dir = tempfile::tempdir()?;
+        let lesson_dir = course_dir.path().join("lesson1.lesson");
+        fs::create_dir(&lesson_dir)?;
+        fs::write(
+            lesson_dir.join("capital.cloze.md"),
+            "The capital of {{c2::France}} is {{c1::Paris}}.",
+        )?;
+        fs::write(lesson_dir.join("capital.name.json"), r#""Capitals""#)?;
+        let course_manifest = CourseManifest {
+            id: "course1".into(),
+            renamed_from: vec![],
+            name: "Course 1".into(),
+            dependencies: vec![],
+            encompassed: vec![],
+            superseded: vec![],
+            description: None,
+            authors: None,
+            metadata: None,
+            course_instructions: None,
+            course_material: None,
+            generator_config: None,
+        };
+
+        // Verify one exercise is generated for each deletion.
+        let (_, mut exercises) =
+            KnowledgeBaseLesson::open_lesson(&lesson_dir, &course_manifest, "lesson1".into())?;
+        exercises.sort_by(|a, b| a.short_id.cmp(&b.short_id));
+        assert_eq!(exercises.len(), 2);
+        assert_eq!(exercises[0].short_id, "capital_c1");
+        assert_eq!(exercises[1].short_id, "capital_c2");
+        assert_eq!(exercises[1].name, Some("Capitals (c2)".into()));
+        let manifest = exercises[0].to_exercise_manifest(None, false)?;
+        assert_eq!(manifest.id, "course1::lesson1::capital_c1");
+        assert_eq!(
+            manifest.exercise_asset,
+            ExerciseAsset::ClozeAsset {
+                note_id: "course1::lesson1::capital".into(),
+                note: "The capital of {{c2::France}} is {{c1::Paris}}.".into(),
+                index: 1,
+            }
+        );
+        assert_eq!(
+            manifest.sibling_group,
+            Some("course1::lesson1::capital".into())
+        );
+        assert_eq!(
+            exercises[1]
+                .to_exercise_manifest(None, false)?
+                .sibling_group,
+            manifest.sibling_group
+        );
+
+        // Adding a deletion before the others does not change the IDs of the existing exercises.
+        fs::write(
+            lesson_dir.join("capital.cloze.md"),
+            "{{c3::Europe}}: the capital of {{c2::France}} is {{c1::Paris}}.",
+        )?;
+        let (_, exercises) =
+            KnowledgeBaseLesson::open_lesson(&lesson_dir, &course_manifest, "lesson1".into())?;
+        let short_ids: HashSet<String> = exercises.into_iter().map(|e| e.short_id).collect();
+        assert_eq!(
+            short_ids,
+            HashSet::from([
+                "capital_c1".to_string(),
+                "capital_c2".to_string(),
+                "capital_c3".to_string()
+            ])
+        );
+
+        // Generated IDs cannot clash with other exercises, and notes need deletions.
+        fs::write(lesson_dir.join("capital_c1.front.md"), "Front")?;
+        assert!(
+            KnowledgeBaseLesson::open_lesson(&lesson_dir, &course_manifest, "lesson1".into())
+                .is_err()
+        );
+        fs::remove_file(lesson_dir.join("capital_c1.front.md"))?;
+        fs::write(lesson_dir.join("capital.cloze.md"), "No deletions")?;
+        assert!(
+            KnowledgeBaseLesson::open_lesson(&lesson_dir, &course_manifest, "lesson1".into())
+                .is_err()
+        );
+        Ok(())
+    }
+
+    /// Verifies generating the reversed exercises of a lesson in a separate lesson.
+    #[test]
+    fn generate_reversed_lesson() -> Result<()> {
+        // Create a lesson with a flashcard, a flashcard without a back, a flashcard that opts out
+        // of being reversed, and a card in the `lesson.md` file.
+        let course_dir = tempfile::tempdir()?;
+        let lesson_dir = course_dir.path().join("lesson1.lesson");
+        fs::create_dir(&lesson_dir)?;
+        fs::write(lesson_dir.join("ex1.front.md"), "uno")?;
+        fs::write(lesson_dir.join("ex1.back.md"), "one")?;
+        fs::write(lesson_dir.join("ex1.name.json"), r#""Number""#)?;
+        fs::write(lesson_dir.join("ex2.front.md"), "dos")?;
+        fs::write(lesson_dir.join("ex3.front.md"), "tres")?;
+        fs::write(lesson_dir.join("ex3.back.md"), "three")?;
+        fs::write(lesson_dir.join("ex3.reverse.json"), "false")?;
+        fs::write(
+            lesson_dir.join(LESSON_MARKDOWN_FILE),
+            "---\nname: Numbers\n---\n## four\ncuatro\n---\nfour\n",
+        )?;
+        fs::write(lesson_dir.join(LESSON_REVERSE_FILE), "true")?;
+        let course_manifest = CourseManifest {
+            id: "course1".into(),
+            renamed_from: vec![],
+            name: "Course 1".into(),
+            dependencies: vec![],
+            encompassed: vec![],
+            superseded: vec![],
+            description: None,
+            authors: None,
+            metadata: None,
+            course_instructions: None,
+            course_material: None,
+            generator_config: None,
+        };
+        let config = KnowledgeBaseConfig { inlined: false };
+        let generated = config.generate_manifests(
+            course_dir.path(),
+            &course_manifest,
+            &UserPreferences::default(),
+        )?;
+
+        // The reversed lesson depends on the original lesson and only contains the reversed
+        // flashcards with a back.
+        assert_eq!(generated.lessons.len(), 2);
+        let (reversed_lesson, mut reversed_exercises) = generated
+            .lessons
+            .iter()
+            .find(|(lesson, _)| lesson.id == "course1::lesson1_reversed")
+            .cloned()
+            .unwrap();
+        assert_eq!(reversed_lesson.dependencies, vec!["course1::lesson1"]);
+        assert_eq!(reversed_lesson.name, "Numbers (Reversed)");
+        reversed_exercises.sort_by_key(|exercise| exercise.id);
+        assert_eq!(reversed_exercises.len(), 2);
+        assert_eq!(reversed_exercises[0].id, "course1::lesson1_reversed::ex1");
+        assert_eq!(reversed_exercises[0].name, "Number (Reversed)");
+        assert_eq!(
+            reversed_exercises[0].exercise_asset,
+            ExerciseAsset::FlashcardAsset {
+                front_path: lesson_dir.join("ex1.back.md").to_str().unwrap().into(),
+                back_path: Some(lesson_dir.join("ex1.front.md").to_str().unwrap().into()),
+            }
+        );
+        assert_eq!(reversed_exercises[1].id, "course1::lesson1_reversed::four");
+        assert_eq!(
+            reversed_exercises[1].exercise_asset,
+            ExerciseAsset::InlineFlashcardAsset {
+                front_content: "four".into(),
+                back_content: Some("cuatro".into()),
+            }
+        );
+
+        // The original and reversed exercises are siblings.
+        let (_, exercises) = generated
+            .lessons
+            .iter()
+            .find(|(lesson, _)| lesson.id == "course1::lesson1")
+            .unwrap();
+        for exercise in exercises {
+            let expected = if exercise.id == "course1::lesson1::ex1"
+                || exercise.id == "course1::lesson1::four"
+            {
+                Some(exercise.id)
+            } else {
+                None
+            };
+            assert_eq!(exercise.sibling_group, expected);
+        }
+        assert_eq!(
+            reversed_exercises[0].sibling_group,
+            Some("course1::lesson1::ex1".into())
+        );
+
+        // The reversed lesson cannot have the same ID as an existing lesson.
+        fs::create_dir(course_dir.path().join("lesson1_reversed.lesson"))?;
+        assert!(
+            config
+                .generate_manifests(
+                    course_dir.path(),
+                    &course_manifest,
+                    &UserPreferences::default()
+                )
+                .is_err()
+        );
+        Ok(())
+    }
+
+    /// Verifies that the previous IDs of lessons and exercises are added to their manifests.
+    #[test]
+    fn generate_renamed_units() -> Result<()> {
+        // Create a lesson with a flashcard and a cloze note that were renamed, and which generates
+        // reversed exercises.
+        let course_dir = tempfile::tempdir()?;
+        let lesson_dir = course_dir.path().join("lesson1.lesson");
+        fs::create_dir(&lesson_dir)?;
+        fs::write(lesson_dir.join("ex1.front.md"), "uno")?;
+        fs::write(lesson_dir.join("ex1.back.md"), "one")?;
+        fs::write(
+            lesson_dir.join("ex1.renamed_from.json"),
+            r#"["course1::lesson1::old_ex1"]"#,
+        )?;
+        fs::write(lesson_dir.join("note.cloze.md"), "{{c1::uno}} is one")?;
+        fs::write(
+            lesson_dir.join("note.renamed_from.json"),
+            r#"["course1::lesson1::old_note"]"#,
+        )?;
+        fs::write(
+            lesson_dir.join(LESSON_RENAMED_FROM_FILE),
+            r#"["course1::old_lesson"]"#,
+        )?;
+        fs::write(lesson_dir.join(LESSON_REVERSE_FILE), "true")?;
+        let course_manifest = CourseManifest {
+            id: "course1".into(),
+            renamed_from: vec![],
+            name: "Course 1".into(),
+            dependencies: vec![],
+            encompassed: vec![],
+            superseded: vec![],
+            description: None,
+            authors: None,
+            metadata: None,
+            course_instructions: None,
+            course_material: None,
+            generator_config: None,
+        };
+        let generated = KnowledgeBaseConfig { inlined: false }.generate_manifests(
+            course_dir.path(),
+            &course_manifest,
+            &UserPreferences::default(),
+        )?;
+
+        // Find the manifest of the exercise with the given ID.
+        let exercise = |exercise_id: &str| {
+            generated
+                .lessons
+                .iter()
+                .flat_map(|(_, exercises)| exercises)
+                .find(|exercise| exercise.id == exercise_id)
+                .unwrap()
+                .clone()
+        };
+        let lesson = |lesson_id: &str| {
+            generated
+                .lessons
+                .iter()
+                .find(|(lesson, _)| lesson.id == lesson_id)
+                .unwrap()
+                .0
+                .clone()
+        };
+
+        // The previous IDs of the lessons and exercises are set, including the generated ones.
+        assert_eq!(
+            lesson("course1::lesson1").renamed_from,
+            vec!["course1::old_lesson"]
+        );
+        assert_eq!(
+            lesson("course1::lesson1_reversed").renamed_from,
+            vec!["course1::old_lesson_reversed"]
+        );
+        assert_eq!(
+            exercise("course1::lesson1::ex1").renamed_from,
+            vec!["course1::lesson1::old_ex1"]
+        );
+        assert_eq!(
+            exercise("course1::lesson1_reversed::ex1").renamed_from,
+            vec!["course1::lesson1_reversed::old_ex1"]
+        );
+        assert_eq!(
+            exercise("course1::lesson1::note_c1").renamed_from,
+            vec!["course1::lesson1::old_note_c1"]
+        );
+
+        // The front matter of the `lesson.md` file overrides the file.
+        fs::write(
+            lesson_dir.join(LESSON_MARKDOWN_FILE),
+            "---\nrenamed_from: [course1::other_lesson]\n---\n",
+        )?;
+        let (lesson, _) =
+            KnowledgeBaseLesson::open_lesson(&lesson_dir, &course_manifest, "lesson1".into())?;
+        assert_eq!(lesson.renamed_from, vec!["course1::other_lesson"]);
+        Ok(())
+    }
 }
@@ -541,6 +541,7 @@ impl LiteracyLesson {
         // Create the lesson manifest.
         let lesson_manifest = LessonManifest {
             id: lesson_id,
+            renamed_from: vec![],
             dependencies,
             encompassed,
             superseded,
@@ -558,11 +559,13 @@ impl LiteracyLesson {
         // Create the exercise manifest.
         let exercise_manifest = ExerciseManifest {
             id: format!("{lesson_id}::exercise").into(),
+            renamed_from: vec![],
             lesson_id: lesson_manifest.id,
             course_id: course_manifest.id,
             name: lesson_name,
             description: self.description.clone(),
             exercise_type: ExerciseType::Procedural,
+            sibling_group: None,
             exercise_asset: ExerciseAsset::LiteracyAsset {
                 lesson_type: LiteracyLessonType::Reading,
                 examples: self.examples.clone(),
@@ -624,6 +627,7 @@ impl LiteracyLesson {
         // Create the lesson manifest.
         let lesson_manifest = LessonManifest {
             id: lesson_id,
+            renamed_from: vec![],
             dependencies,
             encompassed,
             superseded,
@@ -641,11 +645,13 @@ impl LiteracyLesson {
         // Create the exercise manifest.
         let exercise_manifest = ExerciseManifest {
             id: format!("{lesson_id}::exercise").into(),
+            renamed_from: vec![],
             lesson_id: lesson_manifest.id,
             course_id: course_manifest.id,
             name: lesson_name,
             description: self.description.clone(),
             exercise_type: ExerciseType::Procedural,
+            sibling_group: None,
             exercise_asset: ExerciseAsset::LiteracyAsset {
                 lesson_type: LiteracyLessonType::Dictation,
                 examples: self.examples.clone(),
@@ -813,6 +819,7 @@ mod test {
         // Manifest with a name.
         let course_manifest = CourseManifest {
             id: "course_id".into(),
+            renamed_from: vec![],
             name: "Course Name".into(),
             dependencies: vec![],
             encompassed: vec![],
@@ -829,6 +836,7 @@ mod test {
         // Manifest with an empty name.
         let course_manifest = CourseManifest {
             id: "course_id".into(),
+            renamed_from: vec![],
             name: String::new(),
             dependencies: vec![],
             encompassed: vec![],
@@ -1083,6 +1091,7 @@ mod test {
         });
         let course_manifest = CourseManifest {
             id: "literacy_course".into(),
+            renamed_from: vec![],
             name: "Literacy Course".into(),
             dependencies: vec![],
             encompassed: vec![],
@@ -1111,6 +1120,7 @@ mod test {
                 (
                     LessonManifest {
                         id: "literacy_course::lesson_0::dictation".into(),
+                        renamed_from: vec![],
                         dependencies: vec!["literacy_course::lesson_0::reading".into()],
                         encompassed: vec![
                             (Ustr::from("literacy_course::lesson_0::dictation"), 1.0),
@@ -1129,11 +1139,13 @@ mod test {
                     },
                     vec![ExerciseManifest {
                         id: "literacy_course::lesson_0::dictation::exercise".into(),
+                        renamed_from: vec![],
                         lesson_id: "literacy_course::lesson_0::dictation".into(),
                         course_id: "literacy_course".into(),
                         name: "Literacy Course - lesson_0 - Dictation".into(),
                         description: None,
                         exercise_type: ExerciseType::Procedural,
+                        sibling_group: None,
                         exercise_asset: ExerciseAsset::LiteracyAsset {
                             lesson_type: LiteracyLessonType::Dictation,
                             examples: vec![
@@ -1170,6 +1182,7 @@ mod test {
                 (
                     LessonManifest {
                         id: "literacy_course::lesson_0::reading".into(),
+                        renamed_from: vec![],
                         dependencies: vec!["other_lesson".into()],
                         encompassed: vec![
                             (Ustr::from("literacy_course::lesson_0::reading"), 1.0),
@@ -1188,11 +1201,13 @@ mod test {
                     },
                     vec![ExerciseManifest {
                         id: "literacy_course::lesson_0::reading::exercise".into(),
+                        renamed_from: vec![],
                         lesson_id: "literacy_course::lesson_0::reading".into(),
                         course_id: "literacy_course".into(),
                         name: "Literacy Course - lesson_0 - Reading".into(),
                         description: None,
                         exercise_type: ExerciseType::Procedural,
+                        sibling_group: None,
                         exercise_asset: ExerciseAsset::LiteracyAsset {
                             lesson_type: LiteracyLessonType::Reading,
                             examples: vec![
@@ -1229,6 +1244,7 @@ mod test {
                 (
                     LessonManifest {
                         id: "literacy_course::lesson_1::dictation".into(),
+                        renamed_from: vec![],
                         dependencies: vec![
                             "literacy_course::lesson_0::dictation".into(),
                             "literacy_course::lesson_1::reading".into(),
@@ -1250,11 +1266,13 @@ mod test {
                     },
                     vec![ExerciseManifest {
                         id: "literacy_course::lesson_1::dictation::exercise".into(),
+                        renamed_from: vec![],
                         lesson_id: "literacy_course::lesson_1::dictation".into(),
                         course_id: "literacy_course".into(),
                         name: "Literacy Course - lesson_1 - Dictation".into(),
                         description: None,
                         exercise_type: ExerciseType::Procedural,
+                        sibling_group: None,
                         exercise_asset: ExerciseAsset::LiteracyAsset {
                             lesson_type: LiteracyLessonType::Dictation,
                             examples: vec![
@@ -1291,6 +1309,7 @@ mod test {
                 (
                     LessonManifest {
                         id: "literacy_course::lesson_1::reading".into(),
+                        renamed_from: vec![],
                         dependencies: vec![
                             "literacy_course::lesson_0::reading".into(),
                             "other_lesson".into(),
@@ -1312,11 +1331,13 @@ mod test {
                     },
                     vec![ExerciseManifest {
                         id: "literacy_course::lesson_1::reading::exercise".into(),
+                        renamed_from: vec![],
                         lesson_id: "literacy_course::lesson_1::reading".into(),
                         course_id: "literacy_course".into(),
                         name: "Literacy Course - lesson_1 - Reading".into(),
                         description: None,
                         exercise_type: ExerciseType::Procedural,
+                        sibling_group: None,
                         exercise_asset: ExerciseAsset::LiteracyAsset {
                             lesson_type: LiteracyLessonType::Reading,
                             examples: vec![
@@ -1372,6 +1393,7 @@ mod test {
         });
         let course_manifest = CourseManifest {
             id: "literacy_course".into(),
+            renamed_from: vec![],
             name: "Literacy Course".into(),
             dependencies: vec![],
             encompassed: vec![],
@@ -1400,6 +1422,7 @@ mod test {
                 (
                     LessonManifest {
                         id: "literacy_course::lesson_0::reading".into(),
+                        renamed_from: vec![],
                         dependencies: vec!["other_lesson".into()],
                         encompassed: vec![
                             (Ustr::from("literacy_course::lesson_0::reading"), 1.0),
@@ -1418,11 +1441,13 @@ mod test {
                     },
                     vec![ExerciseManifest {
                         id: "literacy_course::lesson_0::reading::exercise".into(),
+                        renamed_from: vec![],
                         lesson_id: "literacy_course::lesson_0::reading".into(),
                         course_id: "literacy_course".into(),
                         name: "Literacy Course - lesson_0 - Reading".into(),
                         description: None,
                         exercise_type: ExerciseType::Procedural,
+                        sibling_group: None,
                         exercise_asset: ExerciseAsset::LiteracyAsset {
                             lesson_type: LiteracyLessonType::Reading,
                             examples: vec![
@@ -1459,6 +1484,7 @@ mod test {
                 (
                     LessonManifest {
                         id: "literacy_course::lesson_1::reading".into(),
+                        renamed_from: vec![],
                         dependencies: vec![
                             "literacy_course::lesson_0::reading".into(),
                             "other_lesson".into(),
@@ -1480,11 +1506,13 @@ mod test {
                     },
                     vec![ExerciseManifest {
                         id: "literacy_course::lesson_1::reading::exercise".into(),
+                        renamed_from: vec![],
                         lesson_id: "literacy_course::lesson_1::reading".into(),
                         course_id: "literacy_course".into(),
                         name: "Literacy Course - lesson_1 - Reading".into(),
                         description: None,
                         exercise_type: ExerciseType::Procedural,
+                        sibling_group: None,
                         exercise_asset: ExerciseAsset::LiteracyAsset {
                             lesson_type: LiteracyLessonType::Reading,
                             examples: vec![
@@ -133,6 +133,7 @@ impl MusicPassage {
         // Create the lesson and exercise manifests for this passage and add them to the list.
         let lesson_manifest = LessonManifest {
             id: Self::generate_lesson_id(course_manifest, passage_path),
+            renamed_from: vec![],
             course_id: course_manifest.id,
             name: course_manifest.name.clone(),
             description: None,
@@ -145,11 +146,13 @@ impl MusicPassage {
         };
         let exercise_manifest = ExerciseManifest {
             id: Ustr::from(&format!("{}::exercise", lesson_manifest.id)),
+            renamed_from: vec![],
             lesson_id: lesson_manifest.id,
             course_id: course_manifest.id,
             name: course_manifest.name.clone(),
             description: None,
             exercise_type: ExerciseType::Procedural,
+            sibling_group: None,
             exercise_asset: music_asset.generate_exercise_asset(&self.start, &self.end),
         };
         lessons.push((lesson_manifest, vec![exercise_manifest]));
@@ -267,6 +270,7 @@ mod test {
     fn generate_lesson_id() {
         let course_manifest = CourseManifest {
             id: "course".into(),
+            renamed_from: vec![],
             name: "Course".to_string(),
             description: None,
             dependencies: vec![],
@@ -305,6 +309,7 @@ mod test {
     fn generate_lessons() {
         let course_manifest = CourseManifest {
             id: "course".into(),
+            renamed_from: vec![],
             name: "Course".to_string(),
             description: None,
             dependencies: vec![],
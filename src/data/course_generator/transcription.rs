@@ -231,6 +231,13 @@ impl TranscriptionConfig {
         }
     }
 
+    /// Returns the sibling group for the exercises generated from the given passage. All the
+    /// singing and transcription exercises for the same passage belong to the same group, so that
+    /// they are not scheduled in the same batch.
+    fn sibling_group(course_id: Ustr, asset_id: &str, passage_id: Option<usize>) -> Ustr {
+        Self::exercise_id(course_id, asset_id, passage_id)
+    }
+
     /// Returns the ID of the singing lesson for the given course.
     fn singing_lesson_id(course_id: Ustr) -> Ustr {
         Ustr::from(&format!("{course_id}::singing"))
@@ -246,11 +253,17 @@ impl TranscriptionConfig {
         if passages.intervals.is_empty() {
             return vec![ExerciseManifest {
                 id: Self::exercise_id(lesson_id, passages.asset.short_id(), None),
+                renamed_from: vec![],
                 lesson_id,
                 course_id: course_manifest.id,
                 name: format!("{} - Singing", course_manifest.name),
                 description: None,
                 exercise_type: ExerciseType::Procedural,
+                sibling_group: Some(Self::sibling_group(
+                    course_manifest.id,
+                    passages.asset.short_id(),
+                    None,
+                )),
                 exercise_asset: passages.generate_exercise_asset(
                     SINGING_DESCRIPTION,
                     "Start of passage",
@@ -266,11 +279,17 @@ impl TranscriptionConfig {
             .iter()
             .map(|(passage_id, (start, end))| ExerciseManifest {
                 id: Self::exercise_id(lesson_id, passages.asset.short_id(), Some(*passage_id)),
+                renamed_from: vec![],
                 lesson_id,
                 course_id: course_manifest.id,
                 name: format!("{} - Singing", course_manifest.name),
                 description: None,
                 exercise_type: ExerciseType::Procedural,
+                sibling_group: Some(Self::sibling_group(
+                    course_manifest.id,
+                    passages.asset.short_id(),
+                    Some(*passage_id),
+                )),
                 exercise_asset: passages.generate_exercise_asset(
                     SINGING_DESCRIPTION,
                     start,
@@ -297,6 +316,7 @@ impl TranscriptionConfig {
             .collect();
         let lesson_manifest = LessonManifest {
             id: Self::singing_lesson_id(course_manifest.id),
+            renamed_from: vec![],
             course_id: course_manifest.id,
             name: format!("{} - Singing", course_manifest.name),
             description: Some(SINGING_DESCRIPTION.to_string()),
@@ -345,11 +365,17 @@ impl TranscriptionConfig {
         if passages.intervals.is_empty() {
             return vec![ExerciseManifest {
                 id: Self::exercise_id(lesson_id, passages.asset.short_id(), None),
+                renamed_from: vec![],
                 lesson_id,
                 course_id: course_manifest.id,
                 name: format!("{} - Advanced Singing", course_manifest.name),
                 description: None,
                 exercise_type: ExerciseType::Procedural,
+                sibling_group: Some(Self::sibling_group(
+                    course_manifest.id,
+                    passages.asset.short_id(),
+                    None,
+                )),
                 exercise_asset: passages.generate_exercise_asset(
                     ADVANCED_SINGING_DESCRIPTION,
                     "Start of passage",
@@ -365,11 +391,17 @@ impl TranscriptionConfig {
             .iter()
             .map(|(passage_id, (start, end))| ExerciseManifest {
                 id: Self::exercise_id(lesson_id, passages.asset.short_id(), Some(*passage_id)),
+                renamed_from: vec![],
                 lesson_id,
                 course_id: course_manifest.id,
                 name: format!("{} - Advanced Singing", course_manifest.name),
                 description: None,
                 exercise_type: ExerciseType::Procedural,
+                sibling_group: Some(Self::sibling_group(
+                    course_manifest.id,
+                    passages.asset.short_id(),
+                    Some(*passage_id),
+                )),
                 exercise_asset: passages.generate_exercise_asset(
                     ADVANCED_SINGING_DESCRIPTION,
                     start,
@@ -389,6 +421,7 @@ impl TranscriptionConfig {
         // Generate the lesson manifest. The lesson depends on the singing lesson.
         let lesson_manifest = LessonManifest {
             id: Self::advanced_singing_lesson_id(course_manifest.id),
+            renamed_from: vec![],
             course_id: course_manifest.id,
             name: format!("{} - Advanced Singing", course_manifest.name),
             description: Some(ADVANCED_SINGING_DESCRIPTION.to_string()),
@@ -445,6 +478,7 @@ impl TranscriptionConfig {
         if passages.intervals.is_empty() {
             return vec![ExerciseManifest {
                 id: Self::exercise_id(lesson_id, passages.asset.short_id(), None),
+                renamed_from: vec![],
                 lesson_id,
                 course_id: course_manifest.id,
                 name: format!(
@@ -453,6 +487,11 @@ impl TranscriptionConfig {
                 ),
                 description: None,
                 exercise_type: ExerciseType::Procedural,
+                sibling_group: Some(Self::sibling_group(
+                    course_manifest.id,
+                    passages.asset.short_id(),
+                    None,
+                )),
                 exercise_asset: passages.generate_exercise_asset(
                     TRANSCRIPTION_DESCRIPTION,
                     "Start of passage",
@@ -468,6 +507,7 @@ impl TranscriptionConfig {
             .iter()
             .map(|(passage_id, (start, end))| ExerciseManifest {
                 id: Self::exercise_id(lesson_id, passages.asset.short_id(), Some(*passage_id)),
+                renamed_from: vec![],
                 lesson_id,
                 course_id: course_manifest.id,
                 name: format!(
@@ -476,6 +516,11 @@ impl TranscriptionConfig {
                 ),
                 description: None,
                 exercise_type: ExerciseType::Procedural,
+                sibling_group: Some(Self::sibling_group(
+                    course_manifest.id,
+                    passages.asset.short_id(),
+                    Some(*passage_id),
+                )),
                 exercise_asset: passages.generate_exercise_asset(
                     TRANSCRIPTION_DESCRIPTION,
                     start,
@@ -504,6 +549,7 @@ impl TranscriptionConfig {
         dependencies.push(Self::singing_lesson_id(course_manifest.id));
         let lesson_manifest = LessonManifest {
             id: Self::transcription_lesson_id(course_manifest.id, instrument),
+            renamed_from: vec![],
             course_id: course_manifest.id,
             name: format!(
                 "{} - Transcription - {}",
@@ -573,6 +619,7 @@ impl TranscriptionConfig {
         if passages.intervals.is_empty() {
             return vec![ExerciseManifest {
                 id: Self::exercise_id(lesson_id, passages.asset.short_id(), None),
+                renamed_from: vec![],
                 lesson_id,
                 course_id: course_manifest.id,
                 name: format!(
@@ -581,6 +628,11 @@ impl TranscriptionConfig {
                 ),
                 description: None,
                 exercise_type: ExerciseType::Procedural,
+                sibling_group: Some(Self::sibling_group(
+                    course_manifest.id,
+                    passages.asset.short_id(),
+                    None,
+                )),
                 exercise_asset: passages.generate_exercise_asset(
                     ADVANCED_TRANSCRIPTION_DESCRIPTION,
                     "Start of passage",
@@ -596,6 +648,7 @@ impl TranscriptionConfig {
             .iter()
             .map(|(passage_id, (start, end))| ExerciseManifest {
                 id: Self::exercise_id(lesson_id, passages.asset.short_id(), Some(*passage_id)),
+                renamed_from: vec![],
                 lesson_id,
                 course_id: course_manifest.id,
                 name: format!(
@@ -604,6 +657,11 @@ impl TranscriptionConfig {
                 ),
                 description: None,
                 exercise_type: ExerciseType::Procedural,
+                sibling_group: Some(Self::sibling_group(
+                    course_manifest.id,
+                    passages.asset.short_id(),
+                    Some(*passage_id),
+                )),
                 exercise_asset: passages.generate_exercise_asset(
                     ADVANCED_TRANSCRIPTION_DESCRIPTION,
                     start,
@@ -625,6 +683,7 @@ impl TranscriptionConfig {
         // transcription lesson for the instrument.
         let lesson_manifest = LessonManifest {
             id: Self::advanced_transcription_lesson_id(course_manifest.id, instrument),
+            renamed_from: vec![],
             course_id: course_manifest.id,
             name: format!(
                 "{} - Advanced Transcription - {}",
@@ -935,6 +994,20 @@ mod test {
         );
     }
 
+    /// Verifies generating the sibling group for the exercises of a passage.
+    #[test]
+    fn sibling_group() {
+        let course_id = Ustr::from("course_id");
+        assert_eq!(
+            TranscriptionConfig::sibling_group(course_id, "asset", Some(1)),
+            Ustr::from("course_id::asset::1"),
+        );
+        assert_eq!(
+            TranscriptionConfig::sibling_group(course_id, "asset", None),
+            Ustr::from("course_id::asset"),
+        );
+    }
+
     /// Verifies generating the asset for an exercise in the course.
     #[test]
     fn generate_exercise_asset() {
@@ -1153,6 +1226,7 @@ mod test {
 
         let course_manifest = CourseManifest {
             id: Ustr::from("testID"),
+            renamed_from: vec![],
             name: "Test".to_string(),
             description: None,
             dependencies: vec![],
@@ -1211,6 +1285,7 @@ mod test {
         });
         let course_manifest = CourseManifest {
             id: Ustr::from("testID"),
+            renamed_from: vec![],
             name: "Test".to_string(),
             description: None,
             dependencies: vec![],
@@ -12,7 +12,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use ustr::Ustr;
+use ustr::{Ustr, UstrMap};
 
 use crate::data::GetMetadata;
 
@@ -256,6 +256,26 @@ impl UnitFilter {
             _ => false,
         }
     }
+
+    /// Replaces the IDs of renamed units in the filter with their current IDs.
+    pub fn rename_units(&mut self, renamed_units: &UstrMap<Ustr>) {
+        let unit_ids = match self {
+            UnitFilter::CourseFilter {
+                course_ids: unit_ids,
+            }
+            | UnitFilter::LessonFilter {
+                lesson_ids: unit_ids,
+            }
+            | UnitFilter::Dependents { unit_ids }
+            | UnitFilter::Dependencies { unit_ids, .. } => unit_ids,
+            UnitFilter::MetadataFilter { .. } | UnitFilter::ReviewListFilter => return,
+        };
+        for unit_id in unit_ids.iter_mut() {
+            if let Some(new_id) = renamed_units.get(unit_id) {
+                *unit_id = *new_id;
+            }
+        }
+    }
 }
 
 //@<saved-filter
@@ -331,6 +351,17 @@ pub struct StudySession {
     pub parts: Vec<SessionPart>,
 }
 
+impl StudySession {
+    /// Replaces the IDs of renamed units in the filters of the session with their current IDs.
+    pub fn rename_units(&mut self, renamed_units: &UstrMap<Ustr>) {
+        for part in &mut self.parts {
+            if let SessionPart::UnitFilter { filter, .. } = part {
+                filter.rename_units(renamed_units);
+            }
+        }
+    }
+}
+
 /// A specific instance of a study session. It contains the start time of the session and its
 /// definition so that the scheduler knows the progress of the session.
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -388,7 +419,7 @@ pub enum ExerciseFilter {
 mod test {
     use chrono::{Duration, Utc};
     use std::collections::BTreeMap;
-    use ustr::Ustr;
+    use ustr::{Ustr, UstrMap};
 
     use crate::data::{
         GetMetadata,
@@ -1103,6 +1134,58 @@ mod test {
         assert_eq!(filter.clone(), filter);
     }
 
+    /// Verifies replacing the IDs of renamed units in filters and study sessions.
+    #[test]
+    fn rename_units() {
+        let mut renamed_units = UstrMap::default();
+        renamed_units.insert(Ustr::from("old1"), Ustr::from("new1"));
+        renamed_units.insert(Ustr::from("old2"), Ustr::from("new2"));
+
+        // Only the renamed IDs are replaced.
+        let mut filter = UnitFilter::LessonFilter {
+            lesson_ids: vec![Ustr::from("old1"), Ustr::from("other")],
+        };
+        filter.rename_units(&renamed_units);
+        assert_eq!(
+            filter,
+            UnitFilter::LessonFilter {
+                lesson_ids: vec![Ustr::from("new1"), Ustr::from("other")],
+            }
+        );
+
+        // Filters without IDs are not modified.
+        let mut filter = UnitFilter::ReviewListFilter;
+        filter.rename_units(&renamed_units);
+        assert_eq!(filter, UnitFilter::ReviewListFilter);
+
+        // The filters defined inline in a study session are updated.
+        let mut session = StudySession {
+            id: "session".into(),
+            description: "session".into(),
+            parts: vec![
+                SessionPart::UnitFilter {
+                    filter: UnitFilter::Dependencies {
+                        unit_ids: vec![Ustr::from("old2")],
+                        depth: 1,
+                    },
+                    duration: 1,
+                },
+                SessionPart::NoFilter { duration: 1 },
+            ],
+        };
+        session.rename_units(&renamed_units);
+        assert_eq!(
+            session.parts[0],
+            SessionPart::UnitFilter {
+                filter: UnitFilter::Dependencies {
+                    unit_ids: vec![Ustr::from("new2")],
+                    depth: 1,
+                },
+                duration: 1,
+            }
+        );
+    }
+
     /// Verifies selecting the right session part based on the time.
     #[test]
     fn get_session_part() {
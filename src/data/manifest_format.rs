@@ -0,0 +1,232 @@
+//! Defines the formats in which manifests and other files describing courses can be written.
+//!
+//! JSON is the format used by Trane when it writes manifests, but authors writing them by hand can
+//! also use YAML or TOML, which allow comments and are more forgiving. The format of a file is
+//! determined by its extension.
+
+use anyhow::{Context, Result, anyhow};
+use noyalib::compat::serde_yaml;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+/// A format in which manifests can be written.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ManifestFormat {
+    /// The JSON format, used by default.
+    Json,
+
+    /// The YAML format.
+    Yaml,
+
+    /// The TOML format.
+    Toml,
+}
+
+impl ManifestFormat {
+    /// All the supported formats, in the order in which they take precedence.
+    pub const ALL: [ManifestFormat; 3] = [
+        ManifestFormat::Json,
+        ManifestFormat::Yaml,
+        ManifestFormat::Toml,
+    ];
+
+    /// Returns the file extensions used by this format.
+    #[must_use]
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            ManifestFormat::Json => &["json"],
+            ManifestFormat::Yaml => &["yaml", "yml"],
+            ManifestFormat::Toml => &["toml"],
+        }
+    }
+
+    /// Returns the format of the file at the given path based on its extension, or `None` if the
+    /// extension does not belong to any of the supported formats.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extensions().contains(&extension))
+    }
+
+    /// Returns the format of the file with the given name if the name consists of the given stem
+    /// followed by one of the supported extensions. For example, `course_manifest.yaml` matches
+    /// the stem `course_manifest`.
+    #[must_use]
+    pub fn from_file_name(file_name: &str, stem: &str) -> Option<Self> {
+        let extension = file_name.strip_prefix(stem)?.strip_prefix('.')?;
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extensions().contains(&extension))
+    }
+
+    /// Returns the paths of all the files in the given directory whose name consists of the given
+    /// stem followed by one of the supported extensions, in order of precedence.
+    #[must_use]
+    pub fn find_files(directory: &Path, stem: &str) -> Vec<PathBuf> {
+        Self::ALL
+            .into_iter()
+            .flat_map(ManifestFormat::extensions)
+            .map(|extension| directory.join(format!("{stem}.{extension}")))
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    /// Deserializes the given contents. The error includes the line and column at which parsing
+    /// failed, if known.
+    pub fn parse<T: DeserializeOwned + 'static>(self, contents: &str) -> Result<T> {
+        match self {
+            // The errors returned by `serde_json` already include the line and column.
+            ManifestFormat::Json => serde_json::from_str(contents).map_err(Into::into),
+            ManifestFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| {
+                let message = e.to_string();
+                match e.location() {
+                    Some(location) if !message.contains("line") => anyhow!(
+                        "{message} at line {}, column {}",
+                        location.line(),
+                        location.column()
+                    ),
+                    _ => anyhow!(message),
+                }
+            }),
+            ManifestFormat::Toml => toml::from_str(contents).map_err(|e| {
+                let message = e.message().trim().to_string();
+                match e.span() {
+                    Some(span) => {
+                        let (line, column) = line_and_column(contents, span.start);
+                        anyhow!("{message} at line {line}, column {column}")
+                    }
+                    None => anyhow!(message),
+                }
+            }),
+        }
+    }
+}
+
+/// Returns the one-based line and column of the character at the given byte offset.
+fn line_and_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+/// Opens the file at the given path and deserializes it using the format indicated by its
+/// extension. Files with an unknown extension are read as JSON. The description of the file is
+/// used in the error messages.
+pub fn open_file<T: DeserializeOwned + 'static>(path: &Path, description: &str) -> Result<T> {
+    let display = path.display();
+    let contents = read_to_string(path).context(format!("cannot open {description} {display}"))?;
+    ManifestFormat::from_path(path)
+        .unwrap_or(ManifestFormat::Json)
+        .parse(&contents)
+        .map_err(|e| anyhow!("cannot parse {description} {display}: {e}"))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use anyhow::Result;
+    use std::{collections::BTreeMap, fs, path::Path};
+
+    use crate::data::manifest_format::*;
+
+    /// Verifies detecting the format of a file.
+    #[test]
+    fn detect_format() {
+        assert_eq!(
+            ManifestFormat::from_path(Path::new("a/course_manifest.yml")),
+            Some(ManifestFormat::Yaml)
+        );
+        assert_eq!(
+            ManifestFormat::from_path(Path::new("lesson_manifest.toml")),
+            Some(ManifestFormat::Toml)
+        );
+        assert_eq!(ManifestFormat::from_path(Path::new("lesson.md")), None);
+        assert_eq!(
+            ManifestFormat::from_file_name("course_manifest.json", "course_manifest"),
+            Some(ManifestFormat::Json)
+        );
+        assert_eq!(
+            ManifestFormat::from_file_name("course_manifest.yaml", "course_manifest"),
+            Some(ManifestFormat::Yaml)
+        );
+        assert_eq!(
+            ManifestFormat::from_file_name("course_manifest.txt", "course_manifest"),
+            None
+        );
+        assert_eq!(
+            ManifestFormat::from_file_name("lesson_manifest.json", "course_manifest"),
+            None
+        );
+    }
+
+    /// Verifies parsing the same value in all the formats.
+    #[test]
+    fn parse_formats() -> Result<()> {
+        let expected = BTreeMap::from([("name".to_string(), vec!["a".to_string()])]);
+        let json: BTreeMap<String, Vec<String>> =
+            ManifestFormat::Json.parse(r#"{"name": ["a"]}"#)?;
+        let yaml: BTreeMap<String, Vec<String>> =
+            ManifestFormat::Yaml.parse("# A comment.\nname:\n  - a\n")?;
+        let toml: BTreeMap<String, Vec<String>> =
+            ManifestFormat::Toml.parse("# A comment.\nname = [\"a\"]\n")?;
+        assert_eq!(json, expected);
+        assert_eq!(yaml, expected);
+        assert_eq!(toml, expected);
+        Ok(())
+    }
+
+    /// Verifies that parsing errors include the line at which they happened.
+    #[test]
+    fn errors_include_line() {
+        let json = ManifestFormat::Json.parse::<BTreeMap<String, String>>("{\n\"a\": \"b\",\n}");
+        assert!(json.unwrap_err().to_string().contains("line 3"));
+        let yaml = ManifestFormat::Yaml.parse::<BTreeMap<String, String>>("a: b\nc: [d\n");
+        assert!(yaml.unwrap_err().to_string().contains("line"));
+        let toml = ManifestFormat::Toml.parse::<BTreeMap<String, String>>("a = \"b\"\nc = \n");
+        assert!(
+            toml.unwrap_err()
+                .to_string()
+                .ends_with("at line 2, column 5")
+        );
+    }
+
+    /// Verifies finding and opening files in any of the formats.
+    #[test]
+    fn find_and_open_files() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        fs::write(temp_dir.path().join("name.yml"), "value")?;
+        fs::write(temp_dir.path().join("name.json"), "\"value\"")?;
+        fs::write(temp_dir.path().join("other.toml"), "")?;
+        let files = ManifestFormat::find_files(temp_dir.path(), "name");
+        assert_eq!(
+            files,
+            vec![
+                temp_dir.path().join("name.json"),
+                temp_dir.path().join("name.yml")
+            ]
+        );
+        assert_eq!(open_file::<String>(&files[0], "file")?, "value");
+        assert_eq!(open_file::<String>(&files[1], "file")?, "value");
+
+        let missing = open_file::<String>(&temp_dir.path().join("missing.json"), "file");
+        assert!(
+            missing
+                .unwrap_err()
+                .to_string()
+                .starts_with("cannot open file")
+        );
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@
 use thiserror::Error;
 use ustr::Ustr;
 
-use crate::data::UnitType;
+use crate::{data::UnitType, importer::csv::RowError};
 
 /// An error returned when dealing with the blacklist.
 #[derive(Debug, Error)]
@@ -15,11 +15,37 @@ pub enum BlacklistError {
     #[error("cannot get entries from the blacklist: {0}")]
     GetEntries(#[source] anyhow::Error),
 
+    #[error("cannot get blacklist entry for unit {0}: {1}")]
+    GetEntry(Ustr, #[source] anyhow::Error),
+
+    #[error("cannot remove expired entries from the blacklist: {0}")]
+    RemoveExpired(#[source] anyhow::Error),
+
     #[error("cannot remove entries with prefix {0} from the blacklist: {1}")]
     RemovePrefix(String, #[source] anyhow::Error),
 
     #[error("cannot remove unit {0} from the blacklist: {1}")]
     RemoveUnit(Ustr, #[source] anyhow::Error),
+
+    #[error("cannot rename unit {0} to {1}: {2}")]
+    RenameUnit(Ustr, Ustr, #[source] anyhow::Error),
+}
+
+/// An error returned when creating, installing, upgrading, or uninstalling course packages.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum CoursePackageError {
+    #[error("cannot create a package from course directory {0}: {1}")]
+    Create(String, #[source] anyhow::Error),
+
+    #[error("cannot install package {0}: {1}")]
+    Install(String, #[source] anyhow::Error),
+
+    #[error("cannot upgrade course with package {0}: {1}")]
+    Upgrade(String, #[source] anyhow::Error),
+
+    #[error("cannot uninstall course {0}: {1}")]
+    Uninstall(Ustr, #[source] anyhow::Error),
 }
 
 /// An error returned when dealing with the exercise scheduler.
@@ -32,10 +58,79 @@ pub enum ExerciseSchedulerError {
     #[error("cannot score exercise: {0}")]
     ScoreExercise(#[source] anyhow::Error),
 
+    #[error("cannot undo the last score: {0}")]
+    UndoScore(#[source] anyhow::Error),
+
+    #[error("cannot amend score for exercise {0}: {1}")]
+    AmendScore(Ustr, #[source] anyhow::Error),
+
     #[error("cannot get score for unit {0}: {1}")]
     GetUnitScore(Ustr, #[source] anyhow::Error),
 }
 
+/// An error returned when exporting courses or progress.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum ExportError {
+    #[error("cannot export the flashcards of course {0}: {1}")]
+    Flashcards(Ustr, #[source] anyhow::Error),
+
+    #[error("cannot write the exported flashcards: {0}")]
+    Write(#[source] anyhow::Error),
+
+    #[error("cannot export the progress report of course {0}: {1}")]
+    ProgressReport(Ustr, #[source] anyhow::Error),
+}
+
+/// An error returned when grading the answer to an exercise.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum GradingError {
+    #[error("cannot grade the answer to exercise {0}: {1}")]
+    Grade(Ustr, #[source] anyhow::Error),
+}
+
+/// An error returned when importing a course from another tool.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum ImportError {
+    #[error("cannot import Anki collection {0}: {1}")]
+    Anki(String, #[source] anyhow::Error),
+
+    #[error("cannot import file {0}: {1}")]
+    Csv(String, #[source] anyhow::Error),
+
+    #[error("cannot import file {0}: found {num_rows} invalid rows", num_rows = .1.len())]
+    CsvRows(String, Vec<RowError>),
+}
+
+/// An error returned when dealing with the data of units that are not in the course library.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum OrphanedDataError {
+    #[error("cannot generate the orphaned data report: {0}")]
+    GetReport(#[source] anyhow::Error),
+
+    #[error("cannot remove orphaned data: {0}")]
+    RemoveData(#[source] anyhow::Error),
+}
+
+/// An error returned when applying the retention policy to the practice history.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum RetentionError {
+    #[error("cannot apply the retention policy: {0}")]
+    ApplyPolicy(#[source] anyhow::Error),
+}
+
+/// An error returned when computing the practice statistics.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum StatisticsError {
+    #[error("cannot compute the practice statistics: {0}")]
+    GetStatistics(#[source] anyhow::Error),
+}
+
 /// An error returned when dealing with the practice stats.
 #[derive(Debug, Error)]
 #[allow(missing_docs)]
@@ -46,11 +141,26 @@ pub enum PracticeRewardsError {
     #[error("cannot record rewards: {0}")]
     RecordRewards(#[source] anyhow::Error),
 
+    #[error("cannot remove rewards: {0}")]
+    RemoveRewards(#[source] anyhow::Error),
+
     #[error("cannot trim rewards: {0}")]
     TrimReward(#[source] anyhow::Error),
 
+    #[error("cannot apply the retention policy: {0}")]
+    ApplyRetention(#[source] anyhow::Error),
+
+    #[error("cannot compact the database: {0}")]
+    Compact(#[source] anyhow::Error),
+
     #[error("cannot remove rewards from units matching prefix {0}: {1}")]
     RemovePrefix(String, #[source] anyhow::Error),
+
+    #[error("cannot rename unit {0} to {1}: {2}")]
+    RenameUnit(Ustr, Ustr, #[source] anyhow::Error),
+
+    #[error("cannot get the IDs of the units with recorded data: {0}")]
+    GetUnitIds(#[source] anyhow::Error),
 }
 
 /// An error returned when dealing with the practice stats.
@@ -60,14 +170,41 @@ pub enum PracticeStatsError {
     #[error("cannot get scores for unit {0}: {1}")]
     GetScores(Ustr, #[source] anyhow::Error),
 
+    #[error("cannot get trials between {0} and {1}: {2}")]
+    GetTrialsInRange(i64, i64, #[source] anyhow::Error),
+
+    #[error("cannot get trials from units matching prefix {0}: {1}")]
+    GetTrialsWithPrefix(String, #[source] anyhow::Error),
+
+    #[error("cannot get the daily trial counts: {0}")]
+    GetDailyCounts(#[source] anyhow::Error),
+
+    #[error("cannot get the trial history: {0}")]
+    GetHistory(#[source] anyhow::Error),
+
     #[error("cannot record scores: {0}")]
     RecordScore(#[source] anyhow::Error),
 
+    #[error("cannot remove trial for exercise {0}: {1}")]
+    RemoveTrial(Ustr, #[source] anyhow::Error),
+
     #[error("cannot trim scores: {0}")]
     TrimScores(#[source] anyhow::Error),
 
+    #[error("cannot apply the retention policy: {0}")]
+    ApplyRetention(#[source] anyhow::Error),
+
+    #[error("cannot compact the database: {0}")]
+    Compact(#[source] anyhow::Error),
+
     #[error("cannot remove scores from units matching prefix {0}: {1}")]
     RemovePrefix(String, #[source] anyhow::Error),
+
+    #[error("cannot rename unit {0} to {1}: {2}")]
+    RenameUnit(Ustr, Ustr, #[source] anyhow::Error),
+
+    #[error("cannot get the IDs of the units with recorded data: {0}")]
+    GetUnitIds(#[source] anyhow::Error),
 }
 
 /// An error returned when dealing with the practice deltas.
@@ -80,11 +217,26 @@ pub enum PracticeDeltasError {
     #[error("cannot record deltas: {0}")]
     RecordDelta(#[source] anyhow::Error),
 
+    #[error("cannot remove delta for exercise {0}: {1}")]
+    RemoveDelta(Ustr, #[source] anyhow::Error),
+
     #[error("cannot trim deltas: {0}")]
     TrimDeltas(#[source] anyhow::Error),
 
+    #[error("cannot apply the retention policy: {0}")]
+    ApplyRetention(#[source] anyhow::Error),
+
+    #[error("cannot compact the database: {0}")]
+    Compact(#[source] anyhow::Error),
+
     #[error("cannot remove deltas from units matching prefix {0}: {1}")]
     RemovePrefix(String, #[source] anyhow::Error),
+
+    #[error("cannot rename unit {0} to {1}: {2}")]
+    RenameUnit(Ustr, Ustr, #[source] anyhow::Error),
+
+    #[error("cannot get the IDs of the units with recorded data: {0}")]
+    GetUnitIds(#[source] anyhow::Error),
 }
 
 /// An error returned when dealing with user preferences.
@@ -108,7 +260,7 @@ pub enum RepositoryManagerError {
     #[error("cannot list repositories: {0}")]
     ListRepos(#[source] anyhow::Error),
 
-    #[error("cannot get repository with ID {0}: {1}")]
+    #[error("cannot remove repository with ID {0}: {1}")]
     RemoveRepo(String, #[source] anyhow::Error),
 
     #[error("cannot update repository with ID {0}: {1}")]
@@ -128,8 +280,31 @@ pub enum ReviewListError {
     #[error("cannot retrieve the entries from the review list: {0}")]
     GetEntries(#[source] anyhow::Error),
 
+    #[error("cannot retrieve the units automatically added to the review list: {0}")]
+    GetAutoAdditions(#[source] anyhow::Error),
+
+    #[error("cannot retrieve the review list entry for unit {0}: {1}")]
+    GetEntry(Ustr, #[source] anyhow::Error),
+
+    #[error("cannot record a successful review for unit {0}: {1}")]
+    RecordReview(Ustr, #[source] anyhow::Error),
+
     #[error("cannot remove unit {0} from the review list: {1}")]
     RemoveUnit(Ustr, #[source] anyhow::Error),
+
+    #[error("cannot rename unit {0} to {1}: {2}")]
+    RenameUnit(Ustr, Ustr, #[source] anyhow::Error),
+}
+
+/// An error returned when dealing with the state of the current study session.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum SessionStateError {
+    #[error("cannot load session state: {0}")]
+    LoadState(#[source] anyhow::Error),
+
+    #[error("cannot save session state: {0}")]
+    SaveState(#[source] anyhow::Error),
 }
 
 /// An error returned when downloading transcription assets.
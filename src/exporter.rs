@@ -0,0 +1,512 @@
+//! Defines utilities to export courses and progress to formats that can be used outside of Trane.
+//!
+//! The flashcards of knowledge base courses, and the examples and exceptions of literacy courses,
+//! can be exported to CSV or to the plain text format that Anki imports, with the metadata of the
+//! course and lesson preserved as tags. The exercises of any course can also be exported along with
+//! their current scores to a markdown report that can be read by people who don't use Trane.
+
+use anyhow::{Context, Result, anyhow};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    io::Write,
+};
+use ustr::Ustr;
+
+use crate::{
+    course_library::CourseLibrary,
+    data::{ExerciseAsset, cloze},
+    error::ExportError,
+    practice_stats::PracticeStats,
+    scheduler::ExerciseScheduler,
+};
+
+/// The metadata key whose values are exported as tags without the key as a prefix.
+pub const TAGS_METADATA_KEY: &str = "tags";
+
+/// The tag added to the flashcards generated from the exceptions of a literacy lesson.
+pub const EXCEPTION_TAG: &str = "exception";
+
+/// A single flashcard ready to be exported.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Flashcard {
+    /// The ID of the exercise from which the flashcard was generated.
+    pub exercise_id: Ustr,
+
+    /// The front of the flashcard.
+    pub front: String,
+
+    /// The back of the flashcard, if any.
+    pub back: Option<String>,
+
+    /// The tags of the flashcard.
+    pub tags: Vec<String>,
+}
+
+/// The formats to which flashcards can be exported.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum FlashcardFormat {
+    /// A CSV file with a header row and the exercise ID, front, back, and tags of each flashcard.
+    /// The tags are separated by semicolons, which matches the default list separator of the CSV
+    /// importer.
+    Csv,
+
+    /// A tab-separated file that can be imported into Anki. The file starts with the headers that
+    /// tell Anki how to interpret the columns, and the tags are separated by spaces.
+    AnkiText,
+}
+
+/// Converts the metadata of a course or lesson into tags. The values of the `tags` key are used as
+/// they are, and the rest of the values are prefixed by their key. Whitespace is replaced with
+/// underscores because Anki uses it to separate tags.
+fn metadata_to_tags(metadata: Option<&BTreeMap<String, Vec<String>>>, tags: &mut Vec<String>) {
+    let Some(metadata) = metadata else {
+        return;
+    };
+    for (key, values) in metadata {
+        for value in values {
+            let tag = if key == TAGS_METADATA_KEY {
+                value.clone()
+            } else {
+                format!("{key}::{value}")
+            };
+            let tag = tag.split_whitespace().collect::<Vec<_>>().join("_");
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+}
+
+/// Helper function to collect the flashcards of a course.
+fn collect_flashcards_helper(
+    library: &impl CourseLibrary,
+    course_id: Ustr,
+) -> Result<Vec<Flashcard>> {
+    // Collect the tags of the course.
+    let course_manifest = library
+        .get_course_manifest(course_id)
+        .ok_or_else(|| anyhow!("course not found"))?;
+    let mut course_tags = vec![];
+    metadata_to_tags(course_manifest.metadata.as_ref(), &mut course_tags);
+
+    // Generate the flashcards of each exercise. Literacy courses have multiple exercises with the
+    // same examples, so duplicate flashcards are skipped.
+    let mut flashcards = vec![];
+    let mut seen = HashSet::new();
+    for lesson_id in library.get_lesson_ids(course_id).unwrap_or_default() {
+        let mut lesson_tags = course_tags.clone();
+        let lesson_manifest = library.get_lesson_manifest(lesson_id);
+        metadata_to_tags(
+            lesson_manifest.as_ref().and_then(|m| m.metadata.as_ref()),
+            &mut lesson_tags,
+        );
+
+        for exercise_id in library.get_exercise_ids(lesson_id).unwrap_or_default() {
+            let Some(exercise_manifest) = library.get_exercise_manifest(exercise_id) else {
+                continue;
+            };
+            let mut cards = vec![];
+            match &exercise_manifest.exercise_asset {
+                ExerciseAsset::FlashcardAsset {
+                    front_path,
+                    back_path,
+                } => {
+                    let front = fs::read_to_string(front_path)
+                        .with_context(|| format!("failed to read {front_path}"))?;
+                    let back = back_path
+                        .as_ref()
+                        .map(|path| {
+                            fs::read_to_string(path)
+                                .with_context(|| format!("failed to read {path}"))
+                        })
+                        .transpose()?;
+                    cards.push((front, back, None));
+                }
+                ExerciseAsset::InlineFlashcardAsset {
+                    front_content,
+                    back_content,
+                } => {
+                    cards.push((front_content.clone(), back_content.clone(), None));
+                }
+                ExerciseAsset::LiteracyAsset {
+                    examples,
+                    exceptions,
+                    ..
+                } => {
+                    for (example, answer) in examples {
+                        cards.push((example.clone(), answer.clone(), None));
+                    }
+                    for (exception, answer) in exceptions {
+                        cards.push((exception.clone(), answer.clone(), Some(EXCEPTION_TAG)));
+                    }
+                }
+                ExerciseAsset::ClozeAsset { note, index, .. } => {
+                    cards.push((
+                        cloze::render_front(note, *index)?,
+                        Some(cloze::render_back(note, *index)?),
+                        None,
+                    ));
+                }
+                ExerciseAsset::TypedAnswerAsset(typed_answer) => {
+                    cards.push((
+                        typed_answer.prompt.clone(),
+                        Some(typed_answer.accepted_answers.join("\n")),
+                        None,
+                    ));
+                }
+                ExerciseAsset::MultipleChoiceAsset(question) => {
+                    // List the options on the front and the correct ones on the back.
+                    let options = question
+                        .options
+                        .iter()
+                        .enumerate()
+                        .map(|(index, option)| format!("{}. {}", index + 1, option.text))
+                        .collect::<Vec<_>>();
+                    let answers = question
+                        .options
+                        .iter()
+                        .filter(|option| option.correct)
+                        .map(|option| option.text.clone())
+                        .collect::<Vec<_>>();
+                    cards.push((
+                        format!("{}\n\n{}", question.prompt, options.join("\n")),
+                        Some(answers.join("\n")),
+                        None,
+                    ));
+                }
+                _ => {}
+            }
+
+            for (front, back, extra_tag) in cards {
+                let front = front.trim().to_string();
+                let back = back.map(|back| back.trim().to_string());
+                if !seen.insert((front.clone(), back.clone())) {
+                    continue;
+                }
+                let mut tags = lesson_tags.clone();
+                if let Some(tag) = extra_tag {
+                    tags.push(tag.to_string());
+                }
+                flashcards.push(Flashcard {
+                    exercise_id,
+                    front,
+                    back,
+                    tags,
+                });
+            }
+        }
+    }
+    Ok(flashcards)
+}
+
+/// Collects the flashcards of the given course. Exercises whose assets are not flashcards or
+/// literacy examples are skipped.
+pub fn collect_flashcards(
+    library: &impl CourseLibrary,
+    course_id: Ustr,
+) -> Result<Vec<Flashcard>, ExportError> {
+    collect_flashcards_helper(library, course_id).map_err(|e| ExportError::Flashcards(course_id, e))
+}
+
+/// Helper function to write the flashcards.
+fn write_flashcards_helper(
+    flashcards: &[Flashcard],
+    format: FlashcardFormat,
+    mut writer: impl Write,
+) -> Result<()> {
+    match format {
+        FlashcardFormat::Csv => {
+            let mut csv_writer = ::csv::Writer::from_writer(writer);
+            csv_writer.write_record(["id", "front", "back", "tags"])?;
+            for flashcard in flashcards {
+                csv_writer.write_record([
+                    flashcard.exercise_id.as_str(),
+                    &flashcard.front,
+                    flashcard.back.as_deref().unwrap_or_default(),
+                    &flashcard.tags.join(";"),
+                ])?;
+            }
+            csv_writer.flush()?;
+        }
+        FlashcardFormat::AnkiText => {
+            // Write the headers that tell Anki how to read the file. Fields with tabs or new lines
+            // are quoted by the CSV writer, which Anki supports.
+            writer.write_all(b"#separator:tab\n#html:false\n#tags column:3\n")?;
+            let mut csv_writer = ::csv::WriterBuilder::new()
+                .delimiter(b'\t')
+                .from_writer(writer);
+            for flashcard in flashcards {
+                csv_writer.write_record([
+                    flashcard.front.as_str(),
+                    flashcard.back.as_deref().unwrap_or_default(),
+                    &flashcard.tags.join(" "),
+                ])?;
+            }
+            csv_writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the flashcards in the given format.
+pub fn write_flashcards(
+    flashcards: &[Flashcard],
+    format: FlashcardFormat,
+    writer: impl Write,
+) -> Result<(), ExportError> {
+    write_flashcards_helper(flashcards, format, writer).map_err(ExportError::Write)
+}
+
+/// Escapes a value so that it can be written inside a cell of a markdown table.
+fn escape_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+/// Formats an optional score for the report.
+fn format_score(score: Option<f32>) -> String {
+    score.map_or_else(|| "-".to_string(), |score| format!("{score:.2}"))
+}
+
+/// Helper function to write the progress report of a course.
+fn write_progress_report_helper(
+    trane: &(impl CourseLibrary + ExerciseScheduler + PracticeStats),
+    course_id: Ustr,
+    mut writer: impl Write,
+) -> Result<()> {
+    // Write the title and the overall score of the course.
+    let course_manifest = trane
+        .get_course_manifest(course_id)
+        .ok_or_else(|| anyhow!("course not found"))?;
+    writeln!(writer, "# {} ({course_id})\n", course_manifest.name)?;
+    if let Some(description) = &course_manifest.description {
+        writeln!(writer, "{description}\n")?;
+    }
+    writeln!(
+        writer,
+        "Course score: {}\n",
+        format_score(trane.get_unit_score(course_id)?)
+    )?;
+
+    // Write a table with the exercises of each lesson.
+    for lesson_id in trane.get_lesson_ids(course_id).unwrap_or_default() {
+        let lesson_name = trane
+            .get_lesson_manifest(lesson_id)
+            .map_or_else(|| lesson_id.to_string(), |m| m.name.clone());
+        writeln!(writer, "## {lesson_name} ({lesson_id})\n")?;
+        writeln!(
+            writer,
+            "Lesson score: {}\n",
+            format_score(trane.get_unit_score(lesson_id)?)
+        )?;
+        writeln!(
+            writer,
+            "| Exercise | Name | Score | Trials | Last trial (UTC) |"
+        )?;
+        writeln!(writer, "| --- | --- | --- | --- | --- |")?;
+        for exercise_id in trane.get_exercise_ids(lesson_id).unwrap_or_default() {
+            let name = trane
+                .get_exercise_manifest(exercise_id)
+                .map(|m| m.name.clone())
+                .unwrap_or_default();
+            let trials = trane.get_scores(exercise_id, u32::MAX)?;
+            let last_trial = trials
+                .first()
+                .and_then(|trial| DateTime::from_timestamp(trial.timestamp, 0))
+                .map_or_else(
+                    || "-".to_string(),
+                    |date| date.format("%Y-%m-%d").to_string(),
+                );
+            writeln!(
+                writer,
+                "| {} | {} | {} | {} | {} |",
+                escape_cell(&exercise_id),
+                escape_cell(&name),
+                format_score(trane.get_unit_score(exercise_id)?),
+                trials.len(),
+                last_trial
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Writes a markdown report with the exercises of the given course, grouped by lesson, along with
+/// their current scores, number of trials, and the date of the last trial.
+pub fn write_progress_report(
+    trane: &(impl CourseLibrary + ExerciseScheduler + PracticeStats),
+    course_id: Ustr,
+    writer: impl Write,
+) -> Result<(), ExportError> {
+    write_progress_report_helper(trane, course_id, writer)
+        .map_err(|e| ExportError::ProgressReport(course_id, e))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+    use ustr::Ustr;
+
+    use crate::{
+        Trane,
+        data::{CourseManifestBuilder, MasteryScore},
+        exporter::*,
+        importer::csv::{Column, ColumnMapping, CsvImporter},
+    };
+
+    /// Imports a small course from a CSV file and opens it.
+    fn init_trane(temp_dir: &TempDir) -> Result<Trane> {
+        let path = temp_dir.path().join("cards.csv");
+        fs::write(
+            &path,
+            "Front,Back,Lesson,Tags\n\
+            uno,one,Numbers,spanish;first numbers\n\
+            dos,two,Numbers,spanish\n\
+            rojo | red,,Colors,\n",
+        )?;
+        let importer = CsvImporter {
+            path,
+            manifest: CourseManifestBuilder::default()
+                .id("spanish")
+                .name("Spanish".to_string())
+                .metadata(Some(BTreeMap::from([(
+                    "language".to_string(),
+                    vec!["spanish".to_string()],
+                )])))
+                .build()?,
+            columns: ColumnMapping {
+                front: Column::Name("Front".to_string()),
+                back: Some(Column::Name("Back".to_string())),
+                lesson: Some(Column::Name("Lesson".to_string())),
+                id: None,
+                name: None,
+                exercise_type: None,
+                dependencies: None,
+                metadata: BTreeMap::from([("tags".to_string(), Column::Name("Tags".to_string()))]),
+            },
+            delimiter: None,
+            has_headers: true,
+            list_separator: ';',
+            default_lesson: "Default".to_string(),
+        };
+        let library_dir = temp_dir.path().join("library");
+        importer.import(&library_dir, "spanish")?;
+        Trane::new_local(&library_dir, &library_dir)
+    }
+
+    /// Verifies collecting the flashcards of a knowledge base course.
+    #[test]
+    fn collect_course_flashcards() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let trane = init_trane(&temp_dir)?;
+        let mut flashcards = collect_flashcards(&trane, Ustr::from("spanish"))?;
+        flashcards.sort_by(|a, b| a.front.cmp(&b.front));
+        assert_eq!(flashcards.len(), 3);
+        assert_eq!(flashcards[0].front, "dos");
+        assert_eq!(flashcards[0].back, Some("two".to_string()));
+        assert_eq!(
+            flashcards[0].tags,
+            vec!["language::spanish", "spanish", "first_numbers"]
+        );
+        assert_eq!(flashcards[1].front, "rojo | red");
+        assert_eq!(flashcards[1].back, None);
+        assert_eq!(flashcards[1].tags, vec!["language::spanish"]);
+
+        // Unknown courses cannot be exported.
+        assert!(collect_flashcards(&trane, Ustr::from("unknown")).is_err());
+        Ok(())
+    }
+
+    /// Verifies writing the progress report of a course.
+    #[test]
+    fn progress_report() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let trane = init_trane(&temp_dir)?;
+        let exercise_id = trane
+            .get_exercise_ids(Ustr::from("spanish::colors"))
+            .unwrap()[0];
+        trane.score_exercise(exercise_id, MasteryScore::Four, 1_700_000_000)?;
+
+        let mut report = vec![];
+        write_progress_report(&trane, Ustr::from("spanish"), &mut report)?;
+        let report = String::from_utf8(report)?;
+        assert!(report.starts_with("# Spanish (spanish)\n"));
+        assert!(report.contains("## Colors (spanish::colors)\n"));
+        let row = report
+            .lines()
+            .find(|line| line.starts_with(&format!("| {exercise_id} |")))
+            .unwrap();
+        assert!(row.ends_with("| 1 | 2023-11-14 |"));
+        assert!(report.contains("| spanish::numbers::row_2 | Exercise row_2 | 0.00 | 0 | - |"));
+        assert!(write_progress_report(&trane, Ustr::from("unknown"), vec![]).is_err());
+        Ok(())
+    }
+
+    /// Verifies converting metadata into tags.
+    #[test]
+    fn tags_from_metadata() {
+        let metadata = BTreeMap::from([
+            ("tags".to_string(), vec!["spanish".to_string()]),
+            (
+                "level".to_string(),
+                vec!["beginner one".to_string(), String::new()],
+            ),
+        ]);
+        let mut tags = vec!["spanish".to_string()];
+        metadata_to_tags(Some(&metadata), &mut tags);
+        assert_eq!(tags, vec!["spanish", "level::beginner_one", "level::"]);
+        metadata_to_tags(None, &mut tags);
+        assert_eq!(tags.len(), 3);
+    }
+
+    /// Verifies writing flashcards in both formats.
+    #[test]
+    fn write_formats() -> Result<()> {
+        let flashcards = vec![
+            Flashcard {
+                exercise_id: Ustr::from("a::b::c"),
+                front: "uno, dos".to_string(),
+                back: Some("one\ttwo".to_string()),
+                tags: vec!["numbers".to_string(), "spanish".to_string()],
+            },
+            Flashcard {
+                exercise_id: Ustr::from("a::b::d"),
+                front: "tres".to_string(),
+                back: None,
+                tags: vec![],
+            },
+        ];
+
+        let mut csv = vec![];
+        write_flashcards(&flashcards, FlashcardFormat::Csv, &mut csv)?;
+        assert_eq!(
+            String::from_utf8(csv)?,
+            "id,front,back,tags\n\
+            a::b::c,\"uno, dos\",one\ttwo,numbers;spanish\n\
+            a::b::d,tres,,\n"
+        );
+
+        let mut anki = vec![];
+        write_flashcards(&flashcards, FlashcardFormat::AnkiText, &mut anki)?;
+        assert_eq!(
+            String::from_utf8(anki)?,
+            "#separator:tab\n#html:false\n#tags column:3\n\
+            uno, dos\t\"one\ttwo\"\tnumbers spanish\n\
+            tres\t\t\n"
+        );
+        Ok(())
+    }
+
+    /// Verifies escaping values in markdown tables.
+    #[test]
+    fn escape_table_cells() {
+        assert_eq!(escape_cell("a|b\nc"), "a\\|b c");
+        assert_eq!(format_score(None), "-");
+        assert_eq!(format_score(Some(4.567)), "4.57");
+    }
+}
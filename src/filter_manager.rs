@@ -6,6 +6,7 @@
 
 use anyhow::{Context, Result, bail};
 use std::{collections::HashMap, fs::File, io::BufReader, sync::Arc};
+use ustr::{Ustr, UstrMap};
 
 use crate::data::filter::SavedFilter;
 
@@ -59,6 +60,14 @@ impl LocalFilterManager {
             filters: LocalFilterManager::scan_filters(filter_directory)?,
         })
     }
+
+    /// Replaces the IDs of renamed units in the saved filters with their current IDs. The files
+    /// are not modified, so the renames are applied every time the filters are loaded.
+    pub fn rename_units(&mut self, renamed_units: &UstrMap<Ustr>) {
+        for filter in self.filters.values_mut() {
+            Arc::make_mut(filter).filter.rename_units(renamed_units);
+        }
+    }
 }
 
 impl FilterManager for LocalFilterManager {
@@ -0,0 +1,460 @@
+//! Contains the logic to grade the answers to exercises that Trane can check automatically.
+//!
+//! Most exercises are self-graded: the student compares their answer with the back of the
+//! flashcard and assigns themselves a mastery score. Some assets, such as multiple-choice
+//! questions or typed answers, contain enough information for Trane to check the answer itself.
+//! This module maps the answer of the student to a [`MasteryScore`], which is then recorded like any
+//! other score.
+
+use anyhow::{Result, bail, ensure};
+use std::collections::BTreeSet;
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+use crate::data::{MasteryScore, MultipleChoice, TypedAnswer};
+
+/// The time in seconds in which a student who has mastered an exercise is expected to answer, used
+/// when the asset does not specify its own value.
+pub const DEFAULT_EXPECTED_RESPONSE_TIME: f32 = 10.0;
+
+/// Lowers the score of a correct answer if the student took longer than expected to answer. Answers
+/// that take up to twice the expected time lose one level, and slower answers lose two. If the
+/// response time is not known, the score is returned unchanged.
+#[must_use]
+pub fn adjust_for_latency(
+    score: MasteryScore,
+    response_time: Option<f32>,
+    expected_response_time: Option<f32>,
+) -> MasteryScore {
+    let Some(response_time) = response_time else {
+        return score;
+    };
+    let expected = expected_response_time.unwrap_or(DEFAULT_EXPECTED_RESPONSE_TIME);
+    let penalty = if response_time <= expected {
+        0
+    } else if response_time <= 2.0 * expected {
+        1
+    } else {
+        2
+    };
+    match (score, penalty) {
+        (score, 0) => score,
+        (MasteryScore::Five, 1) => MasteryScore::Four,
+        (MasteryScore::Five | MasteryScore::Four, _) => MasteryScore::Three,
+        (score, _) => score,
+    }
+}
+
+impl MultipleChoice {
+    /// Verifies that the question can be graded, which requires at least one correct option.
+    pub fn verify(&self) -> Result<()> {
+        ensure!(
+            self.options.iter().any(|option| option.correct),
+            "the multiple-choice question has no correct options",
+        );
+        Ok(())
+    }
+
+    /// Grades the options selected by the student, given by their indices, and returns the
+    /// resulting mastery score. Selecting exactly the correct options is scored as a five, lowered
+    /// if the student took longer than expected. In questions with several correct options, partial
+    /// answers receive partial credit, reduced by each incorrect option selected.
+    pub fn grade(&self, selection: &[usize], response_time: Option<f32>) -> Result<MasteryScore> {
+        // Verify the question and the selection.
+        self.verify()?;
+        if let Some(index) = selection.iter().find(|index| **index >= self.options.len()) {
+            bail!(
+                "selected option {index} does not exist in a question with {} options",
+                self.options.len()
+            );
+        }
+
+        // Compare the selected options with the correct ones.
+        let selected: BTreeSet<usize> = selection.iter().copied().collect();
+        let correct: BTreeSet<usize> = self
+            .options
+            .iter()
+            .enumerate()
+            .filter(|(_, option)| option.correct)
+            .map(|(index, _)| index)
+            .collect();
+        if selected == correct {
+            return Ok(adjust_for_latency(
+                MasteryScore::Five,
+                response_time,
+                self.expected_response_time,
+            ));
+        }
+
+        // Give partial credit based on the fraction of correct options that were selected, minus
+        // the incorrect options that were selected.
+        let hits = selected.intersection(&correct).count();
+        let misses = selected.difference(&correct).count();
+        #[allow(clippy::cast_precision_loss)]
+        let credit = (hits as f32 - misses as f32) / correct.len() as f32;
+        Ok(if credit >= 0.5 {
+            MasteryScore::Three
+        } else if credit > 0.0 {
+            MasteryScore::Two
+        } else {
+            MasteryScore::One
+        })
+    }
+}
+
+/// The maximum number of edits allowed for each answer in a dictation exercise to be considered a
+/// typo.
+pub const DICTATION_TOLERANCE: usize = 1;
+
+/// Normalizes an answer before comparing it. Surrounding whitespace is removed and consecutive
+/// whitespace is collapsed into a single space. The answer is lowercased unless the comparison is
+/// case-sensitive, and diacritics are removed if they should be ignored.
+#[must_use]
+pub fn normalize_answer(answer: &str, case_sensitive: bool, ignore_diacritics: bool) -> String {
+    let answer = answer.split_whitespace().collect::<Vec<_>>().join(" ");
+    let answer = if case_sensitive {
+        answer
+    } else {
+        answer.to_lowercase()
+    };
+    if ignore_diacritics {
+        answer
+            .nfd()
+            .filter(|c| !is_combining_mark(*c))
+            .nfc()
+            .collect()
+    } else {
+        answer.nfc().collect()
+    }
+}
+
+/// Returns the edit distance between two strings, that is, the minimum number of insertions,
+/// deletions, or substitutions of a single character needed to turn one into the other.
+#[must_use]
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+impl TypedAnswer {
+    /// Verifies that the exercise can be graded, which requires at least one accepted answer.
+    pub fn verify(&self) -> Result<()> {
+        ensure!(
+            !self.accepted_answers.is_empty(),
+            "the typed-answer exercise has no accepted answers",
+        );
+        Ok(())
+    }
+
+    /// Grades the answer typed by the student and returns the resulting mastery score, based on the
+    /// closest accepted answer. An exact match is scored as a five, lowered if the student took
+    /// longer than expected. Answers within the tolerance are considered typos and scored as a four
+    /// or a three, depending on the number of edits. Other answers receive a two if at least half
+    /// of the characters match and a one otherwise.
+    pub fn grade(&self, answer: &str, response_time: Option<f32>) -> Result<MasteryScore> {
+        // Find the distance to the closest accepted answer.
+        self.verify()?;
+        let answer = normalize_answer(answer, self.case_sensitive, self.ignore_diacritics);
+        let (distance, length) = self
+            .accepted_answers
+            .iter()
+            .map(|accepted| {
+                let accepted =
+                    normalize_answer(accepted, self.case_sensitive, self.ignore_diacritics);
+                let length = accepted.chars().count().max(answer.chars().count());
+                (edit_distance(&answer, &accepted), length)
+            })
+            .min()
+            .unwrap_or_default();
+
+        // Map the quality of the match to a score.
+        let score = if distance == 0 {
+            MasteryScore::Five
+        } else if distance <= self.tolerance.div_ceil(2) {
+            MasteryScore::Four
+        } else if distance <= self.tolerance {
+            MasteryScore::Three
+        } else if 2 * distance <= length {
+            MasteryScore::Two
+        } else {
+            MasteryScore::One
+        };
+        Ok(adjust_for_latency(
+            score,
+            response_time,
+            self.expected_response_time,
+        ))
+    }
+}
+
+/// Grades the answers typed by the student in a dictation exercise. Each answer is paired with the
+/// index of the dictated item it answers, where the examples come first, followed by the
+/// exceptions, in the order in which they appear in the asset. This allows the student to be
+/// dictated only a part of a long list. Each answer is compared with the answer of the item, or
+/// with the item itself if it has no answer. Comparisons ignore case and allow
+/// [`DICTATION_TOLERANCE`] edits. Only the answered items are graded, and the final score is the
+/// average of their scores, rounded to the nearest level.
+pub fn grade_dictation(
+    examples: &[(String, Option<String>)],
+    exceptions: &[(String, Option<String>)],
+    answers: &[(usize, String)],
+) -> Result<MasteryScore> {
+    // Verify there's at least one answer and that every answer refers to a different item.
+    let expected: Vec<&String> = examples
+        .iter()
+        .chain(exceptions)
+        .map(|(example, answer)| answer.as_ref().unwrap_or(example))
+        .collect();
+    ensure!(
+        !expected.is_empty(),
+        "the dictation exercise has no examples"
+    );
+    ensure!(!answers.is_empty(), "no answers were given");
+    let mut answered = vec![false; expected.len()];
+    for (index, _) in answers {
+        ensure!(
+            *index < expected.len(),
+            "unknown item {index}, the exercise has {} items",
+            expected.len()
+        );
+        ensure!(
+            !answered[*index],
+            "item {index} was answered more than once"
+        );
+        answered[*index] = true;
+    }
+
+    // Grade each answer and average the scores.
+    let mut total = 0.0;
+    for (index, answer) in answers {
+        let typed_answer = TypedAnswer {
+            prompt: String::new(),
+            accepted_answers: vec![expected[*index].clone()],
+            case_sensitive: false,
+            ignore_diacritics: false,
+            tolerance: DICTATION_TOLERANCE,
+            expected_response_time: None,
+        };
+        total += typed_answer.grade(answer, None)?.float_score();
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let average = total / answers.len() as f32;
+    Ok(MasteryScore::try_from(average.round()).unwrap_or(MasteryScore::One))
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use anyhow::Result;
+
+    use crate::{
+        data::{MasteryScore, MultipleChoice, MultipleChoiceOption, TypedAnswer},
+        grading::*,
+    };
+
+    /// Returns a question with the given options, marking the options at the given indices as
+    /// correct.
+    fn question(num_options: usize, correct: &[usize]) -> MultipleChoice {
+        MultipleChoice {
+            prompt: "Prompt".to_string(),
+            options: (0..num_options)
+                .map(|index| MultipleChoiceOption {
+                    text: format!("Option {index}"),
+                    correct: correct.contains(&index),
+                    explanation: None,
+                })
+                .collect(),
+            explanation: None,
+            expected_response_time: Some(5.0),
+        }
+    }
+
+    /// Verifies adjusting the scores of correct answers based on the response time.
+    #[test]
+    fn latency() {
+        assert_eq!(
+            adjust_for_latency(MasteryScore::Five, None, None),
+            MasteryScore::Five
+        );
+        assert_eq!(
+            adjust_for_latency(MasteryScore::Five, Some(10.0), None),
+            MasteryScore::Five
+        );
+        assert_eq!(
+            adjust_for_latency(MasteryScore::Five, Some(15.0), None),
+            MasteryScore::Four
+        );
+        assert_eq!(
+            adjust_for_latency(MasteryScore::Five, Some(25.0), None),
+            MasteryScore::Three
+        );
+        assert_eq!(
+            adjust_for_latency(MasteryScore::Five, Some(3.0), Some(2.0)),
+            MasteryScore::Four
+        );
+        assert_eq!(
+            adjust_for_latency(MasteryScore::Two, Some(100.0), None),
+            MasteryScore::Two
+        );
+    }
+
+    /// Verifies grading questions with a single correct option.
+    #[test]
+    fn single_answer() -> Result<()> {
+        let question = question(4, &[2]);
+        assert_eq!(question.grade(&[2], None)?, MasteryScore::Five);
+        assert_eq!(question.grade(&[2], Some(4.0))?, MasteryScore::Five);
+        assert_eq!(question.grade(&[2], Some(8.0))?, MasteryScore::Four);
+        assert_eq!(question.grade(&[2], Some(20.0))?, MasteryScore::Three);
+        assert_eq!(question.grade(&[1], None)?, MasteryScore::One);
+        assert_eq!(question.grade(&[], None)?, MasteryScore::One);
+        assert_eq!(question.grade(&[1, 2], None)?, MasteryScore::One);
+        Ok(())
+    }
+
+    /// Verifies grading questions with several correct options.
+    #[test]
+    fn multiple_answers() -> Result<()> {
+        let question = question(5, &[0, 1, 2, 3]);
+        assert_eq!(question.grade(&[3, 2, 1, 0], None)?, MasteryScore::Five);
+        assert_eq!(question.grade(&[0, 1, 2, 2, 3], None)?, MasteryScore::Five);
+        assert_eq!(question.grade(&[0, 1, 2], None)?, MasteryScore::Three);
+        assert_eq!(question.grade(&[0, 1, 2, 3, 4], None)?, MasteryScore::Three);
+        assert_eq!(question.grade(&[0], None)?, MasteryScore::Two);
+        assert_eq!(question.grade(&[0, 4], None)?, MasteryScore::One);
+        Ok(())
+    }
+
+    /// Verifies that invalid questions and selections cannot be graded.
+    #[test]
+    fn invalid_answers() {
+        assert!(question(3, &[]).grade(&[0], None).is_err());
+        assert!(question(3, &[0]).grade(&[3], None).is_err());
+    }
+
+    /// Returns a typed-answer exercise with the given accepted answers and tolerance.
+    fn typed_answer(accepted_answers: &[&str], tolerance: usize) -> TypedAnswer {
+        TypedAnswer {
+            prompt: "Prompt".to_string(),
+            accepted_answers: accepted_answers.iter().map(ToString::to_string).collect(),
+            case_sensitive: false,
+            ignore_diacritics: false,
+            tolerance,
+            expected_response_time: None,
+        }
+    }
+
+    /// Verifies normalizing answers and computing the edit distance between them.
+    #[test]
+    fn normalize_and_compare() {
+        assert_eq!(
+            normalize_answer("  Hola   Mundo ", false, false),
+            "hola mundo"
+        );
+        assert_eq!(normalize_answer("Hola", true, false), "Hola");
+        assert_eq!(normalize_answer("Canción", false, true), "cancion");
+        assert_eq!(normalize_answer("Canción", false, false), "canción");
+        assert_eq!(
+            normalize_answer("Cancio\u{301}n", false, false),
+            normalize_answer("Canción", false, false)
+        );
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("gato", ""), 4);
+        assert_eq!(edit_distance("gato", "pato"), 1);
+        assert_eq!(edit_distance("gato", "gatos"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("año", "ano"), 1);
+    }
+
+    /// Verifies grading typed answers.
+    #[test]
+    fn typed_answers() -> Result<()> {
+        let exercise = typed_answer(&["the cat", "a cat"], 2);
+        assert_eq!(exercise.grade("The  Cat", None)?, MasteryScore::Five);
+        assert_eq!(exercise.grade("a cat", Some(15.0))?, MasteryScore::Four);
+        assert_eq!(exercise.grade("the cta", None)?, MasteryScore::Three);
+        assert_eq!(exercise.grade("a bat", None)?, MasteryScore::Four);
+        assert_eq!(exercise.grade("the dog", None)?, MasteryScore::Two);
+        assert_eq!(exercise.grade("horse", None)?, MasteryScore::One);
+
+        // Case and diacritics are only ignored when requested.
+        let mut exercise = typed_answer(&["Canción"], 0);
+        assert_eq!(exercise.grade("canción", None)?, MasteryScore::Five);
+        assert_eq!(exercise.grade("cancion", None)?, MasteryScore::Two);
+        exercise.ignore_diacritics = true;
+        assert_eq!(exercise.grade("cancion", None)?, MasteryScore::Five);
+        exercise.case_sensitive = true;
+        assert_eq!(exercise.grade("cancion", None)?, MasteryScore::Two);
+
+        // Exercises without accepted answers cannot be graded.
+        assert!(typed_answer(&[], 0).grade("answer", None).is_err());
+        Ok(())
+    }
+
+    /// Verifies grading the answers to a dictation exercise.
+    #[test]
+    fn dictation() -> Result<()> {
+        let examples = vec![
+            ("ca".to_string(), None),
+            ("ce".to_string(), Some("se".to_string())),
+        ];
+        let exceptions = vec![("casa".to_string(), None)];
+        let answers = |answers: &[(usize, &str)]| -> Vec<(usize, String)> {
+            answers
+                .iter()
+                .map(|(index, answer)| (*index, (*answer).to_string()))
+                .collect()
+        };
+        assert_eq!(
+            grade_dictation(
+                &examples,
+                &exceptions,
+                &answers(&[(0, "Ca"), (1, "se"), (2, "casa")])
+            )?,
+            MasteryScore::Five
+        );
+        assert_eq!(
+            grade_dictation(
+                &examples,
+                &exceptions,
+                &answers(&[(2, "casa"), (0, "ca"), (1, "ce")])
+            )?,
+            MasteryScore::Five
+        );
+        assert_eq!(
+            grade_dictation(
+                &examples,
+                &exceptions,
+                &answers(&[(0, "xx"), (1, "xx"), (2, "casa")])
+            )?,
+            MasteryScore::Two
+        );
+
+        // Only the answered items are graded.
+        assert_eq!(
+            grade_dictation(&examples, &exceptions, &answers(&[(1, "se")]))?,
+            MasteryScore::Five
+        );
+        assert_eq!(
+            grade_dictation(&examples, &exceptions, &answers(&[(2, "xxxx")]))?,
+            MasteryScore::One
+        );
+
+        // Unknown and repeated items are rejected, as well as empty answers and exercises.
+        assert!(grade_dictation(&examples, &exceptions, &answers(&[(3, "ca")])).is_err());
+        assert!(
+            grade_dictation(&examples, &exceptions, &answers(&[(0, "ca"), (0, "ca")])).is_err()
+        );
+        assert!(grade_dictation(&examples, &exceptions, &[]).is_err());
+        assert!(grade_dictation(&[], &[], &[]).is_err());
+        Ok(())
+    }
+}
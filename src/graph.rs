@@ -47,7 +47,10 @@ use std::fmt::Write;
 use std::sync::Arc;
 use ustr::{Ustr, UstrMap, UstrSet};
 
-use crate::{data::UnitType, error::UnitGraphError};
+use crate::{
+    data::{UnitType, filter::UnitFilter},
+    error::UnitGraphError,
+};
 
 /// Stores the units and their dependency relationships (for lessons and courses only, since
 /// exercises do not define any dependencies). It provides basic functions to update the graph and
@@ -173,6 +176,86 @@ pub trait UnitGraph {
     ///
     /// If courses_only is true, only courses will be included in the graph.
     fn generate_dot_graph(&self, courses_only: bool) -> String;
+
+    /// Generates a DOT graph in the same format as `generate_dot_graph`, but restricted to the
+    /// given units and with each node styled according to its annotation. If `units` is `None`,
+    /// all units are included. Edges are only written when both of their ends are included. Units
+    /// without an annotation are written with the default style.
+    fn generate_annotated_dot_graph(
+        &self,
+        courses_only: bool,
+        units: Option<&UstrSet>,
+        annotations: &UstrMap<DotAnnotation>,
+    ) -> String;
+}
+
+/// Information about the student's progress on a unit used to style its node in the DOT graph.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DotAnnotation {
+    /// The score of the unit, if any. Nodes are filled with a color that goes from red to green as
+    /// the score goes from 0.0 to 5.0. Units without a score are filled in white.
+    pub score: Option<f32>,
+
+    /// Whether the unit is in the blacklist. Blacklisted units are filled in gray and drawn with a
+    /// dashed border.
+    pub blacklisted: bool,
+
+    /// Whether the unit has been superseded by another unit. Superseded units are drawn with a
+    /// dotted border.
+    pub superseded: bool,
+
+    /// Whether the unit is in the review list. Units in the review list are drawn with a double
+    /// border.
+    pub in_review_list: bool,
+}
+
+impl DotAnnotation {
+    /// Returns the attributes of the node for the given unit, using the given border color.
+    fn node_attributes(&self, unit_id: Ustr, color: &str) -> String {
+        // Blacklisted units are grayed out regardless of their score. Otherwise, the hue goes from
+        // red (0.0) to green (0.333) in the HSV color space used by Graphviz.
+        let fill_color = if self.blacklisted {
+            "gray".to_string()
+        } else {
+            match self.score {
+                None => "white".to_string(),
+                Some(score) => format!("\"{:.3} 0.600 1.000\"", score.clamp(0.0, 5.0) / 15.0),
+            }
+        };
+        let style = if self.blacklisted {
+            "\"filled,dashed\""
+        } else if self.superseded {
+            "\"filled,dotted\""
+        } else {
+            "filled"
+        };
+        let label = match self.score {
+            None => format!("{unit_id}"),
+            Some(score) => format!("{unit_id}\\n{score:.2}"),
+        };
+
+        let mut attributes =
+            format!("color={color}, style={style}, fillcolor={fill_color}, label=\"{label}\"");
+        if self.in_review_list {
+            attributes.push_str(", peripheries=2");
+        }
+        attributes
+    }
+}
+
+/// Options to restrict the units included in a DOT graph of the course library.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DotGraphOptions {
+    /// Whether only courses should be included in the graph.
+    pub courses_only: bool,
+
+    /// An optional filter to restrict the graph to the courses and lessons that match it.
+    pub filter: Option<UnitFilter>,
+
+    /// An optional unit and a number of hops. If present, the graph is restricted to the units
+    /// that can be reached from the given unit by following at most that many dependency or
+    /// dependent relationships.
+    pub neighborhood: Option<(Ustr, usize)>,
 }
 
 /// An implementation of [`UnitGraph`] describing the units and relationships as an adjacency list
@@ -690,6 +773,25 @@ impl UnitGraph for InMemoryUnitGraph {
     }
 
     fn generate_dot_graph(&self, courses_only: bool) -> String {
+        self.generate_annotated_dot_graph(courses_only, None, &UstrMap::default())
+    }
+
+    fn generate_annotated_dot_graph(
+        &self,
+        courses_only: bool,
+        units: Option<&UstrSet>,
+        annotations: &UstrMap<DotAnnotation>,
+    ) -> String {
+        // Declare closures to check whether a unit should be included and to write its node.
+        let included = |unit_id: &Ustr| units.is_none_or(|units| units.contains(unit_id));
+        let node_line = |unit_id: Ustr, color: &str| match annotations.get(&unit_id) {
+            None => format!("    \"{unit_id}\" [color={color}, style=filled]"),
+            Some(annotation) => format!(
+                "    \"{unit_id}\" [{}]",
+                annotation.node_attributes(unit_id, color)
+            ),
+        };
+
         // Initialize the output with the first line of the file.
         let mut output = String::from("digraph dependent_graph {\n");
         let mut courses = self.course_lesson_map.keys().copied().collect::<Vec<_>>();
@@ -698,45 +800,49 @@ impl UnitGraph for InMemoryUnitGraph {
         // Add each course to the DOT graph.
         for course_id in courses {
             // Add an entry for the course node and set the color to red.
-            let _ = writeln!(output, "    \"{course_id}\" [color=red, style=filled]");
+            if included(&course_id) {
+                let _ = writeln!(output, "{}", node_line(course_id, "red"));
 
-            // Write the entry in the graph for all the of the dependents of this course. Filter out
-            // lessons if only courses should be added.
-            let mut dependents = self
-                .get_dependents(course_id)
-                .unwrap_or_default()
-                .iter()
-                .copied()
-                .collect::<Vec<_>>();
-            dependents.retain(|dependent_id| {
-                if courses_only {
-                    self.get_unit_type(*dependent_id) == Some(UnitType::Course)
-                } else {
-                    true
+                // Write the entry in the graph for all the of the dependents of this course. Filter
+                // out lessons if only courses should be added.
+                let mut dependents = self
+                    .get_dependents(course_id)
+                    .unwrap_or_default()
+                    .iter()
+                    .copied()
+                    .collect::<Vec<_>>();
+                dependents.retain(|dependent_id| {
+                    if courses_only {
+                        self.get_unit_type(*dependent_id) == Some(UnitType::Course)
+                    } else {
+                        true
+                    }
+                });
+
+                // Add the initial lessons in the course as dependents.
+                //
+                // A course's lessons are not explicitly attached to the graph. This is not exactly
+                // accurate, but properly connecting them in the graph would require each course to
+                // have two nodes, one inbound which is connected to the starting lessons and the
+                // course's dependencies, and one outbound which is connected to the last lessons in
+                // the course (by the order in which they must be traversed to master the entire
+                // course) and to the course's dependents. This might be amended, either here in
+                // this function or in the implementation of the graph itself, but it is not a high
+                // priority.
+                if !courses_only {
+                    dependents.extend(
+                        self.get_starting_lessons(course_id)
+                            .unwrap_or_default()
+                            .iter(),
+                    );
                 }
-            });
-
-            // Add the initial lessons in the course as dependents.
-            //
-            // A course's lessons are not explicitly attached to the graph. This is not exactly
-            // accurate, but properly connecting them in the graph would require each course to have
-            // two nodes, one inbound which is connected to the starting lessons and the course's
-            // dependencies, and one outbound which is connected to the last lessons in the course
-            // (by the order in which they must be traversed to master the entire course) and to the
-            // course's dependents. This might be amended, either here in this function or in the
-            // implementation of the graph itself, but it is not a high priority.
-            if !courses_only {
-                dependents.extend(
-                    self.get_starting_lessons(course_id)
-                        .unwrap_or_default()
-                        .iter(),
-                );
-            }
 
-            // Write an entry for each of the course's dependents.
-            dependents.sort();
-            for dependent in dependents {
-                let _ = writeln!(output, "    \"{course_id}\" -> \"{dependent}\"");
+                // Write an entry for each of the course's dependents.
+                dependents.retain(|dependent_id| included(dependent_id));
+                dependents.sort();
+                for dependent in dependents {
+                    let _ = writeln!(output, "    \"{course_id}\" -> \"{dependent}\"");
+                }
             }
 
             // Repeat the same process for each lesson in this course, unless only the courses
@@ -749,11 +855,12 @@ impl UnitGraph for InMemoryUnitGraph {
                 .unwrap_or_default()
                 .iter()
                 .copied()
+                .filter(|lesson_id| included(lesson_id))
                 .collect::<Vec<_>>();
             lessons.sort();
             for lesson_id in lessons {
                 // Add an entry for the lesson node and set the color to blue.
-                let _ = writeln!(output, "    \"{lesson_id}\" [color=blue, style=filled]");
+                let _ = writeln!(output, "{}", node_line(lesson_id, "blue"));
 
                 // Add an entry in the graph for all of this lesson's dependents.
                 let mut dependents = self
@@ -761,6 +868,7 @@ impl UnitGraph for InMemoryUnitGraph {
                     .unwrap_or_default()
                     .iter()
                     .copied()
+                    .filter(|dependent_id| included(dependent_id))
                     .collect::<Vec<_>>();
                 dependents.sort();
                 for dependent in dependents {
@@ -781,11 +889,11 @@ mod test {
     use anyhow::Result;
     use indoc::indoc;
     use std::sync::Arc;
-    use ustr::{Ustr, UstrSet};
+    use ustr::{Ustr, UstrMap, UstrSet};
 
     use crate::{
         data::UnitType,
-        graph::{InMemoryUnitGraph, UnitGraph},
+        graph::{DotAnnotation, InMemoryUnitGraph, UnitGraph},
     };
 
     /// Verifies retrieving the correct unit type from the graph.
@@ -1160,6 +1268,93 @@ mod test {
         Ok(())
     }
 
+    /// Verifies generating a DOT graph restricted to a set of units and with annotated nodes.
+    #[test]
+    fn generate_annotated_dot_graph() -> Result<()> {
+        let mut graph = InMemoryUnitGraph::default();
+        let course1_id = Ustr::from("1");
+        let course1_lesson1_id = Ustr::from("1::1");
+        let course1_lesson2_id = Ustr::from("1::2");
+        let course2_id = Ustr::from("2");
+        let course2_lesson1_id = Ustr::from("2::1");
+        let course3_id = Ustr::from("3");
+        let course3_lesson1_id = Ustr::from("3::1");
+
+        graph.add_lesson(course1_lesson1_id, course1_id)?;
+        graph.add_lesson(course1_lesson2_id, course1_id)?;
+        graph.add_lesson(course2_lesson1_id, course2_id)?;
+        graph.add_lesson(course3_lesson1_id, course3_id)?;
+        graph.add_dependencies(course1_id, UnitType::Course, &[])?;
+        graph.add_dependencies(course1_lesson2_id, UnitType::Lesson, &[course1_lesson1_id])?;
+        graph.add_dependencies(course2_id, UnitType::Course, &[course1_id])?;
+        graph.add_dependencies(course3_id, UnitType::Course, &[course2_id])?;
+        graph.update_starting_lessons();
+
+        // Only include the first two courses and annotate some of their units.
+        let units: UstrSet = [
+            course1_id,
+            course1_lesson1_id,
+            course1_lesson2_id,
+            course2_id,
+        ]
+        .into_iter()
+        .collect();
+        let annotations: UstrMap<DotAnnotation> = [
+            (
+                course1_id,
+                DotAnnotation {
+                    score: Some(5.0),
+                    superseded: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                course1_lesson1_id,
+                DotAnnotation {
+                    score: Some(0.0),
+                    in_review_list: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                course2_id,
+                DotAnnotation {
+                    blacklisted: true,
+                    ..Default::default()
+                },
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let dot = graph.generate_annotated_dot_graph(false, Some(&units), &annotations);
+        let expected = indoc! {r#"
+            digraph dependent_graph {
+                "1" [color=red, style="filled,dotted", fillcolor="0.333 0.600 1.000", label="1\n5.00"]
+                "1" -> "1::1"
+                "1" -> "2"
+                "1::1" [color=blue, style=filled, fillcolor="0.000 0.600 1.000", label="1::1\n0.00", peripheries=2]
+                "1::1" -> "1::2"
+                "1::2" [color=blue, style=filled]
+                "2" [color=red, style="filled,dashed", fillcolor=gray, label="2"]
+            }
+        "#};
+        assert_eq!(dot, expected);
+
+        // Generate the graph with only the courses and no annotations.
+        let dot_courses_only =
+            graph.generate_annotated_dot_graph(true, Some(&units), &UstrMap::default());
+        let expected_courses_only = indoc! {r#"
+            digraph dependent_graph {
+                "1" [color=red, style=filled]
+                "1" -> "2"
+                "2" [color=red, style=filled]
+            }
+        "#};
+        assert_eq!(dot_courses_only, expected_courses_only);
+        Ok(())
+    }
+
     #[test]
     fn duplicate_ids() -> Result<()> {
         let mut graph = InMemoryUnitGraph::default();
@@ -0,0 +1,63 @@
+//! Defines utilities to import courses and practice history from other tools and file formats.
+//!
+//! Importers read the data of another tool, such as an Anki collection or a spreadsheet exported to
+//! CSV, and generate a knowledge base course in the given
+//! directory using the builders in the `course_builder` module. If the other tool stores the review
+//! history, it can be translated into exercise trials that can be recorded in the practice stats so
+//! that the student does not have to start from scratch.
+
+pub mod anki;
+pub mod csv;
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::data::ExerciseTrial;
+
+/// The result of importing a course from another tool.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ImportResult {
+    /// The directory in which the course was generated.
+    pub course_directory: PathBuf,
+
+    /// The number of lessons in the generated course.
+    pub num_lessons: usize,
+
+    /// The number of exercises in the generated course.
+    pub num_exercises: usize,
+
+    /// The trials translated from the review history of the other tool, sorted by timestamp. They
+    /// are not recorded automatically. Call `PracticeStats::record_exercise_scores` to record them.
+    pub trials: Vec<ExerciseTrial>,
+}
+
+/// Converts a name into a string that can be used as the short ID of a lesson or exercise. All the
+/// characters that are not ASCII alphanumeric are replaced with underscores, and consecutive
+/// underscores are collapsed into one.
+#[must_use]
+pub fn sanitize_id(name: &str) -> String {
+    let mut id = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            id.push(c.to_ascii_lowercase());
+        } else if !id.ends_with('_') {
+            id.push('_');
+        }
+    }
+    id.trim_matches('_').to_string()
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use crate::importer::sanitize_id;
+
+    /// Verifies converting names into short IDs.
+    #[test]
+    fn sanitize_ids() {
+        assert_eq!(sanitize_id("Spanish::Verbs"), "spanish_verbs");
+        assert_eq!(sanitize_id("  Deck 1 (old) "), "deck_1_old");
+        assert_eq!(sanitize_id("Ünïcode"), "n_code");
+        assert_eq!(sanitize_id("!!!"), "");
+    }
+}
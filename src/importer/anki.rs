@@ -0,0 +1,611 @@
+//! Imports Anki collections into knowledge base courses.
+//!
+//! Anki stores its collections in a `SQLite` database, which is also found inside the `.apkg`
+//! archives used to share decks. Each deck in the collection is turned into a lesson and each note
+//! into an exercise, with the first field of the note as the front of the card and the rest of the
+//! fields as the back. The HTML in the fields is converted to plain text. Media files are not
+//! imported. Optionally, the review log is translated into exercise trials so that the spacing
+//! history of the notes is not lost.
+
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, OpenFlags};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+use tempfile::TempDir;
+use ustr::Ustr;
+use zip::ZipArchive;
+
+use crate::{
+    course_builder::{
+        AssetBuilder,
+        knowledge_base_builder::{CourseBuilder, ExerciseBuilder, LessonBuilder},
+    },
+    data::{
+        CourseGenerator, CourseManifestBuilder, ExerciseTrial, MasteryScore,
+        course_generator::knowledge_base::{
+            EXERCISE_BACK_SUFFIX, EXERCISE_FRONT_SUFFIX, KnowledgeBaseConfig,
+            KnowledgeBaseExercise, KnowledgeBaseLesson,
+        },
+    },
+    error::ImportError,
+    importer::{ImportResult, sanitize_id},
+};
+
+/// The names of the collection files inside an `.apkg` archive, in order of preference. Newer
+/// versions of Anki include a placeholder `collection.anki2` file along with the real collection.
+const COLLECTION_FILES: [&str; 2] = ["collection.anki21", "collection.anki2"];
+
+/// The name of the compressed collection file used by the latest versions of Anki, which is not
+/// supported.
+const COMPRESSED_COLLECTION_FILE: &str = "collection.anki21b";
+
+/// The separator between the fields of a note.
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// The options to import an Anki collection.
+#[derive(Clone, Debug)]
+pub struct AnkiImporter {
+    /// The path to the collection, either a `collection.anki2` database or an `.apkg` archive.
+    pub collection_path: PathBuf,
+
+    /// The ID of the generated course.
+    pub course_id: Ustr,
+
+    /// The name of the generated course.
+    pub course_name: String,
+
+    /// The name of the directory in which to generate the course.
+    pub directory_name: String,
+
+    /// Whether to translate the review log into exercise trials.
+    pub import_history: bool,
+}
+
+/// A note read from the collection.
+struct AnkiNote {
+    /// The ID of the note.
+    id: i64,
+
+    /// The ID of the deck to which the first card of the note belongs.
+    deck_id: i64,
+
+    /// The fields of the note.
+    fields: Vec<String>,
+}
+
+impl AnkiImporter {
+    /// Opens the collection database. If the collection is an `.apkg` archive, the database is
+    /// extracted to the given temporary directory first.
+    fn open_collection(&self, temp_dir: &TempDir) -> Result<Connection> {
+        // Extract the collection from the archive if needed.
+        let is_archive = self
+            .collection_path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("apkg"));
+        let database_path = if is_archive {
+            let file = File::open(&self.collection_path)?;
+            let mut archive = ZipArchive::new(file).context("invalid .apkg archive")?;
+            let Some(name) = COLLECTION_FILES
+                .iter()
+                .find(|name| archive.index_for_name(name).is_some())
+            else {
+                if archive.index_for_name(COMPRESSED_COLLECTION_FILE).is_some() {
+                    bail!(
+                        "compressed collections are not supported, export the deck with the \
+                        option to support older Anki versions"
+                    );
+                }
+                bail!("no collection found in the .apkg archive");
+            };
+            let database_path = temp_dir.path().join(name);
+            let mut database_file = File::create(&database_path)?;
+            io::copy(&mut archive.by_name(name)?, &mut database_file)?;
+            database_path
+        } else {
+            self.collection_path.clone()
+        };
+
+        // Open the database in read-only mode to avoid modifying the user's collection.
+        Connection::open_with_flags(&database_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .context("failed to open the Anki collection")
+    }
+
+    /// Reads the names of the decks in the collection. Older versions of Anki store the decks as a
+    /// JSON object in the `col` table, while newer versions use a separate `decks` table in which
+    /// the levels of the deck hierarchy are separated by a special character instead of `::`.
+    fn read_decks(connection: &Connection) -> Result<HashMap<i64, String>> {
+        // Read the decks from the `col` table.
+        let decks_json: String =
+            connection.query_row("SELECT decks FROM col", [], |row| row.get(0))?;
+        let decks: HashMap<String, serde_json::Value> =
+            serde_json::from_str(&decks_json).unwrap_or_default();
+        let mut deck_names = HashMap::new();
+        for (id, deck) in decks {
+            if let (Ok(id), Some(name)) = (id.parse::<i64>(), deck["name"].as_str()) {
+                deck_names.insert(id, name.to_string());
+            }
+        }
+        if !deck_names.is_empty() {
+            return Ok(deck_names);
+        }
+
+        // Otherwise, read them from the `decks` table.
+        let mut stmt = connection.prepare("SELECT id, name FROM decks")?;
+        let rows = stmt.query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            let name: String = row.get(1)?;
+            rusqlite::Result::Ok((id, name.replace(FIELD_SEPARATOR, "::")))
+        })?;
+        for row in rows {
+            let (id, name) = row?;
+            deck_names.insert(id, name);
+        }
+        Ok(deck_names)
+    }
+
+    /// Reads the notes in the collection, along with the deck of their first card.
+    fn read_notes(connection: &Connection) -> Result<Vec<AnkiNote>> {
+        // Cards in a filtered deck store the ID of their original deck in the `odid` column.
+        let mut stmt = connection.prepare(
+            "SELECT notes.id, notes.flds,
+                (SELECT CASE WHEN cards.odid != 0 THEN cards.odid ELSE cards.did END
+                    FROM cards WHERE cards.nid = notes.id ORDER BY cards.ord LIMIT 1)
+            FROM notes ORDER BY notes.id",
+        )?;
+        let notes = stmt
+            .query_map([], |row| {
+                let fields: String = row.get(1)?;
+                rusqlite::Result::Ok(AnkiNote {
+                    id: row.get(0)?,
+                    deck_id: row.get::<_, Option<i64>>(2)?.unwrap_or(1),
+                    fields: fields.split(FIELD_SEPARATOR).map(html_to_text).collect(),
+                })
+            })?
+            .map(|r| r.context("failed to read notes from the Anki collection"))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(notes)
+    }
+
+    /// Reads the review log and returns the ID of the note, the timestamp, and the score of each
+    /// review, sorted by timestamp.
+    fn read_reviews(connection: &Connection) -> Result<Vec<(i64, i64, MasteryScore)>> {
+        // The ID of each review is the timestamp in milliseconds. Reviews with an ease of zero are
+        // manual changes to the schedule, not actual reviews.
+        let mut stmt = connection.prepare(
+            "SELECT cards.nid, revlog.id, revlog.ease FROM revlog
+                JOIN cards ON revlog.cid = cards.id
+                WHERE revlog.ease BETWEEN 1 AND 4 ORDER BY revlog.id",
+        )?;
+        let reviews = stmt
+            .query_map([], |row| {
+                let note_id: i64 = row.get(0)?;
+                let timestamp: i64 = row.get(1)?;
+                let ease: i64 = row.get(2)?;
+                rusqlite::Result::Ok((note_id, timestamp / 1000, ease_to_score(ease)))
+            })?
+            .map(|r| r.context("failed to read the review log from the Anki collection"))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(reviews)
+    }
+
+    /// Assigns a unique short ID to each deck.
+    fn assign_lesson_ids(
+        deck_ids: &[i64],
+        deck_names: &HashMap<i64, String>,
+    ) -> HashMap<i64, Ustr> {
+        let mut used_ids = HashSet::new();
+        let mut lesson_ids = HashMap::new();
+        for deck_id in deck_ids {
+            let name = deck_names.get(deck_id).map_or("", String::as_str);
+            let mut short_id = sanitize_id(name);
+            if short_id.is_empty() || used_ids.contains(&short_id) {
+                short_id = format!("{short_id}_{deck_id}")
+                    .trim_start_matches('_')
+                    .to_string();
+            }
+            used_ids.insert(short_id.clone());
+            lesson_ids.insert(*deck_id, Ustr::from(&short_id));
+        }
+        lesson_ids
+    }
+
+    /// Generates the builder for an exercise from a note.
+    fn exercise_builder(&self, lesson_id: Ustr, note: &AnkiNote) -> ExerciseBuilder {
+        let short_id = format!("note_{}", note.id);
+        let front_file = format!("{short_id}{EXERCISE_FRONT_SUFFIX}");
+        let back = note.fields[1..]
+            .iter()
+            .filter(|field| !field.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let back_file = (!back.is_empty()).then(|| format!("{short_id}{EXERCISE_BACK_SUFFIX}"));
+
+        let mut asset_builders = vec![AssetBuilder {
+            file_name: front_file.clone(),
+            contents: note.fields[0].clone(),
+        }];
+        if let Some(back_file) = &back_file {
+            asset_builders.push(AssetBuilder {
+                file_name: back_file.clone(),
+                contents: back,
+            });
+        }
+        ExerciseBuilder {
+            exercise: KnowledgeBaseExercise {
+                short_id,
+                short_lesson_id: lesson_id,
+                course_id: self.course_id,
+                front_file,
+                back_file,
+                name: None,
+                description: None,
+                exercise_type: None,
+                inline_content: None,
+                multiple_choice: None,
+                cloze: None,
+                reverse: None,
+                renamed_from: vec![],
+            },
+            asset_builders,
+        }
+    }
+
+    /// Helper function to import the collection.
+    fn import_helper(&self, parent_directory: &Path) -> Result<ImportResult> {
+        // Read the decks, notes, and reviews from the collection. Notes without a front are
+        // skipped.
+        let temp_dir = TempDir::new()?;
+        let connection = self.open_collection(&temp_dir)?;
+        let deck_names = Self::read_decks(&connection)?;
+        let notes: Vec<AnkiNote> = Self::read_notes(&connection)?
+            .into_iter()
+            .filter(|note| note.fields.first().is_some_and(|front| !front.is_empty()))
+            .collect();
+        let reviews = if self.import_history {
+            Self::read_reviews(&connection)?
+        } else {
+            vec![]
+        };
+
+        // Group the notes by deck and assign a lesson to each deck with notes.
+        let mut notes_by_deck: BTreeMap<i64, Vec<&AnkiNote>> = BTreeMap::new();
+        for note in &notes {
+            notes_by_deck.entry(note.deck_id).or_default().push(note);
+        }
+        let deck_ids: Vec<i64> = notes_by_deck.keys().copied().collect();
+        let lesson_ids = Self::assign_lesson_ids(&deck_ids, &deck_names);
+
+        // Generate the lesson builders.
+        let mut exercise_ids = HashMap::new();
+        let mut lessons = Vec::new();
+        for (deck_id, deck_notes) in &notes_by_deck {
+            let lesson_id = lesson_ids[deck_id];
+            let exercises: Vec<ExerciseBuilder> = deck_notes
+                .iter()
+                .map(|note| self.exercise_builder(lesson_id, note))
+                .collect();
+            for (note, exercise) in deck_notes.iter().zip(&exercises) {
+                exercise_ids.insert(
+                    note.id,
+                    Ustr::from(&format!(
+                        "{}::{}::{}",
+                        self.course_id, lesson_id, exercise.exercise.short_id
+                    )),
+                );
+            }
+            lessons.push(LessonBuilder {
+                lesson: KnowledgeBaseLesson {
+                    short_id: lesson_id,
+                    course_id: self.course_id,
+                    dependencies: vec![],
+                    encompassed: vec![],
+                    superseded: vec![],
+                    name: deck_names.get(deck_id).cloned(),
+                    description: None,
+                    metadata: None,
+                    has_instructions: false,
+                    has_material: false,
+                    default_exercise_type: None,
+                    reverse: false,
+                    renamed_from: vec![],
+                },
+                exercises,
+                asset_builders: vec![],
+            });
+        }
+
+        // Build the course.
+        let course_builder = CourseBuilder {
+            directory_name: self.directory_name.clone(),
+            lessons,
+            assets: vec![],
+            manifest: CourseManifestBuilder::default()
+                .id(self.course_id)
+                .name(self.course_name.clone())
+                .generator_config(Some(CourseGenerator::KnowledgeBase(KnowledgeBaseConfig {
+                    inlined: false,
+                })))
+                .build()?,
+        };
+        course_builder.build(parent_directory)?;
+
+        // Translate the reviews of the imported notes into trials.
+        let trials = reviews
+            .into_iter()
+            .filter_map(|(note_id, timestamp, score)| {
+                Some(ExerciseTrial {
+                    exercise_id: *exercise_ids.get(&note_id)?,
+                    score: score.float_score(),
+                    timestamp,
+                })
+            })
+            .collect();
+        Ok(ImportResult {
+            course_directory: parent_directory.join(&self.directory_name),
+            num_lessons: notes_by_deck.len(),
+            num_exercises: notes.len(),
+            trials,
+        })
+    }
+
+    /// Imports the collection as a knowledge base course in a new directory inside the given
+    /// directory.
+    pub fn import(&self, parent_directory: &Path) -> Result<ImportResult, ImportError> {
+        self.import_helper(parent_directory)
+            .map_err(|e| ImportError::Anki(self.collection_path.display().to_string(), e))
+    }
+}
+
+/// Converts the ease of an Anki review into a mastery score. The four buttons in Anki are again,
+/// hard, good, and easy.
+fn ease_to_score(ease: i64) -> MasteryScore {
+    match ease {
+        1 => MasteryScore::One,
+        2 => MasteryScore::Three,
+        3 => MasteryScore::Four,
+        _ => MasteryScore::Five,
+    }
+}
+
+/// Converts the HTML in the field of a note into plain text. Line breaks and block elements are
+/// turned into new lines, the rest of the tags and the sound references are removed, and the most
+/// common entities are decoded.
+fn html_to_text(html: &str) -> String {
+    // Replace the tags that represent line breaks with new lines and remove the rest.
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end]
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if matches!(tag.as_str(), "br" | "div" | "p" | "li") && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    // Remove the references to sound files.
+    while let Some(start) = text.find("[sound:") {
+        let Some(end) = text[start..].find(']') else {
+            break;
+        };
+        text.replace_range(start..=start + end, "");
+    }
+
+    // Decode the entities.
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use anyhow::Result;
+    use rusqlite::{Connection, params};
+    use std::{fs, io::Write, path::Path};
+    use tempfile::TempDir;
+    use ustr::Ustr;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use crate::{
+        Trane,
+        course_library::{COURSE_MANIFEST_FILENAME, CourseLibrary},
+        importer::anki::{AnkiImporter, html_to_text},
+    };
+
+    /// Creates a minimal Anki collection with two decks, three notes, and a few reviews.
+    fn create_collection(path: &Path) -> Result<()> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE col (id INTEGER PRIMARY KEY, decks TEXT NOT NULL);
+            CREATE TABLE notes (id INTEGER PRIMARY KEY, flds TEXT NOT NULL);
+            CREATE TABLE cards (
+                id INTEGER PRIMARY KEY, nid INTEGER NOT NULL, did INTEGER NOT NULL,
+                ord INTEGER NOT NULL, odid INTEGER NOT NULL);
+            CREATE TABLE revlog (id INTEGER PRIMARY KEY, cid INTEGER NOT NULL, ease INTEGER);",
+        )?;
+        connection.execute(
+            "INSERT INTO col VALUES (1, ?1)",
+            params![r#"{"1": {"name": "Default"}, "2": {"name": "Spanish::Verbs"}}"#],
+        )?;
+        for (id, fields) in [
+            (100, "hablar\x1fto <b>speak</b>"),
+            (200, "comer<br>verb\x1fto eat\x1f[sound:comer.mp3]"),
+            (300, "\x1fno front"),
+        ] {
+            connection.execute("INSERT INTO notes VALUES (?1, ?2)", params![id, fields])?;
+        }
+        for (id, note_id, deck_id, odid) in [(10, 100, 2, 0), (20, 200, 5, 1), (30, 300, 1, 0)] {
+            connection.execute(
+                "INSERT INTO cards VALUES (?1, ?2, ?3, 0, ?4)",
+                params![id, note_id, deck_id, odid],
+            )?;
+        }
+        for (id, card_id, ease) in [
+            (1_000_000, 10, 1),
+            (2_000_000, 10, 3),
+            (3_000_000, 20, 4),
+            (4_000_000, 10, 0),
+        ] {
+            connection.execute(
+                "INSERT INTO revlog VALUES (?1, ?2, ?3)",
+                params![id, card_id, ease],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Verifies converting the HTML of a field into plain text.
+    #[test]
+    fn convert_html() {
+        assert_eq!(html_to_text("a<br>b<br/>c"), "a\nb\nc");
+        assert_eq!(html_to_text("<div>a</div><div>b</div>"), "a\nb");
+        assert_eq!(
+            html_to_text("<b>bold</b> &amp; &lt;tag&gt;"),
+            "bold & <tag>"
+        );
+        assert_eq!(html_to_text("word [sound:word.mp3]"), "word");
+        assert_eq!(html_to_text("a < b"), "a < b");
+    }
+
+    /// Verifies importing a collection database with its review history.
+    #[test]
+    fn import_collection() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let collection_path = temp_dir.path().join("collection.anki2");
+        create_collection(&collection_path)?;
+        let library_dir = temp_dir.path().join("library");
+        let importer = AnkiImporter {
+            collection_path,
+            course_id: Ustr::from("anki"),
+            course_name: "Anki".to_string(),
+            directory_name: "anki".to_string(),
+            import_history: true,
+        };
+        let result = importer.import(&library_dir)?;
+
+        // Verify the generated course. The note without a front is skipped and the note in a
+        // filtered deck is assigned to its original deck.
+        assert_eq!(result.num_lessons, 2);
+        assert_eq!(result.num_exercises, 2);
+        let course_dir = library_dir.join("anki");
+        assert!(course_dir.join(COURSE_MANIFEST_FILENAME).exists());
+        assert_eq!(
+            fs::read_to_string(course_dir.join("spanish_verbs.lesson/note_100.front.md"))?,
+            "hablar"
+        );
+        assert_eq!(
+            fs::read_to_string(course_dir.join("spanish_verbs.lesson/note_100.back.md"))?,
+            "to speak"
+        );
+        assert_eq!(
+            fs::read_to_string(course_dir.join("default.lesson/note_200.front.md"))?,
+            "comer\nverb"
+        );
+        assert_eq!(
+            fs::read_to_string(course_dir.join("default.lesson/note_200.back.md"))?,
+            "to eat"
+        );
+
+        // Verify that the course can be opened.
+        let trane = Trane::new_local(&library_dir, &library_dir)?;
+        let exercise_id = Ustr::from("anki::spanish_verbs::note_100");
+        assert!(trane.get_exercise_manifest(exercise_id).is_some());
+        assert_eq!(
+            trane
+                .get_lesson_manifest(Ustr::from("anki::spanish_verbs"))
+                .unwrap()
+                .name,
+            "Spanish::Verbs"
+        );
+
+        // Verify the translated trials. Manual changes to the schedule are ignored.
+        let trials: Vec<(&str, f32, i64)> = result
+            .trials
+            .iter()
+            .map(|t| (t.exercise_id.as_str(), t.score, t.timestamp))
+            .collect();
+        assert_eq!(
+            trials,
+            vec![
+                ("anki::spanish_verbs::note_100", 1.0, 1000),
+                ("anki::spanish_verbs::note_100", 4.0, 2000),
+                ("anki::default::note_200", 5.0, 3000),
+            ]
+        );
+        Ok(())
+    }
+
+    /// Verifies importing a collection inside an `.apkg` archive without its review history.
+    #[test]
+    fn import_apkg() -> Result<()> {
+        // Create the archive.
+        let temp_dir = TempDir::new()?;
+        let collection_path = temp_dir.path().join("collection.anki2");
+        create_collection(&collection_path)?;
+        let apkg_path = temp_dir.path().join("deck.apkg");
+        let mut zip = ZipWriter::new(fs::File::create(&apkg_path)?);
+        zip.start_file("collection.anki21", SimpleFileOptions::default())?;
+        zip.write_all(&fs::read(&collection_path)?)?;
+        zip.start_file("media", SimpleFileOptions::default())?;
+        zip.write_all(b"{}")?;
+        zip.finish()?;
+
+        // Import the archive.
+        let importer = AnkiImporter {
+            collection_path: apkg_path,
+            course_id: Ustr::from("anki"),
+            course_name: "Anki".to_string(),
+            directory_name: "anki".to_string(),
+            import_history: false,
+        };
+        let result = importer.import(temp_dir.path())?;
+        assert_eq!(result.num_exercises, 2);
+        assert!(result.trials.is_empty());
+
+        // Importing the course again fails because the directory already exists.
+        assert!(importer.import(temp_dir.path()).is_err());
+        Ok(())
+    }
+
+    /// Verifies that importing an archive with an unsupported collection fails.
+    #[test]
+    fn import_compressed_apkg() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let apkg_path = temp_dir.path().join("deck.apkg");
+        let mut zip = ZipWriter::new(fs::File::create(&apkg_path)?);
+        zip.start_file("collection.anki21b", SimpleFileOptions::default())?;
+        zip.write_all(b"compressed")?;
+        zip.finish()?;
+
+        let importer = AnkiImporter {
+            collection_path: apkg_path,
+            course_id: Ustr::from("anki"),
+            course_name: "Anki".to_string(),
+            directory_name: "anki".to_string(),
+            import_history: false,
+        };
+        assert!(importer.import(temp_dir.path()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,760 @@
+//! Imports flashcards from CSV and TSV files into knowledge base courses.
+//!
+//! Each row of the file is turned into an exercise. The columns containing the front and back of
+//! the card, the lesson, the name and type of the exercise, the dependencies of the lesson, and its
+//! metadata are configured with a [`ColumnMapping`]. Rows without a lesson column are added to a
+//! default lesson. Dependencies and metadata are lesson properties, so the values in all the rows of
+//! a lesson are merged. All the rows are validated before generating the course, and every invalid
+//! row is reported along with its line number.
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    fs::File,
+    path::{Path, PathBuf},
+};
+use ustr::Ustr;
+
+use crate::{
+    course_builder::{
+        AssetBuilder,
+        knowledge_base_builder::{
+            CourseBuilder, ExerciseBuilder, LessonBuilder, SimpleKnowledgeBaseCourse,
+            SimpleKnowledgeBaseExercise, SimpleKnowledgeBaseLesson,
+        },
+    },
+    data::{
+        CourseGenerator, CourseManifest, ExerciseType,
+        course_generator::knowledge_base::{
+            EXERCISE_BACK_SUFFIX, EXERCISE_FRONT_SUFFIX, KnowledgeBaseConfig,
+            KnowledgeBaseExercise, KnowledgeBaseLesson,
+        },
+    },
+    error::ImportError,
+    importer::{ImportResult, sanitize_id},
+};
+
+/// A reference to a column, either by its zero-based index or by its name in the header row.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Column {
+    /// The zero-based index of the column.
+    Index(usize),
+
+    /// The name of the column in the header row.
+    Name(String),
+}
+
+/// The mapping of the columns in the file to the properties of the generated exercises and lessons.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ColumnMapping {
+    /// The column containing the front of the card.
+    pub front: Column,
+
+    /// The column containing the back of the card.
+    #[serde(default)]
+    pub back: Option<Column>,
+
+    /// The column containing the name of the lesson to which the exercise belongs. The short ID of
+    /// the lesson is generated from the name.
+    #[serde(default)]
+    pub lesson: Option<Column>,
+
+    /// The column containing the short ID of the exercise. If it's not set, the ID is generated
+    /// from the line number, so setting it is recommended to keep the IDs stable when rows are
+    /// added or reordered.
+    #[serde(default)]
+    pub id: Option<Column>,
+
+    /// The column containing the name of the exercise.
+    #[serde(default)]
+    pub name: Option<Column>,
+
+    /// The column containing the type of the exercise, either "declarative" or "procedural".
+    #[serde(default)]
+    pub exercise_type: Option<Column>,
+
+    /// The column containing the dependencies of the lesson, separated by the list separator. Each
+    /// value can be the name of another lesson in the file or the full ID of a lesson or course.
+    #[serde(default)]
+    pub dependencies: Option<Column>,
+
+    /// A mapping of metadata keys to the columns containing their values, separated by the list
+    /// separator. For example, a column with tags can be mapped to the "tags" key.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, Column>,
+}
+
+/// An error found in a single row of the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RowError {
+    /// The line number of the row, starting at one.
+    pub line: u64,
+
+    /// A description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// The options to import a CSV or TSV file.
+#[derive(Clone, Debug)]
+pub struct CsvImporter {
+    /// The path to the file.
+    pub path: PathBuf,
+
+    /// The manifest of the generated course. The generator config is always set to generate a
+    /// knowledge base course.
+    pub manifest: CourseManifest,
+
+    /// The mapping of the columns.
+    pub columns: ColumnMapping,
+
+    /// The delimiter between columns. If it's not set, tabs are used for files with the `.tsv`
+    /// extension and commas otherwise.
+    pub delimiter: Option<u8>,
+
+    /// Whether the first row of the file contains the names of the columns.
+    pub has_headers: bool,
+
+    /// The separator between the values in the dependencies and metadata columns.
+    pub list_separator: char,
+
+    /// The name of the lesson for rows without a lesson.
+    pub default_lesson: String,
+}
+
+/// An exercise parsed from a row of the file.
+struct ParsedExercise {
+    /// The short ID of the exercise.
+    short_id: String,
+
+    /// The front of the card.
+    front: String,
+
+    /// The back of the card.
+    back: Option<String>,
+
+    /// The name of the exercise.
+    name: Option<String>,
+
+    /// The type of the exercise.
+    exercise_type: Option<ExerciseType>,
+}
+
+/// A lesson parsed from the rows of the file.
+struct ParsedLesson {
+    /// The short ID of the lesson.
+    short_id: Ustr,
+
+    /// The name of the lesson.
+    name: String,
+
+    /// The dependencies of the lesson, as they appear in the file.
+    dependencies: Vec<String>,
+
+    /// The metadata of the lesson.
+    metadata: BTreeMap<String, Vec<String>>,
+
+    /// The exercises in the lesson.
+    exercises: Vec<ParsedExercise>,
+}
+
+/// The columns of the mapping resolved to their indices.
+struct ResolvedColumns {
+    /// The index of the front column.
+    front: usize,
+
+    /// The index of the back column.
+    back: Option<usize>,
+
+    /// The index of the lesson column.
+    lesson: Option<usize>,
+
+    /// The index of the exercise ID column.
+    id: Option<usize>,
+
+    /// The index of the exercise name column.
+    name: Option<usize>,
+
+    /// The index of the exercise type column.
+    exercise_type: Option<usize>,
+
+    /// The index of the dependencies column.
+    dependencies: Option<usize>,
+
+    /// The metadata keys and the indices of their columns.
+    metadata: Vec<(String, usize)>,
+}
+
+impl CsvImporter {
+    /// Returns the delimiter to use for the file.
+    fn delimiter(&self) -> u8 {
+        self.delimiter.unwrap_or_else(|| {
+            let is_tsv = self
+                .path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("tsv"));
+            if is_tsv { b'\t' } else { b',' }
+        })
+    }
+
+    /// Resolves a column to its index.
+    fn resolve_column(column: &Column, headers: Option<&::csv::StringRecord>) -> Result<usize> {
+        match column {
+            Column::Index(index) => Ok(*index),
+            Column::Name(name) => {
+                let headers =
+                    headers.ok_or_else(|| anyhow!("column {name} requires a header row"))?;
+                headers
+                    .iter()
+                    .position(|header| header.trim() == name)
+                    .ok_or_else(|| anyhow!("column {name} not found in the header row"))
+            }
+        }
+    }
+
+    /// Resolves all the columns in the mapping to their indices.
+    fn resolve_columns(&self, headers: Option<&::csv::StringRecord>) -> Result<ResolvedColumns> {
+        let resolve = |column: &Option<Column>| {
+            column
+                .as_ref()
+                .map(|column| Self::resolve_column(column, headers))
+                .transpose()
+        };
+        Ok(ResolvedColumns {
+            front: Self::resolve_column(&self.columns.front, headers)?,
+            back: resolve(&self.columns.back)?,
+            lesson: resolve(&self.columns.lesson)?,
+            id: resolve(&self.columns.id)?,
+            name: resolve(&self.columns.name)?,
+            exercise_type: resolve(&self.columns.exercise_type)?,
+            dependencies: resolve(&self.columns.dependencies)?,
+            metadata: self
+                .columns
+                .metadata
+                .iter()
+                .map(|(key, column)| Ok((key.clone(), Self::resolve_column(column, headers)?)))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Splits the value of a list column into its trimmed, non-empty values.
+    fn split_list(&self, value: &str) -> Vec<String> {
+        value
+            .split(self.list_separator)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Parses a single row into the name of its lesson, the exercise, and the dependencies and
+    /// metadata of the lesson.
+    #[allow(clippy::type_complexity)]
+    fn parse_row(
+        &self,
+        record: &::csv::StringRecord,
+        columns: &ResolvedColumns,
+        line: u64,
+    ) -> Result<(
+        String,
+        ParsedExercise,
+        Vec<String>,
+        Vec<(String, Vec<String>)>,
+    )> {
+        // Read the optional and required values of the row.
+        let optional = |index: Option<usize>| -> Result<Option<String>> {
+            let Some(index) = index else {
+                return Ok(None);
+            };
+            let value = record
+                .get(index)
+                .ok_or_else(|| anyhow!("missing column {index}"))?
+                .trim();
+            Ok((!value.is_empty()).then(|| value.to_string()))
+        };
+        let front = optional(Some(columns.front))?.context("the front of the card is empty")?;
+        let lesson = optional(columns.lesson)?.unwrap_or_else(|| self.default_lesson.clone());
+
+        // Validate the short ID of the exercise.
+        let short_id = optional(columns.id)?.unwrap_or_else(|| format!("row_{line}"));
+        if short_id.contains("::") || short_id.contains(['/', '\\']) {
+            bail!("invalid exercise ID {short_id}");
+        }
+
+        // Parse the type of the exercise.
+        let exercise_type = optional(columns.exercise_type)?
+            .map(|value| match value.to_ascii_lowercase().as_str() {
+                "declarative" => Ok(ExerciseType::Declarative),
+                "procedural" => Ok(ExerciseType::Procedural),
+                _ => Err(anyhow!("invalid exercise type {value}")),
+            })
+            .transpose()?;
+
+        // Read the dependencies and metadata of the lesson.
+        let dependencies = optional(columns.dependencies)?
+            .map(|value| self.split_list(&value))
+            .unwrap_or_default();
+        let metadata = columns
+            .metadata
+            .iter()
+            .map(|(key, index)| {
+                let values = optional(Some(*index))?
+                    .map(|value| self.split_list(&value))
+                    .unwrap_or_default();
+                Ok((key.clone(), values))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let exercise = ParsedExercise {
+            short_id,
+            front,
+            back: optional(columns.back)?,
+            name: optional(columns.name)?,
+            exercise_type,
+        };
+        Ok((lesson, exercise, dependencies, metadata))
+    }
+
+    /// Reads and validates all the rows in the file and groups them by lesson, in the order in
+    /// which the lessons first appear.
+    fn parse_lessons(&self) -> Result<Vec<ParsedLesson>, ImportError> {
+        let path = self.path.display().to_string();
+        let file_error = |e: anyhow::Error| ImportError::Csv(path.clone(), e);
+
+        // Open the file and resolve the columns.
+        let file = File::open(&self.path)
+            .context("failed to open file")
+            .map_err(file_error)?;
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(self.delimiter())
+            .has_headers(self.has_headers)
+            .flexible(true)
+            .from_reader(file);
+        let headers = if self.has_headers {
+            Some(
+                reader
+                    .headers()
+                    .context("failed to read the header row")
+                    .map_err(file_error)?
+                    .clone(),
+            )
+        } else {
+            None
+        };
+        let columns = self.resolve_columns(headers.as_ref()).map_err(file_error)?;
+
+        // Parse every row, collecting the errors instead of stopping at the first one.
+        let mut lessons: Vec<ParsedLesson> = Vec::new();
+        let mut lesson_indices: HashMap<String, usize> = HashMap::new();
+        let mut lesson_ids: HashSet<Ustr> = HashSet::new();
+        let mut exercise_ids: HashSet<(Ustr, String)> = HashSet::new();
+        let mut errors = Vec::new();
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    let line = e.position().map_or(0, ::csv::Position::line);
+                    errors.push(RowError {
+                        line,
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let line = record.position().map_or(0, ::csv::Position::line);
+            let (lesson_name, exercise, dependencies, metadata) =
+                match self.parse_row(&record, &columns, line) {
+                    Ok(parsed) => parsed,
+                    Err(e) => {
+                        errors.push(RowError {
+                            line,
+                            message: e.to_string(),
+                        });
+                        continue;
+                    }
+                };
+
+            // Find or create the lesson. Different names that map to the same short ID are
+            // reported as errors.
+            let lesson_index = if let Some(index) = lesson_indices.get(&lesson_name) {
+                *index
+            } else {
+                let short_id = Ustr::from(&sanitize_id(&lesson_name));
+                if short_id.is_empty() || lesson_ids.contains(&short_id) {
+                    errors.push(RowError {
+                        line,
+                        message: format!("cannot generate a unique ID for lesson {lesson_name}"),
+                    });
+                    continue;
+                }
+                lesson_ids.insert(short_id);
+                lesson_indices.insert(lesson_name.clone(), lessons.len());
+                lessons.push(ParsedLesson {
+                    short_id,
+                    name: lesson_name,
+                    dependencies: vec![],
+                    metadata: BTreeMap::new(),
+                    exercises: vec![],
+                });
+                lessons.len() - 1
+            };
+            let lesson = &mut lessons[lesson_index];
+
+            // Verify that the exercise ID is unique within the lesson.
+            if !exercise_ids.insert((lesson.short_id, exercise.short_id.clone())) {
+                errors.push(RowError {
+                    line,
+                    message: format!("duplicate exercise ID {}", exercise.short_id),
+                });
+                continue;
+            }
+
+            // Merge the dependencies and metadata into the lesson.
+            for dependency in dependencies {
+                if !lesson.dependencies.contains(&dependency) {
+                    lesson.dependencies.push(dependency);
+                }
+            }
+            for (key, values) in metadata {
+                let lesson_values = lesson.metadata.entry(key).or_default();
+                for value in values {
+                    if !lesson_values.contains(&value) {
+                        lesson_values.push(value);
+                    }
+                }
+            }
+            lesson.exercises.push(exercise);
+        }
+
+        if !errors.is_empty() {
+            return Err(ImportError::CsvRows(path, errors));
+        }
+        Ok(lessons)
+    }
+
+    /// Resolves the dependencies of a lesson. Names of other lessons in the file are replaced with
+    /// their short IDs, and the rest of the values are kept as they are.
+    fn resolve_dependencies(lesson: &ParsedLesson, lessons: &[ParsedLesson]) -> Vec<Ustr> {
+        lesson
+            .dependencies
+            .iter()
+            .map(|dependency| {
+                lessons
+                    .iter()
+                    .find(|other| other.name == *dependency)
+                    .map_or_else(|| Ustr::from(dependency), |other| other.short_id)
+            })
+            .collect()
+    }
+
+    /// Returns the manifest of the course with the generator config set.
+    fn course_manifest(&self) -> CourseManifest {
+        let mut manifest = self.manifest.clone();
+        manifest.generator_config = Some(CourseGenerator::KnowledgeBase(KnowledgeBaseConfig {
+            inlined: false,
+        }));
+        manifest
+    }
+
+    /// Generates a simple knowledge base course from the file. The simple format does not support
+    /// the names and types of the exercises, so those columns are ignored.
+    pub fn to_simple_course(&self) -> Result<SimpleKnowledgeBaseCourse, ImportError> {
+        let lessons = self.parse_lessons()?;
+        Ok(SimpleKnowledgeBaseCourse {
+            manifest: self.course_manifest(),
+            encompassed: vec![],
+            lessons: lessons
+                .iter()
+                .map(|lesson| SimpleKnowledgeBaseLesson {
+                    short_id: lesson.short_id,
+                    dependencies: Self::resolve_dependencies(lesson, &lessons),
+                    superseded: vec![],
+                    encompassed: vec![],
+                    exercises: lesson
+                        .exercises
+                        .iter()
+                        .map(|exercise| SimpleKnowledgeBaseExercise {
+                            short_id: exercise.short_id.clone(),
+                            front: vec![exercise.front.clone()],
+                            back: exercise.back.clone().into_iter().collect(),
+                        })
+                        .collect(),
+                    metadata: (!lesson.metadata.is_empty()).then(|| lesson.metadata.clone()),
+                    additional_files: vec![],
+                })
+                .collect(),
+        })
+    }
+
+    /// Generates the builder for an exercise.
+    fn exercise_builder(&self, lesson_id: Ustr, exercise: &ParsedExercise) -> ExerciseBuilder {
+        let front_file = format!("{}{EXERCISE_FRONT_SUFFIX}", exercise.short_id);
+        let back_file = exercise
+            .back
+            .as_ref()
+            .map(|_| format!("{}{EXERCISE_BACK_SUFFIX}", exercise.short_id));
+        let mut asset_builders = vec![AssetBuilder {
+            file_name: front_file.clone(),
+            contents: exercise.front.clone(),
+        }];
+        if let (Some(back_file), Some(back)) = (&back_file, &exercise.back) {
+            asset_builders.push(AssetBuilder {
+                file_name: back_file.clone(),
+                contents: back.clone(),
+            });
+        }
+        ExerciseBuilder {
+            exercise: KnowledgeBaseExercise {
+                short_id: exercise.short_id.clone(),
+                short_lesson_id: lesson_id,
+                course_id: self.manifest.id,
+                front_file,
+                back_file,
+                name: exercise.name.clone(),
+                description: None,
+                exercise_type: exercise.exercise_type.clone(),
+                inline_content: None,
+                multiple_choice: None,
+                cloze: None,
+                reverse: None,
+                renamed_from: vec![],
+            },
+            asset_builders,
+        }
+    }
+
+    /// Generates a builder for the full knowledge base course, which supports all the columns.
+    pub fn to_course_builder(&self, directory_name: &str) -> Result<CourseBuilder, ImportError> {
+        let lessons = self.parse_lessons()?;
+        Ok(CourseBuilder {
+            directory_name: directory_name.to_string(),
+            lessons: lessons
+                .iter()
+                .map(|lesson| LessonBuilder {
+                    lesson: KnowledgeBaseLesson {
+                        short_id: lesson.short_id,
+                        course_id: self.manifest.id,
+                        dependencies: Self::resolve_dependencies(lesson, &lessons),
+                        encompassed: vec![],
+                        superseded: vec![],
+                        name: Some(lesson.name.clone()),
+                        description: None,
+                        metadata: (!lesson.metadata.is_empty()).then(|| lesson.metadata.clone()),
+                        has_instructions: false,
+                        has_material: false,
+                        default_exercise_type: None,
+                        reverse: false,
+                        renamed_from: vec![],
+                    },
+                    exercises: lesson
+                        .exercises
+                        .iter()
+                        .map(|exercise| self.exercise_builder(lesson.short_id, exercise))
+                        .collect(),
+                    asset_builders: vec![],
+                })
+                .collect(),
+            assets: vec![],
+            manifest: self.course_manifest(),
+        })
+    }
+
+    /// Imports the file as a knowledge base course in a new directory inside the given directory.
+    pub fn import(
+        &self,
+        parent_directory: &Path,
+        directory_name: &str,
+    ) -> Result<ImportResult, ImportError> {
+        let course_builder = self.to_course_builder(directory_name)?;
+        course_builder
+            .build(parent_directory)
+            .map_err(|e| ImportError::Csv(self.path.display().to_string(), e))?;
+        Ok(ImportResult {
+            course_directory: parent_directory.join(directory_name),
+            num_lessons: course_builder.lessons.len(),
+            num_exercises: course_builder
+                .lessons
+                .iter()
+                .map(|lesson| lesson.exercises.len())
+                .sum(),
+            trials: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use anyhow::Result;
+    use std::{collections::BTreeMap, fs, path::Path};
+    use tempfile::TempDir;
+    use ustr::Ustr;
+
+    use crate::{
+        Trane,
+        course_library::CourseLibrary,
+        data::{CourseManifestBuilder, ExerciseType},
+        error::ImportError,
+        importer::csv::{Column, ColumnMapping, CsvImporter},
+    };
+
+    /// Returns an importer for the given file with all the columns mapped by name.
+    fn test_importer(path: &Path) -> Result<CsvImporter> {
+        Ok(CsvImporter {
+            path: path.to_path_buf(),
+            manifest: CourseManifestBuilder::default()
+                .id("csv")
+                .name("CSV".to_string())
+                .build()?,
+            columns: ColumnMapping {
+                front: Column::Name("Front".to_string()),
+                back: Some(Column::Name("Back".to_string())),
+                lesson: Some(Column::Name("Lesson".to_string())),
+                id: Some(Column::Name("ID".to_string())),
+                name: Some(Column::Name("Name".to_string())),
+                exercise_type: Some(Column::Name("Type".to_string())),
+                dependencies: Some(Column::Name("Dependencies".to_string())),
+                metadata: BTreeMap::from([("tags".to_string(), Column::Name("Tags".to_string()))]),
+            },
+            delimiter: None,
+            has_headers: true,
+            list_separator: ';',
+            default_lesson: "Default".to_string(),
+        })
+    }
+
+    /// Verifies importing a CSV file into a full knowledge base course.
+    #[test]
+    fn import_csv() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("cards.csv");
+        fs::write(
+            &path,
+            "ID,Front,Back,Lesson,Name,Type,Dependencies,Tags\n\
+            one,uno,one,Numbers,Number one,declarative,,spanish;numbers\n\
+            two,dos,two,Numbers,,,,spanish\n\
+            red,rojo,red,Colors,,Procedural,Numbers,spanish;colors\n\
+            hello,\"hola, amigo\",,,,,,\n",
+        )?;
+        let library_dir = temp_dir.path().join("library");
+        let result = test_importer(&path)?.import(&library_dir, "csv")?;
+        assert_eq!(result.num_lessons, 3);
+        assert_eq!(result.num_exercises, 4);
+
+        // Open the course and verify the generated lessons and exercises.
+        let trane = Trane::new_local(&library_dir, &library_dir)?;
+        let lesson = trane
+            .get_lesson_manifest(Ustr::from("csv::colors"))
+            .unwrap();
+        assert_eq!(lesson.name, "Colors");
+        assert_eq!(lesson.dependencies, vec![Ustr::from("csv::numbers")]);
+        let numbers = trane
+            .get_lesson_manifest(Ustr::from("csv::numbers"))
+            .unwrap();
+        assert_eq!(
+            numbers.metadata.as_ref().unwrap()["tags"],
+            vec!["spanish".to_string(), "numbers".to_string()]
+        );
+        let exercise = trane
+            .get_exercise_manifest(Ustr::from("csv::numbers::one"))
+            .unwrap();
+        assert_eq!(exercise.name, "Number one");
+        assert_eq!(exercise.exercise_type, ExerciseType::Declarative);
+        assert!(
+            trane
+                .get_exercise_manifest(Ustr::from("csv::default::hello"))
+                .is_some()
+        );
+        Ok(())
+    }
+
+    /// Verifies generating a simple knowledge base course from a TSV file without headers.
+    #[test]
+    fn tsv_to_simple_course() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("cards.tsv");
+        fs::write(
+            &path,
+            "uno\tone\tNumbers\ndos\ttwo\tNumbers\nrojo\t\tColors\n",
+        )?;
+        let mut importer = test_importer(&path)?;
+        importer.has_headers = false;
+        importer.columns = ColumnMapping {
+            front: Column::Index(0),
+            back: Some(Column::Index(1)),
+            lesson: Some(Column::Index(2)),
+            id: None,
+            name: None,
+            exercise_type: None,
+            dependencies: None,
+            metadata: BTreeMap::new(),
+        };
+        let course = importer.to_simple_course()?;
+        assert_eq!(course.lessons.len(), 2);
+        assert_eq!(course.lessons[0].short_id, "numbers");
+        assert_eq!(course.lessons[0].exercises.len(), 2);
+        assert_eq!(course.lessons[0].exercises[0].short_id, "row_1");
+        assert_eq!(
+            course.lessons[0].exercises[1].front,
+            vec!["dos".to_string()]
+        );
+        assert!(course.lessons[1].exercises[0].back.is_empty());
+
+        // Build the course to verify it's valid.
+        course.build(&temp_dir.path().join("course"))?;
+        Ok(())
+    }
+
+    /// Verifies that all the invalid rows are reported with their line numbers.
+    #[test]
+    fn invalid_rows() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("cards.csv");
+        fs::write(
+            &path,
+            "ID,Front,Back,Lesson,Name,Type,Dependencies,Tags\n\
+            one,uno,one,Numbers,,,,\n\
+            two,,two,Numbers,,,,\n\
+            one,otro,one,Numbers,,,,\n\
+            three,tres,three,Numbers,,unknown,,\n\
+            a::b,x,,Numbers,,,,\n\
+            short,row\n\
+            four,cuatro,four,Numbers,,,,\n",
+        )?;
+        let Err(ImportError::CsvRows(_, errors)) = test_importer(&path)?.to_simple_course() else {
+            panic!("expected row errors");
+        };
+        let lines: Vec<u64> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![3, 4, 5, 6, 7]);
+        assert!(errors[0].to_string().contains("front"));
+        assert!(errors[1].message.contains("duplicate"));
+        assert!(errors[2].message.contains("exercise type"));
+        Ok(())
+    }
+
+    /// Verifies that a column missing from the header row is reported.
+    #[test]
+    fn missing_column() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("cards.csv");
+        fs::write(&path, "Front,Back\nuno,one\n")?;
+        assert!(matches!(
+            test_importer(&path)?.to_simple_course(),
+            Err(ImportError::Csv(_, _))
+        ));
+
+        // Columns cannot be referenced by name without a header row.
+        let mut importer = test_importer(&path)?;
+        importer.has_headers = false;
+        assert!(importer.to_simple_course().is_err());
+        Ok(())
+    }
+}
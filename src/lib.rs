@@ -54,52 +54,73 @@ pub mod benchmark;
 pub mod blacklist;
 pub mod course_builder;
 pub mod course_library;
+pub mod course_package;
 pub mod data;
 pub mod error;
 pub mod exercise_scorer;
+pub mod exporter;
 pub mod filter_manager;
+pub mod grading;
 pub mod graph;
+pub mod importer;
+pub mod orphaned_data;
 pub mod practice_deltas;
 pub mod practice_rewards;
 pub mod practice_stats;
 pub mod preferences_manager;
+pub mod repository_manager;
 pub mod review_list;
 pub mod reward_scorer;
 pub mod scheduler;
+pub mod session_state;
+pub mod statistics;
 pub mod study_session_manager;
 #[cfg_attr(coverage, coverage(off))]
 pub mod test_utils;
+pub mod trane_builder;
+pub mod user_database;
 pub mod utils;
 
-use anyhow::{Context, Result, bail, ensure};
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Tz;
 use error::*;
 use parking_lot::RwLock;
 use std::{
-    fs::{File, create_dir},
-    io::Write,
-    path::Path,
+    collections::{BTreeMap, BTreeSet},
+    fs::{File, OpenOptions, create_dir},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
     sync::Arc,
 };
 use ustr::{Ustr, UstrMap, UstrSet};
 
 use crate::{
-    blacklist::{Blacklist, LocalBlacklist},
+    blacklist::Blacklist,
     course_library::{CourseLibrary, GetUnitGraph, LocalCourseLibrary, SerializedCourseLibrary},
     data::{
-        CourseManifest, ExerciseDelta, ExerciseManifest, ExerciseTrial, LessonManifest,
-        MasteryScore, SchedulerOptions, SchedulerPreferences, UnitReward, UnitType,
+        AutoReviewAddition, BlacklistEntry, BlacklistReason, CourseManifest, ExerciseAsset,
+        ExerciseDelta, ExerciseManifest, ExerciseTrial, LessonManifest, MasteryScore,
+        RepositoryMetadata, RetentionPolicy, RetentionSummary, ReviewListEntry, SchedulerOptions,
+        SchedulerPreferences, TrialHistoryCursor, TrialHistoryPage, UnitReward, UnitType,
         UserPreferences,
+        course_generator::literacy::LiteracyLessonType,
         filter::{ExerciseFilter, SavedFilter},
     },
-    filter_manager::{FilterManager, LocalFilterManager},
-    graph::UnitGraph,
-    practice_deltas::{LocalPracticeDeltas, PracticeDeltas},
-    practice_rewards::{LocalPracticeRewards, PracticeRewards},
-    practice_stats::{LocalPracticeStats, PracticeStats},
+    filter_manager::FilterManager,
+    graph::{DotAnnotation, DotGraphOptions, UnitGraph},
+    orphaned_data::{OrphanedDataArchive, OrphanedDataReport},
+    practice_deltas::PracticeDeltas,
+    practice_rewards::PracticeRewards,
+    practice_stats::PracticeStats,
     preferences_manager::{LocalPreferencesManager, PreferencesManager},
-    review_list::{LocalReviewList, ReviewList},
+    repository_manager::RepositoryManager,
+    review_list::ReviewList,
     scheduler::{DepthFirstScheduler, ExerciseScheduler, data::SchedulerData},
-    study_session_manager::{LocalStudySessionManager, StudySessionManager},
+    session_state::SessionStateManager,
+    statistics::PracticeStatistics,
+    study_session_manager::StudySessionManager,
+    trane_builder::TraneBuilder,
 };
 
 /// The path to the folder inside each course library containing the user data.
@@ -129,6 +150,24 @@ pub const STUDY_SESSIONS_DIR: &str = "study_sessions";
 /// The path to the file containing user preferences.
 pub const USER_PREFERENCES_PATH: &str = "user_preferences.json";
 
+/// The path to the file containing the short-term state of the current study session.
+pub const SESSION_STATE_PATH: &str = "session_state.json";
+
+/// The path to the `SQLite` database containing all the user data when using the consolidated
+/// storage layout.
+pub const USER_DATABASE_PATH: &str = "user_data.db";
+
+/// The path to the directory containing the archives of orphaned user data removed by the user.
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// The path to the directory inside the config directory containing the metadata of the course
+/// repositories added by the user.
+pub const REPOSITORY_DIRECTORY: &str = "repositories";
+
+/// The path to the directory inside the library root into which the course repositories are
+/// downloaded.
+pub const DOWNLOAD_DIRECTORY: &str = "managed_courses";
+
 /// Trane is a library for the acquisition of highly hierarchical knowledge and skills based on the
 /// principles of mastery learning and spaced repetition. Given a list of courses, its lessons and
 /// corresponding exercises, Trane presents the student with a list of exercises based on the
@@ -159,6 +198,9 @@ pub struct Trane {
     /// The object managing the user preferences.
     preferences_manager: Arc<RwLock<dyn PreferencesManager + Send + Sync>>,
 
+    /// The object managing the repositories from which courses are downloaded.
+    repository_manager: Arc<RwLock<dyn RepositoryManager + Send + Sync>>,
+
     /// The object managing the list of units to review.
     review_list: Arc<RwLock<dyn ReviewList + Send + Sync>>,
 
@@ -169,6 +211,9 @@ pub struct Trane {
     /// The object managing the scheduling algorithm.
     scheduler: DepthFirstScheduler,
 
+    /// The object managing the short-term state of the current study session.
+    session_state_manager: Arc<RwLock<dyn SessionStateManager + Send + Sync>>,
+
     /// The object managing the study sessions saved by the user.
     study_session_manager: Arc<RwLock<dyn StudySessionManager + Send + Sync>>,
 
@@ -182,10 +227,14 @@ impl Trane {
     #[cfg_attr(coverage, coverage(off))]
     fn create_scheduler_options(preferences: Option<&SchedulerPreferences>) -> SchedulerOptions {
         let mut options = SchedulerOptions::default();
-        if let Some(preferences) = preferences
-            && let Some(batch_size) = preferences.batch_size
-        {
-            options.batch_size = batch_size;
+        if let Some(preferences) = preferences {
+            if let Some(batch_size) = preferences.batch_size {
+                options.batch_size = batch_size;
+            }
+            options.auto_review.clone_from(&preferences.auto_review);
+            if let Some(staleness_window) = preferences.session_staleness_window {
+                options.session_staleness_window = staleness_window;
+            }
         }
         options
     }
@@ -236,74 +285,11 @@ impl Trane {
         Ok(())
     }
 
-    /// A helper function to create all the components needed to create a Trane instance that takes
-    /// the already created preferences manager and course library as parameters.
-    #[cfg_attr(coverage, coverage(off))]
-    fn new_local_helper(
-        library_root: &Path,
-        preferences_manager: Arc<RwLock<LocalPreferencesManager>>,
-        course_library: Arc<RwLock<LocalCourseLibrary>>,
-    ) -> Result<Trane> {
-        let config_path = library_root.join(Path::new(TRANE_CONFIG_DIR_PATH));
-        let user_preferences = preferences_manager.read().get_user_preferences()?;
-        let unit_graph = course_library.write().get_unit_graph();
-        let practice_stats = Arc::new(RwLock::new(LocalPracticeStats::new_from_disk(
-            config_path.join(PRACTICE_STATS_PATH).to_str().unwrap(),
-        )?));
-        let practice_deltas = Arc::new(RwLock::new(LocalPracticeDeltas::new_from_disk(
-            config_path.join(PRACTICE_DELTAS_PATH).to_str().unwrap(),
-        )?));
-        let practice_rewards = Arc::new(RwLock::new(LocalPracticeRewards::new_from_disk(
-            config_path.join(PRACTICE_REWARDS_PATH).to_str().unwrap(),
-        )?));
-        let blacklist = Arc::new(RwLock::new(LocalBlacklist::new_from_disk(
-            config_path.join(BLACKLIST_PATH).to_str().unwrap(),
-        )?));
-        let review_list = Arc::new(RwLock::new(LocalReviewList::new_from_disk(
-            config_path.join(REVIEW_LIST_PATH).to_str().unwrap(),
-        )?));
-        let filter_manager = Arc::new(RwLock::new(LocalFilterManager::new(
-            config_path.join(FILTERS_DIR).to_str().unwrap(),
-        )?));
-        let study_sessions_manager = Arc::new(RwLock::new(LocalStudySessionManager::new(
-            config_path.join(STUDY_SESSIONS_DIR).to_str().unwrap(),
-        )?));
-        let options = Self::create_scheduler_options(user_preferences.scheduler.as_ref());
-        options.verify()?;
-        let scheduler_data = SchedulerData {
-            options,
-            course_library: course_library.clone(),
-            unit_graph: unit_graph.clone(),
-            practice_deltas: practice_deltas.clone(),
-            practice_stats: practice_stats.clone(),
-            practice_rewards: practice_rewards.clone(),
-            blacklist: blacklist.clone(),
-            review_list: review_list.clone(),
-            filter_manager: filter_manager.clone(),
-            frequency_map: Arc::new(RwLock::new(UstrMap::default())),
-            trial_counts: Arc::new(RwLock::new((0, 0))),
-        };
-
-        Ok(Trane {
-            blacklist,
-            course_library,
-            filter_manager,
-            library_root: library_root.to_str().unwrap().to_string(),
-            practice_deltas,
-            practice_stats,
-            practice_rewards,
-            preferences_manager,
-            review_list,
-            scheduler_data: scheduler_data.clone(),
-            scheduler: DepthFirstScheduler::new(scheduler_data),
-            study_session_manager: study_sessions_manager,
-            unit_graph,
-        })
-    }
-
     /// Creates a new local instance of the Trane given the path to the root of a course library.
     /// The user data will be stored in a directory named `.trane` inside the library root
-    /// directory. The working directory will be used to resolve relative paths.
+    /// directory. The working directory will be used to resolve relative paths. Use
+    /// [`TraneBuilder`](trane_builder::TraneBuilder) to supply other implementations of the
+    /// storage traits or to keep the user data in memory.
     #[cfg_attr(coverage, coverage(off))]
     pub fn new_local(working_dir: &Path, library_root: &Path) -> Result<Trane> {
         // Initialize the config directory.
@@ -316,13 +302,14 @@ impl Trane {
                 .join(USER_PREFERENCES_PATH),
         }));
         let user_preferences = preferences_manager.read().get_user_preferences()?;
-        let course_library = Arc::new(RwLock::new(LocalCourseLibrary::new(
-            &working_dir.join(library_root),
-            user_preferences.clone(),
-        )?));
+        let course_library =
+            LocalCourseLibrary::new(&working_dir.join(library_root), user_preferences.clone())?;
 
-        // Call the helper function to create the rest of the components.
-        Self::new_local_helper(library_root, preferences_manager, course_library)
+        // Call the builder to create the rest of the components.
+        TraneBuilder::new(library_root)
+            .preferences_manager(preferences_manager)
+            .course_library(course_library)
+            .build()
     }
 
     /// Creates a new local instance of the Trane given the path to the root of a course library.
@@ -344,13 +331,14 @@ impl Trane {
                 .join(USER_PREFERENCES_PATH),
         }));
         let user_preferences = preferences_manager.read().get_user_preferences()?;
-        let course_library = Arc::new(RwLock::new(LocalCourseLibrary::new_from_serialized(
-            serialized_library,
-            user_preferences.clone(),
-        )?));
+        let course_library =
+            LocalCourseLibrary::new_from_serialized(serialized_library, user_preferences.clone())?;
 
-        // Call the helper function to create the rest of the components.
-        Self::new_local_helper(library_root, preferences_manager, course_library)
+        // Call the builder to create the rest of the components.
+        TraneBuilder::new(library_root)
+            .preferences_manager(preferences_manager)
+            .course_library(course_library)
+            .build()
     }
 
     /// Returns the path to the root of the course library.
@@ -358,6 +346,514 @@ impl Trane {
         self.library_root.clone()
     }
 
+    /// Generates a DOT graph of the units selected by the given options. Each node is annotated with
+    /// the unit's score and marked if the unit is blacklisted, superseded, or in the review list.
+    /// See [`UnitGraph::generate_dot_graph`] for more details about the output.
+    pub fn generate_filtered_dot_graph(&self, options: &DotGraphOptions) -> String {
+        // Compute the set of units to include from the filter and the neighborhood. If both are
+        // given, only units that satisfy both are included.
+        let filter_units = options
+            .filter
+            .as_ref()
+            .map(|filter| self.scheduler_data.get_units_in_filter(filter));
+        let neighborhood_units = options
+            .neighborhood
+            .map(|(unit_id, hops)| self.scheduler_data.get_units_within_hops(unit_id, hops));
+        let units = match (filter_units, neighborhood_units) {
+            (None, None) => None,
+            (Some(units), None) | (None, Some(units)) => Some(units),
+            (Some(filter_units), Some(neighborhood_units)) => Some(
+                filter_units
+                    .intersection(&neighborhood_units)
+                    .copied()
+                    .collect(),
+            ),
+        };
+
+        // Annotate all the courses and lessons that will be included in the graph.
+        let review_list: UstrSet = self
+            .review_list
+            .read()
+            .get_review_list_entries()
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        let mut annotations = UstrMap::default();
+        let course_ids = self.course_library.read().get_course_ids();
+        for course_id in course_ids {
+            let mut unit_ids = vec![course_id];
+            if !options.courses_only {
+                unit_ids.extend(
+                    self.course_library
+                        .read()
+                        .get_lesson_ids(course_id)
+                        .unwrap_or_default(),
+                );
+            }
+            for unit_id in unit_ids {
+                if units
+                    .as_ref()
+                    .is_some_and(|units| !units.contains(&unit_id))
+                {
+                    continue;
+                }
+                annotations.insert(
+                    unit_id,
+                    DotAnnotation {
+                        score: self.scheduler.get_unit_score(unit_id).unwrap_or_default(),
+                        blacklisted: self.blacklist.read().blacklisted(unit_id).unwrap_or(false),
+                        superseded: self.scheduler.is_unit_superseded(unit_id),
+                        in_review_list: review_list.contains(&unit_id),
+                    },
+                );
+            }
+        }
+
+        self.unit_graph.read().generate_annotated_dot_graph(
+            options.courses_only,
+            units.as_ref(),
+            &annotations,
+        )
+    }
+
+    /// Returns the IDs of all the courses, lessons, and exercises in the course library.
+    fn get_library_unit_ids(&self) -> UstrSet {
+        let course_library = self.course_library.read();
+        let mut unit_ids = UstrSet::default();
+        for course_id in course_library.get_course_ids() {
+            unit_ids.insert(course_id);
+            unit_ids.extend(course_library.get_lesson_ids(course_id).unwrap_or_default());
+        }
+        unit_ids.extend(course_library.get_all_exercise_ids(None));
+        unit_ids
+    }
+
+    /// Helper function to generate the orphaned data report.
+    fn get_orphaned_data_report_helper(&self) -> Result<OrphanedDataReport> {
+        let blacklist = self
+            .blacklist
+            .read()
+            .get_detailed_blacklist_entries()?
+            .into_iter()
+            .map(|entry| entry.unit_id)
+            .collect();
+        Ok(OrphanedDataReport::new(
+            self.practice_stats.read().get_unit_ids()?,
+            self.practice_deltas.read().get_unit_ids()?,
+            self.practice_rewards.read().get_unit_ids()?,
+            blacklist,
+            self.review_list.read().get_review_list_entries()?,
+            &self.get_library_unit_ids(),
+        ))
+    }
+
+    /// Returns a report of the user data recorded for units that are not part of the course
+    /// library, for example because their course was removed or is now ignored.
+    pub fn get_orphaned_data_report(&self) -> Result<OrphanedDataReport, OrphanedDataError> {
+        self.get_orphaned_data_report_helper()
+            .map_err(OrphanedDataError::GetReport)
+    }
+
+    /// Writes all the orphaned data in the report that will be removed to a JSON file in the
+    /// archive directory and returns the path to the file.
+    fn archive_orphaned_data(&self, report: &OrphanedDataReport) -> Result<PathBuf> {
+        // Collect the data of the units that will be removed. The scores, deltas, and rewards of
+        // ambiguous units are not removed, so they are not archived.
+        let removable = |unit_id: &&Ustr| !report.ambiguous.contains(unit_id);
+        let mut archive = OrphanedDataArchive {
+            created_at: Utc::now().timestamp(),
+            report: report.clone(),
+            ..Default::default()
+        };
+        for unit_id in report.practice_stats.iter().filter(removable) {
+            archive
+                .trials
+                .extend(self.practice_stats.read().get_scores(*unit_id, u32::MAX)?);
+        }
+        for unit_id in report.practice_deltas.iter().filter(removable) {
+            archive
+                .deltas
+                .extend(self.practice_deltas.read().get_deltas(*unit_id, u32::MAX)?);
+        }
+        for unit_id in report.practice_rewards.iter().filter(removable) {
+            archive.rewards.extend(
+                self.practice_rewards
+                    .read()
+                    .get_rewards(*unit_id, u32::MAX)?,
+            );
+        }
+        archive.blacklist = self
+            .blacklist
+            .read()
+            .get_detailed_blacklist_entries()?
+            .into_iter()
+            .filter(|entry| report.blacklist.contains(&entry.unit_id))
+            .collect();
+        archive.review_list = self
+            .review_list
+            .read()
+            .get_detailed_review_list_entries()?
+            .into_iter()
+            .filter(|entry| report.review_list.contains(&entry.unit_id))
+            .collect();
+
+        // Write the archive to a new file in the archive directory.
+        let archive_dir = Path::new(&self.library_root)
+            .join(TRANE_CONFIG_DIR_PATH)
+            .join(ARCHIVE_DIR);
+        if !archive_dir.is_dir() {
+            create_dir(&archive_dir).context("failed to create archive directory")?;
+        }
+        // Never overwrite an existing archive. If one with the same timestamp exists, add a counter
+        // to the name until a new file can be created.
+        let mut counter = 0;
+        let (archive_path, mut file) = loop {
+            let archive_name = if counter == 0 {
+                format!("orphaned_data_{}.json", archive.created_at)
+            } else {
+                format!("orphaned_data_{}_{counter}.json", archive.created_at)
+            };
+            let archive_path = archive_dir.join(archive_name);
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&archive_path)
+            {
+                Ok(file) => break (archive_path, file),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => counter += 1,
+                Err(e) => return Err(e).context("failed to create archive file"),
+            }
+        };
+        let archive_json = serde_json::to_string_pretty(&archive)? + "\n";
+        file.write_all(archive_json.as_bytes())
+            .context("failed to write archive file")?;
+        Ok(archive_path)
+    }
+
+    /// Compacts the databases storing the trials, rewards, and deltas to reclaim the space freed by
+    /// removed entries. The consolidated database only needs to be compacted once.
+    fn compact_user_data(&self) -> Result<()> {
+        if let Some(user_database) = &self.scheduler_data.user_database {
+            user_database.compact()?;
+        } else {
+            self.practice_stats.write().compact()?;
+            self.practice_rewards.write().compact()?;
+            self.practice_deltas.write().compact()?;
+        }
+        Ok(())
+    }
+
+    /// Helper function to remove the orphaned data.
+    fn remove_orphaned_data_helper(&mut self, archive: bool) -> Result<Option<PathBuf>> {
+        // Generate the report and archive the data before removing it, if requested.
+        let report = self.get_orphaned_data_report_helper()?;
+        if report.is_empty() {
+            return Ok(None);
+        }
+        let archive_path = if archive {
+            Some(self.archive_orphaned_data(&report)?)
+        } else {
+            None
+        };
+
+        // Remove all the data in a single transaction, so that either all of it or none is removed.
+        self.scheduler_data.in_transaction(|| {
+            // Remove all the data under the prefixes that do not match any unit in the library.
+            for prefix in report.prefixes.keys() {
+                self.practice_stats
+                    .write()
+                    .remove_scores_with_prefix(prefix)?;
+                self.practice_deltas
+                    .write()
+                    .remove_deltas_with_prefix(prefix)?;
+                self.practice_rewards
+                    .write()
+                    .remove_rewards_with_prefix(prefix)?;
+            }
+
+            // Entries in the blacklist and the review list can be removed individually, so there's
+            // no need to rely on the prefixes.
+            for unit_id in &report.blacklist {
+                self.blacklist.write().remove_from_blacklist(*unit_id)?;
+            }
+            for unit_id in &report.review_list {
+                self.review_list.write().remove_from_review_list(*unit_id)?;
+            }
+            Ok(())
+        })?;
+
+        // Reclaim the space freed by the removed entries once all of them are gone.
+        self.compact_user_data()?;
+        Ok(archive_path)
+    }
+
+    /// Removes the user data recorded for units that are not part of the course library. If
+    /// `archive` is true, the data is first written to a JSON file inside the `archive` directory
+    /// in the config directory, and the path to that file is returned. The scores, deltas, and
+    /// rewards of the units listed as ambiguous in the report are not removed.
+    pub fn remove_orphaned_data(
+        &mut self,
+        archive: bool,
+    ) -> Result<Option<PathBuf>, OrphanedDataError> {
+        self.remove_orphaned_data_helper(archive)
+            .map_err(OrphanedDataError::RemoveData)
+    }
+
+    /// Helper function to apply the retention policy.
+    fn enforce_retention_policy_helper(&mut self) -> Result<RetentionSummary> {
+        // Read the policy from the user preferences and apply it to all the practice history.
+        let preferences = self.preferences_manager.read().get_user_preferences()?;
+        let policy = preferences.retention.unwrap_or_default();
+        let now = Utc::now().timestamp();
+        let summary = RetentionSummary {
+            trials: self
+                .practice_stats
+                .write()
+                .apply_retention_policy(&policy, now)?,
+            rewards: self
+                .practice_rewards
+                .write()
+                .apply_retention_policy(&policy, now)?,
+            deltas: self
+                .practice_deltas
+                .write()
+                .apply_retention_policy(&policy, now)?,
+        };
+
+        // Reclaim the space freed by the removed entries.
+        if policy.compact {
+            self.compact_user_data()?;
+        }
+        Ok(summary)
+    }
+
+    /// Applies the retention policy in the user preferences, or the default policy if none is set,
+    /// to the trials, rewards, and deltas. Returns the number of removed entries of each kind.
+    pub fn enforce_retention_policy(&mut self) -> Result<RetentionSummary, RetentionError> {
+        self.enforce_retention_policy_helper()
+            .map_err(RetentionError::ApplyPolicy)
+    }
+
+    /// Helper function to compute the practice statistics.
+    fn get_practice_statistics_helper(&self, start: i64, end: i64) -> Result<PracticeStatistics> {
+        // Verify the range and read the time zone from the user preferences.
+        ensure!(start < end, "start {start} must be before end {end}");
+        let preferences = self.preferences_manager.read().get_user_preferences()?;
+        let time_zone = statistics::parse_time_zone(preferences.time_zone.as_deref())?;
+
+        // Collect the dates with at least one trial over the entire history to compute the
+        // streaks.
+        let active_dates: BTreeSet<NaiveDate> = self
+            .practice_stats
+            .read()
+            .get_local_daily_trial_counts(i64::MIN, i64::MAX, time_zone)?
+            .into_keys()
+            .collect();
+        let today = statistics::local_date(Utc::now().timestamp(), time_zone)?;
+        let (current_streak, longest_streak) = statistics::compute_streaks(&active_dates, today);
+
+        // Compute the daily and per-course activity in the requested range.
+        let trials = self.practice_stats.read().get_trials_in_range(start, end)?;
+        let daily_activity = statistics::compute_daily_activity(
+            &trials,
+            time_zone,
+            statistics::local_date(start, time_zone)?,
+            statistics::local_date(end - 1, time_zone)?,
+        )?;
+        let unit_graph = self.unit_graph.read();
+        let course_activity =
+            statistics::compute_course_activity(&trials, time_zone, |exercise_id| {
+                let lesson_id = unit_graph.get_exercise_lesson(exercise_id)?;
+                unit_graph.get_lesson_course(lesson_id)
+            })?;
+        Ok(PracticeStatistics {
+            current_streak,
+            longest_streak,
+            num_trials: trials.len(),
+            time_on_task: daily_activity.iter().map(|day| day.time_on_task).sum(),
+            daily_activity,
+            course_activity,
+        })
+    }
+
+    /// Returns the practice statistics of the user. The streaks are computed over the entire
+    /// history, while the daily and per-course activity only include the trials whose timestamps
+    /// are in the range `[start, end)`, which must not be empty. Days are computed in the time zone
+    /// set in the user preferences.
+    pub fn get_practice_statistics(
+        &self,
+        start: i64,
+        end: i64,
+    ) -> Result<PracticeStatistics, StatisticsError> {
+        self.get_practice_statistics_helper(start, end)
+            .map_err(StatisticsError::GetStatistics)
+    }
+
+    /// Returns the asset of the exercise with the given ID.
+    fn get_exercise_asset(&self, exercise_id: Ustr) -> Result<ExerciseAsset> {
+        Ok(self
+            .course_library
+            .read()
+            .get_exercise_manifest(exercise_id)
+            .ok_or_else(|| anyhow!("exercise {exercise_id} does not exist"))?
+            .exercise_asset
+            .clone())
+    }
+
+    /// Helper function to grade and score the answer to a multiple-choice exercise.
+    fn score_multiple_choice_helper(
+        &self,
+        exercise_id: Ustr,
+        selection: &[usize],
+        response_time: Option<f32>,
+        timestamp: i64,
+    ) -> Result<MasteryScore> {
+        // Find the question and grade the answer.
+        let ExerciseAsset::MultipleChoiceAsset(question) = self.get_exercise_asset(exercise_id)?
+        else {
+            bail!("exercise {exercise_id} is not a multiple-choice question");
+        };
+        let score = question.grade(selection, response_time)?;
+
+        // Record the score like any other.
+        self.score_exercise(exercise_id, score.clone(), timestamp)?;
+        Ok(score)
+    }
+
+    /// Grades the options selected by the student for a multiple-choice exercise, given by their
+    /// indices, records the resulting score, and returns it. If the response time in seconds is
+    /// given, correct answers that took longer than expected receive a lower score. See
+    /// [`MultipleChoice::grade`](data::MultipleChoice::grade) for the details.
+    pub fn score_multiple_choice(
+        &self,
+        exercise_id: Ustr,
+        selection: &[usize],
+        response_time: Option<f32>,
+        timestamp: i64,
+    ) -> Result<MasteryScore, GradingError> {
+        self.score_multiple_choice_helper(exercise_id, selection, response_time, timestamp)
+            .map_err(|e| GradingError::Grade(exercise_id, e))
+    }
+
+    /// Helper function to grade and score the answer to a typed-answer exercise.
+    fn score_typed_answer_helper(
+        &self,
+        exercise_id: Ustr,
+        answer: &str,
+        response_time: Option<f32>,
+        timestamp: i64,
+    ) -> Result<MasteryScore> {
+        let ExerciseAsset::TypedAnswerAsset(typed_answer) = self.get_exercise_asset(exercise_id)?
+        else {
+            bail!("exercise {exercise_id} is not a typed-answer exercise");
+        };
+        let score = typed_answer.grade(answer, response_time)?;
+        self.score_exercise(exercise_id, score.clone(), timestamp)?;
+        Ok(score)
+    }
+
+    /// Grades the answer typed by the student for a typed-answer exercise, records the resulting
+    /// score, and returns it. If the response time in seconds is given, correct answers that took
+    /// longer than expected receive a lower score. See
+    /// [`TypedAnswer::grade`](data::TypedAnswer::grade) for the details.
+    pub fn score_typed_answer(
+        &self,
+        exercise_id: Ustr,
+        answer: &str,
+        response_time: Option<f32>,
+        timestamp: i64,
+    ) -> Result<MasteryScore, GradingError> {
+        self.score_typed_answer_helper(exercise_id, answer, response_time, timestamp)
+            .map_err(|e| GradingError::Grade(exercise_id, e))
+    }
+
+    /// Helper function to grade and score the answers to a dictation exercise.
+    fn score_dictation_helper(
+        &self,
+        exercise_id: Ustr,
+        answers: &[(usize, String)],
+        timestamp: i64,
+    ) -> Result<MasteryScore> {
+        let ExerciseAsset::LiteracyAsset {
+            lesson_type: LiteracyLessonType::Dictation,
+            examples,
+            exceptions,
+        } = self.get_exercise_asset(exercise_id)?
+        else {
+            bail!("exercise {exercise_id} is not a dictation exercise");
+        };
+        let score = grading::grade_dictation(&examples, &exceptions, answers)?;
+        self.score_exercise(exercise_id, score.clone(), timestamp)?;
+        Ok(score)
+    }
+
+    /// Grades the answers typed by the student for a literacy dictation exercise, each paired with
+    /// the index of the dictated example or exception, records the resulting score, and returns it.
+    /// See [`grading::grade_dictation`] for the details.
+    pub fn score_dictation(
+        &self,
+        exercise_id: Ustr,
+        answers: &[(usize, String)],
+        timestamp: i64,
+    ) -> Result<MasteryScore, GradingError> {
+        self.score_dictation_helper(exercise_id, answers, timestamp)
+            .map_err(|e| GradingError::Grade(exercise_id, e))
+    }
+
+    /// Saves the short-term state of the current session so that it can be restored later. The
+    /// state is also saved after every batch and score, but errors are ignored in that case because
+    /// the data has already been committed and the state can be discarded. This method can be used
+    /// to find out whether the state can be saved.
+    pub fn save_session_state(&self) -> Result<(), SessionStateError> {
+        let state = self.scheduler.get_session_state(Utc::now().timestamp());
+        self.session_state_manager
+            .write()
+            .save_session_state(&state)
+    }
+
+    /// Saves the session state after a change that has already been committed. The state is
+    /// short-term and disposable, so failing to save it must not fail the change.
+    fn try_save_session_state(&self) {
+        let _ = self.save_session_state();
+    }
+
+    /// Reads the course library from the library root again, so that courses that were added,
+    /// modified, or removed since Trane was opened are picked up. The data recorded under the
+    /// previous IDs of renamed units is moved to their current IDs. Saved filters and study sessions
+    /// are only updated with the new IDs the next time Trane is opened.
+    pub fn reload_library(&mut self) -> Result<()> {
+        // Read the library with the current preferences.
+        let user_preferences = self.preferences_manager.read().get_user_preferences()?;
+        let course_library =
+            LocalCourseLibrary::new(Path::new(&self.library_root), user_preferences)?;
+        let unit_graph = course_library.get_unit_graph();
+
+        // Move the data recorded under the previous IDs of renamed units to their current IDs.
+        for (old_id, new_id) in &course_library.get_renamed_units() {
+            self.practice_stats.write().rename_unit(*old_id, *new_id)?;
+            self.practice_deltas.write().rename_unit(*old_id, *new_id)?;
+            self.practice_rewards
+                .write()
+                .rename_unit(*old_id, *new_id)?;
+            self.blacklist.write().rename_unit(*old_id, *new_id)?;
+            self.review_list.write().rename_unit(*old_id, *new_id)?;
+        }
+
+        // Replace the library and the graph, and create a new scheduler that uses them.
+        let course_library: Arc<RwLock<dyn CourseLibrary + Send + Sync>> =
+            Arc::new(RwLock::new(course_library));
+        self.course_library = course_library.clone();
+        self.unit_graph = unit_graph.clone();
+        self.scheduler_data = SchedulerData {
+            course_library,
+            unit_graph,
+            frequency_map: Arc::new(RwLock::new(UstrMap::default())),
+            ..self.scheduler_data.clone()
+        };
+        self.scheduler = DepthFirstScheduler::new(self.scheduler_data.clone());
+        Ok(())
+    }
+
     /// Returns a clone of the data used by the scheduler. This function is needed by tests that
     /// need to verify internal methods.
     #[allow(dead_code)]
@@ -374,6 +870,19 @@ impl Blacklist for Trane {
         self.blacklist.write().add_to_blacklist(unit_id)
     }
 
+    fn add_to_blacklist_with_details(
+        &mut self,
+        unit_id: Ustr,
+        reason: BlacklistReason,
+        expires_at: Option<i64>,
+    ) -> Result<(), BlacklistError> {
+        // Make sure to invalidate any cached scores for the given unit.
+        self.scheduler.invalidate_cached_score(unit_id);
+        self.blacklist
+            .write()
+            .add_to_blacklist_with_details(unit_id, reason, expires_at)
+    }
+
     fn remove_from_blacklist(&mut self, unit_id: Ustr) -> Result<(), BlacklistError> {
         // Make sure to invalidate any cached scores for the given unit.
         self.scheduler.invalidate_cached_score(unit_id);
@@ -386,6 +895,22 @@ impl Blacklist for Trane {
         self.blacklist.write().remove_prefix_from_blacklist(prefix)
     }
 
+    fn remove_expired_from_blacklist(&mut self, now: i64) -> Result<Vec<Ustr>, BlacklistError> {
+        // Make sure to invalidate any cached scores of the units whose entries expired.
+        let expired = self.blacklist.write().remove_expired_from_blacklist(now)?;
+        for unit_id in &expired {
+            self.scheduler.invalidate_cached_scores_with_prefix(unit_id);
+        }
+        Ok(expired)
+    }
+
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), BlacklistError> {
+        // Make sure to invalidate any cached scores for both units.
+        self.scheduler.invalidate_cached_score(old_id);
+        self.scheduler.invalidate_cached_score(new_id);
+        self.blacklist.write().rename_unit(old_id, new_id)
+    }
+
     fn blacklisted(&self, unit_id: Ustr) -> Result<bool, BlacklistError> {
         self.blacklist.read().blacklisted(unit_id)
     }
@@ -393,6 +918,14 @@ impl Blacklist for Trane {
     fn get_blacklist_entries(&self) -> Result<Vec<Ustr>, BlacklistError> {
         self.blacklist.read().get_blacklist_entries()
     }
+
+    fn get_blacklist_entry(&self, unit_id: Ustr) -> Result<Option<BlacklistEntry>, BlacklistError> {
+        self.blacklist.read().get_blacklist_entry(unit_id)
+    }
+
+    fn get_detailed_blacklist_entries(&self) -> Result<Vec<BlacklistEntry>, BlacklistError> {
+        self.blacklist.read().get_detailed_blacklist_entries()
+    }
 }
 
 #[cfg_attr(coverage, coverage(off))]
@@ -432,6 +965,10 @@ impl CourseLibrary for Trane {
             .read()
             .get_matching_prefix(prefix, unit_type)
     }
+
+    fn get_renamed_units(&self) -> UstrMap<Ustr> {
+        self.course_library.read().get_renamed_units()
+    }
 }
 
 #[cfg_attr(coverage, coverage(off))]
@@ -440,7 +977,9 @@ impl ExerciseScheduler for Trane {
         &self,
         filter: Option<ExerciseFilter>,
     ) -> Result<Vec<ExerciseManifest>, ExerciseSchedulerError> {
-        self.scheduler.get_exercise_batch(filter)
+        let batch = self.scheduler.get_exercise_batch(filter)?;
+        self.try_save_session_state();
+        Ok(batch)
     }
 
     fn score_exercise(
@@ -449,7 +988,28 @@ impl ExerciseScheduler for Trane {
         score: MasteryScore,
         timestamp: i64,
     ) -> Result<(), ExerciseSchedulerError> {
-        self.scheduler.score_exercise(exercise_id, score, timestamp)
+        self.scheduler
+            .score_exercise(exercise_id, score, timestamp)?;
+        self.try_save_session_state();
+        Ok(())
+    }
+
+    fn undo_last_score(&self) -> Result<Option<ExerciseTrial>, ExerciseSchedulerError> {
+        let trial = self.scheduler.undo_last_score()?;
+        self.try_save_session_state();
+        Ok(trial)
+    }
+
+    fn amend_score(
+        &self,
+        exercise_id: Ustr,
+        timestamp: i64,
+        new_score: MasteryScore,
+    ) -> Result<(), ExerciseSchedulerError> {
+        self.scheduler
+            .amend_score(exercise_id, timestamp, new_score)?;
+        self.try_save_session_state();
+        Ok(())
     }
 
     fn get_unit_score(&self, unit_id: Ustr) -> Result<Option<f32>, ExerciseSchedulerError> {
@@ -504,17 +1064,52 @@ impl PracticeRewards for Trane {
             .get_rewards(unit_id, num_rewards)
     }
 
+    fn get_unit_ids(&self) -> Result<Vec<Ustr>, PracticeRewardsError> {
+        self.practice_rewards.read().get_unit_ids()
+    }
+
     fn record_unit_rewards(
         &mut self,
+        exercise_id: Ustr,
         rewards: &[UnitReward],
     ) -> Result<Vec<Ustr>, PracticeRewardsError> {
-        self.practice_rewards.write().record_unit_rewards(rewards)
+        self.practice_rewards
+            .write()
+            .record_unit_rewards(exercise_id, rewards)
+    }
+
+    fn remove_unit_rewards(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<Vec<Ustr>, PracticeRewardsError> {
+        self.practice_rewards
+            .write()
+            .remove_unit_rewards(exercise_id, timestamp)
+    }
+
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), PracticeRewardsError> {
+        self.practice_rewards.write().rename_unit(old_id, new_id)
     }
 
     fn trim_rewards(&mut self, num_rewards: u32) -> Result<(), PracticeRewardsError> {
         self.practice_rewards.write().trim_rewards(num_rewards)
     }
 
+    fn apply_retention_policy(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize, PracticeRewardsError> {
+        self.practice_rewards
+            .write()
+            .apply_retention_policy(policy, now)
+    }
+
+    fn compact(&mut self) -> Result<(), PracticeRewardsError> {
+        self.practice_rewards.write().compact()
+    }
+
     fn remove_rewards_with_prefix(&mut self, prefix: &str) -> Result<(), PracticeRewardsError> {
         self.practice_rewards
             .write()
@@ -534,6 +1129,10 @@ impl PracticeDeltas for Trane {
             .get_deltas(exercise_id, num_deltas)
     }
 
+    fn get_unit_ids(&self) -> Result<Vec<Ustr>, PracticeDeltasError> {
+        self.practice_deltas.read().get_unit_ids()
+    }
+
     fn record_exercise_deltas(
         &mut self,
         deltas: &[ExerciseDelta],
@@ -541,10 +1140,38 @@ impl PracticeDeltas for Trane {
         self.practice_deltas.write().record_exercise_deltas(deltas)
     }
 
+    fn remove_delta(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<(), PracticeDeltasError> {
+        self.practice_deltas
+            .write()
+            .remove_delta(exercise_id, timestamp)
+    }
+
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), PracticeDeltasError> {
+        self.practice_deltas.write().rename_unit(old_id, new_id)
+    }
+
     fn trim_deltas(&mut self, num_deltas: u32) -> Result<(), PracticeDeltasError> {
         self.practice_deltas.write().trim_deltas(num_deltas)
     }
 
+    fn apply_retention_policy(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize, PracticeDeltasError> {
+        self.practice_deltas
+            .write()
+            .apply_retention_policy(policy, now)
+    }
+
+    fn compact(&mut self) -> Result<(), PracticeDeltasError> {
+        self.practice_deltas.write().compact()
+    }
+
     fn remove_deltas_with_prefix(&mut self, prefix: &str) -> Result<(), PracticeDeltasError> {
         self.practice_deltas
             .write()
@@ -564,6 +1191,57 @@ impl PracticeStats for Trane {
             .get_scores(exercise_id, num_scores)
     }
 
+    fn get_unit_ids(&self) -> Result<Vec<Ustr>, PracticeStatsError> {
+        self.practice_stats.read().get_unit_ids()
+    }
+
+    fn get_trials_in_range(
+        &self,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<ExerciseTrial>, PracticeStatsError> {
+        self.practice_stats.read().get_trials_in_range(start, end)
+    }
+
+    fn get_trials_with_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<ExerciseTrial>, PracticeStatsError> {
+        self.practice_stats.read().get_trials_with_prefix(prefix)
+    }
+
+    fn get_daily_trial_counts(
+        &self,
+        start: i64,
+        end: i64,
+        utc_offset: i32,
+    ) -> Result<BTreeMap<NaiveDate, usize>, PracticeStatsError> {
+        self.practice_stats
+            .read()
+            .get_daily_trial_counts(start, end, utc_offset)
+    }
+
+    fn get_local_daily_trial_counts(
+        &self,
+        start: i64,
+        end: i64,
+        time_zone: Tz,
+    ) -> Result<BTreeMap<NaiveDate, usize>, PracticeStatsError> {
+        self.practice_stats
+            .read()
+            .get_local_daily_trial_counts(start, end, time_zone)
+    }
+
+    fn get_trial_history(
+        &self,
+        cursor: Option<TrialHistoryCursor>,
+        page_size: u32,
+    ) -> Result<TrialHistoryPage, PracticeStatsError> {
+        self.practice_stats
+            .read()
+            .get_trial_history(cursor, page_size)
+    }
+
     fn record_exercise_scores(
         &mut self,
         trials: &[ExerciseTrial],
@@ -571,10 +1249,38 @@ impl PracticeStats for Trane {
         self.practice_stats.write().record_exercise_scores(trials)
     }
 
+    fn remove_trial(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<Option<ExerciseTrial>, PracticeStatsError> {
+        self.practice_stats
+            .write()
+            .remove_trial(exercise_id, timestamp)
+    }
+
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), PracticeStatsError> {
+        self.practice_stats.write().rename_unit(old_id, new_id)
+    }
+
     fn trim_scores(&mut self, num_scores: u32) -> Result<(), PracticeStatsError> {
         self.practice_stats.write().trim_scores(num_scores)
     }
 
+    fn apply_retention_policy(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize, PracticeStatsError> {
+        self.practice_stats
+            .write()
+            .apply_retention_policy(policy, now)
+    }
+
+    fn compact(&mut self) -> Result<(), PracticeStatsError> {
+        self.practice_stats.write().compact()
+    }
+
     fn remove_scores_with_prefix(&mut self, prefix: &str) -> Result<(), PracticeStatsError> {
         self.practice_stats
             .write()
@@ -597,19 +1303,107 @@ impl PreferencesManager for Trane {
     }
 }
 
+#[cfg_attr(coverage, coverage(off))]
+impl RepositoryManager for Trane {
+    fn add_repo(&mut self, url: &str, repo_id: Option<&str>) -> Result<(), RepositoryManagerError> {
+        self.repository_manager.write().add_repo(url, repo_id)?;
+        self.reload_library()
+            .map_err(|e| RepositoryManagerError::AddRepo(url.to_string(), e))
+    }
+
+    fn remove_repo(&mut self, repo_id: &str) -> Result<(), RepositoryManagerError> {
+        self.repository_manager.write().remove_repo(repo_id)?;
+        self.reload_library()
+            .map_err(|e| RepositoryManagerError::RemoveRepo(repo_id.to_string(), e))
+    }
+
+    fn update_repo(&mut self, repo_id: &str) -> Result<(), RepositoryManagerError> {
+        self.repository_manager.write().update_repo(repo_id)?;
+        self.reload_library()
+            .map_err(|e| RepositoryManagerError::UpdateRepo(repo_id.to_string(), e))
+    }
+
+    fn update_all_repos(&mut self) -> Result<(), RepositoryManagerError> {
+        self.repository_manager.write().update_all_repos()?;
+        self.reload_library()
+            .map_err(RepositoryManagerError::UpdateRepos)
+    }
+
+    fn list_repos(&self) -> Result<Vec<RepositoryMetadata>, RepositoryManagerError> {
+        self.repository_manager.read().list_repos()
+    }
+}
+
 #[cfg_attr(coverage, coverage(off))]
 impl ReviewList for Trane {
     fn add_to_review_list(&mut self, unit_id: Ustr) -> Result<(), ReviewListError> {
         self.review_list.write().add_to_review_list(unit_id)
     }
 
+    fn add_to_review_list_with_details(
+        &mut self,
+        unit_id: Ustr,
+        priority: u32,
+        remove_after: Option<u32>,
+    ) -> Result<(), ReviewListError> {
+        self.review_list
+            .write()
+            .add_to_review_list_with_details(unit_id, priority, remove_after)
+    }
+
+    fn add_to_review_list_automatically(
+        &mut self,
+        addition: &AutoReviewAddition,
+    ) -> Result<bool, ReviewListError> {
+        self.review_list
+            .write()
+            .add_to_review_list_automatically(addition)
+    }
+
     fn remove_from_review_list(&mut self, unit_id: Ustr) -> Result<(), ReviewListError> {
         self.review_list.write().remove_from_review_list(unit_id)
     }
 
+    fn record_successful_review(&mut self, unit_id: Ustr) -> Result<bool, ReviewListError> {
+        self.review_list.write().record_successful_review(unit_id)
+    }
+
+    fn restore_review_list_entry(
+        &mut self,
+        entry: &ReviewListEntry,
+    ) -> Result<(), ReviewListError> {
+        self.review_list.write().restore_review_list_entry(entry)
+    }
+
+    fn remove_automatic_addition(
+        &mut self,
+        addition: &AutoReviewAddition,
+    ) -> Result<(), ReviewListError> {
+        self.review_list.write().remove_automatic_addition(addition)
+    }
+
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), ReviewListError> {
+        self.review_list.write().rename_unit(old_id, new_id)
+    }
+
     fn get_review_list_entries(&self) -> Result<Vec<Ustr>, ReviewListError> {
         self.review_list.read().get_review_list_entries()
     }
+
+    fn get_review_list_entry(
+        &self,
+        unit_id: Ustr,
+    ) -> Result<Option<ReviewListEntry>, ReviewListError> {
+        self.review_list.read().get_review_list_entry(unit_id)
+    }
+
+    fn get_detailed_review_list_entries(&self) -> Result<Vec<ReviewListEntry>, ReviewListError> {
+        self.review_list.read().get_detailed_review_list_entries()
+    }
+
+    fn get_auto_review_additions(&self) -> Result<Vec<AutoReviewAddition>, ReviewListError> {
+        self.review_list.read().get_auto_review_additions()
+    }
 }
 
 #[cfg_attr(coverage, coverage(off))]
@@ -734,6 +1528,17 @@ impl UnitGraph for Trane {
     fn generate_dot_graph(&self, courses_only: bool) -> String {
         self.unit_graph.read().generate_dot_graph(courses_only)
     }
+
+    fn generate_annotated_dot_graph(
+        &self,
+        courses_only: bool,
+        units: Option<&UstrSet>,
+        annotations: &UstrMap<DotAnnotation>,
+    ) -> String {
+        self.unit_graph
+            .read()
+            .generate_annotated_dot_graph(courses_only, units, annotations)
+    }
 }
 
 /// Implementation of `Send` and `Sync` for `Trane`. The implementation is safe because all the
@@ -746,10 +1551,16 @@ unsafe impl Sync for Trane {}
 mod test {
     use anyhow::Result;
     use std::{fs::*, os::unix::prelude::PermissionsExt};
+    use ustr::Ustr;
 
     use crate::{
-        FILTERS_DIR, STUDY_SESSIONS_DIR, TRANE_CONFIG_DIR_PATH, Trane, USER_PREFERENCES_PATH,
-        data::{SchedulerOptions, SchedulerPreferences, UserPreferences},
+        FILTERS_DIR, SESSION_STATE_PATH, STUDY_SESSIONS_DIR, TRANE_CONFIG_DIR_PATH, Trane,
+        USER_PREFERENCES_PATH,
+        data::{
+            AutoReviewPolicy, MasteryScore, SchedulerOptions, SchedulerPreferences, UserPreferences,
+        },
+        practice_stats::PracticeStats,
+        scheduler::ExerciseScheduler,
     };
 
     /// Verifies retrieving the root of a library.
@@ -884,6 +1695,8 @@ mod test {
             scheduler: None,
             transcription: None,
             ignored_paths: vec![],
+            time_zone: None,
+            retention: None,
         };
         let options = Trane::create_scheduler_options(user_preferences.scheduler.as_ref());
         assert_eq!(options.batch_size, SchedulerOptions::default().batch_size);
@@ -892,11 +1705,67 @@ mod test {
         let user_preferences = UserPreferences {
             scheduler: Some(SchedulerPreferences {
                 batch_size: Some(10),
+                auto_review: Some(AutoReviewPolicy::default()),
+                session_staleness_window: Some(60),
             }),
             transcription: None,
             ignored_paths: vec![],
+            time_zone: None,
+            retention: None,
         };
         let options = Trane::create_scheduler_options(user_preferences.scheduler.as_ref());
         assert_eq!(options.batch_size, 10);
+        assert_eq!(options.auto_review, Some(AutoReviewPolicy::default()));
+        assert_eq!(options.session_staleness_window, 60);
+    }
+
+    /// Verifies that failing to save the session state does not fail the calls that save it after
+    /// committing their changes.
+    #[test]
+    fn ignore_session_state_errors() -> Result<()> {
+        // Replace the session state file with a directory so that it cannot be written.
+        let dir = tempfile::tempdir()?;
+        let trane = Trane::new_local(dir.path(), dir.path())?;
+        let state_path = dir
+            .path()
+            .join(TRANE_CONFIG_DIR_PATH)
+            .join(SESSION_STATE_PATH);
+        let _ = remove_file(&state_path);
+        create_dir(&state_path)?;
+
+        // The batch is returned and the score is recorded, but saving the state explicitly fails.
+        assert!(trane.get_exercise_batch(None)?.is_empty());
+        let exercise_id = Ustr::from("a::b::c");
+        trane.score_exercise(exercise_id, MasteryScore::Five, 1)?;
+        assert_eq!(trane.get_scores(exercise_id, 10)?.len(), 1);
+        assert!(trane.save_session_state().is_err());
+        Ok(())
+    }
+
+    /// Verifies that the state of the session is restored after re-opening the library.
+    #[test]
+    fn restore_session_state() -> Result<()> {
+        // Modify the session state and save it.
+        let dir = tempfile::tempdir()?;
+        let trane = Trane::new_local(dir.path(), dir.path())?;
+        let exercise_id = Ustr::from("a::b::c");
+        trane
+            .scheduler_data
+            .frequency_map
+            .write()
+            .insert(exercise_id, 2);
+        *trane.scheduler_data.trial_counts.write() = (3, 1);
+        trane.save_session_state()?;
+        drop(trane);
+
+        // Re-open the library and verify the state was restored.
+        let trane = Trane::new_local(dir.path(), dir.path())?;
+        let scheduler_data = trane.get_scheduler_data();
+        assert_eq!(
+            scheduler_data.frequency_map.read().get(&exercise_id),
+            Some(&2)
+        );
+        assert_eq!(*scheduler_data.trial_counts.read(), (3, 1));
+        Ok(())
     }
 }
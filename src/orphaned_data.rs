@@ -0,0 +1,254 @@
+//! Defines how to find the user data recorded for units that are no longer part of the course
+//! library.
+//!
+//! Removing a course from the library, or changing the paths ignored by the user preferences, does
+//! not remove the trials, rewards, deltas, blacklist and review list entries recorded for its
+//! units. That data is never read again, but it takes up space in the databases. This module finds
+//! it and summarizes it by prefix so that it can be archived and removed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use ustr::{Ustr, UstrSet};
+
+use crate::data::{BlacklistEntry, ExerciseDelta, ExerciseTrial, ReviewListEntry, UnitReward};
+
+/// A report of the user data recorded for units that are not in the course library.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct OrphanedDataReport {
+    /// The IDs of the units with recorded scores.
+    pub practice_stats: Vec<Ustr>,
+
+    /// The IDs of the units with recorded deltas.
+    pub practice_deltas: Vec<Ustr>,
+
+    /// The IDs of the units with recorded rewards.
+    pub practice_rewards: Vec<Ustr>,
+
+    /// The IDs of the units in the blacklist.
+    pub blacklist: Vec<Ustr>,
+
+    /// The IDs of the units in the review list.
+    pub review_list: Vec<Ustr>,
+
+    /// A summary of the orphaned units, mapping the shortest prefix that contains each unit but no
+    /// unit in the library to the number of orphaned units under it. All the data under these
+    /// prefixes can be removed without affecting the units in the library.
+    pub prefixes: BTreeMap<String, usize>,
+
+    /// The IDs of the orphaned units that share every possible prefix with a unit in the library,
+    /// for example because a lesson was removed but a sibling lesson whose ID starts with the ID of
+    /// the removed lesson still exists. Their scores, deltas, and rewards cannot be removed by
+    /// prefix and are kept when the orphaned data is removed.
+    pub ambiguous: Vec<Ustr>,
+}
+
+impl OrphanedDataReport {
+    /// Creates a report from the IDs of the units with data in each store and the IDs of all the
+    /// units in the library.
+    #[must_use]
+    pub fn new(
+        practice_stats: Vec<Ustr>,
+        practice_deltas: Vec<Ustr>,
+        practice_rewards: Vec<Ustr>,
+        blacklist: Vec<Ustr>,
+        review_list: Vec<Ustr>,
+        library_ids: &UstrSet,
+    ) -> Self {
+        // Only keep the IDs that are not in the library, sorted to make the report easier to read.
+        let orphaned = |unit_ids: Vec<Ustr>| {
+            let mut unit_ids: Vec<Ustr> = unit_ids
+                .into_iter()
+                .filter(|unit_id| !library_ids.contains(unit_id))
+                .collect();
+            unit_ids.sort();
+            unit_ids.dedup();
+            unit_ids
+        };
+        let mut report = OrphanedDataReport {
+            practice_stats: orphaned(practice_stats),
+            practice_deltas: orphaned(practice_deltas),
+            practice_rewards: orphaned(practice_rewards),
+            blacklist: orphaned(blacklist),
+            review_list: orphaned(review_list),
+            ..Default::default()
+        };
+
+        // Summarize all the orphaned units by prefix.
+        for unit_id in report.unit_ids() {
+            match Self::find_safe_prefix(unit_id, library_ids) {
+                Some(prefix) => *report.prefixes.entry(prefix).or_default() += 1,
+                None => report.ambiguous.push(unit_id),
+            }
+        }
+        report
+    }
+
+    /// Returns the IDs of all the orphaned units, sorted alphabetically.
+    #[must_use]
+    pub fn unit_ids(&self) -> Vec<Ustr> {
+        let mut unit_ids: Vec<Ustr> = self
+            .practice_stats
+            .iter()
+            .chain(self.practice_deltas.iter())
+            .chain(self.practice_rewards.iter())
+            .chain(self.blacklist.iter())
+            .chain(self.review_list.iter())
+            .copied()
+            .collect::<UstrSet>()
+            .into_iter()
+            .collect();
+        unit_ids.sort();
+        unit_ids
+    }
+
+    /// Returns whether the report contains no orphaned data.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.practice_stats.is_empty()
+            && self.practice_deltas.is_empty()
+            && self.practice_rewards.is_empty()
+            && self.blacklist.is_empty()
+            && self.review_list.is_empty()
+    }
+
+    /// Returns whether the given prefix matches the given unit ID, using the semantics of the
+    /// `LIKE` operator used by the methods that remove data by prefix. That is, `_` matches any
+    /// character, `%` matches any sequence of characters, and the comparison of ASCII characters
+    /// is case-insensitive.
+    fn matches_like_prefix(prefix: &str, unit_id: &str) -> bool {
+        let mut unit_chars = unit_id.chars();
+        for prefix_char in prefix.chars() {
+            if prefix_char == '%' {
+                return true;
+            }
+            let Some(unit_char) = unit_chars.next() else {
+                return false;
+            };
+            if prefix_char != '_' && !prefix_char.eq_ignore_ascii_case(&unit_char) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Returns the shortest prefix of the given unit ID, made of whole components separated by
+    /// `::`, that does not match any unit in the library. Returns `None` if there's no such
+    /// prefix.
+    fn find_safe_prefix(unit_id: Ustr, library_ids: &UstrSet) -> Option<String> {
+        let components: Vec<&str> = unit_id.split("::").collect();
+        (1..=components.len())
+            .map(|num_components| components[..num_components].join("::"))
+            .find(|prefix| {
+                !library_ids
+                    .iter()
+                    .any(|library_id| Self::matches_like_prefix(prefix, library_id))
+            })
+    }
+}
+
+/// The orphaned data saved before removing it from the databases, so that it can be inspected or
+/// restored manually.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct OrphanedDataArchive {
+    /// The timestamp at which the archive was created.
+    pub created_at: i64,
+
+    /// The report of the orphaned data.
+    pub report: OrphanedDataReport,
+
+    /// The trials of the orphaned units.
+    pub trials: Vec<ExerciseTrial>,
+
+    /// The deltas of the orphaned units.
+    pub deltas: Vec<ExerciseDelta>,
+
+    /// The rewards of the orphaned units.
+    pub rewards: Vec<UnitReward>,
+
+    /// The blacklist entries of the orphaned units.
+    pub blacklist: Vec<BlacklistEntry>,
+
+    /// The review list entries of the orphaned units.
+    pub review_list: Vec<ReviewListEntry>,
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use ustr::{Ustr, UstrSet};
+
+    use crate::orphaned_data::OrphanedDataReport;
+
+    /// Returns a set with the given unit IDs.
+    fn id_set(ids: &[&str]) -> UstrSet {
+        ids.iter().map(|id| Ustr::from(id)).collect()
+    }
+
+    /// Returns a vector with the given unit IDs.
+    fn id_vec(ids: &[&str]) -> Vec<Ustr> {
+        ids.iter().map(|id| Ustr::from(id)).collect()
+    }
+
+    /// Verifies matching prefixes with the semantics of the `LIKE` operator.
+    #[test]
+    fn matches_like_prefix() {
+        assert!(OrphanedDataReport::matches_like_prefix("a::b", "a::b::c"));
+        assert!(OrphanedDataReport::matches_like_prefix("a::b", "a::bc"));
+        assert!(OrphanedDataReport::matches_like_prefix("A::B", "a::b"));
+        assert!(OrphanedDataReport::matches_like_prefix("a_b", "axb::c"));
+        assert!(OrphanedDataReport::matches_like_prefix("a%", "a::b"));
+        assert!(!OrphanedDataReport::matches_like_prefix("a::b", "a::c"));
+        assert!(!OrphanedDataReport::matches_like_prefix("a::b::c", "a::b"));
+    }
+
+    /// Verifies generating a report of the orphaned data.
+    #[test]
+    fn generate_report() {
+        let library_ids = id_set(&["a", "a::0", "a::0::0", "a::10", "a::10::0"]);
+        let report = OrphanedDataReport::new(
+            id_vec(&["a::0::0", "b::0::0", "b::0::1", "a::1::0", "a::0::1"]),
+            id_vec(&["b::0::0"]),
+            id_vec(&["a::0", "a::1", "b::0", "b"]),
+            id_vec(&["c::0"]),
+            vec![],
+            &library_ids,
+        );
+
+        // Only the units not in the library are included.
+        assert_eq!(
+            report.practice_stats,
+            id_vec(&["a::0::1", "a::1::0", "b::0::0", "b::0::1"])
+        );
+        assert_eq!(report.practice_deltas, id_vec(&["b::0::0"]));
+        assert_eq!(report.practice_rewards, id_vec(&["a::1", "b", "b::0"]));
+        assert_eq!(report.blacklist, id_vec(&["c::0"]));
+        assert!(report.review_list.is_empty());
+        assert!(!report.is_empty());
+
+        // The units are summarized by the shortest prefix that does not match the library. The
+        // lesson `a::1` is a prefix of the lesson `a::10`, so it's ambiguous.
+        assert_eq!(report.prefixes.get("b"), Some(&4));
+        assert_eq!(report.prefixes.get("c"), Some(&1));
+        assert_eq!(report.prefixes.get("a::0::1"), Some(&1));
+        assert_eq!(report.prefixes.get("a::1::0"), Some(&1));
+        assert_eq!(report.prefixes.len(), 4);
+        assert_eq!(report.ambiguous, id_vec(&["a::1"]));
+    }
+
+    /// Verifies that a report with no orphaned data is empty.
+    #[test]
+    fn empty_report() {
+        let library_ids = id_set(&["a", "a::0", "a::0::0"]);
+        let report = OrphanedDataReport::new(
+            id_vec(&["a::0::0"]),
+            vec![],
+            id_vec(&["a::0"]),
+            vec![],
+            id_vec(&["a"]),
+            &library_ids,
+        );
+        assert!(report.is_empty());
+        assert!(report.prefixes.is_empty());
+        assert!(report.unit_ids().is_empty());
+    }
+}
@@ -8,9 +8,14 @@ use anyhow::{Context, Ok, Result};
 use parking_lot::Mutex;
 use rusqlite::{Connection, params};
 use rusqlite_migration::{M, Migrations};
+use std::sync::Arc;
 use ustr::Ustr;
 
-use crate::{data::ExerciseDelta, error::PracticeDeltasError, utils};
+use crate::{
+    data::{ExerciseDelta, RetentionPolicy},
+    error::PracticeDeltasError,
+    utils,
+};
 
 /// Contains functions to retrieve and record the deltas between the student's actual scores and the
 /// predicted scores.
@@ -23,6 +28,9 @@ pub trait PracticeDeltas {
         num_deltas: u32,
     ) -> Result<Vec<ExerciseDelta>, PracticeDeltasError>;
 
+    /// Returns the IDs of all the units with recorded deltas.
+    fn get_unit_ids(&self) -> Result<Vec<Ustr>, PracticeDeltasError>;
+
     /// Records the deltas between the student's actual scores and the predicted scores for one or
     /// more exercises.
     fn record_exercise_deltas(
@@ -30,18 +38,42 @@ pub trait PracticeDeltas {
         deltas: &[ExerciseDelta],
     ) -> Result<(), PracticeDeltasError>;
 
+    /// Removes the delta of the given exercise recorded at the given timestamp, if it exists.
+    fn remove_delta(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<(), PracticeDeltasError>;
+
+    /// Moves all the deltas of the unit with ID `old_id` to the unit with ID `new_id`. Used when a
+    /// unit is renamed. If the new unit already has deltas, both sets are merged.
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), PracticeDeltasError>;
+
     /// Deletes all the exercise trials except for the last `num_deltas` with the aim of keeping the
     /// storage size under check.
     fn trim_deltas(&mut self, num_deltas: u32) -> Result<(), PracticeDeltasError>;
 
-    /// Removes all the deltas from the units that match the given prefix.
+    /// Removes the old deltas according to the given retention policy. All the deltas newer than
+    /// the retention window and the first delta of each unit are kept. Returns the number of
+    /// removed deltas.
+    fn apply_retention_policy(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize, PracticeDeltasError>;
+
+    /// Compacts the database to reclaim the space freed by removed deltas.
+    fn compact(&mut self) -> Result<(), PracticeDeltasError>;
+
+    /// Removes all the deltas from the units that match the given prefix. The freed space is not
+    /// reclaimed until the database is compacted.
     fn remove_deltas_with_prefix(&mut self, prefix: &str) -> Result<(), PracticeDeltasError>;
 }
 
 /// An implementation of [`PracticeDeltas`] backed by `SQLite`.
 pub struct LocalPracticeDeltas {
     /// A connection to the database.
-    connection: Mutex<Connection>,
+    connection: Arc<Mutex<Connection>>,
 }
 
 impl LocalPracticeDeltas {
@@ -77,17 +109,30 @@ impl LocalPracticeDeltas {
     /// Creates a new instance with the given connection and initializes the database.
     fn new(connection: Connection) -> Result<LocalPracticeDeltas> {
         let mut deltas = LocalPracticeDeltas {
-            connection: Mutex::new(connection),
+            connection: Arc::new(Mutex::new(connection)),
         };
         deltas.init()?;
         Ok(deltas)
     }
 
+    /// Creates a new instance that shares the given connection with the other stores in the
+    /// consolidated user database. The schema is managed by
+    /// [`UserDatabase`](crate::user_database::UserDatabase), so no migrations are run.
+    pub(crate) fn new_shared(connection: Arc<Mutex<Connection>>) -> LocalPracticeDeltas {
+        LocalPracticeDeltas { connection }
+    }
+
     /// A constructor taking the path to a database file.
     pub fn new_from_disk(db_path: &str) -> Result<LocalPracticeDeltas> {
         Self::new(utils::new_connection(db_path)?)
     }
 
+    /// A constructor for a database that is kept in memory and discarded when dropped. Useful to
+    /// run Trane without writing any user data to the file system.
+    pub fn new_in_memory() -> Result<LocalPracticeDeltas> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
     /// Helper function to retrieve deltas from the database.
     fn get_deltas_helper(&self, exercise_id: Ustr, num_deltas: u32) -> Result<Vec<ExerciseDelta>> {
         let connection = self.connection.lock();
@@ -113,10 +158,27 @@ impl LocalPracticeDeltas {
         Ok(rows)
     }
 
+    /// Helper function to retrieve the IDs of all the units with recorded deltas.
+    fn get_unit_ids_helper(&self) -> Result<Vec<Ustr>> {
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT unit_id FROM uids WHERE unit_uid IN (
+                SELECT DISTINCT unit_uid FROM practice_deltas);",
+        )?;
+        let unit_ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|r| {
+                r.map(|unit_id| Ustr::from(&unit_id))
+                    .context("failed to retrieve unit IDs from practice_deltas DB")
+            })
+            .collect::<Result<Vec<Ustr>, _>>()?;
+        Ok(unit_ids)
+    }
+
     /// Helper function to record deltas to the database.
     fn record_exercise_deltas_helper(&mut self, deltas: &[ExerciseDelta]) -> Result<()> {
         let mut connection = self.connection.lock();
-        let tx = connection.transaction()?;
+        let tx = connection.savepoint()?;
         {
             let mut uid_stmt =
                 tx.prepare_cached("INSERT OR IGNORE INTO uids(unit_id) VALUES ($1);")?;
@@ -137,6 +199,30 @@ impl LocalPracticeDeltas {
         Ok(())
     }
 
+    /// Helper function to remove a single delta from the database.
+    fn remove_delta_helper(&mut self, exercise_id: Ustr, timestamp: i64) -> Result<()> {
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "DELETE FROM practice_deltas WHERE id IN (
+                SELECT id FROM practice_deltas WHERE unit_uid = (
+                    SELECT unit_uid FROM uids WHERE unit_id = $1) AND timestamp = $2
+                ORDER BY id DESC LIMIT 1);",
+        )?;
+        let _ = stmt.execute(params![exercise_id.as_str(), timestamp])?;
+        Ok(())
+    }
+
+    /// Helper function to move the deltas of a unit to a new ID.
+    fn rename_unit_helper(&mut self, old_id: Ustr, new_id: Ustr) -> Result<()> {
+        utils::rename_unit_uid(
+            &mut self.connection.lock(),
+            "practice_deltas",
+            &["unit_uid"],
+            &old_id,
+            &new_id,
+        )
+    }
+
     /// Helper function to trim the number of deltas for each exercise.
     fn trim_deltas_helper(&mut self, num_deltas: u32) -> Result<()> {
         let connection = self.connection.lock();
@@ -154,7 +240,22 @@ impl LocalPracticeDeltas {
             )?;
             let _ = stmt.execute(params![uid, num_deltas])?;
         }
+        Ok(())
+    }
+
+    /// Helper function to apply a retention policy to the deltas.
+    fn apply_retention_policy_helper(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize> {
+        let connection = self.connection.lock();
+        utils::apply_retention_policy(&connection, "practice_deltas", policy, now, "FALSE", &[])
+    }
 
+    /// Helper function to compact the database.
+    fn compact_helper(&mut self) -> Result<()> {
+        let connection = self.connection.lock();
         connection.execute_batch("VACUUM;")?;
         Ok(())
     }
@@ -174,8 +275,6 @@ impl LocalPracticeDeltas {
                 connection.prepare_cached("DELETE FROM practice_deltas WHERE unit_uid = $1;")?;
             let _ = stmt.execute(params![uid])?;
         }
-
-        connection.execute_batch("VACUUM;")?;
         Ok(())
     }
 }
@@ -190,6 +289,11 @@ impl PracticeDeltas for LocalPracticeDeltas {
             .map_err(|e| PracticeDeltasError::GetDeltas(exercise_id, e))
     }
 
+    fn get_unit_ids(&self) -> Result<Vec<Ustr>, PracticeDeltasError> {
+        self.get_unit_ids_helper()
+            .map_err(PracticeDeltasError::GetUnitIds)
+    }
+
     fn record_exercise_deltas(
         &mut self,
         deltas: &[ExerciseDelta],
@@ -198,11 +302,38 @@ impl PracticeDeltas for LocalPracticeDeltas {
             .map_err(PracticeDeltasError::RecordDelta)
     }
 
+    fn remove_delta(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<(), PracticeDeltasError> {
+        self.remove_delta_helper(exercise_id, timestamp)
+            .map_err(|e| PracticeDeltasError::RemoveDelta(exercise_id, e))
+    }
+
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), PracticeDeltasError> {
+        self.rename_unit_helper(old_id, new_id)
+            .map_err(|e| PracticeDeltasError::RenameUnit(old_id, new_id, e))
+    }
+
     fn trim_deltas(&mut self, num_deltas: u32) -> Result<(), PracticeDeltasError> {
         self.trim_deltas_helper(num_deltas)
             .map_err(PracticeDeltasError::TrimDeltas)
     }
 
+    fn apply_retention_policy(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize, PracticeDeltasError> {
+        self.apply_retention_policy_helper(policy, now)
+            .map_err(PracticeDeltasError::ApplyRetention)
+    }
+
+    fn compact(&mut self) -> Result<(), PracticeDeltasError> {
+        self.compact_helper().map_err(PracticeDeltasError::Compact)
+    }
+
     fn remove_deltas_with_prefix(&mut self, prefix: &str) -> Result<(), PracticeDeltasError> {
         self.remove_deltas_with_prefix_helper(prefix)
             .map_err(|e| PracticeDeltasError::RemovePrefix(prefix.to_string(), e))
@@ -217,7 +348,7 @@ mod test {
     use ustr::Ustr;
 
     use crate::{
-        data::ExerciseDelta,
+        data::{ExerciseDelta, RetentionPolicy},
         practice_deltas::{LocalPracticeDeltas, PracticeDeltas},
     };
 
@@ -387,4 +518,54 @@ mod test {
 
         Ok(())
     }
+
+    /// Verifies removing a single delta.
+    #[test]
+    fn remove_delta() -> Result<()> {
+        let mut deltas = new_test_deltas()?;
+        let exercise_id = Ustr::from("exercise1");
+        deltas.record_exercise_deltas(&[
+            delta(exercise_id, 0.1, 1),
+            delta(exercise_id, 0.2, 2),
+            delta(exercise_id, 0.3, 3),
+        ])?;
+
+        // Remove the delta in the middle and verify the other deltas are kept.
+        deltas.remove_delta(exercise_id, 2)?;
+        let results = deltas.get_deltas(exercise_id, 10)?;
+        assert_deltas(&[0.3, 0.1], &results);
+
+        // Removing a delta that does not exist is not an error.
+        deltas.remove_delta(exercise_id, 2)?;
+        let results = deltas.get_deltas(exercise_id, 10)?;
+        assert_deltas(&[0.3, 0.1], &results);
+        Ok(())
+    }
+
+    /// Verifies applying a retention policy to the deltas.
+    #[test]
+    fn apply_retention_policy() -> Result<()> {
+        let mut deltas = new_test_deltas()?;
+        let exercise_id = Ustr::from("a::b::1");
+        deltas.record_exercise_deltas(&[
+            delta(exercise_id, 1.0, 1),
+            delta(exercise_id, 2.0, 5),
+            delta(exercise_id, 3.0, 8),
+            delta(exercise_id, 4.0, 12),
+            delta(exercise_id, 5.0, 150),
+        ])?;
+
+        // The first delta, the last delta in each interval, and the recent deltas are kept.
+        let policy = RetentionPolicy {
+            retention_window: 100,
+            downsample_interval: 10,
+            mastered_score: 4.0,
+            lapse_score: 3.0,
+            compact: true,
+        };
+        assert_eq!(deltas.apply_retention_policy(&policy, 200)?, 1);
+        assert_deltas(&[5.0, 4.0, 3.0, 1.0], &deltas.get_deltas(exercise_id, 10)?);
+        deltas.compact()?;
+        Ok(())
+    }
 }
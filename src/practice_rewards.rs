@@ -17,10 +17,14 @@ use anyhow::{Context, Ok, Result};
 use parking_lot::Mutex;
 use rusqlite::{Connection, params};
 use rusqlite_migration::{M, Migrations};
-use std::collections::VecDeque;
+use std::{collections::VecDeque, sync::Arc};
 use ustr::{Ustr, UstrMap};
 
-use crate::{data::UnitReward, error::PracticeRewardsError, utils};
+use crate::{
+    data::{RetentionPolicy, UnitReward},
+    error::PracticeRewardsError,
+    utils,
+};
 
 /// Contains functions to retrieve and record rewards for lessons and courses.
 pub trait PracticeRewards {
@@ -32,18 +36,49 @@ pub trait PracticeRewards {
         num_rewards: u32,
     ) -> Result<Vec<UnitReward>, PracticeRewardsError>;
 
-    /// Records multiple rewards in a single transaction. Returns the list of unit IDs whose
-    /// rewards were actually written (not skipped by cache).
+    /// Returns the IDs of all the units with recorded rewards.
+    fn get_unit_ids(&self) -> Result<Vec<Ustr>, PracticeRewardsError>;
+
+    /// Records multiple rewards produced by scoring the exercise with the given ID in a single
+    /// transaction. Returns the list of unit IDs whose rewards were actually written (not skipped
+    /// by cache).
     fn record_unit_rewards(
         &mut self,
+        exercise_id: Ustr,
         rewards: &[UnitReward],
     ) -> Result<Vec<Ustr>, PracticeRewardsError>;
 
+    /// Removes the rewards produced by the trial of the given exercise at the given timestamp. Used
+    /// to roll back the rewards of a trial. Returns the list of unit IDs whose rewards were
+    /// removed.
+    fn remove_unit_rewards(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<Vec<Ustr>, PracticeRewardsError>;
+
+    /// Moves all the rewards of the unit with ID `old_id` to the unit with ID `new_id`. Used when a
+    /// unit is renamed. If the new unit already has rewards, both sets are merged.
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), PracticeRewardsError>;
+
     /// Deletes all rewards of the given unit except for the last given number with the aim of
     /// keeping the storage size under check.
     fn trim_rewards(&mut self, num_rewards: u32) -> Result<(), PracticeRewardsError>;
 
-    /// Removes all the rewards from the units that match the given prefix.
+    /// Removes the old rewards according to the given retention policy. All the rewards newer than
+    /// the retention window and the first reward of each unit are kept. Returns the number of
+    /// removed rewards.
+    fn apply_retention_policy(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize, PracticeRewardsError>;
+
+    /// Compacts the database to reclaim the space freed by removed rewards.
+    fn compact(&mut self) -> Result<(), PracticeRewardsError>;
+
+    /// Removes all the rewards from the units that match the given prefix. The freed space is not
+    /// reclaimed until the database is compacted.
     fn remove_rewards_with_prefix(&mut self, prefix: &str) -> Result<(), PracticeRewardsError>;
 }
 
@@ -90,12 +125,19 @@ impl RewardCache {
         }
         rewards.push_back(reward);
     }
+
+    /// Removes the rewards of the given unit with the given timestamp from the cache.
+    fn remove_reward(&mut self, unit_id: Ustr, timestamp: i64) {
+        if let Some(rewards) = self.cache.get_mut(&unit_id) {
+            rewards.retain(|r| r.timestamp != timestamp);
+        }
+    }
 }
 
 /// An implementation of [`PracticeRewards`] backed by `SQLite`.
 pub struct LocalPracticeRewards {
     /// A connection to the database.
-    connection: Mutex<Connection>,
+    connection: Arc<Mutex<Connection>>,
 
     /// A cache of previous rewards to avoid storing the same reward multiple times.
     cache: RewardCache,
@@ -125,6 +167,14 @@ impl LocalPracticeRewards {
             // Create a combined index of `unit_uid` and `timestamp` for fast reward retrieval.
             M::up("CREATE INDEX rewards ON practice_rewards (unit_uid, timestamp);")
                 .down("DROP INDEX rewards"),
+            // Store the exercise whose score produced each reward so that the rewards of a trial
+            // can be removed without affecting those of other trials with the same timestamp.
+            M::up("ALTER TABLE practice_rewards ADD COLUMN exercise_uid INTEGER;")
+                .down("ALTER TABLE practice_rewards DROP COLUMN exercise_uid;"),
+            M::up(
+                "CREATE INDEX rewards_by_exercise ON practice_rewards (exercise_uid, timestamp);",
+            )
+            .down("DROP INDEX rewards_by_exercise"),
         ])
     }
 
@@ -141,7 +191,7 @@ impl LocalPracticeRewards {
     /// Creates a new instance with the given connection and initializes the database.
     fn new(connection: Connection) -> Result<LocalPracticeRewards> {
         let mut rewards = LocalPracticeRewards {
-            connection: Mutex::new(connection),
+            connection: Arc::new(Mutex::new(connection)),
             cache: RewardCache {
                 cache: UstrMap::default(),
             },
@@ -150,11 +200,29 @@ impl LocalPracticeRewards {
         Ok(rewards)
     }
 
+    /// Creates a new instance that shares the given connection with the other stores in the
+    /// consolidated user database. The schema is managed by
+    /// [`UserDatabase`](crate::user_database::UserDatabase), so no migrations are run.
+    pub(crate) fn new_shared(connection: Arc<Mutex<Connection>>) -> LocalPracticeRewards {
+        LocalPracticeRewards {
+            connection,
+            cache: RewardCache {
+                cache: UstrMap::default(),
+            },
+        }
+    }
+
     /// A constructor taking the path to a database file.
     pub fn new_from_disk(db_path: &str) -> Result<LocalPracticeRewards> {
         Self::new(utils::new_connection(db_path)?)
     }
 
+    /// A constructor for a database that is kept in memory and discarded when dropped. Useful to
+    /// run Trane without writing any user data to the file system.
+    pub fn new_in_memory() -> Result<LocalPracticeRewards> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
     /// Helper function to retrieve rewards from the database.
     fn get_rewards_helper(&self, unit_id: Ustr, num_rewards: u32) -> Result<Vec<UnitReward>> {
         // Retrieve the rewards from the database.
@@ -184,12 +252,37 @@ impl LocalPracticeRewards {
         Ok(rows)
     }
 
+    /// Helper function to retrieve the IDs of all the units with recorded rewards.
+    fn get_unit_ids_helper(&self) -> Result<Vec<Ustr>> {
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT unit_id FROM uids WHERE unit_uid IN (
+                SELECT DISTINCT unit_uid FROM practice_rewards);",
+        )?;
+        let unit_ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|r| {
+                r.map(|unit_id| Ustr::from(&unit_id))
+                    .context("failed to retrieve unit IDs from practice_rewards DB")
+            })
+            .collect::<Result<Vec<Ustr>, _>>()?;
+        Ok(unit_ids)
+    }
+
     /// Helper function to record multiple rewards in a single transaction.
-    fn record_unit_rewards_helper(&mut self, rewards: &[UnitReward]) -> Result<Vec<Ustr>> {
+    fn record_unit_rewards_helper(
+        &mut self,
+        exercise_id: Ustr,
+        rewards: &[UnitReward],
+    ) -> Result<Vec<Ustr>> {
         let mut updated = Vec::new();
         let mut connection = self.connection.lock();
-        let tx = connection.transaction()?;
+        let tx = connection.savepoint()?;
         {
+            tx.execute(
+                "INSERT OR IGNORE INTO uids(unit_id) VALUES ($1);",
+                params![exercise_id.as_str()],
+            )?;
             for reward in rewards {
                 if self.cache.has_similar_reward(reward.unit_id, reward) {
                     continue;
@@ -200,14 +293,17 @@ impl LocalPracticeRewards {
                 uid_stmt.execute(params![reward.unit_id.as_str()])?;
 
                 let mut stmt = tx.prepare_cached(
-                    "INSERT INTO practice_rewards (unit_uid, reward, weight, timestamp) VALUES (
-                        (SELECT unit_uid FROM uids WHERE unit_id = $1), $2, $3, $4);",
+                    "INSERT INTO practice_rewards (
+                        unit_uid, reward, weight, timestamp, exercise_uid) VALUES (
+                        (SELECT unit_uid FROM uids WHERE unit_id = $1), $2, $3, $4,
+                        (SELECT unit_uid FROM uids WHERE unit_id = $5));",
                 )?;
                 stmt.execute(params![
                     reward.unit_id.as_str(),
                     reward.value,
                     reward.weight,
-                    reward.timestamp
+                    reward.timestamp,
+                    exercise_id.as_str(),
                 ])?;
 
                 let mut del_stmt = tx.prepare_cached(
@@ -227,6 +323,58 @@ impl LocalPracticeRewards {
         Ok(updated)
     }
 
+    /// Helper function to remove the rewards of a trial in a single transaction.
+    fn remove_unit_rewards_helper(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<Vec<Ustr>> {
+        // Retrieve the units rewarded by the trial before removing their rewards.
+        let mut connection = self.connection.lock();
+        let tx = connection.savepoint()?;
+        let removed = {
+            let mut stmt = tx.prepare_cached(
+                "SELECT DISTINCT uids.unit_id FROM practice_rewards
+                    JOIN uids ON practice_rewards.unit_uid = uids.unit_uid
+                    WHERE exercise_uid = (SELECT unit_uid FROM uids WHERE unit_id = $1)
+                    AND timestamp = $2;",
+            )?;
+            stmt.query_map(params![exercise_id.as_str(), timestamp], |row| {
+                row.get::<_, String>(0)
+            })?
+            .map(|r| r.map(|unit_id| Ustr::from(&unit_id)))
+            .collect::<Result<Vec<Ustr>, _>>()?
+        };
+        tx.execute(
+            "DELETE FROM practice_rewards WHERE exercise_uid = (
+                SELECT unit_uid FROM uids WHERE unit_id = $1) AND timestamp = $2;",
+            params![exercise_id.as_str(), timestamp],
+        )?;
+        tx.commit()?;
+
+        // Also remove the rewards from the cache so that a new reward with the same timestamp is
+        // not skipped.
+        for unit_id in &removed {
+            self.cache.remove_reward(*unit_id, timestamp);
+        }
+        Ok(removed)
+    }
+
+    /// Helper function to move the rewards of a unit to a new ID.
+    fn rename_unit_helper(&mut self, old_id: Ustr, new_id: Ustr) -> Result<()> {
+        utils::rename_unit_uid(
+            &mut self.connection.lock(),
+            "practice_rewards",
+            &["unit_uid", "exercise_uid"],
+            &old_id,
+            &new_id,
+        )?;
+
+        // The cache is only used to skip similar rewards, so the old entries can be dropped.
+        self.cache.cache.remove(&old_id);
+        Ok(())
+    }
+
     /// Helper function to trim the number of rewards for each unit to the given number. If the
     /// number of rewards is less than the given number, the method deletes no rewards.
     fn trim_rewards_helper(&mut self, num_rewards: u32) -> Result<()> {
@@ -247,6 +395,23 @@ impl LocalPracticeRewards {
         Ok(())
     }
 
+    /// Helper function to apply a retention policy to the rewards.
+    fn apply_retention_policy_helper(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize> {
+        let connection = self.connection.lock();
+        utils::apply_retention_policy(&connection, "practice_rewards", policy, now, "FALSE", &[])
+    }
+
+    /// Helper function to compact the database.
+    fn compact_helper(&mut self) -> Result<()> {
+        let connection = self.connection.lock();
+        connection.execute_batch("VACUUM;")?;
+        Ok(())
+    }
+
     /// Helper function to remove all the rewards from units that match the given prefix.
     fn remove_rewards_with_prefix_helper(&mut self, prefix: &str) -> Result<()> {
         // Get all the UIDs for the units that match the prefix.
@@ -262,8 +427,6 @@ impl LocalPracticeRewards {
             )?;
         }
 
-        // Call the `VACUUM` command to reclaim the space freed by the deleted trials.
-        connection.execute_batch("VACUUM;")?;
         Ok(())
     }
 }
@@ -278,19 +441,52 @@ impl PracticeRewards for LocalPracticeRewards {
             .map_err(|e| PracticeRewardsError::GetRewards(unit_id, e))
     }
 
+    fn get_unit_ids(&self) -> Result<Vec<Ustr>, PracticeRewardsError> {
+        self.get_unit_ids_helper()
+            .map_err(PracticeRewardsError::GetUnitIds)
+    }
+
     fn record_unit_rewards(
         &mut self,
+        exercise_id: Ustr,
         rewards: &[UnitReward],
     ) -> Result<Vec<Ustr>, PracticeRewardsError> {
-        self.record_unit_rewards_helper(rewards)
+        self.record_unit_rewards_helper(exercise_id, rewards)
             .map_err(PracticeRewardsError::RecordRewards)
     }
 
+    fn remove_unit_rewards(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<Vec<Ustr>, PracticeRewardsError> {
+        self.remove_unit_rewards_helper(exercise_id, timestamp)
+            .map_err(PracticeRewardsError::RemoveRewards)
+    }
+
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), PracticeRewardsError> {
+        self.rename_unit_helper(old_id, new_id)
+            .map_err(|e| PracticeRewardsError::RenameUnit(old_id, new_id, e))
+    }
+
     fn trim_rewards(&mut self, num_rewards: u32) -> Result<(), PracticeRewardsError> {
         self.trim_rewards_helper(num_rewards)
             .map_err(PracticeRewardsError::TrimReward)
     }
 
+    fn apply_retention_policy(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize, PracticeRewardsError> {
+        self.apply_retention_policy_helper(policy, now)
+            .map_err(PracticeRewardsError::ApplyRetention)
+    }
+
+    fn compact(&mut self) -> Result<(), PracticeRewardsError> {
+        self.compact_helper().map_err(PracticeRewardsError::Compact)
+    }
+
     fn remove_rewards_with_prefix(&mut self, prefix: &str) -> Result<(), PracticeRewardsError> {
         self.remove_rewards_with_prefix_helper(prefix)
             .map_err(|e| PracticeRewardsError::RemovePrefix(prefix.to_string(), e))
@@ -305,10 +501,15 @@ mod test {
     use ustr::Ustr;
 
     use crate::{
-        data::UnitReward,
+        data::{RetentionPolicy, UnitReward},
         practice_rewards::{LocalPracticeRewards, PracticeRewards},
     };
 
+    /// Returns the ID of the exercise that produces the rewards in the tests.
+    fn exercise_id() -> Ustr {
+        Ustr::from("exercise_1")
+    }
+
     fn new_tests_rewards() -> Result<Box<dyn PracticeRewards>> {
         let practice_rewards = LocalPracticeRewards::new(Connection::open_in_memory()?)?;
         Ok(Box::new(practice_rewards))
@@ -333,12 +534,15 @@ mod test {
     fn basic() -> Result<()> {
         let mut practice_rewards = new_tests_rewards()?;
         let unit_id = Ustr::from("unit_123");
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id,
-            value: 3.0,
-            weight: 1.0,
-            timestamp: 1,
-        }])?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id,
+                value: 3.0,
+                weight: 1.0,
+                timestamp: 1,
+            }],
+        )?;
         let rewards = practice_rewards.get_rewards(unit_id, 1)?;
         assert_rewards(&[3.0], &[1.0], &rewards);
         Ok(())
@@ -349,24 +553,33 @@ mod test {
     fn multiple_rewards() -> Result<()> {
         let mut practice_rewards = new_tests_rewards()?;
         let unit_id = Ustr::from("unit_123");
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id,
-            value: 3.0,
-            weight: 1.0,
-            timestamp: 1,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id,
-            value: 2.0,
-            weight: 1.0,
-            timestamp: 2,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id,
-            value: -1.0,
-            weight: 0.05,
-            timestamp: 3,
-        }])?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id,
+                value: 3.0,
+                weight: 1.0,
+                timestamp: 1,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id,
+                value: 2.0,
+                weight: 1.0,
+                timestamp: 2,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id,
+                value: -1.0,
+                weight: 0.05,
+                timestamp: 3,
+            }],
+        )?;
 
         let one_reward = practice_rewards.get_rewards(unit_id, 1)?;
         assert_rewards(&[-1.0], &[0.05], &one_reward);
@@ -385,12 +598,15 @@ mod test {
         let mut practice_rewards = new_tests_rewards()?;
         let unit_id = Ustr::from("unit_123");
         for i in 0..20 {
-            practice_rewards.record_unit_rewards(&[UnitReward {
-                unit_id,
-                value: i as f32,
-                weight: 1.0,
-                timestamp: i64::from(i),
-            }])?;
+            practice_rewards.record_unit_rewards(
+                exercise_id(),
+                &[UnitReward {
+                    unit_id,
+                    value: i as f32,
+                    weight: 1.0,
+                    timestamp: i64::from(i),
+                }],
+            )?;
         }
 
         let rewards = practice_rewards.get_rewards(unit_id, 10)?;
@@ -414,45 +630,63 @@ mod test {
     fn trim_rewards_some_rewards_removed() -> Result<()> {
         let mut practice_rewards = new_tests_rewards()?;
         let unit1_id = Ustr::from("unit1");
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit1_id,
-            value: 3.0,
-            weight: 1.0,
-            timestamp: 1,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit1_id,
-            value: 4.0,
-            weight: 1.0,
-            timestamp: 2,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit1_id,
-            value: 5.0,
-            weight: 1.0,
-            timestamp: 3,
-        }])?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit1_id,
+                value: 3.0,
+                weight: 1.0,
+                timestamp: 1,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit1_id,
+                value: 4.0,
+                weight: 1.0,
+                timestamp: 2,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit1_id,
+                value: 5.0,
+                weight: 1.0,
+                timestamp: 3,
+            }],
+        )?;
         assert_eq!(3, practice_rewards.get_rewards(unit1_id, 10)?.len());
 
         let unit2_id = Ustr::from("unit2");
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit2_id,
-            value: 1.0,
-            weight: 1.0,
-            timestamp: 1,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit2_id,
-            value: 2.0,
-            weight: 1.0,
-            timestamp: 2,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit2_id,
-            value: 3.0,
-            weight: 1.0,
-            timestamp: 3,
-        }])?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit2_id,
+                value: 1.0,
+                weight: 1.0,
+                timestamp: 1,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit2_id,
+                value: 2.0,
+                weight: 1.0,
+                timestamp: 2,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit2_id,
+                value: 3.0,
+                weight: 1.0,
+                timestamp: 3,
+            }],
+        )?;
         assert_eq!(3, practice_rewards.get_rewards(unit2_id, 10)?.len());
 
         practice_rewards.trim_rewards(2)?;
@@ -468,44 +702,62 @@ mod test {
     fn trim_rewards_no_rewards_removed() -> Result<()> {
         let mut practice_rewards = new_tests_rewards()?;
         let unit1_id = Ustr::from("unit1");
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit1_id,
-            value: 3.0,
-            weight: 1.0,
-            timestamp: 1,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit1_id,
-            value: 4.0,
-            weight: 1.0,
-            timestamp: 2,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit1_id,
-            value: 5.0,
-            weight: 1.0,
-            timestamp: 3,
-        }])?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit1_id,
+                value: 3.0,
+                weight: 1.0,
+                timestamp: 1,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit1_id,
+                value: 4.0,
+                weight: 1.0,
+                timestamp: 2,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit1_id,
+                value: 5.0,
+                weight: 1.0,
+                timestamp: 3,
+            }],
+        )?;
 
         let unit2_id = Ustr::from("unit2");
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit2_id,
-            value: 1.0,
-            weight: 1.0,
-            timestamp: 1,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit2_id,
-            value: 2.0,
-            weight: 1.0,
-            timestamp: 2,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit2_id,
-            value: 3.0,
-            weight: 1.0,
-            timestamp: 3,
-        }])?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit2_id,
+                value: 1.0,
+                weight: 1.0,
+                timestamp: 1,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit2_id,
+                value: 2.0,
+                weight: 1.0,
+                timestamp: 2,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit2_id,
+                value: 3.0,
+                weight: 1.0,
+                timestamp: 3,
+            }],
+        )?;
 
         practice_rewards.trim_rewards(10)?;
 
@@ -521,64 +773,91 @@ mod test {
     fn remove_rewards_with_prefix() -> Result<()> {
         let mut practice_rewards = new_tests_rewards()?;
         let unit1_id = Ustr::from("unit1");
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit1_id,
-            value: 3.0,
-            weight: 1.0,
-            timestamp: 1,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit1_id,
-            value: 4.0,
-            weight: 1.0,
-            timestamp: 2,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit1_id,
-            value: 5.0,
-            weight: 1.0,
-            timestamp: 3,
-        }])?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit1_id,
+                value: 3.0,
+                weight: 1.0,
+                timestamp: 1,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit1_id,
+                value: 4.0,
+                weight: 1.0,
+                timestamp: 2,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit1_id,
+                value: 5.0,
+                weight: 1.0,
+                timestamp: 3,
+            }],
+        )?;
 
         let unit2_id = Ustr::from("unit2");
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit2_id,
-            value: 1.0,
-            weight: 1.0,
-            timestamp: 1,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit2_id,
-            value: 2.0,
-            weight: 1.0,
-            timestamp: 2,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit2_id,
-            value: 3.0,
-            weight: 1.0,
-            timestamp: 3,
-        }])?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit2_id,
+                value: 1.0,
+                weight: 1.0,
+                timestamp: 1,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit2_id,
+                value: 2.0,
+                weight: 1.0,
+                timestamp: 2,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit2_id,
+                value: 3.0,
+                weight: 1.0,
+                timestamp: 3,
+            }],
+        )?;
 
         let unit3_id = Ustr::from("unit3");
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit3_id,
-            value: 1.0,
-            weight: 1.0,
-            timestamp: 1,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit3_id,
-            value: 2.0,
-            weight: 1.0,
-            timestamp: 2,
-        }])?;
-        practice_rewards.record_unit_rewards(&[UnitReward {
-            unit_id: unit3_id,
-            value: 3.0,
-            weight: 1.0,
-            timestamp: 3,
-        }])?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit3_id,
+                value: 1.0,
+                weight: 1.0,
+                timestamp: 1,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit3_id,
+                value: 2.0,
+                weight: 1.0,
+                timestamp: 2,
+            }],
+        )?;
+        practice_rewards.record_unit_rewards(
+            exercise_id(),
+            &[UnitReward {
+                unit_id: unit3_id,
+                value: 3.0,
+                weight: 1.0,
+                timestamp: 3,
+            }],
+        )?;
 
         // Remove the prefix "unit1".
         practice_rewards.remove_rewards_with_prefix("unit1")?;
@@ -600,4 +879,89 @@ mod test {
 
         Ok(())
     }
+
+    /// Verifies removing the rewards of a trial and recording them again after they have been
+    /// removed.
+    #[test]
+    fn remove_unit_rewards() -> Result<()> {
+        let mut practice_rewards = new_tests_rewards()?;
+        let unit1_id = Ustr::from("unit1");
+        let unit2_id = Ustr::from("unit2");
+        let reward1 = UnitReward {
+            unit_id: unit1_id,
+            value: 3.0,
+            weight: 1.0,
+            timestamp: 1,
+        };
+        let reward2 = UnitReward {
+            unit_id: unit2_id,
+            value: -1.0,
+            weight: 0.5,
+            timestamp: 1,
+        };
+        practice_rewards.record_unit_rewards(exercise_id(), &[reward1.clone(), reward2])?;
+
+        // Record a reward from another exercise in the same second.
+        let other_exercise_id = Ustr::from("exercise_2");
+        let reward3 = UnitReward {
+            unit_id: unit1_id,
+            value: 1.0,
+            weight: 1.0,
+            timestamp: 1,
+        };
+        practice_rewards.record_unit_rewards(other_exercise_id, &[reward3])?;
+
+        // Remove the rewards of the first exercise. The reward of the other exercise is kept.
+        let mut removed = practice_rewards.remove_unit_rewards(exercise_id(), 1)?;
+        removed.sort();
+        assert_eq!(removed, vec![unit1_id, unit2_id]);
+        assert_rewards(&[1.0], &[1.0], &practice_rewards.get_rewards(unit1_id, 10)?);
+        assert_rewards(&[], &[], &practice_rewards.get_rewards(unit2_id, 10)?);
+        assert!(
+            practice_rewards
+                .remove_unit_rewards(exercise_id(), 1)?
+                .is_empty()
+        );
+
+        // The removed reward is not in the cache anymore, so recording it again works.
+        let updated = practice_rewards.record_unit_rewards(exercise_id(), &[reward1])?;
+        assert_eq!(updated, vec![unit1_id]);
+        assert_eq!(practice_rewards.get_rewards(unit1_id, 10)?.len(), 2);
+        Ok(())
+    }
+
+    /// Verifies applying a retention policy to the rewards.
+    #[test]
+    fn apply_retention_policy() -> Result<()> {
+        let mut practice_rewards = new_tests_rewards()?;
+        let unit_id = Ustr::from("unit_123");
+        let rewards: Vec<UnitReward> = [(1, 1.0), (5, 2.0), (8, 3.0), (12, 4.0), (150, 5.0)]
+            .into_iter()
+            .map(|(timestamp, value)| UnitReward {
+                unit_id,
+                value,
+                weight: 1.0,
+                timestamp,
+            })
+            .collect();
+        practice_rewards.record_unit_rewards(exercise_id(), &rewards)?;
+
+        // The first reward, the last reward in each interval, and the recent rewards are kept.
+        let policy = RetentionPolicy {
+            retention_window: 100,
+            downsample_interval: 10,
+            mastered_score: 4.0,
+            lapse_score: 3.0,
+            compact: true,
+        };
+        assert_eq!(practice_rewards.apply_retention_policy(&policy, 200)?, 1);
+        let timestamps: Vec<i64> = practice_rewards
+            .get_rewards(unit_id, 10)?
+            .iter()
+            .map(|r| r.timestamp)
+            .collect();
+        assert_eq!(timestamps, vec![150, 12, 8, 1]);
+        practice_rewards.compact()?;
+        Ok(())
+    }
 }
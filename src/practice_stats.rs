@@ -5,12 +5,24 @@
 //! documentation in [exercise_scorer](crate::exercise_scorer) for more details.
 
 use anyhow::{Context, Ok, Result};
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
 use parking_lot::Mutex;
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, Row, params};
 use rusqlite_migration::{M, Migrations};
+use std::{collections::BTreeMap, sync::Arc};
 use ustr::Ustr;
 
-use crate::{data::ExerciseTrial, error::PracticeStatsError, utils};
+use crate::{
+    data::{ExerciseTrial, RetentionPolicy, TrialHistoryCursor, TrialHistoryPage},
+    error::PracticeStatsError,
+    statistics, utils,
+};
+
+/// The length in seconds of the blocks in which the trials are grouped to count them by day in a
+/// time zone. The offsets from UTC of all the time zones in use are multiples of this length, so
+/// all the trials in a block fall on the same local date.
+const TIME_ZONE_BLOCK: i64 = 900;
 
 /// Contains functions to retrieve and record the scores from each exercise trial.
 pub trait PracticeStats {
@@ -22,6 +34,54 @@ pub trait PracticeStats {
         num_scores: u32,
     ) -> Result<Vec<ExerciseTrial>, PracticeStatsError>;
 
+    /// Returns the IDs of all the units with recorded scores.
+    fn get_unit_ids(&self) -> Result<Vec<Ustr>, PracticeStatsError>;
+
+    /// Retrieves the trials of all exercises with a timestamp in the range `[start, end)`. The
+    /// trials are returned in ascending order according to the timestamp.
+    fn get_trials_in_range(
+        &self,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<ExerciseTrial>, PracticeStatsError>;
+
+    /// Retrieves the trials of all the exercises whose IDs start with the given prefix. Unlike
+    /// [`PracticeStats::remove_scores_with_prefix`], the prefix is matched exactly. The trials are
+    /// returned in ascending order according to the timestamp.
+    fn get_trials_with_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<ExerciseTrial>, PracticeStatsError>;
+
+    /// Returns the number of trials on each day with a timestamp in the range `[start, end)`. Days
+    /// start at midnight in the time zone with the given offset from UTC, in seconds. Days with no
+    /// trials are not included.
+    fn get_daily_trial_counts(
+        &self,
+        start: i64,
+        end: i64,
+        utc_offset: i32,
+    ) -> Result<BTreeMap<NaiveDate, usize>, PracticeStatsError>;
+
+    /// Returns the number of trials on each day with a timestamp in the range `[start, end)`. Unlike
+    /// [`PracticeStats::get_daily_trial_counts`], days start at midnight in the given time zone, so
+    /// changes in its offset from UTC are taken into account. Days with no trials are not included.
+    fn get_local_daily_trial_counts(
+        &self,
+        start: i64,
+        end: i64,
+        time_zone: Tz,
+    ) -> Result<BTreeMap<NaiveDate, usize>, PracticeStatsError>;
+
+    /// Retrieves a page of at most `page_size` trials from the full history of trials, sorted in
+    /// ascending order according to the timestamp. The history starts at the beginning if
+    /// `cursor` is `None`, or right after the position it indicates otherwise.
+    fn get_trial_history(
+        &self,
+        cursor: Option<TrialHistoryCursor>,
+        page_size: u32,
+    ) -> Result<TrialHistoryPage, PracticeStatsError>;
+
     /// Records the scores assigned to exercises in one or more trials. Only units of type
     /// `UnitType::Exercise` should have scores recorded. However, the enforcement of this
     /// requirement is left to the caller.
@@ -30,18 +90,50 @@ pub trait PracticeStats {
         trials: &[ExerciseTrial],
     ) -> Result<(), PracticeStatsError>;
 
+    /// Removes the trial of the given exercise recorded at the given timestamp. Returns the removed
+    /// trial, or `None` if no such trial exists. If multiple trials share the same timestamp, only
+    /// the one recorded last is removed.
+    fn remove_trial(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<Option<ExerciseTrial>, PracticeStatsError>;
+
+    /// Moves all the scores of the unit with ID `old_id` to the unit with ID `new_id`. Used when a
+    /// unit is renamed. If the new unit already has scores, both sets are merged.
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), PracticeStatsError>;
+
     /// Deletes all the exercise trials except for the last `num_scores` with the aim of keeping the
     /// storage size under check.
     fn trim_scores(&mut self, num_scores: u32) -> Result<(), PracticeStatsError>;
 
-    /// Removes all the scores from the units that match the given prefix.
+    /// Removes the old trials according to the given retention policy. All the trials newer than
+    /// the retention window, the first trial of each exercise, and the lapses along with the trials
+    /// before them are kept. Returns the number of removed trials.
+    fn apply_retention_policy(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize, PracticeStatsError>;
+
+    /// Compacts the database to reclaim the space freed by removed trials.
+    fn compact(&mut self) -> Result<(), PracticeStatsError>;
+
+    /// Removes all the scores from the units that match the given prefix. The freed space is not
+    /// reclaimed until the database is compacted.
     fn remove_scores_with_prefix(&mut self, prefix: &str) -> Result<(), PracticeStatsError>;
 }
 
+/// The SQL condition used by the retention policy to select the lapses, that is, the trials with a
+/// score below the lapse score (`?3`) right after a trial with a score at or above the mastered
+/// score (`?4`), and the trials right before them.
+const LAPSE_CONDITION: &str = "(score < ?3 AND LAG(score, 1, 0) OVER unit >= ?4)
+    OR (score >= ?4 AND LEAD(score, 1, ?3) OVER unit < ?3)";
+
 /// An implementation of [`PracticeStats`] backed by `SQLite`.
 pub struct LocalPracticeStats {
     /// A connection to the database.
-    connection: Mutex<Connection>,
+    connection: Arc<Mutex<Connection>>,
 }
 
 impl LocalPracticeStats {
@@ -76,6 +168,10 @@ impl LocalPracticeStats {
             // Create a combined index of `unit_uid` and `timestamp` for fast trial retrieval.
             M::up("CREATE INDEX trials ON practice_stats (unit_uid, timestamp);")
                 .down("DROP INDEX trials"),
+            // Create an index of `timestamp` for fast retrieval of the trials in a time range and
+            // of the full history.
+            M::up("CREATE INDEX trials_by_timestamp ON practice_stats (timestamp, id);")
+                .down("DROP INDEX trials_by_timestamp"),
         ])
     }
 
@@ -92,17 +188,30 @@ impl LocalPracticeStats {
     /// Creates a new instance with the given connection and initializes the database.
     fn new(connection: Connection) -> Result<LocalPracticeStats> {
         let mut stats = LocalPracticeStats {
-            connection: Mutex::new(connection),
+            connection: Arc::new(Mutex::new(connection)),
         };
         stats.init()?;
         Ok(stats)
     }
 
+    /// Creates a new instance that shares the given connection with the other stores in the
+    /// consolidated user database. The schema is managed by
+    /// [`UserDatabase`](crate::user_database::UserDatabase), so no migrations are run.
+    pub(crate) fn new_shared(connection: Arc<Mutex<Connection>>) -> LocalPracticeStats {
+        LocalPracticeStats { connection }
+    }
+
     /// A constructor taking the path to a database file.
     pub fn new_from_disk(db_path: &str) -> Result<LocalPracticeStats> {
         Self::new(utils::new_connection(db_path)?)
     }
 
+    /// A constructor for a database that is kept in memory and discarded when dropped. Useful to
+    /// run Trane without writing any user data to the file system.
+    pub fn new_in_memory() -> Result<LocalPracticeStats> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
     /// Helper function to retrieve scores from the database.
     fn get_scores_helper(&self, exercise_id: Ustr, num_scores: u32) -> Result<Vec<ExerciseTrial>> {
         // Retrieve the exercise trials from the database.
@@ -130,12 +239,181 @@ impl LocalPracticeStats {
         Ok(rows)
     }
 
+    /// Converts a row with the unit ID, score, and timestamp of a trial into an `ExerciseTrial`.
+    fn row_to_trial(row: &Row) -> rusqlite::Result<ExerciseTrial> {
+        let exercise_id: String = row.get(0)?;
+        rusqlite::Result::Ok(ExerciseTrial {
+            exercise_id: Ustr::from(&exercise_id),
+            score: row.get(1)?,
+            timestamp: row.get(2)?,
+        })
+    }
+
+    /// Helper function to retrieve the trials in a time range.
+    fn get_trials_in_range_helper(&self, start: i64, end: i64) -> Result<Vec<ExerciseTrial>> {
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT uids.unit_id, practice_stats.score, practice_stats.timestamp
+                FROM practice_stats JOIN uids ON practice_stats.unit_uid = uids.unit_uid
+                WHERE practice_stats.timestamp >= ?1 AND practice_stats.timestamp < ?2
+                ORDER BY practice_stats.timestamp, practice_stats.id;",
+        )?;
+        let trials = stmt
+            .query_map(params![start, end], Self::row_to_trial)?
+            .map(|r| r.context("failed to retrieve trials from practice stats DB"))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(trials)
+    }
+
+    /// Helper function to retrieve the trials of the units matching a prefix.
+    fn get_trials_with_prefix_helper(&self, prefix: &str) -> Result<Vec<ExerciseTrial>> {
+        // Match the prefix with a range query, which can use the index of unit IDs. All the IDs
+        // starting with the prefix sort between the prefix and the prefix followed by the largest
+        // possible character.
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT uids.unit_id, practice_stats.score, practice_stats.timestamp
+                FROM practice_stats JOIN uids ON practice_stats.unit_uid = uids.unit_uid
+                WHERE uids.unit_id >= ?1 AND uids.unit_id < ?2
+                ORDER BY practice_stats.timestamp, practice_stats.id;",
+        )?;
+        let trials = stmt
+            .query_map(
+                params![prefix, format!("{prefix}{}", char::MAX)],
+                Self::row_to_trial,
+            )?
+            .map(|r| r.context("failed to retrieve trials from practice stats DB"))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(trials)
+    }
+
+    /// Helper function to count the trials on each day.
+    fn get_daily_trial_counts_helper(
+        &self,
+        start: i64,
+        end: i64,
+        utc_offset: i32,
+    ) -> Result<BTreeMap<NaiveDate, usize>> {
+        // Group the trials by the number of days since the epoch in the given time zone.
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT (timestamp + ?3) / 86400 AS day, COUNT(*) FROM practice_stats
+                WHERE timestamp >= ?1 AND timestamp < ?2 GROUP BY day;",
+        )?;
+        let counts = stmt
+            .query_map(params![start, end, utc_offset], |row| {
+                let day: i64 = row.get(0)?;
+                let count: i64 = row.get(1)?;
+                rusqlite::Result::Ok((day, count))
+            })?
+            .map(|r| r.context("failed to retrieve trial counts from practice stats DB"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Convert the day numbers into dates.
+        counts
+            .into_iter()
+            .map(|(day, count)| {
+                let date = DateTime::from_timestamp(day * 86400, 0)
+                    .context("invalid day in trial counts")?
+                    .date_naive();
+                Ok((date, usize::try_from(count)?))
+            })
+            .collect()
+    }
+
+    /// Helper function to count the trials on each day in a time zone.
+    fn get_local_daily_trial_counts_helper(
+        &self,
+        start: i64,
+        end: i64,
+        time_zone: Tz,
+    ) -> Result<BTreeMap<NaiveDate, usize>> {
+        // Group the trials into blocks that cannot span two dates in any time zone.
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT timestamp / ?3 AS block, COUNT(*) FROM practice_stats
+                WHERE timestamp >= ?1 AND timestamp < ?2 GROUP BY block;",
+        )?;
+        let counts = stmt
+            .query_map(params![start, end, TIME_ZONE_BLOCK], |row| {
+                let block: i64 = row.get(0)?;
+                let count: i64 = row.get(1)?;
+                rusqlite::Result::Ok((block, count))
+            })?
+            .map(|r| r.context("failed to retrieve trial counts from practice stats DB"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Add up the counts of the blocks on each local date.
+        let mut daily_counts = BTreeMap::new();
+        for (block, count) in counts {
+            let date = statistics::local_date(block * TIME_ZONE_BLOCK, time_zone)?;
+            *daily_counts.entry(date).or_default() += usize::try_from(count)?;
+        }
+        Ok(daily_counts)
+    }
+
+    /// Helper function to retrieve a page of the trial history.
+    fn get_trial_history_helper(
+        &self,
+        cursor: Option<TrialHistoryCursor>,
+        page_size: u32,
+    ) -> Result<TrialHistoryPage> {
+        // Retrieve the trials after the cursor along with their internal IDs.
+        let (timestamp, id) = cursor.map_or((i64::MIN, i64::MIN), |c| (c.timestamp, c.id));
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT uids.unit_id, practice_stats.score, practice_stats.timestamp, practice_stats.id
+                FROM practice_stats JOIN uids ON practice_stats.unit_uid = uids.unit_uid
+                WHERE (practice_stats.timestamp, practice_stats.id) > (?1, ?2)
+                ORDER BY practice_stats.timestamp, practice_stats.id LIMIT ?3;",
+        )?;
+        let rows = stmt
+            .query_map(params![timestamp, id, page_size], |row| {
+                let trial = Self::row_to_trial(row)?;
+                let id: i64 = row.get(3)?;
+                rusqlite::Result::Ok((trial, id))
+            })?
+            .map(|r| r.context("failed to retrieve trial history from practice stats DB"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The history might continue only if the page is full.
+        let next = if rows.len() < page_size as usize {
+            None
+        } else {
+            rows.last().map(|(trial, id)| TrialHistoryCursor {
+                timestamp: trial.timestamp,
+                id: *id,
+            })
+        };
+        Ok(TrialHistoryPage {
+            trials: rows.into_iter().map(|(trial, _)| trial).collect(),
+            next,
+        })
+    }
+
+    /// Helper function to retrieve the IDs of all the units with recorded scores.
+    fn get_unit_ids_helper(&self) -> Result<Vec<Ustr>> {
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT unit_id FROM uids WHERE unit_uid IN (
+                SELECT DISTINCT unit_uid FROM practice_stats);",
+        )?;
+        let unit_ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|r| {
+                r.map(|unit_id| Ustr::from(&unit_id))
+                    .context("failed to retrieve unit IDs from practice_stats DB")
+            })
+            .collect::<Result<Vec<Ustr>, _>>()?;
+        Ok(unit_ids)
+    }
+
     /// Helper function to record scores to the database.
     fn record_exercise_scores_helper(&mut self, trials: &[ExerciseTrial]) -> Result<()> {
         // Update the mapping of unit ID to unique integer ID and add the trial in a single
         // transaction.
         let mut connection = self.connection.lock();
-        let tx = connection.transaction()?;
+        let tx = connection.savepoint()?;
         {
             let mut uid_stmt =
                 tx.prepare_cached("INSERT OR IGNORE INTO uids(unit_id) VALUES ($1);")?;
@@ -156,6 +434,51 @@ impl LocalPracticeStats {
         Ok(())
     }
 
+    /// Helper function to remove a single trial from the database.
+    fn remove_trial_helper(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<Option<ExerciseTrial>> {
+        // Find the trial with the given timestamp.
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT id, score FROM practice_stats WHERE unit_uid = (
+                SELECT unit_uid FROM uids WHERE unit_id = $1) AND timestamp = $2
+                ORDER BY id DESC LIMIT 1;",
+        )?;
+        let mut rows = stmt
+            .query_map(params![exercise_id.as_str(), timestamp], |row| {
+                rusqlite::Result::Ok((row.get::<_, i64>(0)?, row.get::<_, f32>(1)?))
+            })?
+            .map(|r| r.context("failed to retrieve trial from practice stats DB"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some((id, score)) = rows.pop() else {
+            return Ok(None);
+        };
+
+        // Delete the trial and return it.
+        let mut delete_stmt =
+            connection.prepare_cached("DELETE FROM practice_stats WHERE id = $1;")?;
+        let _ = delete_stmt.execute(params![id])?;
+        Ok(Some(ExerciseTrial {
+            exercise_id,
+            score,
+            timestamp,
+        }))
+    }
+
+    /// Helper function to move the scores of a unit to a new ID.
+    fn rename_unit_helper(&mut self, old_id: Ustr, new_id: Ustr) -> Result<()> {
+        utils::rename_unit_uid(
+            &mut self.connection.lock(),
+            "practice_stats",
+            &["unit_uid"],
+            &old_id,
+            &new_id,
+        )
+    }
+
     /// Helper function to trim the number of scores for each exercise.
     fn trim_scores_helper(&mut self, num_scores: u32) -> Result<()> {
         // Get all the UIDs from the database.
@@ -176,7 +499,31 @@ impl LocalPracticeStats {
             let _ = stmt.execute(params![uid, num_scores])?;
         }
 
-        // Call the `VACUUM` command to reclaim the space freed by the deleted trials.
+        Ok(())
+    }
+
+    /// Helper function to apply a retention policy to the trials.
+    fn apply_retention_policy_helper(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize> {
+        // Besides the entries kept by every retention policy, keep the lapses and the trials
+        // right before them.
+        let connection = self.connection.lock();
+        utils::apply_retention_policy(
+            &connection,
+            "practice_stats",
+            policy,
+            now,
+            LAPSE_CONDITION,
+            &[&policy.lapse_score, &policy.mastered_score],
+        )
+    }
+
+    /// Helper function to compact the database.
+    fn compact_helper(&mut self) -> Result<()> {
+        let connection = self.connection.lock();
         connection.execute_batch("VACUUM;")?;
         Ok(())
     }
@@ -199,8 +546,6 @@ impl LocalPracticeStats {
             let _ = stmt.execute(params![uid])?;
         }
 
-        // Call the `VACUUM` command to reclaim the space freed by the deleted trials.
-        connection.execute_batch("VACUUM;")?;
         Ok(())
     }
 }
@@ -215,6 +560,57 @@ impl PracticeStats for LocalPracticeStats {
             .map_err(|e| PracticeStatsError::GetScores(exercise_id, e))
     }
 
+    fn get_unit_ids(&self) -> Result<Vec<Ustr>, PracticeStatsError> {
+        self.get_unit_ids_helper()
+            .map_err(PracticeStatsError::GetUnitIds)
+    }
+
+    fn get_trials_in_range(
+        &self,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<ExerciseTrial>, PracticeStatsError> {
+        self.get_trials_in_range_helper(start, end)
+            .map_err(|e| PracticeStatsError::GetTrialsInRange(start, end, e))
+    }
+
+    fn get_trials_with_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<ExerciseTrial>, PracticeStatsError> {
+        self.get_trials_with_prefix_helper(prefix)
+            .map_err(|e| PracticeStatsError::GetTrialsWithPrefix(prefix.to_string(), e))
+    }
+
+    fn get_daily_trial_counts(
+        &self,
+        start: i64,
+        end: i64,
+        utc_offset: i32,
+    ) -> Result<BTreeMap<NaiveDate, usize>, PracticeStatsError> {
+        self.get_daily_trial_counts_helper(start, end, utc_offset)
+            .map_err(PracticeStatsError::GetDailyCounts)
+    }
+
+    fn get_local_daily_trial_counts(
+        &self,
+        start: i64,
+        end: i64,
+        time_zone: Tz,
+    ) -> Result<BTreeMap<NaiveDate, usize>, PracticeStatsError> {
+        self.get_local_daily_trial_counts_helper(start, end, time_zone)
+            .map_err(PracticeStatsError::GetDailyCounts)
+    }
+
+    fn get_trial_history(
+        &self,
+        cursor: Option<TrialHistoryCursor>,
+        page_size: u32,
+    ) -> Result<TrialHistoryPage, PracticeStatsError> {
+        self.get_trial_history_helper(cursor, page_size)
+            .map_err(PracticeStatsError::GetHistory)
+    }
+
     fn record_exercise_scores(
         &mut self,
         trials: &[ExerciseTrial],
@@ -223,11 +619,38 @@ impl PracticeStats for LocalPracticeStats {
             .map_err(PracticeStatsError::RecordScore)
     }
 
+    fn remove_trial(
+        &mut self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<Option<ExerciseTrial>, PracticeStatsError> {
+        self.remove_trial_helper(exercise_id, timestamp)
+            .map_err(|e| PracticeStatsError::RemoveTrial(exercise_id, e))
+    }
+
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), PracticeStatsError> {
+        self.rename_unit_helper(old_id, new_id)
+            .map_err(|e| PracticeStatsError::RenameUnit(old_id, new_id, e))
+    }
+
     fn trim_scores(&mut self, num_scores: u32) -> Result<(), PracticeStatsError> {
         self.trim_scores_helper(num_scores)
             .map_err(PracticeStatsError::TrimScores)
     }
 
+    fn apply_retention_policy(
+        &mut self,
+        policy: &RetentionPolicy,
+        now: i64,
+    ) -> Result<usize, PracticeStatsError> {
+        self.apply_retention_policy_helper(policy, now)
+            .map_err(PracticeStatsError::ApplyRetention)
+    }
+
+    fn compact(&mut self) -> Result<(), PracticeStatsError> {
+        self.compact_helper().map_err(PracticeStatsError::Compact)
+    }
+
     fn remove_scores_with_prefix(&mut self, prefix: &str) -> Result<(), PracticeStatsError> {
         self.remove_scores_with_prefix_helper(prefix)
             .map_err(|e| PracticeStatsError::RemovePrefix(prefix.to_string(), e))
@@ -238,11 +661,13 @@ impl PracticeStats for LocalPracticeStats {
 #[cfg_attr(coverage, coverage(off))]
 mod test {
     use anyhow::{Ok, Result};
+    use chrono::NaiveDate;
+    use chrono_tz::Tz;
     use rusqlite::Connection;
     use ustr::Ustr;
 
     use crate::{
-        data::ExerciseTrial,
+        data::{ExerciseTrial, RetentionPolicy, TrialHistoryCursor},
         practice_stats::{LocalPracticeStats, PracticeStats},
     };
 
@@ -412,4 +837,254 @@ mod test {
 
         Ok(())
     }
+
+    /// Verifies removing a single trial.
+    #[test]
+    fn remove_trial() -> Result<()> {
+        let mut stats = new_tests_stats()?;
+        let exercise_id = Ustr::from("ex_123");
+        stats.record_exercise_scores(&[
+            trial(exercise_id, 3.0, 1),
+            trial(exercise_id, 4.0, 2),
+            trial(exercise_id, 5.0, 3),
+        ])?;
+
+        // Remove the trial in the middle and verify the other trials are kept.
+        let removed = stats.remove_trial(exercise_id, 2)?;
+        assert_eq!(removed, Some(trial(exercise_id, 4.0, 2)));
+        let scores = stats.get_scores(exercise_id, 10)?;
+        assert_scores(&[5.0, 3.0], &scores);
+
+        // Removing a trial that does not exist returns `None`.
+        assert_eq!(stats.remove_trial(exercise_id, 2)?, None);
+        assert_eq!(stats.remove_trial(Ustr::from("ex_456"), 1)?, None);
+        Ok(())
+    }
+
+    /// Verifies moving the scores of a unit to a new ID.
+    #[test]
+    fn rename_unit() -> Result<()> {
+        let mut stats = new_tests_stats()?;
+        let old_id = Ustr::from("old_ex");
+        let new_id = Ustr::from("new_ex");
+        let other_id = Ustr::from("other_ex");
+        stats.record_exercise_scores(&[trial(old_id, 3.0, 1), trial(old_id, 4.0, 2)])?;
+        stats.record_exercise_scores(&[trial(other_id, 5.0, 3)])?;
+
+        // Move the scores to a new unit.
+        stats.rename_unit(old_id, new_id)?;
+        assert_scores(&[], &stats.get_scores(old_id, 10)?);
+        assert_scores(&[4.0, 3.0], &stats.get_scores(new_id, 10)?);
+
+        // Move the scores to a unit that already has scores. Both sets are merged.
+        stats.rename_unit(new_id, other_id)?;
+        assert_scores(&[], &stats.get_scores(new_id, 10)?);
+        assert_scores(&[5.0, 4.0, 3.0], &stats.get_scores(other_id, 10)?);
+
+        // Renaming a unit with no scores does nothing.
+        stats.rename_unit(old_id, new_id)?;
+        assert_scores(&[], &stats.get_scores(new_id, 10)?);
+        Ok(())
+    }
+
+    /// Verifies retrieving all the trials in a time range.
+    #[test]
+    fn trials_in_range() -> Result<()> {
+        let mut stats = new_tests_stats()?;
+        let exercise1_id = Ustr::from("a::b::1");
+        let exercise2_id = Ustr::from("a::b::2");
+        stats.record_exercise_scores(&[
+            trial(exercise1_id, 1.0, 10),
+            trial(exercise2_id, 2.0, 20),
+            trial(exercise1_id, 3.0, 30),
+            trial(exercise2_id, 4.0, 40),
+        ])?;
+
+        // The start of the range is inclusive and the end is exclusive.
+        let trials = stats.get_trials_in_range(20, 40)?;
+        assert_eq!(
+            trials,
+            vec![trial(exercise2_id, 2.0, 20), trial(exercise1_id, 3.0, 30)]
+        );
+        assert!(stats.get_trials_in_range(50, 100)?.is_empty());
+        Ok(())
+    }
+
+    /// Verifies retrieving all the trials of the units matching a prefix.
+    #[test]
+    fn trials_with_prefix() -> Result<()> {
+        let mut stats = new_tests_stats()?;
+        let exercise1_id = Ustr::from("a::b::1");
+        let exercise2_id = Ustr::from("a::c::1");
+        let exercise3_id = Ustr::from("ab::b::1");
+        stats.record_exercise_scores(&[
+            trial(exercise1_id, 1.0, 10),
+            trial(exercise2_id, 2.0, 20),
+            trial(exercise3_id, 3.0, 30),
+        ])?;
+
+        assert_eq!(
+            stats.get_trials_with_prefix("a::")?,
+            vec![trial(exercise1_id, 1.0, 10), trial(exercise2_id, 2.0, 20)]
+        );
+        assert_eq!(stats.get_trials_with_prefix("a")?.len(), 3);
+        assert_eq!(
+            stats.get_trials_with_prefix("a::c")?,
+            vec![trial(exercise2_id, 2.0, 20)]
+        );
+        assert!(stats.get_trials_with_prefix("b")?.is_empty());
+        Ok(())
+    }
+
+    /// Verifies counting the trials on each day.
+    #[test]
+    fn daily_trial_counts() -> Result<()> {
+        let mut stats = new_tests_stats()?;
+        let exercise_id = Ustr::from("a::b::1");
+        let day = 86400;
+        stats.record_exercise_scores(&[
+            trial(exercise_id, 1.0, day + 100),
+            trial(exercise_id, 2.0, day + 200),
+            trial(exercise_id, 3.0, 2 * day - 100),
+            trial(exercise_id, 4.0, 3 * day + 100),
+        ])?;
+
+        // Count the trials in UTC.
+        let counts = stats.get_daily_trial_counts(0, 4 * day, 0)?;
+        let date = |d: u32| NaiveDate::from_ymd_opt(1970, 1, d).unwrap();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&date(2)], 3);
+        assert_eq!(counts[&date(4)], 1);
+
+        // An offset moves trials close to midnight to the next day.
+        let counts = stats.get_daily_trial_counts(0, 4 * day, 3600)?;
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts[&date(2)], 2);
+        assert_eq!(counts[&date(3)], 1);
+        assert_eq!(counts[&date(4)], 1);
+        Ok(())
+    }
+
+    /// Verifies counting the trials on each day in a time zone.
+    #[test]
+    fn local_daily_trial_counts() -> Result<()> {
+        // Record trials close to midnight in Madrid, which is one hour ahead of UTC in winter and
+        // two hours ahead in summer.
+        let mut stats = new_tests_stats()?;
+        let exercise_id = Ustr::from("a::b::1");
+        let winter = 1_704_067_200; // 2024-01-01T00:00:00Z
+        let summer = 1_719_792_000; // 2024-07-01T00:00:00Z
+        stats.record_exercise_scores(&[
+            trial(exercise_id, 1.0, winter - 3000),
+            trial(exercise_id, 2.0, winter - 4000),
+            trial(exercise_id, 3.0, summer - 6000),
+            trial(exercise_id, 4.0, summer - 8000),
+        ])?;
+
+        // The trials are counted on the local date of each time zone.
+        let date = |m: u32, d: u32| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let madrid: Tz = "Europe/Madrid".parse().unwrap();
+        let counts = stats.get_local_daily_trial_counts(i64::MIN, i64::MAX, madrid)?;
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts[&date(1, 1)], 1);
+        assert_eq!(counts[&date(6, 30)], 1);
+        assert_eq!(counts[&date(7, 1)], 1);
+        assert_eq!(counts[&NaiveDate::from_ymd_opt(2023, 12, 31).unwrap()], 1);
+        let counts = stats.get_local_daily_trial_counts(winter, summer, Tz::UTC)?;
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[&date(6, 30)], 2);
+        Ok(())
+    }
+
+    /// Verifies iterating over the full trial history in pages.
+    #[test]
+    fn trial_history() -> Result<()> {
+        let mut stats = new_tests_stats()?;
+        let exercise1_id = Ustr::from("a::b::1");
+        let exercise2_id = Ustr::from("a::b::2");
+
+        // Record several trials with the same timestamp to verify that no trial is skipped or
+        // repeated across pages.
+        let trials = vec![
+            trial(exercise1_id, 1.0, 10),
+            trial(exercise2_id, 2.0, 10),
+            trial(exercise1_id, 3.0, 10),
+            trial(exercise2_id, 4.0, 20),
+            trial(exercise1_id, 5.0, 30),
+        ];
+        stats.record_exercise_scores(&trials)?;
+
+        let mut history = vec![];
+        let mut cursor: Option<TrialHistoryCursor> = None;
+        let mut num_pages = 0;
+        loop {
+            let page = stats.get_trial_history(cursor, 2)?;
+            history.extend(page.trials);
+            num_pages += 1;
+            cursor = page.next;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(history, trials);
+        assert_eq!(num_pages, 3);
+
+        // An empty history returns an empty page with no cursor.
+        let stats = new_tests_stats()?;
+        let page = stats.get_trial_history(None, 10)?;
+        assert!(page.trials.is_empty());
+        assert!(page.next.is_none());
+        Ok(())
+    }
+
+    /// Verifies applying a retention policy to the trials.
+    #[test]
+    fn apply_retention_policy() -> Result<()> {
+        let mut stats = new_tests_stats()?;
+        let exercise_id = Ustr::from("a::b::1");
+        stats.record_exercise_scores(&[
+            trial(exercise_id, 5.0, 1),
+            trial(exercise_id, 5.0, 5),
+            trial(exercise_id, 5.0, 8),
+            trial(exercise_id, 4.0, 11),
+            trial(exercise_id, 2.0, 12),
+            trial(exercise_id, 1.0, 13),
+            trial(exercise_id, 4.0, 15),
+            trial(exercise_id, 5.0, 25),
+            trial(exercise_id, 5.0, 150),
+        ])?;
+
+        // Only the first trial, the last trial in each interval, the lapse and the trial before it,
+        // and the recent trials are kept. The failure after the lapse is not a lapse because the
+        // exercise was not mastered at that point.
+        let mut policy = RetentionPolicy {
+            retention_window: 100,
+            downsample_interval: 10,
+            mastered_score: 4.0,
+            lapse_score: 3.0,
+            compact: true,
+        };
+        assert_eq!(stats.apply_retention_policy(&policy, 200)?, 2);
+        let timestamps: Vec<i64> = stats
+            .get_scores(exercise_id, 10)?
+            .iter()
+            .map(|t| t.timestamp)
+            .collect();
+        assert_eq!(timestamps, vec![150, 25, 15, 12, 11, 8, 1]);
+
+        // Applying the policy again removes nothing.
+        assert_eq!(stats.apply_retention_policy(&policy, 200)?, 0);
+
+        // Without downsampling, only the first trial, the lapse and the trial before it, and the
+        // recent trials are kept.
+        policy.downsample_interval = 0;
+        assert_eq!(stats.apply_retention_policy(&policy, 200)?, 3);
+        assert_scores(&[5.0, 2.0, 4.0, 5.0], &stats.get_scores(exercise_id, 10)?);
+        stats.compact()?;
+
+        // Invalid policies are rejected.
+        policy.retention_window = -1;
+        assert!(stats.apply_retention_policy(&policy, 200).is_err());
+        Ok(())
+    }
 }
@@ -56,6 +56,28 @@ impl PreferencesManager for LocalPreferencesManager {
     }
 }
 
+/// A preferences manager that keeps the user preferences in memory. Changes are lost when the
+/// manager is dropped.
+#[derive(Default)]
+pub struct InMemoryPreferencesManager {
+    /// The current user preferences.
+    pub preferences: UserPreferences,
+}
+
+impl PreferencesManager for InMemoryPreferencesManager {
+    fn get_user_preferences(&self) -> Result<UserPreferences, PreferencesManagerError> {
+        Ok(self.preferences.clone())
+    }
+
+    fn set_user_preferences(
+        &mut self,
+        preferences: UserPreferences,
+    ) -> Result<(), PreferencesManagerError> {
+        self.preferences = preferences;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{fs, os::unix::fs::PermissionsExt};
@@ -66,7 +88,9 @@ mod tests {
     use crate::{
         USER_PREFERENCES_PATH,
         data::UserPreferences,
-        preferences_manager::{LocalPreferencesManager, PreferencesManager},
+        preferences_manager::{
+            InMemoryPreferencesManager, LocalPreferencesManager, PreferencesManager,
+        },
     };
 
     /// Verifies setting and getting user preferences using the local filesystem.
@@ -113,4 +137,18 @@ mod tests {
         assert!(manager.set_user_preferences(preferences).is_err());
         Ok(())
     }
+
+    /// Verifies setting and getting the preferences from the in-memory manager.
+    #[test]
+    fn in_memory_preferences_manager() -> Result<()> {
+        let mut manager = InMemoryPreferencesManager::default();
+        assert_eq!(manager.get_user_preferences()?, UserPreferences::default());
+        let new_preferences = UserPreferences {
+            ignored_paths: vec!["foo".to_string()],
+            ..Default::default()
+        };
+        manager.set_user_preferences(new_preferences.clone())?;
+        assert_eq!(manager.get_user_preferences()?, new_preferences);
+        Ok(())
+    }
 }
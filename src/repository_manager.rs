@@ -0,0 +1,541 @@
+//! Contains utilities to manage the repositories from which courses are downloaded.
+//!
+//! Courses are often distributed as git repositories, each containing one or more courses. The
+//! repository manager downloads them into a directory inside the course library, keeps track of
+//! their URLs in the `.trane` directory, and updates them on request. Local directories that are
+//! not git repositories can also be added, in which case their contents are copied into the
+//! library and updating them copies the contents again.
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+use walkdir::WalkDir;
+
+use crate::{
+    DOWNLOAD_DIRECTORY, REPOSITORY_DIRECTORY, TRANE_CONFIG_DIR_PATH, data::RepositoryMetadata,
+    error::RepositoryManagerError, importer::sanitize_id,
+};
+
+/// A trait with functions to manage the repositories containing the courses in the library. Each
+/// repository is given a unique ID, which is also used to name the directory into which it's
+/// downloaded.
+pub trait RepositoryManager {
+    /// Downloads the repository at the given URL into the library. If no ID is given, one is
+    /// derived from the last segment of the URL.
+    fn add_repo(&mut self, url: &str, repo_id: Option<&str>) -> Result<(), RepositoryManagerError>;
+
+    /// Removes the repository with the given ID and its contents from the library.
+    fn remove_repo(&mut self, repo_id: &str) -> Result<(), RepositoryManagerError>;
+
+    /// Updates the repository with the given ID to the latest version.
+    fn update_repo(&mut self, repo_id: &str) -> Result<(), RepositoryManagerError>;
+
+    /// Updates all the repositories to their latest versions.
+    fn update_all_repos(&mut self) -> Result<(), RepositoryManagerError>;
+
+    /// Returns the metadata of all the repositories, sorted by their IDs.
+    fn list_repos(&self) -> Result<Vec<RepositoryMetadata>, RepositoryManagerError>;
+}
+
+/// Returns whether the given URL should be downloaded with git. Otherwise, it's treated as the path
+/// to a local directory.
+fn is_git_url(url: &str) -> bool {
+    url.contains("://")
+        || url.starts_with("git@")
+        || Path::new(url)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("git"))
+}
+
+/// Verifies that the URL of a repository cannot be interpreted as an option by git or any other
+/// command that receives it.
+fn verify_url(url: &str) -> Result<()> {
+    ensure!(!url.is_empty(), "the repository URL cannot be empty");
+    ensure!(
+        !url.starts_with('-'),
+        "the repository URL {url} cannot start with a dash"
+    );
+    Ok(())
+}
+
+/// Returns the default ID of the repository at the given URL, derived from its last segment.
+fn default_repo_id(url: &str) -> String {
+    let last_segment = url
+        .trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\', ':'])
+        .next()
+        .unwrap_or_default();
+    sanitize_id(last_segment.strip_suffix(".git").unwrap_or(last_segment))
+}
+
+/// Verifies that the ID of a repository can be safely used as the name of a directory.
+fn verify_repo_id(repo_id: &str) -> Result<()> {
+    ensure!(!repo_id.is_empty(), "the repository ID cannot be empty");
+    ensure!(
+        repo_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'),
+        "the repository ID {repo_id} can only contain ASCII letters, digits, underscores, and \
+         dashes",
+    );
+    Ok(())
+}
+
+/// Runs git with the given arguments and returns an error with its output if it fails.
+fn run_git(args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Copies the contents of the source directory into the destination directory, skipping the git
+/// directory.
+fn copy_directory(source: &Path, destination: &Path) -> Result<()> {
+    ensure!(
+        source.is_dir(),
+        "{} is not a git URL or a directory",
+        source.display()
+    );
+    for entry in WalkDir::new(source)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+    {
+        let entry = entry?;
+        let target = destination.join(entry.path().strip_prefix(source)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .context(format!("failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// An implementation of [`RepositoryManager`] that downloads the repositories into the
+/// `managed_courses` directory of the library and stores their metadata in the `.trane` directory.
+pub struct LocalRepositoryManager {
+    /// The directory in which the metadata of each repository is stored as a JSON file. If it's
+    /// `None`, the metadata is only kept in memory.
+    metadata_directory: Option<PathBuf>,
+
+    /// The directory into which the repositories are downloaded.
+    download_directory: PathBuf,
+
+    /// A map of repository IDs to their metadata.
+    repositories: BTreeMap<String, RepositoryMetadata>,
+}
+
+impl LocalRepositoryManager {
+    /// Scans the metadata of all the repositories in the given directory.
+    fn scan_repositories(
+        metadata_directory: &Path,
+    ) -> Result<BTreeMap<String, RepositoryMetadata>> {
+        let mut repositories = BTreeMap::new();
+        if !metadata_directory.is_dir() {
+            return Ok(repositories);
+        }
+        for entry in
+            fs::read_dir(metadata_directory).context("failed to read repository directory")?
+        {
+            // Try to read the file as a `RepositoryMetadata`.
+            let entry = entry.context("failed to read repository metadata entry")?;
+            let file = File::open(entry.path()).context(format!(
+                "failed to open repository metadata file {}",
+                entry.path().display()
+            ))?;
+            let metadata: RepositoryMetadata = serde_json::from_reader(BufReader::new(file))
+                .context(format!(
+                    "failed to parse repository metadata from {}",
+                    entry.path().display()
+                ))?;
+
+            // Check for duplicate IDs before inserting the metadata.
+            if repositories.contains_key(&metadata.id) {
+                bail!("found multiple repositories with ID {}", metadata.id);
+            }
+            repositories.insert(metadata.id.clone(), metadata);
+        }
+        Ok(repositories)
+    }
+
+    /// Creates a new repository manager for the library at the given path, reading the metadata
+    /// of the repositories that were previously added.
+    pub fn new(library_root: &Path) -> Result<LocalRepositoryManager> {
+        let metadata_directory = library_root
+            .join(TRANE_CONFIG_DIR_PATH)
+            .join(REPOSITORY_DIRECTORY);
+        Ok(LocalRepositoryManager {
+            repositories: Self::scan_repositories(&metadata_directory)?,
+            metadata_directory: Some(metadata_directory),
+            download_directory: library_root.join(DOWNLOAD_DIRECTORY),
+        })
+    }
+
+    /// Creates a new repository manager for the library at the given path that keeps the metadata
+    /// of the repositories in memory. The repositories are still downloaded into the library.
+    #[must_use]
+    pub fn new_in_memory(library_root: &Path) -> LocalRepositoryManager {
+        LocalRepositoryManager {
+            metadata_directory: None,
+            download_directory: library_root.join(DOWNLOAD_DIRECTORY),
+            repositories: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the directory into which the repository with the given ID is downloaded.
+    fn repo_directory(&self, repo_id: &str) -> PathBuf {
+        self.download_directory.join(repo_id)
+    }
+
+    /// Downloads the repository into the given directory, either by cloning it or by copying it.
+    fn download(url: &str, directory: &Path) -> Result<()> {
+        verify_url(url)?;
+        if is_git_url(url) {
+            let directory = directory.to_str().context("invalid repository directory")?;
+            run_git(&["clone", "--depth", "1", "--", url, directory])
+        } else {
+            copy_directory(Path::new(url), directory)
+        }
+    }
+
+    /// Writes the metadata of the repository to its file in the metadata directory, if any.
+    fn write_metadata(&self, metadata: &RepositoryMetadata) -> Result<()> {
+        let Some(metadata_directory) = &self.metadata_directory else {
+            return Ok(());
+        };
+        fs::create_dir_all(metadata_directory).context("failed to create repository directory")?;
+        let path = metadata_directory.join(format!("{}.json", metadata.id));
+        let mut file = File::create(&path).context(format!(
+            "failed to create repository metadata file {}",
+            path.display()
+        ))?;
+        let metadata_json = serde_json::to_string_pretty(metadata)? + "\n";
+        file.write_all(metadata_json.as_bytes())
+            .context("failed to write repository metadata file")?;
+        Ok(())
+    }
+
+    /// Helper function to add a repository.
+    fn add_repo_helper(&mut self, url: &str, repo_id: Option<&str>) -> Result<()> {
+        // Compute the ID of the repository and verify it's not already in use.
+        verify_url(url)?;
+        let repo_id = match repo_id {
+            Some(repo_id) => repo_id.to_string(),
+            None => default_repo_id(url),
+        };
+        verify_repo_id(&repo_id)?;
+        ensure!(
+            !self.repositories.contains_key(&repo_id),
+            "a repository with ID {repo_id} already exists",
+        );
+        if let Some(existing) = self.repositories.values().find(|repo| repo.url == url) {
+            bail!("the repository was already added with ID {}", existing.id);
+        }
+        let directory = self.repo_directory(&repo_id);
+        ensure!(
+            !directory.exists(),
+            "directory {} already exists",
+            directory.display()
+        );
+
+        // Download the repository, removing any partial download if it fails.
+        fs::create_dir_all(&self.download_directory)
+            .context("failed to create download directory")?;
+        if let Err(e) = Self::download(url, &directory) {
+            let _ = fs::remove_dir_all(&directory);
+            return Err(e);
+        }
+
+        // Save the metadata of the repository.
+        let metadata = RepositoryMetadata {
+            id: repo_id.clone(),
+            url: url.to_string(),
+        };
+        self.write_metadata(&metadata)?;
+        self.repositories.insert(repo_id, metadata);
+        Ok(())
+    }
+
+    /// Helper function to remove a repository.
+    fn remove_repo_helper(&mut self, repo_id: &str) -> Result<()> {
+        ensure!(
+            self.repositories.contains_key(repo_id),
+            "no repository with ID {repo_id} exists",
+        );
+
+        // Remove the downloaded contents and the metadata of the repository.
+        let directory = self.repo_directory(repo_id);
+        if directory.exists() {
+            fs::remove_dir_all(&directory).context(format!(
+                "failed to remove directory {}",
+                directory.display()
+            ))?;
+        }
+        if let Some(metadata_directory) = &self.metadata_directory {
+            let path = metadata_directory.join(format!("{repo_id}.json"));
+            if path.exists() {
+                fs::remove_file(&path).context(format!(
+                    "failed to remove repository metadata file {}",
+                    path.display()
+                ))?;
+            }
+        }
+        self.repositories.remove(repo_id);
+        Ok(())
+    }
+
+    /// Helper function to update a repository.
+    fn update_repo_helper(&self, repo_id: &str) -> Result<()> {
+        let metadata = self
+            .repositories
+            .get(repo_id)
+            .ok_or_else(|| anyhow!("no repository with ID {repo_id} exists"))?;
+        let directory = self.repo_directory(repo_id);
+
+        // Download the repository again if its directory was deleted.
+        if !directory.exists() {
+            fs::create_dir_all(&self.download_directory)
+                .context("failed to create download directory")?;
+            return Self::download(&metadata.url, &directory);
+        }
+
+        // Pull the latest changes of git repositories.
+        if directory.join(".git").exists() {
+            let directory = directory.to_str().context("invalid repository directory")?;
+            return run_git(&["-C", directory, "pull", "--ff-only", "--"]);
+        }
+
+        // Copy local directories to a staging directory first, so that a failed copy does not
+        // leave the repository in an inconsistent state.
+        let staging = tempfile::TempDir::new_in(&self.download_directory)
+            .context("failed to create staging directory")?;
+        copy_directory(Path::new(&metadata.url), staging.path())?;
+        fs::remove_dir_all(&directory).context(format!(
+            "failed to remove directory {}",
+            directory.display()
+        ))?;
+        fs::rename(staging.keep(), &directory).context(format!(
+            "failed to move updated contents into {}",
+            directory.display()
+        ))?;
+        Ok(())
+    }
+}
+
+impl RepositoryManager for LocalRepositoryManager {
+    fn add_repo(&mut self, url: &str, repo_id: Option<&str>) -> Result<(), RepositoryManagerError> {
+        self.add_repo_helper(url, repo_id)
+            .map_err(|e| RepositoryManagerError::AddRepo(url.to_string(), e))
+    }
+
+    fn remove_repo(&mut self, repo_id: &str) -> Result<(), RepositoryManagerError> {
+        self.remove_repo_helper(repo_id)
+            .map_err(|e| RepositoryManagerError::RemoveRepo(repo_id.to_string(), e))
+    }
+
+    fn update_repo(&mut self, repo_id: &str) -> Result<(), RepositoryManagerError> {
+        self.update_repo_helper(repo_id)
+            .map_err(|e| RepositoryManagerError::UpdateRepo(repo_id.to_string(), e))
+    }
+
+    fn update_all_repos(&mut self) -> Result<(), RepositoryManagerError> {
+        for repo_id in self.repositories.keys() {
+            self.update_repo_helper(repo_id)
+                .map_err(|e| RepositoryManagerError::UpdateRepos(anyhow!("{repo_id}: {e}")))?;
+        }
+        Ok(())
+    }
+
+    fn list_repos(&self) -> Result<Vec<RepositoryMetadata>, RepositoryManagerError> {
+        Ok(self.repositories.values().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use anyhow::Result;
+    use std::{fs, path::Path};
+
+    use crate::{DOWNLOAD_DIRECTORY, data::RepositoryMetadata, repository_manager::*};
+
+    /// Creates a git repository in the given directory with a file with the given contents.
+    fn init_git_repo(directory: &Path, contents: &str) -> Result<()> {
+        fs::create_dir_all(directory)?;
+        let directory = directory.to_str().unwrap();
+        run_git(&["init", "--quiet", directory])?;
+        commit_file(directory, contents)
+    }
+
+    /// Writes the given contents to the file in the git repository and commits it.
+    fn commit_file(directory: &str, contents: &str) -> Result<()> {
+        fs::write(Path::new(directory).join("file.txt"), contents)?;
+        run_git(&["-C", directory, "add", "-A"])?;
+        run_git(&[
+            "-C",
+            directory,
+            "-c",
+            "user.name=Trane",
+            "-c",
+            "user.email=trane@example.com",
+            "commit",
+            "--quiet",
+            "-m",
+            "Update file",
+        ])
+    }
+
+    /// Verifies deriving the default IDs of repositories from their URLs.
+    #[test]
+    fn default_ids() {
+        assert_eq!(
+            default_repo_id("https://github.com/trane-project/trane-music.git"),
+            "trane_music"
+        );
+        assert_eq!(default_repo_id("file:///tmp/Courses/"), "courses");
+        assert_eq!(default_repo_id("git@github.com:user/repo.git"), "repo");
+        assert!(verify_repo_id("trane-music_1").is_ok());
+        assert!(verify_repo_id("").is_err());
+        assert!(verify_repo_id("../escape").is_err());
+        assert!(verify_url("https://example.com/repo.git").is_ok());
+        assert!(verify_url("").is_err());
+        assert!(is_git_url("file:///tmp/repo"));
+        assert!(!is_git_url("/tmp/repo"));
+    }
+
+    /// Verifies that URLs that git would interpret as options are rejected without running git.
+    #[test]
+    fn reject_option_urls() -> Result<()> {
+        let library_dir = tempfile::tempdir()?;
+        let marker = library_dir.path().join("marker");
+        let url = format!("--upload-pack=touch {};://x", marker.display());
+        let mut manager = LocalRepositoryManager::new(library_dir.path())?;
+        assert!(manager.add_repo(&url, Some("repo")).is_err());
+        assert!(LocalRepositoryManager::download(&url, &library_dir.path().join("repo")).is_err());
+        assert!(!marker.exists());
+        assert!(!library_dir.path().join(DOWNLOAD_DIRECTORY).exists());
+        assert!(manager.list_repos()?.is_empty());
+        Ok(())
+    }
+
+    /// Verifies adding, updating, listing, and removing a git repository.
+    #[test]
+    fn git_repository() -> Result<()> {
+        let remote_dir = tempfile::tempdir()?;
+        let library_dir = tempfile::tempdir()?;
+        init_git_repo(&remote_dir.path().join("remote"), "first")?;
+        let url = format!("file://{}", remote_dir.path().join("remote").display());
+
+        // Add the repository and verify it was cloned and its metadata was saved.
+        let mut manager = LocalRepositoryManager::new(library_dir.path())?;
+        manager.add_repo(&url, None)?;
+        let file = library_dir
+            .path()
+            .join(DOWNLOAD_DIRECTORY)
+            .join("remote")
+            .join("file.txt");
+        assert_eq!(fs::read_to_string(&file)?, "first");
+        let expected = vec![RepositoryMetadata {
+            id: "remote".to_string(),
+            url: url.clone(),
+        }];
+        assert_eq!(manager.list_repos()?, expected);
+        assert_eq!(
+            LocalRepositoryManager::new(library_dir.path())?.list_repos()?,
+            expected
+        );
+
+        // Adding the same URL or ID again fails.
+        assert!(manager.add_repo(&url, Some("other")).is_err());
+        assert!(manager.add_repo("file:///other", Some("remote")).is_err());
+
+        // Commit a change to the remote and update the repository.
+        commit_file(remote_dir.path().join("remote").to_str().unwrap(), "second")?;
+        manager.update_repo("remote")?;
+        assert_eq!(fs::read_to_string(&file)?, "second");
+        commit_file(remote_dir.path().join("remote").to_str().unwrap(), "third")?;
+        manager.update_all_repos()?;
+        assert_eq!(fs::read_to_string(&file)?, "third");
+
+        // Remove the repository.
+        manager.remove_repo("remote")?;
+        assert!(!file.exists());
+        assert!(manager.list_repos()?.is_empty());
+        assert!(
+            LocalRepositoryManager::new(library_dir.path())?
+                .list_repos()?
+                .is_empty()
+        );
+        assert!(manager.remove_repo("remote").is_err());
+        assert!(manager.update_repo("remote").is_err());
+        Ok(())
+    }
+
+    /// Verifies adding and updating a local directory that is not a git repository.
+    #[test]
+    fn local_directory() -> Result<()> {
+        let source_dir = tempfile::tempdir()?;
+        let library_dir = tempfile::tempdir()?;
+        fs::create_dir(source_dir.path().join("course"))?;
+        fs::write(source_dir.path().join("course").join("file.txt"), "first")?;
+
+        let mut manager = LocalRepositoryManager::new_in_memory(library_dir.path());
+        manager.add_repo(source_dir.path().to_str().unwrap(), Some("local"))?;
+        let file = library_dir
+            .path()
+            .join(DOWNLOAD_DIRECTORY)
+            .join("local")
+            .join("course")
+            .join("file.txt");
+        assert_eq!(fs::read_to_string(&file)?, "first");
+
+        // Update the source and verify the contents are copied again.
+        fs::write(source_dir.path().join("course").join("file.txt"), "second")?;
+        manager.update_repo("local")?;
+        assert_eq!(fs::read_to_string(&file)?, "second");
+
+        // Restore the contents if the directory was deleted.
+        fs::remove_dir_all(library_dir.path().join(DOWNLOAD_DIRECTORY).join("local"))?;
+        manager.update_repo("local")?;
+        assert_eq!(fs::read_to_string(&file)?, "second");
+        Ok(())
+    }
+
+    /// Verifies that failed downloads do not leave any data behind.
+    #[test]
+    fn failed_download() -> Result<()> {
+        let library_dir = tempfile::tempdir()?;
+        let mut manager = LocalRepositoryManager::new(library_dir.path())?;
+        let missing = library_dir.path().join("missing");
+        assert!(
+            manager
+                .add_repo(&format!("file://{}", missing.display()), None)
+                .is_err()
+        );
+        assert!(manager.add_repo(missing.to_str().unwrap(), None).is_err());
+        assert!(
+            !library_dir
+                .path()
+                .join(DOWNLOAD_DIRECTORY)
+                .join("missing")
+                .exists()
+        );
+        assert!(manager.list_repos()?.is_empty());
+        Ok(())
+    }
+}
@@ -3,32 +3,98 @@
 //! Students might identify exercises, lessons, or courses which need additional review. They can
 //! add them to the review list. The scheduler implements a special mode that will only schedule
 //! exercises from the units in the review list.
+//!
+//! Each entry has a priority, which makes exercises from the entry more likely to be scheduled,
+//! and an optional number of successful reviews after which the entry is removed automatically.
 
 use anyhow::{Context, Result};
+use chrono::Utc;
 use parking_lot::Mutex;
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, Row, params};
 use rusqlite_migration::{M, Migrations};
+use std::sync::Arc;
 use ustr::Ustr;
 
-use crate::{error::ReviewListError, utils};
+use crate::{
+    data::{AutoReviewAddition, ReviewListEntry},
+    error::ReviewListError,
+    utils,
+};
+
+/// The priority assigned to entries added without an explicit priority.
+pub const DEFAULT_REVIEW_PRIORITY: u32 = 1;
 
 /// An interface to store and read a list of units that need review.
 pub trait ReviewList {
-    /// Adds the given unit to the review list.
+    /// Adds the given unit to the review list with the default priority. Do nothing if the unit is
+    /// already in the list.
     fn add_to_review_list(&mut self, unit_id: Ustr) -> Result<(), ReviewListError>;
 
+    /// Adds the given unit to the review list with the given priority and an optional number of
+    /// successful reviews after which the entry is removed. If the unit is already in the list, its
+    /// details are replaced and its count of successful reviews is reset.
+    fn add_to_review_list_with_details(
+        &mut self,
+        unit_id: Ustr,
+        priority: u32,
+        remove_after: Option<u32>,
+    ) -> Result<(), ReviewListError>;
+
+    /// Adds a unit to the review list on behalf of the student because the performance on one of
+    /// its exercises lapsed, and records the reason for doing so. Returns whether the unit was
+    /// added. Do nothing if the unit is already in the list.
+    fn add_to_review_list_automatically(
+        &mut self,
+        addition: &AutoReviewAddition,
+    ) -> Result<bool, ReviewListError>;
+
     /// Removes the given unit from the review list. Do nothing if the unit is not already in the
     /// list.
     fn remove_from_review_list(&mut self, unit_id: Ustr) -> Result<(), ReviewListError>;
 
+    /// Records a successful review of the given unit. If the entry has reached the number of
+    /// successful reviews after which it should be removed, it's removed from the list. Returns
+    /// whether the entry was removed. Do nothing if the unit is not in the list.
+    fn record_successful_review(&mut self, unit_id: Ustr) -> Result<bool, ReviewListError>;
+
+    /// Writes the given entry to the review list, replacing the existing entry of the same unit.
+    /// Used to roll back the changes made to an entry when a score is undone.
+    fn restore_review_list_entry(&mut self, entry: &ReviewListEntry)
+    -> Result<(), ReviewListError>;
+
+    /// Removes the unit added by the given automatic addition from the review list, along with the
+    /// record of the addition. Used to roll back the additions made when a score is undone.
+    fn remove_automatic_addition(
+        &mut self,
+        addition: &AutoReviewAddition,
+    ) -> Result<(), ReviewListError>;
+
+    /// Moves the review list entry of the unit with ID `old_id` to the unit with ID `new_id`,
+    /// including the record of automatic additions. Used when a unit is renamed. If the new unit
+    /// is already in the review list, its entry is kept.
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), ReviewListError>;
+
     /// Returns all the entries in the review list.
     fn get_review_list_entries(&self) -> Result<Vec<Ustr>, ReviewListError>;
+
+    /// Returns the details of the entry for the given unit, if it's in the review list.
+    fn get_review_list_entry(
+        &self,
+        unit_id: Ustr,
+    ) -> Result<Option<ReviewListEntry>, ReviewListError>;
+
+    /// Returns the details of all the entries in the review list.
+    fn get_detailed_review_list_entries(&self) -> Result<Vec<ReviewListEntry>, ReviewListError>;
+
+    /// Returns a report of all the units that were automatically added to the review list and the
+    /// reasons for doing so, sorted by timestamp.
+    fn get_auto_review_additions(&self) -> Result<Vec<AutoReviewAddition>, ReviewListError>;
 }
 
 /// An implementation of [`ReviewList`] backed by `SQLite`.
 pub struct LocalReviewList {
     /// A connection to the database.
-    connection: Mutex<Connection>,
+    connection: Arc<Mutex<Connection>>,
 }
 
 impl LocalReviewList {
@@ -41,6 +107,27 @@ impl LocalReviewList {
             // Create an index of the unit IDs in the review list.
             M::up("CREATE INDEX unit_id_index ON review_list (unit_id);")
                 .down("DROP INDEX unit_id_index"),
+            // Record the priority of each entry. Existing entries get the default priority.
+            M::up("ALTER TABLE review_list ADD COLUMN priority INTEGER NOT NULL DEFAULT 1;")
+                .down("ALTER TABLE review_list DROP COLUMN priority;"),
+            // Record when each unit was added to the review list. Existing entries get a value of
+            // zero.
+            M::up("ALTER TABLE review_list ADD COLUMN added_at INTEGER NOT NULL DEFAULT 0;")
+                .down("ALTER TABLE review_list DROP COLUMN added_at;"),
+            // Record the number of successful reviews after which each entry is removed.
+            M::up("ALTER TABLE review_list ADD COLUMN remove_after INTEGER;")
+                .down("ALTER TABLE review_list DROP COLUMN remove_after;"),
+            // Record the number of successful reviews of each entry.
+            M::up(
+                "ALTER TABLE review_list ADD COLUMN successful_reviews INTEGER NOT NULL DEFAULT 0;",
+            )
+            .down("ALTER TABLE review_list DROP COLUMN successful_reviews;"),
+            // Create a table with the units that were automatically added to the review list.
+            M::up(
+                "CREATE TABLE auto_additions(unit_id TEXT NOT NULL, exercise_id TEXT NOT NULL, \
+                reason TEXT NOT NULL, timestamp INTEGER NOT NULL);",
+            )
+            .down("DROP TABLE auto_additions"),
         ])
     }
 
@@ -57,27 +144,107 @@ impl LocalReviewList {
     /// Creates a new instance with the given connection and initializes the database.
     fn new(connection: Connection) -> Result<LocalReviewList> {
         let mut review_list = LocalReviewList {
-            connection: Mutex::new(connection),
+            connection: Arc::new(Mutex::new(connection)),
         };
         review_list.init()?;
         Ok(review_list)
     }
 
+    /// Creates a new instance that shares the given connection with the other stores in the
+    /// consolidated user database. The schema is managed by
+    /// [`UserDatabase`](crate::user_database::UserDatabase), so no migrations are run.
+    pub(crate) fn new_shared(connection: Arc<Mutex<Connection>>) -> LocalReviewList {
+        LocalReviewList { connection }
+    }
+
     /// A constructor taking the path to the database file.
     pub fn new_from_disk(db_path: &str) -> Result<LocalReviewList> {
         Self::new(utils::new_connection(db_path)?)
     }
 
+    /// A constructor for a database that is kept in memory and discarded when dropped. Useful to
+    /// run Trane without writing any user data to the file system.
+    pub fn new_in_memory() -> Result<LocalReviewList> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    /// Converts a row from the review list table into a review list entry.
+    fn row_to_entry(row: &Row) -> Result<ReviewListEntry> {
+        let unit_id: String = row.get(0)?;
+        Ok(ReviewListEntry {
+            unit_id: Ustr::from(&unit_id),
+            priority: row.get(1)?,
+            added_at: row.get(2)?,
+            remove_after: row.get(3)?,
+            successful_reviews: row.get(4)?,
+        })
+    }
+
     /// Helper to add a unit to the review list.
     fn add_to_review_list_helper(&mut self, unit_id: Ustr) -> Result<()> {
         // Add the unit to the database.
         let connection = self.connection.lock();
-        let mut stmt =
-            connection.prepare_cached("INSERT OR IGNORE INTO review_list (unit_id) VALUES (?1)")?;
-        stmt.execute(params![unit_id.as_str()])?;
+        let mut stmt = connection.prepare_cached(
+            "INSERT OR IGNORE INTO review_list (unit_id, added_at) VALUES (?1, ?2)",
+        )?;
+        stmt.execute(params![unit_id.as_str(), Utc::now().timestamp()])?;
         Ok(())
     }
 
+    /// Helper to add a unit to the review list with the given details.
+    fn add_to_review_list_with_details_helper(
+        &mut self,
+        unit_id: Ustr,
+        priority: u32,
+        remove_after: Option<u32>,
+    ) -> Result<()> {
+        // Add the unit to the database or replace the details of the existing entry. The time at
+        // which the unit was first added is preserved.
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "INSERT INTO review_list (unit_id, priority, added_at, remove_after) \
+            VALUES (?1, ?2, ?3, ?4) ON CONFLICT(unit_id) DO UPDATE SET \
+            priority = excluded.priority, remove_after = excluded.remove_after, \
+            successful_reviews = 0",
+        )?;
+        stmt.execute(params![
+            unit_id.as_str(),
+            priority,
+            Utc::now().timestamp(),
+            remove_after
+        ])?;
+        Ok(())
+    }
+
+    /// Helper to automatically add a unit to the review list.
+    fn add_to_review_list_automatically_helper(
+        &mut self,
+        addition: &AutoReviewAddition,
+    ) -> Result<bool> {
+        // Add the unit to the database if it's not already there.
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "INSERT OR IGNORE INTO review_list (unit_id, added_at) VALUES (?1, ?2)",
+        )?;
+        let added = stmt.execute(params![addition.unit_id.as_str(), addition.timestamp])? > 0;
+        if !added {
+            return Ok(false);
+        }
+
+        // Record why the unit was added.
+        let mut stmt = connection.prepare_cached(
+            "INSERT INTO auto_additions (unit_id, exercise_id, reason, timestamp) \
+            VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        stmt.execute(params![
+            addition.unit_id.as_str(),
+            addition.exercise_id.as_str(),
+            serde_json::to_string(&addition.reason)?,
+            addition.timestamp
+        ])?;
+        Ok(true)
+    }
+
     /// Helper to remove a unit from the review list.
     fn remove_from_review_list_helper(&mut self, unit_id: Ustr) -> Result<()> {
         // Remove the unit from the database.
@@ -87,6 +254,91 @@ impl LocalReviewList {
         Ok(())
     }
 
+    /// Helper to record a successful review of a unit in the review list.
+    fn record_successful_review_helper(&mut self, unit_id: Ustr) -> Result<bool> {
+        // Increment the number of successful reviews.
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "UPDATE review_list SET successful_reviews = successful_reviews + 1 \
+            WHERE unit_id = ?1",
+        )?;
+        stmt.execute(params![unit_id.as_str()])?;
+
+        // Remove the entry if it has been reviewed successfully enough times.
+        let mut stmt = connection.prepare_cached(
+            "DELETE FROM review_list WHERE unit_id = ?1 AND remove_after IS NOT NULL \
+            AND successful_reviews >= remove_after",
+        )?;
+        let removed = stmt.execute(params![unit_id.as_str()])?;
+        Ok(removed > 0)
+    }
+
+    /// Helper to write an entry to the review list.
+    fn restore_review_list_entry_helper(&mut self, entry: &ReviewListEntry) -> Result<()> {
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "INSERT OR REPLACE INTO review_list \
+            (unit_id, priority, added_at, remove_after, successful_reviews) \
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        stmt.execute(params![
+            entry.unit_id.as_str(),
+            entry.priority,
+            entry.added_at,
+            entry.remove_after,
+            entry.successful_reviews
+        ])?;
+        Ok(())
+    }
+
+    /// Helper to remove a unit that was automatically added to the review list.
+    fn remove_automatic_addition_helper(&mut self, addition: &AutoReviewAddition) -> Result<()> {
+        // Remove the entry and the record of the addition in a single transaction.
+        let mut connection = self.connection.lock();
+        let tx = connection.savepoint()?;
+        tx.execute(
+            "DELETE FROM review_list WHERE unit_id = ?1",
+            params![addition.unit_id.as_str()],
+        )?;
+        tx.execute(
+            "DELETE FROM auto_additions WHERE unit_id = ?1 AND exercise_id = ?2 \
+            AND timestamp = ?3",
+            params![
+                addition.unit_id.as_str(),
+                addition.exercise_id.as_str(),
+                addition.timestamp
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Helper function to move the entry of a unit in the review list to a new ID.
+    fn rename_unit_helper(&mut self, old_id: Ustr, new_id: Ustr) -> Result<()> {
+        // Update the entry unless the new unit already has one, in which case the old entry is
+        // deleted. The automatic additions can refer to the unit or the exercise that caused them.
+        let mut connection = self.connection.lock();
+        let tx = connection.savepoint()?;
+        tx.execute(
+            "UPDATE OR IGNORE review_list SET unit_id = ?2 WHERE unit_id = ?1;",
+            params![old_id.as_str(), new_id.as_str()],
+        )?;
+        tx.execute(
+            "DELETE FROM review_list WHERE unit_id = ?1;",
+            params![old_id.as_str()],
+        )?;
+        tx.execute(
+            "UPDATE auto_additions SET unit_id = ?2 WHERE unit_id = ?1;",
+            params![old_id.as_str(), new_id.as_str()],
+        )?;
+        tx.execute(
+            "UPDATE auto_additions SET exercise_id = ?2 WHERE exercise_id = ?1;",
+            params![old_id.as_str(), new_id.as_str()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
     /// Helper to get all the entries in the review list.
     fn get_review_list_entries_helper(&self) -> Result<Vec<Ustr>> {
         // Retrieve all the units from the database.
@@ -102,6 +354,60 @@ impl LocalReviewList {
         }
         Ok(entries)
     }
+
+    /// Helper to get the details of the entry for the given unit.
+    fn get_review_list_entry_helper(&self, unit_id: Ustr) -> Result<Option<ReviewListEntry>> {
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT unit_id, priority, added_at, remove_after, successful_reviews \
+            from review_list WHERE unit_id = ?1;",
+        )?;
+        let mut rows = stmt.query(params![unit_id.as_str()])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::row_to_entry(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Helper to get the details of all the entries in the review list.
+    fn get_detailed_review_list_entries_helper(&self) -> Result<Vec<ReviewListEntry>> {
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT unit_id, priority, added_at, remove_after, successful_reviews \
+            from review_list;",
+        )?;
+        let mut rows = stmt.query(params![])?;
+
+        let mut entries = Vec::new();
+        while let Some(row) = rows.next()? {
+            entries.push(Self::row_to_entry(row)?);
+        }
+        Ok(entries)
+    }
+
+    /// Helper to get the units that were automatically added to the review list.
+    fn get_auto_review_additions_helper(&self) -> Result<Vec<AutoReviewAddition>> {
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare_cached(
+            "SELECT unit_id, exercise_id, reason, timestamp from auto_additions \
+            ORDER BY timestamp;",
+        )?;
+        let mut rows = stmt.query(params![])?;
+
+        let mut additions = Vec::new();
+        while let Some(row) = rows.next()? {
+            let unit_id: String = row.get(0)?;
+            let exercise_id: String = row.get(1)?;
+            let reason: String = row.get(2)?;
+            additions.push(AutoReviewAddition {
+                unit_id: Ustr::from(&unit_id),
+                exercise_id: Ustr::from(&exercise_id),
+                reason: serde_json::from_str(&reason)?,
+                timestamp: row.get(3)?,
+            });
+        }
+        Ok(additions)
+    }
 }
 
 impl ReviewList for LocalReviewList {
@@ -110,25 +416,91 @@ impl ReviewList for LocalReviewList {
             .map_err(|e| ReviewListError::AddUnit(unit_id, e))
     }
 
+    fn add_to_review_list_with_details(
+        &mut self,
+        unit_id: Ustr,
+        priority: u32,
+        remove_after: Option<u32>,
+    ) -> Result<(), ReviewListError> {
+        self.add_to_review_list_with_details_helper(unit_id, priority, remove_after)
+            .map_err(|e| ReviewListError::AddUnit(unit_id, e))
+    }
+
+    fn add_to_review_list_automatically(
+        &mut self,
+        addition: &AutoReviewAddition,
+    ) -> Result<bool, ReviewListError> {
+        self.add_to_review_list_automatically_helper(addition)
+            .map_err(|e| ReviewListError::AddUnit(addition.unit_id, e))
+    }
+
     fn remove_from_review_list(&mut self, unit_id: Ustr) -> Result<(), ReviewListError> {
         self.remove_from_review_list_helper(unit_id)
             .map_err(|e| ReviewListError::RemoveUnit(unit_id, e))
     }
 
+    fn record_successful_review(&mut self, unit_id: Ustr) -> Result<bool, ReviewListError> {
+        self.record_successful_review_helper(unit_id)
+            .map_err(|e| ReviewListError::RecordReview(unit_id, e))
+    }
+
+    fn restore_review_list_entry(
+        &mut self,
+        entry: &ReviewListEntry,
+    ) -> Result<(), ReviewListError> {
+        self.restore_review_list_entry_helper(entry)
+            .map_err(|e| ReviewListError::AddUnit(entry.unit_id, e))
+    }
+
+    fn remove_automatic_addition(
+        &mut self,
+        addition: &AutoReviewAddition,
+    ) -> Result<(), ReviewListError> {
+        self.remove_automatic_addition_helper(addition)
+            .map_err(|e| ReviewListError::RemoveUnit(addition.unit_id, e))
+    }
+
+    fn rename_unit(&mut self, old_id: Ustr, new_id: Ustr) -> Result<(), ReviewListError> {
+        self.rename_unit_helper(old_id, new_id)
+            .map_err(|e| ReviewListError::RenameUnit(old_id, new_id, e))
+    }
+
     fn get_review_list_entries(&self) -> Result<Vec<Ustr>, ReviewListError> {
         self.get_review_list_entries_helper()
             .map_err(ReviewListError::GetEntries)
     }
+
+    fn get_review_list_entry(
+        &self,
+        unit_id: Ustr,
+    ) -> Result<Option<ReviewListEntry>, ReviewListError> {
+        self.get_review_list_entry_helper(unit_id)
+            .map_err(|e| ReviewListError::GetEntry(unit_id, e))
+    }
+
+    fn get_detailed_review_list_entries(&self) -> Result<Vec<ReviewListEntry>, ReviewListError> {
+        self.get_detailed_review_list_entries_helper()
+            .map_err(ReviewListError::GetEntries)
+    }
+
+    fn get_auto_review_additions(&self) -> Result<Vec<AutoReviewAddition>, ReviewListError> {
+        self.get_auto_review_additions_helper()
+            .map_err(ReviewListError::GetAutoAdditions)
+    }
 }
 
 #[cfg(test)]
 #[cfg_attr(coverage, coverage(off))]
 mod test {
     use anyhow::Result;
-    use rusqlite::Connection;
+    use rusqlite::{Connection, params};
+    use rusqlite_migration::{M, Migrations};
     use ustr::Ustr;
 
-    use crate::review_list::{LocalReviewList, ReviewList};
+    use crate::{
+        data::{AutoReviewAddition, AutoReviewReason},
+        review_list::{DEFAULT_REVIEW_PRIORITY, LocalReviewList, ReviewList},
+    };
 
     fn new_test_review_list() -> Result<Box<dyn ReviewList>> {
         let review_list = LocalReviewList::new(Connection::open_in_memory()?)?;
@@ -158,4 +530,194 @@ mod test {
         assert!(entries.contains(&unit_id2));
         Ok(())
     }
+
+    /// Verifies adding entries with details and retrieving them.
+    #[test]
+    fn add_with_details() -> Result<()> {
+        let mut review_list = new_test_review_list()?;
+        let unit_id = Ustr::from("unit_id");
+        let unit_id2 = Ustr::from("unit_id2");
+        review_list.add_to_review_list(unit_id)?;
+        review_list.add_to_review_list_with_details(unit_id2, 5, Some(3))?;
+
+        let entry = review_list.get_review_list_entry(unit_id)?.unwrap();
+        assert_eq!(entry.priority, DEFAULT_REVIEW_PRIORITY);
+        assert_eq!(entry.remove_after, None);
+        assert_eq!(entry.successful_reviews, 0);
+        assert!(entry.added_at > 0);
+        let entry2 = review_list.get_review_list_entry(unit_id2)?.unwrap();
+        assert_eq!(entry2.priority, 5);
+        assert_eq!(entry2.remove_after, Some(3));
+        assert_eq!(
+            review_list.get_detailed_review_list_entries()?,
+            vec![entry.clone(), entry2]
+        );
+
+        // Replacing the details preserves the time at which the unit was added.
+        review_list.record_successful_review(unit_id)?;
+        review_list.add_to_review_list_with_details(unit_id, 2, None)?;
+        let updated_entry = review_list.get_review_list_entry(unit_id)?.unwrap();
+        assert_eq!(updated_entry.priority, 2);
+        assert_eq!(updated_entry.added_at, entry.added_at);
+        assert_eq!(updated_entry.successful_reviews, 0);
+        assert!(
+            review_list
+                .get_review_list_entry(Ustr::from("other"))?
+                .is_none()
+        );
+        Ok(())
+    }
+
+    /// Verifies that entries are removed after the given number of successful reviews.
+    #[test]
+    fn remove_after_successful_reviews() -> Result<()> {
+        let mut review_list = new_test_review_list()?;
+        let unit_id = Ustr::from("unit_id");
+        let unit_id2 = Ustr::from("unit_id2");
+        review_list.add_to_review_list_with_details(unit_id, 1, Some(2))?;
+        review_list.add_to_review_list(unit_id2)?;
+
+        assert!(!review_list.record_successful_review(unit_id)?);
+        assert_eq!(
+            review_list
+                .get_review_list_entry(unit_id)?
+                .unwrap()
+                .successful_reviews,
+            1
+        );
+        assert!(review_list.record_successful_review(unit_id)?);
+        assert!(review_list.get_review_list_entry(unit_id)?.is_none());
+
+        // Entries without a limit are never removed.
+        for _ in 0..5 {
+            assert!(!review_list.record_successful_review(unit_id2)?);
+        }
+        assert_eq!(review_list.get_review_list_entries()?, vec![unit_id2]);
+
+        // Recording a review for a unit not in the list does nothing.
+        assert!(!review_list.record_successful_review(unit_id)?);
+        Ok(())
+    }
+
+    /// Verifies that entries created before the details were recorded are migrated.
+    #[test]
+    fn migrate_old_entries() -> Result<()> {
+        // Create a database with the original schema and add an entry to it.
+        let mut connection = Connection::open_in_memory()?;
+        Migrations::new(vec![
+            M::up("CREATE TABLE review_list(unit_id TEXT NOT NULL UNIQUE);"),
+            M::up("CREATE INDEX unit_id_index ON review_list (unit_id);"),
+        ])
+        .to_latest(&mut connection)?;
+        connection.execute(
+            "INSERT INTO review_list (unit_id) VALUES (?1)",
+            params!["unit_id"],
+        )?;
+
+        // Open the review list and verify the entry has default details.
+        let review_list = LocalReviewList::new(connection)?;
+        let entry = review_list
+            .get_review_list_entry(Ustr::from("unit_id"))?
+            .unwrap();
+        assert_eq!(entry.priority, DEFAULT_REVIEW_PRIORITY);
+        assert_eq!(entry.added_at, 0);
+        assert_eq!(entry.remove_after, None);
+        assert_eq!(entry.successful_reviews, 0);
+        Ok(())
+    }
+
+    /// Verifies automatically adding units to the review list and retrieving the report.
+    #[test]
+    fn add_automatically() -> Result<()> {
+        let mut review_list = new_test_review_list()?;
+        let lapse = AutoReviewAddition {
+            unit_id: Ustr::from("a::b::c"),
+            exercise_id: Ustr::from("a::b::c"),
+            reason: AutoReviewReason::Lapse {
+                previous_score: 4.5,
+                score: 2.0,
+            },
+            timestamp: 2,
+        };
+        let failures = AutoReviewAddition {
+            unit_id: Ustr::from("a::d"),
+            exercise_id: Ustr::from("a::d::e"),
+            reason: AutoReviewReason::RepeatedFailures {
+                num_failures: 3,
+                window: 100,
+            },
+            timestamp: 1,
+        };
+        assert!(review_list.add_to_review_list_automatically(&lapse)?);
+        assert!(review_list.add_to_review_list_automatically(&failures)?);
+
+        // Units already in the list are not added again nor reported.
+        let duplicate = AutoReviewAddition {
+            timestamp: 3,
+            ..lapse.clone()
+        };
+        assert!(!review_list.add_to_review_list_automatically(&duplicate)?);
+
+        let entries = review_list.get_review_list_entries()?;
+        assert_eq!(entries, vec![lapse.unit_id, failures.unit_id]);
+        assert_eq!(
+            review_list.get_auto_review_additions()?,
+            vec![failures, lapse]
+        );
+        Ok(())
+    }
+
+    /// Verifies restoring entries and removing automatic additions.
+    #[test]
+    fn roll_back_changes() -> Result<()> {
+        // Restore an entry after it was removed by a successful review.
+        let mut review_list = new_test_review_list()?;
+        let unit_id = Ustr::from("a::b");
+        review_list.add_to_review_list_with_details(unit_id, 3, Some(1))?;
+        let entry = review_list.get_review_list_entry(unit_id)?.unwrap();
+        assert!(review_list.record_successful_review(unit_id)?);
+        review_list.restore_review_list_entry(&entry)?;
+        assert_eq!(review_list.get_review_list_entry(unit_id)?, Some(entry));
+
+        // Remove an automatic addition.
+        let addition = AutoReviewAddition {
+            unit_id: Ustr::from("a::c"),
+            exercise_id: Ustr::from("a::c::d"),
+            reason: AutoReviewReason::Lapse {
+                previous_score: 4.5,
+                score: 2.0,
+            },
+            timestamp: 1,
+        };
+        assert!(review_list.add_to_review_list_automatically(&addition)?);
+        review_list.remove_automatic_addition(&addition)?;
+        assert_eq!(review_list.get_review_list_entries()?, vec![unit_id]);
+        assert!(review_list.get_auto_review_additions()?.is_empty());
+        Ok(())
+    }
+
+    /// Verifies moving a review list entry to a new ID.
+    #[test]
+    fn rename_unit() -> Result<()> {
+        let mut review_list = new_test_review_list()?;
+        let old_id = Ustr::from("old_unit");
+        let new_id = Ustr::from("new_unit");
+        review_list.add_to_review_list_with_details(old_id, 5, Some(2))?;
+
+        // The entry and its details are moved to the new ID.
+        review_list.rename_unit(old_id, new_id)?;
+        assert!(review_list.get_review_list_entry(old_id)?.is_none());
+        let entry = review_list.get_review_list_entry(new_id)?.unwrap();
+        assert_eq!(entry.priority, 5);
+        assert_eq!(entry.remove_after, Some(2));
+
+        // If the new unit already has an entry, it's kept.
+        let other_id = Ustr::from("other_unit");
+        review_list.add_to_review_list(other_id)?;
+        review_list.rename_unit(new_id, other_id)?;
+        assert_eq!(review_list.get_review_list_entries()?, vec![other_id]);
+        let entry = review_list.get_review_list_entry(other_id)?.unwrap();
+        assert_eq!(entry.priority, DEFAULT_REVIEW_PRIORITY);
+        Ok(())
+    }
 }
@@ -16,14 +16,17 @@
 
 pub mod data;
 mod filter;
+mod lapse_detector;
 mod relearn_pile;
 mod review_knocker;
 mod reward_propagator;
 mod shuffler;
+mod sibling_separator;
 mod unit_scorer;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail};
 use chrono::Utc;
+use parking_lot::RwLock;
 use rand::{rng, seq::SliceRandom};
 use reward_propagator::RewardPropagator;
 use std::sync::Arc;
@@ -31,15 +34,17 @@ use ustr::{Ustr, UstrMap, UstrSet};
 
 use crate::{
     data::{
-        ExerciseDelta, ExerciseManifest, ExerciseTrial, FULL_CANDIDATES_SCORE, MasteryScore,
-        PassingScoreOptions, SchedulerOptions, UnitType,
+        AutoReviewAddition, ExerciseDelta, ExerciseManifest, ExerciseTrial, FULL_CANDIDATES_SCORE,
+        MasteryScore, PassingScoreOptions, ReviewListEntry, SchedulerOptions, UnitType,
         filter::{ExerciseFilter, KeyValueFilter, UnitFilter},
     },
     error::ExerciseSchedulerError,
     scheduler::{
-        data::SchedulerData, filter::CandidateFilter, relearn_pile::RelearnPile,
-        review_knocker::ReviewKnocker, shuffler::Shuffler, unit_scorer::UnitScorer,
+        data::SchedulerData, filter::CandidateFilter, lapse_detector::LapseDetector,
+        relearn_pile::RelearnPile, review_knocker::ReviewKnocker, shuffler::Shuffler,
+        sibling_separator::SiblingSeparator, unit_scorer::UnitScorer,
     },
+    session_state::SessionState,
 };
 
 /// The scheduler returns early if the search reaches a dead end and the number of candidates is
@@ -47,6 +52,9 @@ use crate::{
 /// search the entire graph if the search already found a decently sized pool of candidates.
 const MAX_CANDIDATE_FACTOR: usize = 10;
 
+/// The maximum number of scores from the current session that can be undone.
+const MAX_UNDO_HISTORY: usize = 100;
+
 /// The trait that defines the interface for the scheduler. Contains functions to request a new
 /// batch of exercises and to provide Trane the self-reported scores for said exercises.
 pub trait ExerciseScheduler {
@@ -69,6 +77,22 @@ pub trait ExerciseScheduler {
         timestamp: i64,
     ) -> Result<(), ExerciseSchedulerError>;
 
+    /// Reverts the most recent score recorded during the current session. The trial, its delta,
+    /// and the rewards it produced are removed, the changes it made to the review list are
+    /// reverted, and the affected cached scores are invalidated. Returns the trial that was
+    /// removed, or `None` if there are no scores to undo.
+    fn undo_last_score(&self) -> Result<Option<ExerciseTrial>, ExerciseSchedulerError>;
+
+    /// Replaces the score of the trial of the given exercise recorded at the given timestamp. The
+    /// delta and rewards produced by the original score are replaced with those of the new score.
+    /// Changes to the review list made when the original score was recorded are not reverted.
+    fn amend_score(
+        &self,
+        exercise_id: Ustr,
+        timestamp: i64,
+        new_score: MasteryScore,
+    ) -> Result<(), ExerciseSchedulerError>;
+
     /// Gets the score for the given unit. The unit can be a course, lesson, or exercise.
     fn get_unit_score(&self, unit_id: Ustr) -> Result<Option<f32>, ExerciseSchedulerError>;
 
@@ -158,6 +182,25 @@ struct Candidate {
     encompassed_weight: f32,
 }
 
+/// A score recorded during the current session, kept so that it can be undone.
+#[derive(Clone)]
+struct ScoredTrial {
+    /// The ID of the scored exercise.
+    exercise_id: Ustr,
+
+    /// The timestamp of the trial.
+    timestamp: i64,
+
+    /// Whether the exercise was in the relearn pile before the trial.
+    in_relearn_pile: bool,
+
+    /// The review list entries updated by the trial, as they were before the trial.
+    reviewed_entries: Vec<ReviewListEntry>,
+
+    /// The unit added to the review list because the trial lapsed, if any.
+    auto_addition: Option<AutoReviewAddition>,
+}
+
 /// An implementation of [`ExerciseScheduler`] based on depth-first search.
 pub struct DepthFirstScheduler {
     /// The external data used by the scheduler. Contains pointers to the graph, blacklist, and
@@ -182,6 +225,12 @@ pub struct DepthFirstScheduler {
     /// The pile of recently failed exercises that need to be re-scheduled soon to improve
     /// retention.
     relearn_pile: RelearnPile,
+
+    /// Detects exercises whose performance lapsed so that they can be added to the review list.
+    lapse_detector: LapseDetector,
+
+    /// The scores recorded during the current session, in the order they were recorded.
+    score_history: RwLock<Vec<ScoredTrial>>,
 }
 
 impl DepthFirstScheduler {
@@ -194,6 +243,7 @@ impl DepthFirstScheduler {
         let review_knocker = ReviewKnocker::new(data.clone());
         let filter = CandidateFilter::new(data.clone());
         let relearn_pile = RelearnPile::new(options);
+        let lapse_detector = LapseDetector::new(data.clone());
         Self {
             data,
             unit_scorer,
@@ -201,6 +251,8 @@ impl DepthFirstScheduler {
             review_knocker,
             filter,
             relearn_pile,
+            lapse_detector,
+            score_history: RwLock::new(Vec::new()),
         }
     }
 
@@ -274,6 +326,38 @@ impl DepthFirstScheduler {
             .collect())
     }
 
+    /// Returns the short-term state of the current session, to be saved at the given timestamp.
+    #[must_use]
+    pub fn get_session_state(&self, saved_at: i64) -> SessionState {
+        SessionState {
+            saved_at,
+            relearn_pile: self.relearn_pile.get_exercises(),
+            frequency_map: self.data.frequency_map.read().clone(),
+            trial_counts: *self.data.trial_counts.read(),
+        }
+    }
+
+    /// Restores the short-term state of a previous session.
+    pub fn restore_session_state(&self, state: &SessionState) {
+        self.relearn_pile.restore(&state.relearn_pile);
+        self.data
+            .frequency_map
+            .write()
+            .clone_from(&state.frequency_map);
+        *self.data.trial_counts.write() = state.trial_counts;
+    }
+
+    /// Returns whether the given unit has been superseded by other units that the student has
+    /// already mastered.
+    #[must_use]
+    pub fn is_unit_superseded(&self, unit_id: Ustr) -> bool {
+        let superseding_units = self
+            .unit_scorer
+            .get_superseding_recursive(unit_id)
+            .unwrap_or_default();
+        self.unit_scorer.is_superseded(unit_id, &superseding_units)
+    }
+
     //@<lp-example-1
     /// Returns an initial stack with all the starting units in the graph that are used to search
     /// the entire graph.
@@ -918,22 +1002,29 @@ impl DepthFirstScheduler {
     }
 
     /// Searches for candidates from the units in the review list. This mode allows the student to
-    /// exclusively practice the courses, lessons, and exercises they have marked for review.
+    /// exclusively practice the courses, lessons, and exercises they have marked for review. The
+    /// urgency of each candidate is scaled by the priority of its entry, so that exercises from
+    /// entries with higher priority are more likely to be selected.
     fn get_candidates_from_review_list(&self) -> Result<Vec<Candidate>> {
         // Retrieve candidates from each entry in the review list.
         let mut candidates = vec![];
-        let review_list = self.data.review_list.read().get_review_list_entries()?;
+        let review_list = self
+            .data
+            .review_list
+            .read()
+            .get_detailed_review_list_entries()?;
         let frequency_map = self.data.frequency_map.read();
-        for unit_id in review_list {
-            match self.data.get_unit_type_strict(unit_id)? {
+        for entry in review_list {
+            let unit_id = entry.unit_id;
+            let mut entry_candidates = match self.data.get_unit_type_strict(unit_id)? {
                 UnitType::Course => {
                     // If the unit is a course, use the course scheduler to retrieve candidates.
                     let course_ids = vec![unit_id];
-                    candidates.extend(self.get_candidates_from_course(&course_ids)?);
+                    self.get_candidates_from_course(&course_ids)?
                 }
                 UnitType::Lesson => {
                     // If the unit is a lesson, use the lesson scheduler to retrieve candidates.
-                    candidates.extend(self.get_candidates_from_lesson(unit_id)?);
+                    self.get_candidates_from_lesson(unit_id)?
                 }
                 UnitType::Exercise => {
                     // Retrieve the exercise's lesson and course IDs.
@@ -941,7 +1032,7 @@ impl DepthFirstScheduler {
                     let course_id = self.data.get_course_id(lesson_id).unwrap_or_default();
 
                     // If the unit is an exercise, directly add it to the list of candidates.
-                    candidates.push(Candidate {
+                    vec![Candidate {
                         exercise_id: unit_id,
                         lesson_id,
                         course_id,
@@ -956,9 +1047,15 @@ impl DepthFirstScheduler {
                         dead_end: false,
                         encompasses_weight: 0.0,
                         encompassed_weight: 0.0,
-                    });
+                    }]
                 }
+            };
+
+            // Weight the candidates by the priority of the entry.
+            for candidate in &mut entry_candidates {
+                candidate.urgency *= entry.priority as f32;
             }
+            candidates.extend(entry_candidates);
         }
 
         Ok(candidates)
@@ -1032,6 +1129,225 @@ impl DepthFirstScheduler {
         Ok(candidates)
     }
 
+    /// Records the trial, the delta, and the rewards produced by the given score and invalidates
+    /// the cached scores of the affected units. Returns the score of the exercise before the trial,
+    /// or `None` if the exercise had no trials before it.
+    fn record_score(
+        &self,
+        exercise_id: Ustr,
+        score: &MasteryScore,
+        timestamp: i64,
+    ) -> Result<Option<f32>> {
+        // Retreive the existing score to compute the delta. Do not apply it to exercises with no
+        // trials as those have no previous data for comparison. If there are trials at or after the
+        // given timestamp, as happens when amending an older trial, the existing score must be
+        // computed only from the data recorded before it.
+        let latest_timestamp = self
+            .data
+            .practice_stats
+            .read()
+            .get_scores(exercise_id, 1)?
+            .first()
+            .map(|trial| trial.timestamp);
+        let existing_score = if latest_timestamp.is_some_and(|latest| latest >= timestamp) {
+            self.unit_scorer
+                .get_exercise_score_before(exercise_id, timestamp)?
+        } else {
+            let num_trials = self
+                .unit_scorer
+                .get_exercise_num_trials(exercise_id)
+                .unwrap_or_default()
+                .unwrap_or_default();
+            let existing_score = self
+                .unit_scorer
+                .get_unit_score(exercise_id)
+                .unwrap_or_default()
+                .unwrap_or_default();
+            (num_trials > 0).then_some(existing_score)
+        };
+        if let Some(existing_score) = existing_score {
+            let delta = score.float_score() - existing_score;
+            self.data
+                .practice_deltas
+                .write()
+                .record_exercise_deltas(&[ExerciseDelta {
+                    exercise_id,
+                    delta,
+                    timestamp,
+                }])?;
+        }
+
+        // Write the score to the practice stats database and invalidate the cache.
+        self.data
+            .practice_stats
+            .write()
+            .record_exercise_scores(&[ExerciseTrial {
+                exercise_id,
+                score: score.float_score(),
+                timestamp,
+            }])?;
+        self.unit_scorer.invalidate_cached_score(exercise_id);
+
+        // Propagate the rewards along the unit graph and store those that have existing scores.
+        let rewards = self
+            .reward_propagator
+            .propagate_rewards(exercise_id, score, timestamp);
+        let updated_ids = self
+            .data
+            .practice_rewards
+            .write()
+            .record_unit_rewards(exercise_id, &rewards)?;
+
+        // Invalidate caches for units were updated.
+        for unit_id in updated_ids {
+            self.unit_scorer.invalidate_cached_score(unit_id);
+        }
+        Ok(existing_score)
+    }
+
+    /// Removes the trial of the given exercise recorded at the given timestamp, along with its
+    /// delta and the rewards it produced, and invalidates the cached scores of the affected units.
+    /// Returns the score of the removed trial, or `None` if the trial does not exist.
+    fn remove_score(&self, exercise_id: Ustr, timestamp: i64) -> Result<Option<MasteryScore>> {
+        // Remove the trial and its delta.
+        let Some(trial) = self
+            .data
+            .practice_stats
+            .write()
+            .remove_trial(exercise_id, timestamp)?
+        else {
+            return Ok(None);
+        };
+        self.data
+            .practice_deltas
+            .write()
+            .remove_delta(exercise_id, timestamp)?;
+        self.unit_scorer.invalidate_cached_score(exercise_id);
+
+        // Remove the rewards produced by the trial.
+        let score = MasteryScore::try_from(trial.score)
+            .map_err(|()| anyhow!("invalid score {} in trial", trial.score))?;
+        let removed_ids = self
+            .data
+            .practice_rewards
+            .write()
+            .remove_unit_rewards(exercise_id, timestamp)?;
+        for unit_id in removed_ids {
+            self.unit_scorer.invalidate_cached_score(unit_id);
+        }
+        Ok(Some(score))
+    }
+
+    /// Updates the review list after the given exercise was scored and, if enabled, adds the
+    /// exercise or its lesson to the review list if its performance lapsed. Returns the entries as
+    /// they were before they were updated and the automatic addition, if any.
+    fn update_review_list(
+        &self,
+        exercise_id: Ustr,
+        score: &MasteryScore,
+        previous_score: Option<f32>,
+        timestamp: i64,
+    ) -> Result<(Vec<ReviewListEntry>, Option<AutoReviewAddition>)> {
+        let reviewed_entries = self.data.update_review_list(exercise_id, score)?;
+        let Some(policy) = &self.data.options.auto_review else {
+            return Ok((reviewed_entries, None));
+        };
+        let addition = self.lapse_detector.detect(
+            policy,
+            exercise_id,
+            previous_score,
+            score.float_score(),
+            timestamp,
+        )?;
+        let Some(addition) = addition else {
+            return Ok((reviewed_entries, None));
+        };
+        let added = self
+            .data
+            .review_list
+            .write()
+            .add_to_review_list_automatically(&addition)?;
+        Ok((reviewed_entries, added.then_some(addition)))
+    }
+
+    /// Reverts the changes made to the review list by the given trial.
+    fn revert_review_list(&self, scored_trial: &ScoredTrial) -> Result<()> {
+        // The automatic addition was made after the entries were updated, so it's reverted first.
+        let mut review_list = self.data.review_list.write();
+        if let Some(addition) = &scored_trial.auto_addition {
+            review_list.remove_automatic_addition(addition)?;
+        }
+        for entry in &scored_trial.reviewed_entries {
+            review_list.restore_review_list_entry(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Helper function to undo the last score recorded during the current session.
+    fn undo_last_score_helper(&self) -> Result<Option<ExerciseTrial>> {
+        // Retrieve the last score. Return early if there are no scores to undo. The history is
+        // locked until the score is undone, and the score is only removed from it if the
+        // transaction succeeds.
+        let mut score_history = self.score_history.write();
+        let Some(scored_trial) = score_history.last().cloned() else {
+            return Ok(None);
+        };
+        let score = self.data.in_transaction(|| {
+            let Some(score) =
+                self.remove_score(scored_trial.exercise_id, scored_trial.timestamp)?
+            else {
+                return Ok(None);
+            };
+            self.revert_review_list(&scored_trial)?;
+            Ok(Some(score))
+        })?;
+        score_history.pop();
+        drop(score_history);
+        let Some(score) = score else {
+            return Ok(None);
+        };
+
+        // Revert the changes to the success rate and the relearn pile.
+        self.data.revert_success_rate(&score);
+        self.relearn_pile
+            .set(scored_trial.exercise_id, scored_trial.in_relearn_pile);
+        Ok(Some(ExerciseTrial {
+            exercise_id: scored_trial.exercise_id,
+            score: score.float_score(),
+            timestamp: scored_trial.timestamp,
+        }))
+    }
+
+    /// Helper function to replace the score of an existing trial.
+    fn amend_score_helper(
+        &self,
+        exercise_id: Ustr,
+        timestamp: i64,
+        new_score: &MasteryScore,
+    ) -> Result<()> {
+        // Remove the effects of the original score and record the new one in its place in a single
+        // transaction.
+        let old_score = self.data.in_transaction(|| {
+            let Some(old_score) = self.remove_score(exercise_id, timestamp)? else {
+                bail!("no trial found at timestamp {timestamp}");
+            };
+            self.record_score(exercise_id, new_score, timestamp)?;
+            Ok(old_score)
+        })?;
+        self.data.revert_success_rate(&old_score);
+        self.data.update_success_rate(new_score);
+
+        // Only update the relearn pile if the amended trial is the most recent one.
+        let latest_trial = self.data.practice_stats.read().get_scores(exercise_id, 1)?;
+        if latest_trial
+            .first()
+            .is_some_and(|trial| trial.timestamp == timestamp)
+        {
+            self.relearn_pile.update(exercise_id, new_score);
+        }
+        Ok(())
+    }
+
     /// Takes a list of candidates and returns a vector of tuples of exercises IDs and manifests.
     fn candidates_to_exercises(&self, candidates: Vec<Candidate>) -> Result<Vec<ExerciseManifest>> {
         let exercises = candidates
@@ -1050,6 +1366,19 @@ impl ExerciseScheduler for DepthFirstScheduler {
         &self,
         filter: Option<ExerciseFilter>,
     ) -> Result<Vec<ExerciseManifest>, ExerciseSchedulerError> {
+        // Remove the expired entries from the blacklist so that the units they paused are
+        // scheduled again, and invalidate the cached scores of those units.
+        let expired = self
+            .data
+            .blacklist
+            .write()
+            .remove_expired_from_blacklist(Utc::now().timestamp())
+            .map_err(|e| ExerciseSchedulerError::GetExerciseBatch(e.into()))?;
+        for unit_id in expired {
+            self.unit_scorer
+                .invalidate_cached_scores_with_prefix(&unit_id);
+        }
+
         // Retrieve an initial batch of candidates based on the type of the filter.
         let initial_candidates = self
             .get_initial_candidates(filter)
@@ -1075,11 +1404,19 @@ impl ExerciseScheduler for DepthFirstScheduler {
             })
             .collect::<Vec<_>>();
 
-        // Create the final list of candidates, shuffle them, and convert them to manifests.
+        // Create the final list of candidates, keep at most one exercise from each sibling group,
+        // shuffle them, and convert them to manifests.
         let final_candidates = filtered_candidates
             .into_iter()
             .chain(relearn_candidates)
             .collect::<Vec<_>>();
+        let final_candidates =
+            SiblingSeparator::separate_siblings(final_candidates, |exercise_id| {
+                self.data
+                    .get_exercise_manifest(exercise_id)
+                    .ok()
+                    .and_then(|manifest| manifest.sibling_group)
+            });
         let shuffled_candidates =
             Shuffler::shuffle_candidates(final_candidates, &self.data.options);
         let manifests = self
@@ -1100,65 +1437,53 @@ impl ExerciseScheduler for DepthFirstScheduler {
         score: MasteryScore,
         timestamp: i64,
     ) -> Result<(), ExerciseSchedulerError> {
-        // Retreive the existing score to compute the delta. Do not apply it to exercises with no
-        // trials as those have no previous data for comparison.
-        let existing_score = self
-            .unit_scorer
-            .get_unit_score(exercise_id)
-            .unwrap_or_default()
-            .unwrap_or_default();
-        let num_trials = self
-            .unit_scorer
-            .get_exercise_num_trials(exercise_id)
-            .unwrap_or_default()
-            .unwrap_or_default();
-        if num_trials > 0 {
-            let delta = score.float_score() - existing_score;
-            self.data
-                .practice_deltas
-                .write()
-                .record_exercise_deltas(&[ExerciseDelta {
+        // Record the trial and update the review list in a single transaction, and remember the
+        // changes so that they can be undone.
+        let in_relearn_pile = self.relearn_pile.contains(exercise_id);
+        let scored_trial = self
+            .data
+            .in_transaction(|| {
+                let previous_score = self.record_score(exercise_id, &score, timestamp)?;
+                let (reviewed_entries, auto_addition) =
+                    self.update_review_list(exercise_id, &score, previous_score, timestamp)?;
+                Ok(ScoredTrial {
                     exercise_id,
-                    delta,
                     timestamp,
-                }])
-                .map_err(|e| ExerciseSchedulerError::ScoreExercise(e.into()))?;
+                    in_relearn_pile,
+                    reviewed_entries,
+                    auto_addition,
+                })
+            })
+            .map_err(ExerciseSchedulerError::ScoreExercise)?;
+        {
+            let mut score_history = self.score_history.write();
+            if score_history.len() >= MAX_UNDO_HISTORY {
+                score_history.remove(0);
+            }
+            score_history.push(scored_trial);
         }
 
-        // Write the score to the practice stats database, invalidate the cache, and update the
-        // relearning pile and the success rate.
-        self.data
-            .practice_stats
-            .write()
-            .record_exercise_scores(&[ExerciseTrial {
-                exercise_id,
-                score: score.float_score(),
-                timestamp,
-            }])
-            .map_err(|e| ExerciseSchedulerError::ScoreExercise(e.into()))?;
-        self.unit_scorer.invalidate_cached_score(exercise_id);
+        // Update the relearning pile and the success rate.
         self.relearn_pile.update(exercise_id, &score);
         self.data.update_success_rate(&score);
+        Ok(())
+    }
 
-        // Propagate the rewards along the unit graph and store those that have existing scores.
-        let rewards = self
-            .reward_propagator
-            .propagate_rewards(exercise_id, &score, timestamp);
-        let updated_ids = self
-            .data
-            .practice_rewards
-            .write()
-            .record_unit_rewards(&rewards)
-            .map_err(|e| ExerciseSchedulerError::ScoreExercise(e.into()))?;
+    fn undo_last_score(&self) -> Result<Option<ExerciseTrial>, ExerciseSchedulerError> {
+        self.undo_last_score_helper()
+            .map_err(ExerciseSchedulerError::UndoScore)
+    }
 
-        // Invalidate caches for units were updated.
-        for unit_id in updated_ids {
-            self.unit_scorer.invalidate_cached_score(unit_id);
-        }
-        Ok(())
+    fn amend_score(
+        &self,
+        exercise_id: Ustr,
+        timestamp: i64,
+        new_score: MasteryScore,
+    ) -> Result<(), ExerciseSchedulerError> {
+        self.amend_score_helper(exercise_id, timestamp, &new_score)
+            .map_err(|e| ExerciseSchedulerError::AmendScore(exercise_id, e))
     }
 
-    #[cfg_attr(coverage, coverage(off))]
     fn get_unit_score(&self, unit_id: Ustr) -> Result<Option<f32>, ExerciseSchedulerError> {
         self.unit_scorer
             .get_unit_score(unit_id)
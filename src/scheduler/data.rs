@@ -10,7 +10,8 @@ use crate::{
     blacklist::Blacklist,
     course_library::CourseLibrary,
     data::{
-        CourseManifest, ExerciseManifest, LessonManifest, MasteryScore, SchedulerOptions, UnitType,
+        CourseManifest, ExerciseManifest, LessonManifest, MasteryScore, ReviewListEntry,
+        SchedulerOptions, UnitType,
         filter::{KeyValueFilter, SavedFilter, SessionPart, StudySessionData, UnitFilter},
     },
     filter_manager::FilterManager,
@@ -19,6 +20,7 @@ use crate::{
     practice_rewards::PracticeRewards,
     practice_stats::PracticeStats,
     review_list::ReviewList,
+    user_database::UserDatabase,
 };
 
 /// A struct encapsulating all the state needed by the scheduler.
@@ -58,9 +60,22 @@ pub struct SchedulerData {
 
     /// The number of (successful, failed) exercises during the session.
     pub trial_counts: Arc<RwLock<(usize, usize)>>,
+
+    /// The consolidated database storing the user data, if the stores were created from one. Used
+    /// to make the changes to multiple stores atomic.
+    pub user_database: Option<Arc<UserDatabase>>,
 }
 
 impl SchedulerData {
+    /// Runs the given function in a single transaction of the consolidated user database. If the
+    /// stores do not share a database, the function is run as is.
+    pub fn in_transaction<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        match &self.user_database {
+            Some(user_database) => user_database.in_transaction(f),
+            None => f(),
+        }
+    }
+
     /// Returns the ID of the lesson to which the exercise with the given ID belongs.
     #[inline]
     pub fn get_lesson_id(&self, exercise_id: Ustr) -> Result<Ustr> {
@@ -212,6 +227,145 @@ impl SchedulerData {
         dependencies
     }
 
+    /// Returns all the units that can be reached from the given unit by following at most `hops`
+    /// dependency or dependent relationships. The unit itself is included in the result.
+    #[must_use]
+    pub fn get_units_within_hops(&self, unit_id: Ustr, hops: usize) -> UstrSet {
+        // Perform a breadth-first search in both directions, one level at a time.
+        let graph = self.unit_graph.read();
+        let mut units: UstrSet = [unit_id].into_iter().collect();
+        let mut frontier = vec![unit_id];
+        for _ in 0..hops {
+            let mut next_frontier = vec![];
+            for candidate_id in frontier {
+                let dependencies = graph.get_dependencies(candidate_id).unwrap_or_default();
+                let dependents = graph.get_dependents(candidate_id).unwrap_or_default();
+                for neighbor_id in dependencies.iter().chain(dependents.iter()) {
+                    if units.insert(*neighbor_id) {
+                        next_frontier.push(*neighbor_id);
+                    }
+                }
+            }
+            frontier = next_frontier;
+        }
+
+        // Remove any units not found in the graph.
+        units.retain(|id| graph.get_unit_type(*id).is_some());
+        units
+    }
+
+    /// Returns the courses and lessons that match the given unit filter. Courses are included
+    /// along with all of their lessons, and exercises are replaced by the lessons to which they
+    /// belong.
+    #[must_use]
+    pub fn get_units_in_filter(&self, filter: &UnitFilter) -> UstrSet {
+        // Declare a closure to add a unit to the set, expanding courses and exercises.
+        let mut units = UstrSet::default();
+        let add_unit = |units: &mut UstrSet, unit_id: Ustr| match self.get_unit_type(unit_id) {
+            Some(UnitType::Course) => {
+                units.insert(unit_id);
+                units.extend(
+                    self.unit_graph
+                        .read()
+                        .get_course_lessons(unit_id)
+                        .unwrap_or_default()
+                        .iter(),
+                );
+            }
+            Some(UnitType::Lesson) => {
+                units.insert(unit_id);
+            }
+            Some(UnitType::Exercise) => {
+                if let Some(lesson_id) = self.unit_graph.read().get_exercise_lesson(unit_id) {
+                    units.insert(lesson_id);
+                }
+            }
+            None => (),
+        };
+
+        match filter {
+            UnitFilter::CourseFilter { course_ids } => {
+                for course_id in course_ids {
+                    add_unit(&mut units, *course_id);
+                }
+            }
+            UnitFilter::LessonFilter { lesson_ids } => {
+                for lesson_id in lesson_ids {
+                    add_unit(&mut units, *lesson_id);
+                }
+            }
+            UnitFilter::MetadataFilter { filter } => {
+                // Add all the courses and lessons that pass the metadata filter.
+                let course_ids = self.course_library.read().get_course_ids();
+                for course_id in course_ids {
+                    if self
+                        .unit_passes_filter(course_id, Some(filter))
+                        .unwrap_or(false)
+                    {
+                        units.insert(course_id);
+                    }
+                    let lesson_ids = self
+                        .course_library
+                        .read()
+                        .get_lesson_ids(course_id)
+                        .unwrap_or_default();
+                    units.extend(lesson_ids.into_iter().filter(|lesson_id| {
+                        self.unit_passes_filter(*lesson_id, Some(filter))
+                            .unwrap_or(false)
+                    }));
+                }
+            }
+            UnitFilter::ReviewListFilter => {
+                let entries = self
+                    .review_list
+                    .read()
+                    .get_review_list_entries()
+                    .unwrap_or_default();
+                for unit_id in entries {
+                    add_unit(&mut units, unit_id);
+                }
+            }
+            UnitFilter::Dependents { unit_ids } => {
+                // Add the units and all their transitive dependents.
+                let mut stack = unit_ids.clone();
+                let mut visited = UstrSet::default();
+                while let Some(unit_id) = stack.pop() {
+                    if !visited.insert(unit_id) {
+                        continue;
+                    }
+                    add_unit(&mut units, unit_id);
+                    stack.extend(self.get_all_dependents(unit_id));
+                }
+            }
+            UnitFilter::Dependencies { unit_ids, depth } => {
+                // Add the units and all their dependencies up to the given depth.
+                for unit_id in unit_ids {
+                    add_unit(&mut units, *unit_id);
+                    let mut frontier = vec![*unit_id];
+                    for _ in 0..*depth {
+                        let graph = self.unit_graph.read();
+                        frontier = frontier
+                            .into_iter()
+                            .flat_map(|id| {
+                                graph
+                                    .get_dependencies(id)
+                                    .unwrap_or_default()
+                                    .iter()
+                                    .copied()
+                                    .collect::<Vec<_>>()
+                            })
+                            .collect();
+                        drop(graph);
+                        for dependency_id in &frontier {
+                            add_unit(&mut units, *dependency_id);
+                        }
+                    }
+                }
+            }
+        }
+        units
+    }
+
     /// Returns the value of the `course_id` field in the manifest of the given lesson.
     #[inline]
     #[must_use]
@@ -407,6 +561,52 @@ impl SchedulerData {
         }
     }
 
+    /// Reverts the update to the count of successful and failed exercises made for the given score.
+    pub fn revert_success_rate(&self, score: &MasteryScore) {
+        let mut counts = self.trial_counts.write();
+        match score {
+            MasteryScore::One | MasteryScore::Two => counts.1 = counts.1.saturating_sub(1),
+            MasteryScore::Three | MasteryScore::Four | MasteryScore::Five => {
+                counts.0 = counts.0.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Records a successful review for the entries in the review list that contain the given
+    /// exercise if the score is high enough. Entries that have been reviewed successfully enough
+    /// times are removed from the list. Returns the updated entries as they were before the review
+    /// so that the changes can be rolled back.
+    pub fn update_review_list(
+        &self,
+        exercise_id: Ustr,
+        score: &MasteryScore,
+    ) -> Result<Vec<ReviewListEntry>> {
+        // Only high scores count as successful reviews.
+        if !matches!(score, MasteryScore::Four | MasteryScore::Five) {
+            return Ok(vec![]);
+        }
+
+        // Find the entries that match the exercise, its lesson, or its course.
+        let lesson_id = self.get_lesson_id(exercise_id).unwrap_or_default();
+        let course_id = self.get_course_id(lesson_id).unwrap_or_default();
+        let mut review_list = self.review_list.write();
+        let matching_entries: Vec<ReviewListEntry> = review_list
+            .get_detailed_review_list_entries()?
+            .into_iter()
+            .filter(|entry| {
+                entry.unit_id == exercise_id
+                    || entry.unit_id == lesson_id
+                    || entry.unit_id == course_id
+            })
+            .collect();
+
+        // Record the review for each entry.
+        for entry in &matching_entries {
+            review_list.record_successful_review(entry.unit_id)?;
+        }
+        Ok(matching_entries)
+    }
+
     /// Returns the success rate of the current session.
     #[must_use]
     pub fn get_success_rate(&self) -> f32 {
@@ -0,0 +1,101 @@
+//! Defines the logic for detecting exercises whose performance has lapsed, so that they or their
+//! lessons can be added to the review list automatically. Unlike the relearn pile, which only keeps
+//! recent failures in memory, additions to the review list persist across sessions.
+
+use anyhow::Result;
+use ustr::Ustr;
+
+use crate::{
+    data::{AutoReviewAddition, AutoReviewPolicy, AutoReviewReason},
+    scheduler::data::SchedulerData,
+};
+
+/// Detects lapses in the performance of exercises based on a policy.
+pub(super) struct LapseDetector {
+    /// The external data used by the scheduler.
+    data: SchedulerData,
+}
+
+impl LapseDetector {
+    /// Creates a new lapse detector.
+    pub fn new(data: SchedulerData) -> Self {
+        Self { data }
+    }
+
+    /// Returns the ID of the unit that should be added to the review list for the given exercise.
+    fn unit_to_add(&self, policy: &AutoReviewPolicy, exercise_id: Ustr) -> Result<Ustr> {
+        if policy.add_lessons {
+            self.data.get_lesson_id(exercise_id)
+        } else {
+            Ok(exercise_id)
+        }
+    }
+
+    /// Returns the number of failures of the exercise within the failure window that ends at the
+    /// given timestamp. The trial being scored is expected to be already recorded.
+    fn num_recent_failures(
+        &self,
+        policy: &AutoReviewPolicy,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<usize> {
+        let num_scores = self.data.options.num_trials.max(policy.max_failures as u32);
+        let trials = self
+            .data
+            .practice_stats
+            .read()
+            .get_scores(exercise_id, num_scores)?;
+        Ok(trials
+            .iter()
+            .filter(|trial| timestamp - trial.timestamp < policy.failure_window)
+            .filter(|trial| trial.score <= policy.failure_score)
+            .count())
+    }
+
+    /// Checks whether the performance of the exercise has lapsed after being assigned the given
+    /// score and returns the unit that should be added to the review list, if any. The previous
+    /// score is the score of the exercise before the trial, or `None` if the exercise had no
+    /// trials.
+    pub fn detect(
+        &self,
+        policy: &AutoReviewPolicy,
+        exercise_id: Ustr,
+        previous_score: Option<f32>,
+        score: f32,
+        timestamp: i64,
+    ) -> Result<Option<AutoReviewAddition>> {
+        // Check if the exercise had been mastered and received a low score.
+        if let Some(previous_score) = previous_score
+            && previous_score >= policy.mastered_score
+            && score < policy.lapse_score
+        {
+            return Ok(Some(AutoReviewAddition {
+                unit_id: self.unit_to_add(policy, exercise_id)?,
+                exercise_id,
+                reason: AutoReviewReason::Lapse {
+                    previous_score,
+                    score,
+                },
+                timestamp,
+            }));
+        }
+
+        // Otherwise, check if the exercise has been failed too many times within the window.
+        if score > policy.failure_score {
+            return Ok(None);
+        }
+        let num_failures = self.num_recent_failures(policy, exercise_id, timestamp)?;
+        if num_failures < policy.max_failures {
+            return Ok(None);
+        }
+        Ok(Some(AutoReviewAddition {
+            unit_id: self.unit_to_add(policy, exercise_id)?,
+            exercise_id,
+            reason: AutoReviewReason::RepeatedFailures {
+                num_failures,
+                window: policy.failure_window,
+            },
+            timestamp,
+        }))
+    }
+}
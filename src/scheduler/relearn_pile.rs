@@ -40,6 +40,33 @@ impl RelearnPile {
         };
     }
 
+    /// Returns whether the given exercise is in the relearn pile.
+    pub fn contains(&self, exercise_id: Ustr) -> bool {
+        self.pile.read().contains(&exercise_id)
+    }
+
+    /// Adds the exercise to the relearn pile or removes it from it.
+    pub fn set(&self, exercise_id: Ustr, in_pile: bool) {
+        let mut pile = self.pile.write();
+        if in_pile {
+            pile.insert(exercise_id);
+        } else {
+            pile.remove(&exercise_id);
+        }
+    }
+
+    /// Returns all the exercises in the relearn pile.
+    pub fn get_exercises(&self) -> Vec<Ustr> {
+        self.pile.read().iter().copied().collect()
+    }
+
+    /// Replaces the contents of the relearn pile with the given exercises.
+    pub fn restore(&self, exercises: &[Ustr]) {
+        let mut pile = self.pile.write();
+        pile.clear();
+        pile.extend(exercises.iter().copied());
+    }
+
     /// Helper function to add exercises from the relearn pile to the final batch.
     fn select_exercises_helper(&self) -> Vec<Candidate> {
         // Select a random subset of exercises from the relearn pile.
@@ -91,6 +118,19 @@ mod tests {
         assert!(!relearn_pile.pile.read().contains(&exercise_id_2));
     }
 
+    /// Verifies retrieving and restoring the exercises in the relearn pile.
+    #[test]
+    fn test_restore() {
+        let relearn_pile = RelearnPile::new(SchedulerOptions::default());
+        relearn_pile.update(Ustr::from("exercise_1"), &MasteryScore::One);
+
+        let exercises = vec![Ustr::from("exercise_2"), Ustr::from("exercise_3")];
+        relearn_pile.restore(&exercises);
+        let mut restored = relearn_pile.get_exercises();
+        restored.sort();
+        assert_eq!(restored, exercises);
+    }
+
     /// Verifies exercises from the relearn pile are added to the batch.
     #[test]
     fn test_add_to_batch() {
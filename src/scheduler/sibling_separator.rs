@@ -0,0 +1,111 @@
+//! Defines the logic for keeping sibling exercises out of the same batch.
+//!
+//! Exercises generated from the same source (for example, the cards generated from the same cloze
+//! note or the transcription exercises for the same passage across instruments) give each other
+//! away when they appear together. Exercises in the same sibling group are considered siblings, and
+//! at most one of them is kept in the final batch. The rest will have a chance to be scheduled in
+//! later batches.
+
+use ustr::{Ustr, UstrSet};
+
+use crate::scheduler::Candidate;
+
+pub(crate) struct SiblingSeparator;
+
+impl SiblingSeparator {
+    /// Removes candidates from the batch so that at most one candidate from each sibling group
+    /// remains. The candidate kept is the one that has been scheduled the least number of times,
+    /// with ties broken by choosing the one with the lowest score. The order of the remaining
+    /// candidates is preserved.
+    pub(crate) fn separate_siblings(
+        candidates: Vec<Candidate>,
+        sibling_group: impl Fn(Ustr) -> Option<Ustr>,
+    ) -> Vec<Candidate> {
+        // Find the sibling group of each candidate.
+        let groups: Vec<Option<Ustr>> = candidates
+            .iter()
+            .map(|candidate| sibling_group(candidate.exercise_id))
+            .collect();
+
+        // Select the candidate to keep from each group by visiting them in order of preference.
+        let mut order: Vec<usize> = (0..candidates.len()).collect();
+        order.sort_by(|a, b| {
+            let (a, b) = (&candidates[*a], &candidates[*b]);
+            a.frequency
+                .cmp(&b.frequency)
+                .then(a.exercise_score.total_cmp(&b.exercise_score))
+        });
+        let mut seen_groups = UstrSet::default();
+        let mut keep = vec![true; candidates.len()];
+        for index in order {
+            if let Some(group) = groups[index]
+                && !seen_groups.insert(group)
+            {
+                keep[index] = false;
+            }
+        }
+
+        // Return the candidates that were kept in their original order.
+        candidates
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(candidate, keep)| keep.then_some(candidate))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod tests {
+    use ustr::{Ustr, UstrMap};
+
+    use super::*;
+
+    /// Creates a candidate with the given exercise ID, score, and frequency.
+    fn candidate(exercise_id: &str, exercise_score: f32, frequency: usize) -> Candidate {
+        Candidate {
+            exercise_id: Ustr::from(exercise_id),
+            lesson_id: Ustr::from("lesson_1"),
+            course_id: Ustr::from("course_1"),
+            exercise_score,
+            frequency,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the IDs of the given candidates.
+    fn ids(candidates: &[Candidate]) -> Vec<&str> {
+        candidates.iter().map(|c| c.exercise_id.as_str()).collect()
+    }
+
+    /// Verifies that at most one candidate from each sibling group is kept.
+    #[test]
+    fn keeps_one_sibling_per_group() {
+        let groups: UstrMap<Ustr> = [("e1", "g1"), ("e2", "g1"), ("e3", "g2"), ("e4", "g2")]
+            .into_iter()
+            .map(|(id, group)| (Ustr::from(id), Ustr::from(group)))
+            .collect();
+        let candidates = vec![
+            candidate("e1", 3.0, 1),
+            candidate("e2", 4.0, 0),
+            candidate("e3", 2.0, 0),
+            candidate("e4", 1.0, 0),
+            candidate("e5", 1.0, 0),
+            candidate("e6", 1.0, 0),
+        ];
+        let result = SiblingSeparator::separate_siblings(candidates, |id| groups.get(&id).copied());
+
+        // The least scheduled candidate is kept from the first group, the lowest scoring one from
+        // the second, and the candidates without a group are not affected.
+        assert_eq!(ids(&result), vec!["e2", "e4", "e5", "e6"]);
+    }
+
+    /// Verifies that candidates without sibling groups are all kept.
+    #[test]
+    fn no_sibling_groups() {
+        let candidates = vec![candidate("e1", 1.0, 0), candidate("e2", 1.0, 0)];
+        let result = SiblingSeparator::separate_siblings(candidates, |_| None);
+        assert_eq!(ids(&result), vec!["e1", "e2"]);
+        assert!(SiblingSeparator::separate_siblings(vec![], |_| None).is_empty());
+    }
+}
@@ -11,7 +11,10 @@ use std::cell::RefCell;
 use ustr::{Ustr, UstrMap, UstrSet};
 
 use crate::{
-    data::{ExerciseType, SchedulerOptions, UnitType},
+    data::{
+        ExerciseDelta, ExerciseScore, ExerciseTrial, ExerciseType, SchedulerOptions, UnitReward,
+        UnitType,
+    },
     exercise_scorer::{ExerciseScorer, PowerLawScorer},
     reward_scorer::{RewardScorer, WeightedRewardScorer},
     scheduler::SchedulerData,
@@ -175,17 +178,23 @@ impl UnitScorer {
             .retain(|unit_id, _| !unit_id.starts_with(prefix));
     }
 
-    /// Returns the score for the given exercise.
-    fn get_exercise_score(&self, exercise_id: Ustr) -> Result<f32> {
-        // Return the cached score if it exists.
-        let cached_score = self
-            .exercise_cache
-            .borrow()
-            .get(&exercise_id)
-            .map(|c| c.score);
-        if let Some(score) = cached_score {
-            return Ok(score);
-        }
+    /// Computes the score for the given exercise. If `before` is set, only the trials, deltas, and
+    /// rewards recorded before that timestamp are considered, and the score is computed as of that
+    /// timestamp. Returns the final score, the score computed from the trials alone, and the number
+    /// of trials used.
+    fn compute_exercise_score(
+        &self,
+        exercise_id: Ustr,
+        before: Option<i64>,
+    ) -> Result<(f32, ExerciseScore, usize)> {
+        // When computing a past score, all the data must be retrieved so that the entries recorded
+        // after the timestamp can be filtered out before applying the limits.
+        let (num_trials, num_rewards) = if before.is_some() {
+            (u32::MAX, u32::MAX)
+        } else {
+            (self.options.num_trials, self.options.num_rewards)
+        };
+        let is_before = |timestamp: i64| before.is_none_or(|before| timestamp < before);
 
         // Retrieve the exercise's type and previous trials and compute its score.
         let exercise_type = self
@@ -196,56 +205,102 @@ impl UnitScorer {
             .map_or(ExerciseType::Procedural, |manifest| {
                 manifest.exercise_type.clone()
             });
-        let scores = self
+        let scores: Vec<ExerciseTrial> = self
             .data
             .practice_stats
             .read()
-            .get_scores(exercise_id, self.options.num_trials)
-            .unwrap_or_default();
-        let deltas = self
+            .get_scores(exercise_id, num_trials)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|trial| is_before(trial.timestamp))
+            .take(self.options.num_trials as usize)
+            .collect();
+        let deltas: Vec<ExerciseDelta> = self
             .data
             .practice_deltas
             .read()
-            .get_deltas(exercise_id, self.options.num_trials)
-            .unwrap_or_default();
-        let score = self
-            .exercise_scorer
-            .score(exercise_type, &scores, &deltas, self.now())?;
+            .get_deltas(exercise_id, num_trials)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|delta| is_before(delta.timestamp))
+            .take(self.options.num_trials as usize)
+            .collect();
+        let score = self.exercise_scorer.score(
+            exercise_type,
+            &scores,
+            &deltas,
+            before.unwrap_or_else(|| self.now()),
+        )?;
 
         // Retrieve the rewards for this exercise's lesson and course and compute the reward.
         let graph = self.data.unit_graph.read();
         let rewards = self.data.practice_rewards.read();
+        let get_rewards = |unit_id: Ustr| -> Vec<UnitReward> {
+            rewards
+                .get_rewards(unit_id, num_rewards)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|reward| is_before(reward.timestamp))
+                .take(self.options.num_rewards as usize)
+                .collect()
+        };
         let lesson_id = graph.get_exercise_lesson(exercise_id).unwrap_or_default();
-        let lesson_rewards = rewards
-            .get_rewards(lesson_id, self.options.num_rewards)
-            .unwrap_or_default();
+        let lesson_rewards = get_rewards(lesson_id);
         let course_id = graph.get_lesson_course(lesson_id).unwrap_or_default();
-        let course_rewards = rewards
-            .get_rewards(course_id, self.options.num_rewards)
-            .unwrap_or_default();
+        let course_rewards = get_rewards(course_id);
         let reward = self
             .reward_scorer
             .score_rewards(&course_rewards, &lesson_rewards)
             .unwrap_or_default();
 
-        // Apply the reward if it meets the criteria and cache the final score.
+        // Apply the reward if it meets the criteria.
         let final_score = if self.reward_scorer.apply_reward(reward, &scores) {
             (score.value + reward).clamp(0.0, 5.0)
         } else {
             score.value
         };
+        Ok((final_score, score, scores.len()))
+    }
+
+    /// Returns the score for the given exercise.
+    fn get_exercise_score(&self, exercise_id: Ustr) -> Result<f32> {
+        // Return the cached score if it exists.
+        let cached_score = self
+            .exercise_cache
+            .borrow()
+            .get(&exercise_id)
+            .map(|c| c.score);
+        if let Some(score) = cached_score {
+            return Ok(score);
+        }
+
+        // Compute the score and cache it.
+        let (final_score, score, num_trials) = self.compute_exercise_score(exercise_id, None)?;
         self.exercise_cache.borrow_mut().insert(
             exercise_id,
             CachedScore {
                 score: final_score,
                 urgency: score.urgency,
                 velocity: score.velocity,
-                num_trials: scores.len(),
+                num_trials,
             },
         );
         Ok(final_score)
     }
 
+    /// Returns the score the given exercise had right before the given timestamp, computed only
+    /// from the data recorded before it. Returns `None` if the exercise has no trials before the
+    /// timestamp. The score is not cached.
+    pub(super) fn get_exercise_score_before(
+        &self,
+        exercise_id: Ustr,
+        timestamp: i64,
+    ) -> Result<Option<f32>> {
+        let (final_score, _, num_trials) =
+            self.compute_exercise_score(exercise_id, Some(timestamp))?;
+        Ok((num_trials > 0).then_some(final_score))
+    }
+
     /// Returns the urgency of scheduling the given exercise, as a value between 0.0 and 1.0.
     pub(super) fn get_exercise_urgency(&self, exercise_id: Ustr) -> Result<f32> {
         // Return the cached value if it exists.
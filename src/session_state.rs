@@ -0,0 +1,210 @@
+//! A module containing methods to persist the short-term state of a study session.
+//!
+//! The scheduler keeps some state that only makes sense within a single session, such as the
+//! recently failed exercises in the relearn pile, how often each exercise has been scheduled, and
+//! the success rate used to adjust the mastery windows. This state is saved so that closing the app
+//! in the middle of a session does not reset it. State older than the staleness window is discarded
+//! because it no longer belongs to the current session.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+use ustr::{Ustr, UstrMap};
+
+use crate::SessionStateError;
+
+/// The short-term state of a study session.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct SessionState {
+    /// The timestamp at which the state was saved.
+    pub saved_at: i64,
+
+    /// The exercises in the relearn pile.
+    pub relearn_pile: Vec<Ustr>,
+
+    /// The number of times each exercise has been scheduled during the session.
+    pub frequency_map: UstrMap<usize>,
+
+    /// The number of successful and failed trials during the session.
+    pub trial_counts: (usize, usize),
+}
+
+/// A trait for managing the state of the current study session.
+pub trait SessionStateManager {
+    /// Returns the saved session state if it exists and was saved less than `staleness_window`
+    /// seconds before `now`.
+    fn load_session_state(
+        &self,
+        now: i64,
+        staleness_window: i64,
+    ) -> Result<Option<SessionState>, SessionStateError>;
+
+    /// Saves the given session state, replacing any previously saved state.
+    fn save_session_state(&mut self, state: &SessionState) -> Result<(), SessionStateError>;
+}
+
+/// A session state manager backed by a local file containing a serialized `SessionState` object.
+pub struct LocalSessionStateManager {
+    /// The path to the session state file.
+    pub path: PathBuf,
+}
+
+impl LocalSessionStateManager {
+    /// Helper function to load the session state.
+    fn load_session_state_helper(
+        &self,
+        now: i64,
+        staleness_window: i64,
+    ) -> Result<Option<SessionState>> {
+        // Return early if there is no saved state.
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        // Read the state and discard it if it's stale.
+        let raw_state = fs::read_to_string(&self.path).context("failed to read session state")?;
+        let state =
+            serde_json::from_str::<SessionState>(&raw_state).context("invalid session state")?;
+        if now - state.saved_at >= staleness_window {
+            return Ok(None);
+        }
+        Ok(Some(state))
+    }
+
+    /// Helper function to save the session state. The state is written to a temporary file first
+    /// so that a crash while writing does not leave a corrupted file behind.
+    fn save_session_state_helper(&self, state: &SessionState) -> Result<()> {
+        let json = serde_json::to_string(state).context("invalid session state")?;
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, json).context("failed to write session state")?;
+        fs::rename(&temp_path, &self.path).context("failed to write session state")
+    }
+}
+
+impl SessionStateManager for LocalSessionStateManager {
+    fn load_session_state(
+        &self,
+        now: i64,
+        staleness_window: i64,
+    ) -> Result<Option<SessionState>, SessionStateError> {
+        self.load_session_state_helper(now, staleness_window)
+            .map_err(SessionStateError::LoadState)
+    }
+
+    fn save_session_state(&mut self, state: &SessionState) -> Result<(), SessionStateError> {
+        self.save_session_state_helper(state)
+            .map_err(SessionStateError::SaveState)
+    }
+}
+
+/// A session state manager that keeps the session state in memory. The state is lost when the
+/// manager is dropped.
+#[derive(Default)]
+pub struct InMemorySessionStateManager {
+    /// The last saved session state.
+    pub state: Option<SessionState>,
+}
+
+impl SessionStateManager for InMemorySessionStateManager {
+    fn load_session_state(
+        &self,
+        now: i64,
+        staleness_window: i64,
+    ) -> Result<Option<SessionState>, SessionStateError> {
+        Ok(self
+            .state
+            .clone()
+            .filter(|state| now - state.saved_at < staleness_window))
+    }
+
+    fn save_session_state(&mut self, state: &SessionState) -> Result<(), SessionStateError> {
+        self.state = Some(state.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod tests {
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::tempdir;
+    use ustr::{Ustr, UstrMap};
+
+    use crate::{
+        SESSION_STATE_PATH,
+        session_state::{
+            InMemorySessionStateManager, LocalSessionStateManager, SessionState,
+            SessionStateManager,
+        },
+    };
+
+    /// Verifies saving and loading the session state.
+    #[test]
+    fn save_and_load() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let mut manager = LocalSessionStateManager {
+            path: temp_dir.path().join(SESSION_STATE_PATH),
+        };
+
+        // There is no state before saving it.
+        assert!(manager.load_session_state(100, 50)?.is_none());
+
+        // Save the state and load it back.
+        let mut frequency_map = UstrMap::default();
+        frequency_map.insert(Ustr::from("a::b::c"), 3);
+        let state = SessionState {
+            saved_at: 100,
+            relearn_pile: vec![Ustr::from("a::b::d")],
+            frequency_map,
+            trial_counts: (5, 2),
+        };
+        manager.save_session_state(&state)?;
+        assert_eq!(manager.load_session_state(120, 50)?, Some(state));
+        Ok(())
+    }
+
+    /// Verifies that stale session state is discarded.
+    #[test]
+    fn stale_state() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let mut manager = LocalSessionStateManager {
+            path: temp_dir.path().join(SESSION_STATE_PATH),
+        };
+        let state = SessionState {
+            saved_at: 100,
+            ..Default::default()
+        };
+        manager.save_session_state(&state)?;
+        assert!(manager.load_session_state(150, 50)?.is_none());
+        assert!(manager.load_session_state(200, 50)?.is_none());
+        Ok(())
+    }
+
+    /// Verifies that loading an invalid session state fails.
+    #[test]
+    fn invalid_state() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join(SESSION_STATE_PATH);
+        fs::write(&path, "invalid")?;
+        let manager = LocalSessionStateManager { path };
+        assert!(manager.load_session_state(100, 50).is_err());
+        Ok(())
+    }
+
+    /// Verifies saving and loading the session state from the in-memory manager.
+    #[test]
+    fn in_memory_state() -> Result<()> {
+        let mut manager = InMemorySessionStateManager::default();
+        assert!(manager.load_session_state(100, 50)?.is_none());
+        let state = SessionState {
+            saved_at: 100,
+            relearn_pile: vec![Ustr::from("a::b::c")],
+            ..Default::default()
+        };
+        manager.save_session_state(&state)?;
+        assert_eq!(manager.load_session_state(120, 50)?, Some(state));
+        assert!(manager.load_session_state(150, 50)?.is_none());
+        Ok(())
+    }
+}
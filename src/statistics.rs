@@ -0,0 +1,371 @@
+//! Defines the statistics computed from the practice history of the user.
+//!
+//! These statistics are meant to motivate students and to keep them accountable. They include the
+//! current and longest daily streaks, the number of trials and the distribution of scores on each
+//! day, an estimate of the time spent practicing, and the activity in each course. Days are
+//! computed in the time zone set in the user preferences, so that a trial done late at night counts
+//! towards the day in which the student did it. The daily activity includes every day in the
+//! requested range, including those without trials, so that it can be displayed as a heatmap.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Days, NaiveDate};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use ustr::{Ustr, UstrMap, UstrSet};
+
+use crate::data::ExerciseTrial;
+
+/// The maximum number of seconds between two consecutive trials for the time between them to count
+/// as time spent practicing. Longer gaps are considered breaks.
+pub const MAX_TRIAL_GAP: i64 = 300;
+
+/// The number of distinct scores that can be assigned to a trial.
+const NUM_SCORES: usize = 5;
+
+/// The practice activity of the user on a single day.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct DailyActivity {
+    /// The date in the time zone of the user.
+    pub date: NaiveDate,
+
+    /// The number of trials done on this day.
+    pub num_trials: usize,
+
+    /// The number of distinct exercises practiced on this day.
+    pub num_exercises: usize,
+
+    /// The number of trials with each score, from one to five, done on this day.
+    pub score_counts: [usize; NUM_SCORES],
+
+    /// The estimated number of seconds spent practicing on this day. See [`MAX_TRIAL_GAP`].
+    pub time_on_task: i64,
+}
+
+/// The practice activity of the user in a single course.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CourseActivity {
+    /// The ID of the course.
+    pub course_id: Ustr,
+
+    /// The number of trials done in this course.
+    pub num_trials: usize,
+
+    /// The number of distinct exercises practiced in this course.
+    pub num_exercises: usize,
+
+    /// The number of days in which the course was practiced.
+    pub active_days: usize,
+
+    /// The average score of the trials in this course.
+    pub average_score: f32,
+
+    /// The timestamp of the last trial in this course.
+    pub last_trial: i64,
+}
+
+/// The statistics computed from the practice history of the user.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PracticeStatistics {
+    /// The number of consecutive days with at least one trial ending today. If there are no trials
+    /// today, the streak ends yesterday, so that the streak is not lost before the user has had a
+    /// chance to practice.
+    pub current_streak: usize,
+
+    /// The longest number of consecutive days with at least one trial in the entire history.
+    pub longest_streak: usize,
+
+    /// The total number of trials in the requested range.
+    pub num_trials: usize,
+
+    /// The estimated number of seconds spent practicing in the requested range.
+    pub time_on_task: i64,
+
+    /// The activity on each day of the requested range, sorted by date. Days without trials are
+    /// included.
+    pub daily_activity: Vec<DailyActivity>,
+
+    /// The activity in each course practiced in the requested range, sorted by course ID.
+    pub course_activity: Vec<CourseActivity>,
+}
+
+/// Returns the date of the given timestamp in the given time zone.
+pub fn local_date(timestamp: i64, time_zone: Tz) -> Result<NaiveDate> {
+    Ok(DateTime::from_timestamp(timestamp, 0)
+        .with_context(|| format!("invalid timestamp {timestamp}"))?
+        .with_timezone(&time_zone)
+        .date_naive())
+}
+
+/// Parses the name of a time zone from the IANA database. If no name is given, UTC is used.
+pub fn parse_time_zone(name: Option<&str>) -> Result<Tz> {
+    match name {
+        None => Ok(Tz::UTC),
+        Some(name) => name
+            .parse::<Tz>()
+            .map_err(|_| anyhow::anyhow!("invalid time zone {name}")),
+    }
+}
+
+/// Returns the current and longest streaks given the dates with at least one trial and the current
+/// date.
+#[must_use]
+pub fn compute_streaks(active_dates: &BTreeSet<NaiveDate>, today: NaiveDate) -> (usize, usize) {
+    // Compute the longest streak by walking the dates in order.
+    let mut longest_streak = 0;
+    let mut streak = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for date in active_dates {
+        if previous.and_then(|p| p.checked_add_days(Days::new(1))) == Some(*date) {
+            streak += 1;
+        } else {
+            streak = 1;
+        }
+        longest_streak = longest_streak.max(streak);
+        previous = Some(*date);
+    }
+
+    // Compute the current streak by walking back from today, or from yesterday if there are no
+    // trials today.
+    let mut current_streak = 0;
+    let mut date = if active_dates.contains(&today) {
+        Some(today)
+    } else {
+        today.checked_sub_days(Days::new(1))
+    };
+    while let Some(d) = date {
+        if !active_dates.contains(&d) {
+            break;
+        }
+        current_streak += 1;
+        date = d.checked_sub_days(Days::new(1));
+    }
+    (current_streak, longest_streak)
+}
+
+/// Returns the index of the given score in the score counts of a day.
+fn score_index(score: f32) -> usize {
+    // Scores are between one and five. The cast is safe because the value is clamped.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let index = score.round().clamp(1.0, NUM_SCORES as f32) as usize;
+    index - 1
+}
+
+/// Returns the activity on each day between `start_date` and `end_date`, both inclusive. The trials
+/// must be sorted by timestamp.
+pub fn compute_daily_activity(
+    trials: &[ExerciseTrial],
+    time_zone: Tz,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> Result<Vec<DailyActivity>> {
+    // Aggregate the trials by date. The time between two consecutive trials is added to the day of
+    // the second trial if it's short enough to not be considered a break.
+    let mut activity: BTreeMap<NaiveDate, (DailyActivity, UstrSet)> = BTreeMap::new();
+    let mut previous_timestamp: Option<i64> = None;
+    for trial in trials {
+        let date = local_date(trial.timestamp, time_zone)?;
+        let (day, exercises) = activity.entry(date).or_default();
+        day.num_trials += 1;
+        day.score_counts[score_index(trial.score)] += 1;
+        exercises.insert(trial.exercise_id);
+        if let Some(previous) = previous_timestamp {
+            let gap = trial.timestamp - previous;
+            if gap <= MAX_TRIAL_GAP {
+                day.time_on_task += gap;
+            }
+        }
+        previous_timestamp = Some(trial.timestamp);
+    }
+
+    // Fill in every day in the range, including those without trials.
+    let mut daily_activity = Vec::new();
+    for date in start_date.iter_days().take_while(|date| *date <= end_date) {
+        let day = match activity.remove(&date) {
+            Some((day, exercises)) => DailyActivity {
+                date,
+                num_exercises: exercises.len(),
+                ..day
+            },
+            None => DailyActivity {
+                date,
+                ..Default::default()
+            },
+        };
+        daily_activity.push(day);
+    }
+    Ok(daily_activity)
+}
+
+/// Returns the activity in each course. The function `get_course` returns the ID of the course to
+/// which an exercise belongs. Trials of exercises whose course is unknown are ignored.
+pub fn compute_course_activity(
+    trials: &[ExerciseTrial],
+    time_zone: Tz,
+    get_course: impl Fn(Ustr) -> Option<Ustr>,
+) -> Result<Vec<CourseActivity>> {
+    // Aggregate the trials by course, keeping track of the exercises, days, and sum of the scores.
+    let mut activity: UstrMap<(CourseActivity, UstrSet, BTreeSet<NaiveDate>, f32)> =
+        UstrMap::default();
+    for trial in trials {
+        let Some(course_id) = get_course(trial.exercise_id) else {
+            continue;
+        };
+        let (course, exercises, dates, score_sum) = activity.entry(course_id).or_default();
+        course.num_trials += 1;
+        course.last_trial = course.last_trial.max(trial.timestamp);
+        exercises.insert(trial.exercise_id);
+        dates.insert(local_date(trial.timestamp, time_zone)?);
+        *score_sum += trial.score;
+    }
+
+    // Compute the final values and sort the courses by ID.
+    #[allow(clippy::cast_precision_loss)]
+    let mut course_activity: Vec<CourseActivity> = activity
+        .into_iter()
+        .map(
+            |(course_id, (course, exercises, dates, score_sum))| CourseActivity {
+                course_id,
+                num_exercises: exercises.len(),
+                active_days: dates.len(),
+                average_score: score_sum / course.num_trials as f32,
+                ..course
+            },
+        )
+        .collect();
+    course_activity.sort_by(|a, b| a.course_id.as_str().cmp(b.course_id.as_str()));
+    Ok(course_activity)
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use anyhow::Result;
+    use chrono::NaiveDate;
+    use chrono_tz::Tz;
+    use std::collections::BTreeSet;
+    use ustr::Ustr;
+
+    use crate::{data::ExerciseTrial, statistics::*};
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn trial(exercise_id: &str, score: f32, timestamp: i64) -> ExerciseTrial {
+        ExerciseTrial {
+            exercise_id: Ustr::from(exercise_id),
+            score,
+            timestamp,
+        }
+    }
+
+    /// Verifies computing the current and longest streaks.
+    #[test]
+    fn streaks() {
+        let dates: BTreeSet<NaiveDate> = [
+            date(1, 1),
+            date(1, 2),
+            date(1, 3),
+            date(1, 4),
+            date(1, 10),
+            date(1, 11),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(compute_streaks(&dates, date(1, 11)), (2, 4));
+        assert_eq!(compute_streaks(&dates, date(1, 12)), (2, 4));
+        assert_eq!(compute_streaks(&dates, date(1, 13)), (0, 4));
+        assert_eq!(compute_streaks(&BTreeSet::new(), date(1, 13)), (0, 0));
+    }
+
+    /// Verifies parsing time zones and computing dates in them.
+    #[test]
+    fn time_zones() -> Result<()> {
+        assert_eq!(parse_time_zone(None)?, Tz::UTC);
+        let time_zone = parse_time_zone(Some("America/New_York"))?;
+        assert!(parse_time_zone(Some("Invalid/Zone")).is_err());
+
+        // 2024-01-02 03:00:00 UTC is still January 1st in New York.
+        let timestamp = 1_704_164_400;
+        assert_eq!(local_date(timestamp, Tz::UTC)?, date(1, 2));
+        assert_eq!(local_date(timestamp, time_zone)?, date(1, 1));
+        Ok(())
+    }
+
+    /// Verifies computing the daily activity.
+    #[test]
+    fn daily_activity() -> Result<()> {
+        // 2024-01-01 00:00:00 UTC.
+        let start = 1_704_067_200;
+        let trials = vec![
+            trial("a::b::1", 5.0, start + 100),
+            trial("a::b::2", 4.0, start + 160),
+            trial("a::b::1", 1.0, start + 200),
+            trial("a::b::1", 3.0, start + 10_000),
+            trial("a::b::2", 2.0, start + 2 * 86400 + 100),
+        ];
+        let activity = compute_daily_activity(&trials, Tz::UTC, date(1, 1), date(1, 4))?;
+        assert_eq!(activity.len(), 4);
+        assert_eq!(
+            activity[0],
+            DailyActivity {
+                date: date(1, 1),
+                num_trials: 4,
+                num_exercises: 2,
+                score_counts: [1, 0, 1, 1, 1],
+                time_on_task: 100,
+            }
+        );
+        assert_eq!(
+            activity[1],
+            DailyActivity {
+                date: date(1, 2),
+                ..Default::default()
+            }
+        );
+        assert_eq!(activity[2].num_trials, 1);
+        assert_eq!(activity[2].time_on_task, 0);
+        assert_eq!(activity[3].num_trials, 0);
+        Ok(())
+    }
+
+    /// Verifies computing the activity in each course.
+    #[test]
+    fn course_activity() -> Result<()> {
+        let trials = vec![
+            trial("a::b::1", 5.0, 100),
+            trial("a::b::2", 3.0, 200),
+            trial("b::a::1", 2.0, 300),
+            trial("b::a::1", 4.0, 86400 + 300),
+            trial("unknown", 4.0, 500),
+        ];
+        let get_course = |exercise_id: Ustr| {
+            let (course_id, _) = exercise_id.as_str().split_once("::")?;
+            Some(Ustr::from(course_id))
+        };
+        let activity = compute_course_activity(&trials, Tz::UTC, get_course)?;
+        assert_eq!(
+            activity,
+            vec![
+                CourseActivity {
+                    course_id: Ustr::from("a"),
+                    num_trials: 2,
+                    num_exercises: 2,
+                    active_days: 1,
+                    average_score: 4.0,
+                    last_trial: 200,
+                },
+                CourseActivity {
+                    course_id: Ustr::from("b"),
+                    num_trials: 2,
+                    num_exercises: 1,
+                    active_days: 2,
+                    average_score: 3.0,
+                    last_trial: 86400 + 300,
+                },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@
 
 use anyhow::{Context, Result, bail};
 use std::{collections::HashMap, fs::File, io::BufReader};
+use ustr::{Ustr, UstrMap};
 
 use crate::data::filter::StudySession;
 
@@ -59,6 +60,14 @@ impl LocalStudySessionManager {
             sessions: LocalStudySessionManager::scan_sessions(session_directory)?,
         })
     }
+
+    /// Replaces the IDs of renamed units in the saved study sessions with their current IDs. The
+    /// files are not modified, so the renames are applied every time the sessions are loaded.
+    pub fn rename_units(&mut self, renamed_units: &UstrMap<Ustr>) {
+        for session in self.sessions.values_mut() {
+            session.rename_units(renamed_units);
+        }
+    }
 }
 
 impl StudySessionManager for LocalStudySessionManager {
@@ -257,6 +257,7 @@ impl TestCourse {
             directory_name: format!("course_{}", self.id.0),
             course_manifest: CourseManifest {
                 id: course_id,
+                renamed_from: vec![],
                 name: format!("Course {course_id}"),
                 dependencies: self.dependencies.iter().map(TestId::to_ustr).collect(),
                 encompassed: self
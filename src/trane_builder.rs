@@ -0,0 +1,380 @@
+//! Contains a builder to create instances of Trane with custom components.
+//!
+//! By default, Trane stores the user data in `SQLite` databases and files inside the `.trane`
+//! directory of the course library. Embedders that need to store the data elsewhere can supply
+//! their own implementations of the storage traits. Alternatively, the builder can be configured
+//! to keep all the user data in memory, which allows Trane to run without writing to the file
+//! system. This is useful for demos, tests, and hosts that run in a sandbox.
+
+use anyhow::Result;
+use chrono::Utc;
+use parking_lot::RwLock;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use ustr::UstrMap;
+
+use crate::{
+    BLACKLIST_PATH, FILTERS_DIR, PRACTICE_DELTAS_PATH, PRACTICE_REWARDS_PATH, PRACTICE_STATS_PATH,
+    REVIEW_LIST_PATH, SESSION_STATE_PATH, STUDY_SESSIONS_DIR, TRANE_CONFIG_DIR_PATH, Trane,
+    USER_DATABASE_PATH, USER_PREFERENCES_PATH,
+    blacklist::{Blacklist, LocalBlacklist},
+    course_library::{CourseLibrary, GetUnitGraph, LocalCourseLibrary},
+    filter_manager::LocalFilterManager,
+    practice_deltas::{LocalPracticeDeltas, PracticeDeltas},
+    practice_rewards::{LocalPracticeRewards, PracticeRewards},
+    practice_stats::{LocalPracticeStats, PracticeStats},
+    preferences_manager::{
+        InMemoryPreferencesManager, LocalPreferencesManager, PreferencesManager,
+    },
+    repository_manager::{LocalRepositoryManager, RepositoryManager},
+    review_list::{LocalReviewList, ReviewList},
+    scheduler::{DepthFirstScheduler, data::SchedulerData},
+    session_state::{InMemorySessionStateManager, LocalSessionStateManager, SessionStateManager},
+    study_session_manager::LocalStudySessionManager,
+    user_database::UserDatabase,
+};
+
+/// A builder to create an instance of Trane. Any component that is not supplied is created with
+/// its default implementation, which stores its data inside the `.trane` directory of the library
+/// root, or in memory if [`TraneBuilder::in_memory`] is set.
+pub struct TraneBuilder {
+    /// The path to the root of the course library.
+    library_root: PathBuf,
+
+    /// Whether the components that are not supplied should keep their data in memory instead of
+    /// storing it in the `.trane` directory.
+    in_memory: bool,
+
+    /// Whether the stores that are not supplied should keep their data in a single consolidated
+    /// database instead of one database per store.
+    consolidated_database: bool,
+
+    /// The course library to use. If not supplied, it's read from the library root.
+    course_library: Option<LocalCourseLibrary>,
+
+    /// The object managing the user preferences.
+    preferences_manager: Option<Arc<RwLock<dyn PreferencesManager + Send + Sync>>>,
+
+    /// The object managing the information on previous exercise trials.
+    practice_stats: Option<Arc<RwLock<dyn PracticeStats + Send + Sync>>>,
+
+    /// The object managing the deltas between predicted and actual scores.
+    practice_deltas: Option<Arc<RwLock<dyn PracticeDeltas + Send + Sync>>>,
+
+    /// The object managing rewards for lessons and courses.
+    practice_rewards: Option<Arc<RwLock<dyn PracticeRewards + Send + Sync>>>,
+
+    /// The object managing the list of courses, lessons, and exercises to be skipped.
+    blacklist: Option<Arc<RwLock<dyn Blacklist + Send + Sync>>>,
+
+    /// The object managing the list of units to review.
+    review_list: Option<Arc<RwLock<dyn ReviewList + Send + Sync>>>,
+
+    /// The object managing the repositories from which courses are downloaded.
+    repository_manager: Option<Arc<RwLock<dyn RepositoryManager + Send + Sync>>>,
+}
+
+impl TraneBuilder {
+    /// Creates a new builder for the course library at the given path.
+    #[must_use]
+    pub fn new(library_root: &Path) -> Self {
+        Self {
+            library_root: library_root.to_path_buf(),
+            in_memory: false,
+            consolidated_database: false,
+            course_library: None,
+            preferences_manager: None,
+            practice_stats: None,
+            practice_deltas: None,
+            practice_rewards: None,
+            blacklist: None,
+            review_list: None,
+            repository_manager: None,
+        }
+    }
+
+    /// Keeps all the user data of the components that are not supplied in memory. The `.trane`
+    /// directory is neither created nor read, and all the data is lost when Trane is dropped.
+    #[must_use]
+    pub fn in_memory(mut self) -> Self {
+        self.in_memory = true;
+        self
+    }
+
+    /// Stores the data of the practice stats, deltas, rewards, blacklist, and review list that are
+    /// not supplied in a single database, so that each scoring operation is written in a single
+    /// transaction. If the consolidated database does not exist yet, it's created from the data in
+    /// the separate databases of each store. See [`UserDatabase`] for more details.
+    #[must_use]
+    pub fn consolidated_database(mut self) -> Self {
+        self.consolidated_database = true;
+        self
+    }
+
+    /// Sets the course library. Use it to open a library that has already been loaded, for example
+    /// from a serialized library, instead of reading it from the library root.
+    #[must_use]
+    pub fn course_library(mut self, course_library: LocalCourseLibrary) -> Self {
+        self.course_library = Some(course_library);
+        self
+    }
+
+    /// Sets the object managing the user preferences.
+    #[must_use]
+    pub fn preferences_manager(
+        mut self,
+        preferences_manager: Arc<RwLock<dyn PreferencesManager + Send + Sync>>,
+    ) -> Self {
+        self.preferences_manager = Some(preferences_manager);
+        self
+    }
+
+    /// Sets the object managing the information on previous exercise trials.
+    #[must_use]
+    pub fn practice_stats(
+        mut self,
+        practice_stats: Arc<RwLock<dyn PracticeStats + Send + Sync>>,
+    ) -> Self {
+        self.practice_stats = Some(practice_stats);
+        self
+    }
+
+    /// Sets the object managing the deltas between predicted and actual scores.
+    #[must_use]
+    pub fn practice_deltas(
+        mut self,
+        practice_deltas: Arc<RwLock<dyn PracticeDeltas + Send + Sync>>,
+    ) -> Self {
+        self.practice_deltas = Some(practice_deltas);
+        self
+    }
+
+    /// Sets the object managing rewards for lessons and courses.
+    #[must_use]
+    pub fn practice_rewards(
+        mut self,
+        practice_rewards: Arc<RwLock<dyn PracticeRewards + Send + Sync>>,
+    ) -> Self {
+        self.practice_rewards = Some(practice_rewards);
+        self
+    }
+
+    /// Sets the object managing the list of courses, lessons, and exercises to be skipped.
+    #[must_use]
+    pub fn blacklist(mut self, blacklist: Arc<RwLock<dyn Blacklist + Send + Sync>>) -> Self {
+        self.blacklist = Some(blacklist);
+        self
+    }
+
+    /// Sets the object managing the list of units to review.
+    #[must_use]
+    pub fn review_list(mut self, review_list: Arc<RwLock<dyn ReviewList + Send + Sync>>) -> Self {
+        self.review_list = Some(review_list);
+        self
+    }
+
+    /// Sets the object managing the repositories from which courses are downloaded.
+    #[must_use]
+    pub fn repository_manager(
+        mut self,
+        repository_manager: Arc<RwLock<dyn RepositoryManager + Send + Sync>>,
+    ) -> Self {
+        self.repository_manager = Some(repository_manager);
+        self
+    }
+
+    /// Builds the Trane instance.
+    #[cfg_attr(coverage, coverage(off))]
+    pub fn build(self) -> Result<Trane> {
+        // Initialize the config directory unless all the data is kept in memory.
+        if !self.in_memory {
+            Trane::init_config_directory(&self.library_root)?;
+        }
+        let config_path = self.library_root.join(TRANE_CONFIG_DIR_PATH);
+        let db_path = |path: &str| config_path.join(path).to_str().unwrap().to_string();
+
+        // Create the preferences manager and the course library.
+        let preferences_manager = match self.preferences_manager {
+            Some(preferences_manager) => preferences_manager,
+            None if self.in_memory => Arc::new(RwLock::new(InMemoryPreferencesManager::default())),
+            None => Arc::new(RwLock::new(LocalPreferencesManager {
+                path: config_path.join(USER_PREFERENCES_PATH),
+            })),
+        };
+        let user_preferences = preferences_manager.read().get_user_preferences()?;
+        let course_library = match self.course_library {
+            Some(course_library) => course_library,
+            None => LocalCourseLibrary::new(&self.library_root, user_preferences.clone())?,
+        };
+        let course_library = Arc::new(RwLock::new(course_library));
+        let unit_graph = course_library.write().get_unit_graph();
+
+        // Open the consolidated database if requested, migrating the data from the separate
+        // databases if it does not exist yet.
+        let user_database = if !self.consolidated_database {
+            None
+        } else if self.in_memory {
+            Some(Arc::new(UserDatabase::new_in_memory()?))
+        } else if config_path.join(USER_DATABASE_PATH).exists() {
+            Some(Arc::new(UserDatabase::new_from_disk(&db_path(
+                USER_DATABASE_PATH,
+            ))?))
+        } else {
+            Some(Arc::new(UserDatabase::migrate_from_separate_files(
+                &config_path,
+            )?))
+        };
+
+        // Create the stores for the user data that were not supplied.
+        let practice_stats = match (self.practice_stats, &user_database) {
+            (Some(practice_stats), _) => practice_stats,
+            (None, Some(user_database)) => Arc::new(RwLock::new(user_database.practice_stats())),
+            (None, None) if self.in_memory => {
+                Arc::new(RwLock::new(LocalPracticeStats::new_in_memory()?))
+            }
+            (None, None) => Arc::new(RwLock::new(LocalPracticeStats::new_from_disk(&db_path(
+                PRACTICE_STATS_PATH,
+            ))?)),
+        };
+        let practice_deltas = match (self.practice_deltas, &user_database) {
+            (Some(practice_deltas), _) => practice_deltas,
+            (None, Some(user_database)) => Arc::new(RwLock::new(user_database.practice_deltas())),
+            (None, None) if self.in_memory => {
+                Arc::new(RwLock::new(LocalPracticeDeltas::new_in_memory()?))
+            }
+            (None, None) => Arc::new(RwLock::new(LocalPracticeDeltas::new_from_disk(&db_path(
+                PRACTICE_DELTAS_PATH,
+            ))?)),
+        };
+        let practice_rewards = match (self.practice_rewards, &user_database) {
+            (Some(practice_rewards), _) => practice_rewards,
+            (None, Some(user_database)) => Arc::new(RwLock::new(user_database.practice_rewards())),
+            (None, None) if self.in_memory => {
+                Arc::new(RwLock::new(LocalPracticeRewards::new_in_memory()?))
+            }
+            (None, None) => Arc::new(RwLock::new(LocalPracticeRewards::new_from_disk(&db_path(
+                PRACTICE_REWARDS_PATH,
+            ))?)),
+        };
+        let blacklist = match (self.blacklist, &user_database) {
+            (Some(blacklist), _) => blacklist,
+            (None, Some(user_database)) => Arc::new(RwLock::new(user_database.blacklist()?)),
+            (None, None) if self.in_memory => {
+                Arc::new(RwLock::new(LocalBlacklist::new_in_memory()?))
+            }
+            (None, None) => Arc::new(RwLock::new(LocalBlacklist::new_from_disk(&db_path(
+                BLACKLIST_PATH,
+            ))?)),
+        };
+        let review_list = match (self.review_list, &user_database) {
+            (Some(review_list), _) => review_list,
+            (None, Some(user_database)) => Arc::new(RwLock::new(user_database.review_list())),
+            (None, None) if self.in_memory => {
+                Arc::new(RwLock::new(LocalReviewList::new_in_memory()?))
+            }
+            (None, None) => Arc::new(RwLock::new(LocalReviewList::new_from_disk(&db_path(
+                REVIEW_LIST_PATH,
+            ))?)),
+        };
+
+        // Create the managers for saved filters, study sessions, repositories, and the session
+        // state. When the data is kept in memory, there are no saved filters, study sessions, or
+        // repositories to load.
+        let (mut filter_manager, mut study_session_manager) = if self.in_memory {
+            (
+                LocalFilterManager {
+                    filters: HashMap::new(),
+                },
+                LocalStudySessionManager {
+                    sessions: HashMap::new(),
+                },
+            )
+        } else {
+            (
+                LocalFilterManager::new(&db_path(FILTERS_DIR))?,
+                LocalStudySessionManager::new(&db_path(STUDY_SESSIONS_DIR))?,
+            )
+        };
+        let repository_manager: Arc<RwLock<dyn RepositoryManager + Send + Sync>> =
+            match self.repository_manager {
+                Some(repository_manager) => repository_manager,
+                None if self.in_memory => Arc::new(RwLock::new(
+                    LocalRepositoryManager::new_in_memory(&self.library_root),
+                )),
+                None => Arc::new(RwLock::new(LocalRepositoryManager::new(
+                    &self.library_root,
+                )?)),
+            };
+        let session_state_manager: Arc<RwLock<dyn SessionStateManager + Send + Sync>> =
+            if self.in_memory {
+                Arc::new(RwLock::new(InMemorySessionStateManager::default()))
+            } else {
+                Arc::new(RwLock::new(LocalSessionStateManager {
+                    path: config_path.join(SESSION_STATE_PATH),
+                }))
+            };
+
+        // Move the data recorded under the previous IDs of renamed units to their current IDs.
+        // Units that have already been migrated have no data under their previous IDs, so this
+        // is a no-op for them.
+        let renamed_units = course_library.read().get_renamed_units();
+        for (old_id, new_id) in &renamed_units {
+            practice_stats.write().rename_unit(*old_id, *new_id)?;
+            practice_deltas.write().rename_unit(*old_id, *new_id)?;
+            practice_rewards.write().rename_unit(*old_id, *new_id)?;
+            blacklist.write().rename_unit(*old_id, *new_id)?;
+            review_list.write().rename_unit(*old_id, *new_id)?;
+        }
+        filter_manager.rename_units(&renamed_units);
+        study_session_manager.rename_units(&renamed_units);
+        let filter_manager = Arc::new(RwLock::new(filter_manager));
+
+        let options = Trane::create_scheduler_options(user_preferences.scheduler.as_ref());
+        options.verify()?;
+        let scheduler_data = SchedulerData {
+            options,
+            course_library: course_library.clone(),
+            unit_graph: unit_graph.clone(),
+            practice_deltas: practice_deltas.clone(),
+            practice_stats: practice_stats.clone(),
+            practice_rewards: practice_rewards.clone(),
+            blacklist: blacklist.clone(),
+            review_list: review_list.clone(),
+            filter_manager: filter_manager.clone(),
+            frequency_map: Arc::new(RwLock::new(UstrMap::default())),
+            trial_counts: Arc::new(RwLock::new((0, 0))),
+            user_database,
+        };
+
+        // Restore the state of the previous session if it's not stale. The state is disposable, so
+        // failing to load it should not prevent Trane from starting.
+        let scheduler = DepthFirstScheduler::new(scheduler_data.clone());
+        if let Ok(Some(state)) = session_state_manager.read().load_session_state(
+            Utc::now().timestamp(),
+            scheduler_data.options.session_staleness_window,
+        ) {
+            scheduler.restore_session_state(&state);
+        }
+
+        Ok(Trane {
+            blacklist,
+            course_library,
+            filter_manager,
+            library_root: self.library_root.to_str().unwrap().to_string(),
+            practice_deltas,
+            practice_stats,
+            practice_rewards,
+            preferences_manager,
+            repository_manager,
+            review_list,
+            scheduler_data,
+            scheduler,
+            session_state_manager,
+            study_session_manager: Arc::new(RwLock::new(study_session_manager)),
+            unit_graph,
+        })
+    }
+}
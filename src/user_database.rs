@@ -0,0 +1,557 @@
+//! Contains a database that stores all the user data in a single `SQLite` file.
+//!
+//! By default, each store keeps its data in its own database inside the `.trane` directory.
+//! Scoring an exercise writes to three of them, so a crash in the middle of scoring can leave the
+//! trials, deltas, and rewards inconsistent with each other. The consolidated layout keeps the data
+//! of all the stores in a single database with a shared connection, which allows every scoring
+//! operation to be wrapped in a single transaction.
+
+use anyhow::{Context, Result, bail};
+use parking_lot::{Mutex, ReentrantMutex};
+use rusqlite::Connection;
+use rusqlite_migration::{M, Migrations};
+use std::{fs, path::Path, sync::Arc};
+
+use crate::{
+    BLACKLIST_PATH, PRACTICE_DELTAS_PATH, PRACTICE_REWARDS_PATH, PRACTICE_STATS_PATH,
+    REVIEW_LIST_PATH, USER_DATABASE_PATH, blacklist::LocalBlacklist,
+    practice_deltas::LocalPracticeDeltas, practice_rewards::LocalPracticeRewards,
+    practice_stats::LocalPracticeStats, review_list::LocalReviewList, utils,
+};
+
+/// The information needed to copy the data of a store from its own database into the
+/// consolidated database.
+struct StoreImport {
+    /// The name of the store's database file inside the config directory.
+    path: &'static str,
+
+    /// The name under which the store's database is attached.
+    schema: &'static str,
+
+    /// A function that brings the store's database to its latest version.
+    upgrade: fn(&str) -> Result<()>,
+
+    /// The statements that copy the data. The unit UIDs are not copied directly because each store
+    /// assigned them independently.
+    statements: &'static str,
+}
+
+/// The imports of all the stores, in the order in which they are copied.
+const STORE_IMPORTS: &[StoreImport] = &[
+    StoreImport {
+        path: PRACTICE_STATS_PATH,
+        schema: "old_stats",
+        upgrade: |path| LocalPracticeStats::new_from_disk(path).map(drop),
+        statements: "INSERT OR IGNORE INTO main.uids(unit_id) SELECT unit_id FROM old_stats.uids;
+            INSERT INTO main.practice_stats(unit_uid, score, timestamp)
+                SELECT new.unit_uid, old.score, old.timestamp FROM old_stats.practice_stats old
+                JOIN old_stats.uids old_uids ON old.unit_uid = old_uids.unit_uid
+                JOIN main.uids new ON new.unit_id = old_uids.unit_id ORDER BY old.id;",
+    },
+    StoreImport {
+        path: PRACTICE_DELTAS_PATH,
+        schema: "old_deltas",
+        upgrade: |path| LocalPracticeDeltas::new_from_disk(path).map(drop),
+        statements: "INSERT OR IGNORE INTO main.uids(unit_id) SELECT unit_id FROM old_deltas.uids;
+            INSERT INTO main.practice_deltas(unit_uid, delta, timestamp)
+                SELECT new.unit_uid, old.delta, old.timestamp FROM old_deltas.practice_deltas old
+                JOIN old_deltas.uids old_uids ON old.unit_uid = old_uids.unit_uid
+                JOIN main.uids new ON new.unit_id = old_uids.unit_id ORDER BY old.id;",
+    },
+    StoreImport {
+        path: PRACTICE_REWARDS_PATH,
+        schema: "old_rewards",
+        upgrade: |path| LocalPracticeRewards::new_from_disk(path).map(drop),
+        statements: "INSERT OR IGNORE INTO main.uids(unit_id) SELECT unit_id FROM old_rewards.uids;
+            INSERT INTO main.practice_rewards(unit_uid, reward, weight, timestamp, exercise_uid)
+                SELECT new.unit_uid, old.reward, old.weight, old.timestamp, new_ex.unit_uid
+                FROM old_rewards.practice_rewards old
+                JOIN old_rewards.uids old_uids ON old.unit_uid = old_uids.unit_uid
+                JOIN main.uids new ON new.unit_id = old_uids.unit_id
+                LEFT JOIN old_rewards.uids old_ex ON old.exercise_uid = old_ex.unit_uid
+                LEFT JOIN main.uids new_ex ON new_ex.unit_id = old_ex.unit_id ORDER BY old.id;",
+    },
+    StoreImport {
+        path: BLACKLIST_PATH,
+        schema: "old_blacklist",
+        upgrade: |path| LocalBlacklist::new_from_disk(path).map(drop),
+        statements: "INSERT INTO main.blacklist(unit_id, added_at, reason, expires_at)
+            SELECT unit_id, added_at, reason, expires_at FROM old_blacklist.blacklist;",
+    },
+    StoreImport {
+        path: REVIEW_LIST_PATH,
+        schema: "old_review_list",
+        upgrade: |path| LocalReviewList::new_from_disk(path).map(drop),
+        statements: "INSERT INTO main.review_list(
+                unit_id, priority, added_at, remove_after, successful_reviews)
+                SELECT unit_id, priority, added_at, remove_after, successful_reviews
+                FROM old_review_list.review_list;
+            INSERT INTO main.auto_additions(unit_id, exercise_id, reason, timestamp)
+                SELECT unit_id, exercise_id, reason, timestamp
+                FROM old_review_list.auto_additions;",
+    },
+];
+
+/// A single database storing the data of the practice stats, practice deltas, practice rewards,
+/// blacklist, and review list. The stores created from it share its connection.
+pub struct UserDatabase {
+    /// A connection to the database shared by all the stores.
+    connection: Arc<Mutex<Connection>>,
+
+    /// A lock held for the whole duration of a transaction. The connection is locked separately by
+    /// each statement, so without this lock the transactions of different threads would be
+    /// interleaved in the same savepoint. The lock is reentrant so that transactions can be nested
+    /// in the same thread.
+    transaction_lock: ReentrantMutex<()>,
+}
+
+impl UserDatabase {
+    /// Returns all the migrations needed to set up the database. The tables are the same as those
+    /// in the databases of each store, except that the table mapping unit IDs to UIDs is shared
+    /// by the practice stats, deltas, and rewards.
+    fn migrations() -> Migrations<'static> {
+        Migrations::new(vec![
+            // Create the table with the mapping of unit IDs to a unique integer ID.
+            M::up("CREATE TABLE uids(unit_uid INTEGER PRIMARY KEY, unit_id TEXT NOT NULL UNIQUE);")
+                .down("DROP TABLE uids;"),
+            // Create the tables storing the exercise trials, the deltas, and the unit rewards.
+            M::up(
+                "CREATE TABLE practice_stats(
+                id INTEGER PRIMARY KEY,
+                unit_uid INTEGER NOT NULL REFERENCES uids(unit_uid),
+                score REAL, timestamp INTEGER);",
+            )
+            .down("DROP TABLE practice_stats"),
+            M::up(
+                "CREATE TABLE practice_deltas(
+                id INTEGER PRIMARY KEY,
+                unit_uid INTEGER NOT NULL REFERENCES uids(unit_uid),
+                delta REAL, timestamp INTEGER);",
+            )
+            .down("DROP TABLE practice_deltas"),
+            M::up(
+                "CREATE TABLE practice_rewards(
+                id INTEGER PRIMARY KEY,
+                unit_uid INTEGER NOT NULL REFERENCES uids(unit_uid),
+                reward REAL,
+                weight REAL,
+                timestamp INTEGER);",
+            )
+            .down("DROP TABLE practice_rewards"),
+            // Create combined indices of `unit_uid` and `timestamp` for fast retrieval.
+            M::up("CREATE INDEX trials ON practice_stats (unit_uid, timestamp);")
+                .down("DROP INDEX trials"),
+            M::up("CREATE INDEX deltas_trials ON practice_deltas (unit_uid, timestamp);")
+                .down("DROP INDEX deltas_trials"),
+            M::up("CREATE INDEX rewards ON practice_rewards (unit_uid, timestamp);")
+                .down("DROP INDEX rewards"),
+            // Create the tables storing the blacklist and the review list.
+            M::up(
+                "CREATE TABLE blacklist(
+                unit_id TEXT NOT NULL UNIQUE,
+                added_at INTEGER NOT NULL DEFAULT 0,
+                reason TEXT,
+                expires_at INTEGER);",
+            )
+            .down("DROP TABLE blacklist"),
+            M::up(
+                "CREATE TABLE review_list(
+                unit_id TEXT NOT NULL UNIQUE,
+                priority INTEGER NOT NULL DEFAULT 1,
+                added_at INTEGER NOT NULL DEFAULT 0,
+                remove_after INTEGER,
+                successful_reviews INTEGER NOT NULL DEFAULT 0);",
+            )
+            .down("DROP TABLE review_list"),
+            M::up(
+                "CREATE TABLE auto_additions(unit_id TEXT NOT NULL, exercise_id TEXT NOT NULL, \
+                reason TEXT NOT NULL, timestamp INTEGER NOT NULL);",
+            )
+            .down("DROP TABLE auto_additions"),
+            // Create an index of the timestamps of the trials for fast retrieval of the history.
+            M::up("CREATE INDEX trials_by_timestamp ON practice_stats (timestamp, id);")
+                .down("DROP INDEX trials_by_timestamp"),
+            // Store the exercise whose score produced each reward.
+            M::up("ALTER TABLE practice_rewards ADD COLUMN exercise_uid INTEGER;")
+                .down("ALTER TABLE practice_rewards DROP COLUMN exercise_uid;"),
+            M::up(
+                "CREATE INDEX rewards_by_exercise ON practice_rewards (exercise_uid, timestamp);",
+            )
+            .down("DROP INDEX rewards_by_exercise"),
+        ])
+    }
+
+    /// Creates a new instance with the given connection and initializes the database.
+    fn new(mut connection: Connection) -> Result<UserDatabase> {
+        Self::migrations()
+            .to_latest(&mut connection)
+            .context("failed to initialize user database")?;
+        Ok(UserDatabase {
+            connection: Arc::new(Mutex::new(connection)),
+            transaction_lock: ReentrantMutex::new(()),
+        })
+    }
+
+    /// A constructor taking the path to a database file. Unlike the databases of the individual
+    /// stores, writes are synchronized to disk at the end of every transaction so that a crash
+    /// cannot leave the database corrupted.
+    pub fn new_from_disk(db_path: &str) -> Result<UserDatabase> {
+        let connection = utils::new_connection(db_path)?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        Self::new(connection)
+    }
+
+    /// A constructor for a database that is kept in memory and discarded when dropped.
+    pub fn new_in_memory() -> Result<UserDatabase> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    /// Returns a practice stats store backed by this database.
+    #[must_use]
+    pub fn practice_stats(&self) -> LocalPracticeStats {
+        LocalPracticeStats::new_shared(self.connection.clone())
+    }
+
+    /// Returns a practice deltas store backed by this database.
+    #[must_use]
+    pub fn practice_deltas(&self) -> LocalPracticeDeltas {
+        LocalPracticeDeltas::new_shared(self.connection.clone())
+    }
+
+    /// Returns a practice rewards store backed by this database.
+    #[must_use]
+    pub fn practice_rewards(&self) -> LocalPracticeRewards {
+        LocalPracticeRewards::new_shared(self.connection.clone())
+    }
+
+    /// Returns a blacklist backed by this database.
+    pub fn blacklist(&self) -> Result<LocalBlacklist> {
+        LocalBlacklist::new_shared(self.connection.clone())
+    }
+
+    /// Returns a review list backed by this database.
+    #[must_use]
+    pub fn review_list(&self) -> LocalReviewList {
+        LocalReviewList::new_shared(self.connection.clone())
+    }
+
+    /// Compacts the database to reclaim the space freed by removed entries. Waits for the running
+    /// transactions to finish because the database cannot be compacted inside a transaction.
+    pub fn compact(&self) -> Result<()> {
+        let _transaction_guard = self.transaction_lock.lock();
+        self.connection.lock().execute_batch("VACUUM;")?;
+        Ok(())
+    }
+
+    /// Runs the given function inside a transaction. All the changes made by the stores created
+    /// from this database are committed if the function succeeds and rolled back otherwise.
+    /// Savepoints are used instead of regular transactions, so calls can be nested. Transactions
+    /// from different threads are run one after the other.
+    pub fn in_transaction<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let _transaction_guard = self.transaction_lock.lock();
+        self.connection
+            .lock()
+            .execute_batch("SAVEPOINT user_data;")?;
+        match f() {
+            Ok(value) => {
+                self.connection.lock().execute_batch("RELEASE user_data;")?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.connection
+                    .lock()
+                    .execute_batch("ROLLBACK TO user_data; RELEASE user_data;")?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Creates the consolidated database inside the given config directory and copies into it the
+    /// data from the separate database of each store. The databases of the stores are brought to
+    /// their latest version before copying. Missing databases are skipped, and the original files
+    /// are left untouched. Fails if the consolidated database already exists.
+    pub fn migrate_from_separate_files(config_path: &Path) -> Result<UserDatabase> {
+        let db_path = config_path.join(USER_DATABASE_PATH);
+        if db_path.exists() {
+            bail!("user database {} already exists", db_path.display());
+        }
+
+        // Make sure the existing databases are at their latest version.
+        let imports: Vec<_> = STORE_IMPORTS
+            .iter()
+            .filter(|import| config_path.join(import.path).exists())
+            .collect();
+        for import in &imports {
+            (import.upgrade)(config_path.join(import.path).to_str().unwrap())?;
+        }
+
+        // Copy the data into a temporary database, so that a failed migration does not leave a
+        // partially filled database behind.
+        let temp_path = db_path.with_extension("db.tmp");
+        if temp_path.exists() {
+            fs::remove_file(&temp_path).context("failed to remove temporary user database")?;
+        }
+        {
+            let user_database = Self::new_from_disk(temp_path.to_str().unwrap())?;
+            let mut connection = user_database.connection.lock();
+            for import in &imports {
+                connection
+                    .execute(
+                        &format!("ATTACH DATABASE ?1 AS {};", import.schema),
+                        [config_path.join(import.path).to_str().unwrap()],
+                    )
+                    .context(format!("failed to attach database {}", import.path))?;
+            }
+            let tx = connection.transaction()?;
+            for import in &imports {
+                tx.execute_batch(import.statements)
+                    .context(format!("failed to copy data from {}", import.path))?;
+            }
+            tx.commit()?;
+        }
+
+        // Move the database to its final location once all the data has been copied.
+        fs::rename(&temp_path, &db_path).context("failed to move user database")?;
+        Self::new_from_disk(db_path.to_str().unwrap())
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(coverage, coverage(off))]
+mod test {
+    use anyhow::{Result, bail};
+    use tempfile::tempdir;
+    use ustr::Ustr;
+
+    use crate::{
+        BLACKLIST_PATH, PRACTICE_DELTAS_PATH, PRACTICE_REWARDS_PATH, PRACTICE_STATS_PATH,
+        REVIEW_LIST_PATH,
+        blacklist::{Blacklist, LocalBlacklist},
+        data::{ExerciseDelta, ExerciseTrial, UnitReward},
+        practice_deltas::{LocalPracticeDeltas, PracticeDeltas},
+        practice_rewards::{LocalPracticeRewards, PracticeRewards},
+        practice_stats::{LocalPracticeStats, PracticeStats},
+        review_list::{LocalReviewList, ReviewList},
+        user_database::UserDatabase,
+    };
+
+    /// Returns a trial for the given exercise.
+    fn trial(exercise_id: &str, timestamp: i64) -> ExerciseTrial {
+        ExerciseTrial {
+            exercise_id: Ustr::from(exercise_id),
+            score: 4.0,
+            timestamp,
+        }
+    }
+
+    /// Returns a delta for the given exercise.
+    fn delta(exercise_id: &str, timestamp: i64) -> ExerciseDelta {
+        ExerciseDelta {
+            exercise_id: Ustr::from(exercise_id),
+            delta: 1.0,
+            timestamp,
+        }
+    }
+
+    /// Returns a reward for the given unit.
+    fn reward(unit_id: &str, timestamp: i64) -> UnitReward {
+        UnitReward {
+            unit_id: Ustr::from(unit_id),
+            value: 1.0,
+            weight: 1.0,
+            timestamp,
+        }
+    }
+
+    /// Verifies that the stores created from the same database share the table of unit IDs.
+    #[test]
+    fn shared_stores() -> Result<()> {
+        let user_database = UserDatabase::new_in_memory()?;
+        let mut practice_stats = user_database.practice_stats();
+        let mut practice_deltas = user_database.practice_deltas();
+        practice_stats.record_exercise_scores(&[trial("a::b::c", 1)])?;
+        practice_deltas.record_exercise_deltas(&[delta("a::b::c", 1)])?;
+        assert_eq!(
+            practice_stats.get_scores(Ustr::from("a::b::c"), 10)?.len(),
+            1
+        );
+        assert_eq!(
+            practice_deltas.get_deltas(Ustr::from("a::b::c"), 10)?.len(),
+            1
+        );
+
+        // Renaming the unit in one store does not affect the data of the other.
+        practice_stats.rename_unit(Ustr::from("a::b::c"), Ustr::from("a::b::d"))?;
+        assert_eq!(
+            practice_stats.get_scores(Ustr::from("a::b::d"), 10)?.len(),
+            1
+        );
+        assert_eq!(
+            practice_deltas.get_deltas(Ustr::from("a::b::c"), 10)?.len(),
+            1
+        );
+        practice_deltas.rename_unit(Ustr::from("a::b::c"), Ustr::from("a::b::d"))?;
+        assert_eq!(
+            practice_deltas.get_deltas(Ustr::from("a::b::d"), 10)?.len(),
+            1
+        );
+
+        // Compacting the database keeps the data of all the stores.
+        user_database.compact()?;
+        assert_eq!(
+            practice_stats.get_scores(Ustr::from("a::b::d"), 10)?.len(),
+            1
+        );
+        Ok(())
+    }
+
+    /// Verifies that the changes made inside a failed transaction are rolled back.
+    #[test]
+    fn transaction_rollback() -> Result<()> {
+        let user_database = UserDatabase::new_in_memory()?;
+        let mut practice_stats = user_database.practice_stats();
+        let mut practice_rewards = user_database.practice_rewards();
+
+        // The changes of a successful transaction are kept.
+        user_database.in_transaction(|| {
+            practice_stats.record_exercise_scores(&[trial("a::b::c", 1)])?;
+            Ok(())
+        })?;
+        assert_eq!(
+            practice_stats.get_scores(Ustr::from("a::b::c"), 10)?.len(),
+            1
+        );
+
+        // All the changes of a failed transaction, including those of nested transactions, are
+        // rolled back.
+        let result: Result<()> = user_database.in_transaction(|| {
+            practice_stats.record_exercise_scores(&[trial("a::b::c", 2)])?;
+            user_database.in_transaction(|| {
+                practice_rewards
+                    .record_unit_rewards(Ustr::from("a::b::c"), &[reward("a::b", 2)])?;
+                Ok(())
+            })?;
+            bail!("failed to score exercise");
+        });
+        assert!(result.is_err());
+        assert_eq!(
+            practice_stats.get_scores(Ustr::from("a::b::c"), 10)?.len(),
+            1
+        );
+        assert!(
+            practice_rewards
+                .get_rewards(Ustr::from("a::b"), 10)?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    /// Verifies that a failed transaction does not roll back the changes of a concurrent transaction
+    /// from another thread.
+    #[test]
+    fn concurrent_transactions() -> Result<()> {
+        let user_database = UserDatabase::new_in_memory()?;
+        std::thread::scope(|scope| {
+            // Start a transaction that fails after waiting for the other one to start.
+            let failed = scope.spawn(|| {
+                let mut practice_stats = user_database.practice_stats();
+                user_database.in_transaction::<()>(|| {
+                    practice_stats.record_exercise_scores(&[trial("a::b::c", 1)])?;
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    bail!("failed to score exercise")
+                })
+            });
+
+            // Run a successful transaction while the first one is in progress.
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            let mut practice_stats = user_database.practice_stats();
+            user_database.in_transaction(|| {
+                practice_stats.record_exercise_scores(&[trial("d::e::f", 1)])?;
+                Ok(())
+            })?;
+            assert!(failed.join().unwrap().is_err());
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        // Only the changes of the failed transaction are rolled back.
+        let practice_stats = user_database.practice_stats();
+        assert!(
+            practice_stats
+                .get_scores(Ustr::from("a::b::c"), 10)?
+                .is_empty()
+        );
+        assert_eq!(
+            practice_stats.get_scores(Ustr::from("d::e::f"), 10)?.len(),
+            1
+        );
+        Ok(())
+    }
+
+    /// Verifies migrating the data from the separate databases of each store.
+    #[test]
+    fn migrate_from_separate_files() -> Result<()> {
+        // Write some data to the separate databases.
+        let temp_dir = tempdir()?;
+        let path = |file: &str| temp_dir.path().join(file).to_str().unwrap().to_string();
+        let mut practice_stats = LocalPracticeStats::new_from_disk(&path(PRACTICE_STATS_PATH))?;
+        practice_stats.record_exercise_scores(&[trial("a::b::c", 1), trial("a::b::d", 2)])?;
+        let mut practice_deltas = LocalPracticeDeltas::new_from_disk(&path(PRACTICE_DELTAS_PATH))?;
+        practice_deltas.record_exercise_deltas(&[delta("a::b::d", 2)])?;
+        let mut practice_rewards =
+            LocalPracticeRewards::new_from_disk(&path(PRACTICE_REWARDS_PATH))?;
+        practice_rewards.record_unit_rewards(Ustr::from("a::b::c"), &[reward("a::b", 1)])?;
+        let mut blacklist = LocalBlacklist::new_from_disk(&path(BLACKLIST_PATH))?;
+        blacklist.add_to_blacklist(Ustr::from("e"))?;
+        let mut review_list = LocalReviewList::new_from_disk(&path(REVIEW_LIST_PATH))?;
+        review_list.add_to_review_list(Ustr::from("f"))?;
+        drop((
+            practice_stats,
+            practice_deltas,
+            practice_rewards,
+            blacklist,
+            review_list,
+        ));
+
+        // Migrate the data and verify it's available from the consolidated database.
+        let user_database = UserDatabase::migrate_from_separate_files(temp_dir.path())?;
+        let practice_stats = user_database.practice_stats();
+        assert_eq!(
+            practice_stats.get_scores(Ustr::from("a::b::c"), 10)?.len(),
+            1
+        );
+        assert_eq!(
+            practice_stats.get_scores(Ustr::from("a::b::d"), 10)?.len(),
+            1
+        );
+        let deltas = user_database
+            .practice_deltas()
+            .get_deltas(Ustr::from("a::b::d"), 10)?;
+        assert_eq!(deltas, vec![delta("a::b::d", 2)]);
+        let mut practice_rewards = user_database.practice_rewards();
+        let rewards = practice_rewards.get_rewards(Ustr::from("a::b"), 10)?;
+        assert_eq!(rewards, vec![reward("a::b", 1)]);
+
+        // The rewards are still linked to the exercise that produced them.
+        let removed = practice_rewards.remove_unit_rewards(Ustr::from("a::b::c"), 1)?;
+        assert_eq!(removed, vec![Ustr::from("a::b")]);
+        assert!(user_database.blacklist()?.blacklisted(Ustr::from("e"))?);
+        assert_eq!(
+            user_database.review_list().get_review_list_entries()?,
+            vec![Ustr::from("f")]
+        );
+
+        // The migration cannot be run again once the database exists.
+        drop(user_database);
+        assert!(UserDatabase::migrate_from_separate_files(temp_dir.path()).is_err());
+        Ok(())
+    }
+
+    /// Verifies migrating when none of the separate databases exist.
+    #[test]
+    fn migrate_without_databases() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let user_database = UserDatabase::migrate_from_separate_files(temp_dir.path())?;
+        assert!(user_database.practice_stats().get_unit_ids()?.is_empty());
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 //! Contains common utilities used in multiple modules.
 
-use rusqlite::Connection;
+use anyhow::Result;
+use rusqlite::{Connection, ToSql, params};
+
+use crate::data::RetentionPolicy;
 
 /// Returns the weighted average of the scores.
 #[must_use]
@@ -26,6 +29,77 @@ pub fn new_connection(db_path: &str) -> Result<Connection, rusqlite::Error> {
     Ok(connection)
 }
 
+/// Removes the entries of the given table that are older than the retention window of the policy,
+/// except for the first entry of each unit, the last entry of each unit in each downsampling
+/// interval, and the entries that match the `kept` condition. The table must have the `id`,
+/// `unit_uid`, and `timestamp` columns used by the practice stats, deltas, and rewards.
+///
+/// The condition is an SQL expression evaluated on the old entries. It can use the window `unit`,
+/// which orders the entries of each unit from oldest to newest, and refer to the values in `params`
+/// starting with `?3`. Returns the number of removed entries.
+pub(crate) fn apply_retention_policy(
+    connection: &Connection,
+    table: &str,
+    policy: &RetentionPolicy,
+    now: i64,
+    kept: &str,
+    params: &[&dyn ToSql],
+) -> Result<usize> {
+    // Number the old entries of each unit from oldest to newest, and the entries in each
+    // downsampling interval from newest to oldest. Then remove all the old entries except for the
+    // first entry of each unit, the last entry in each interval, and the kept entries. The bucket
+    // of every entry is null when the interval is zero, so the interval condition is ignored.
+    policy.verify()?;
+    let cutoff = now - policy.retention_window;
+    let mut stmt = connection.prepare_cached(&format!(
+        "DELETE FROM {table} WHERE id IN (
+            SELECT id FROM (
+                SELECT id, {kept} AS kept,
+                    ROW_NUMBER() OVER unit AS entry_num,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY unit_uid, timestamp / ?2 ORDER BY timestamp DESC, id DESC
+                    ) AS bucket_num
+                FROM {table} WHERE timestamp < ?1
+                WINDOW unit AS (PARTITION BY unit_uid ORDER BY timestamp, id)
+            ) WHERE NOT kept AND entry_num > 1 AND (?2 = 0 OR bucket_num > 1)
+        );"
+    ))?;
+    let all_params: Vec<&dyn ToSql> = [&cutoff as &dyn ToSql, &policy.downsample_interval]
+        .into_iter()
+        .chain(params.iter().copied())
+        .collect();
+    Ok(stmt.execute(all_params.as_slice())?)
+}
+
+/// Points the entries of the given unit ID in the given columns of the table to a new ID, adding
+/// the new ID to the UIDs table if needed. All the changes are made in a single transaction. The
+/// old ID is kept in the UIDs table because the table is shared with other stores in the
+/// consolidated user database, which might still reference it.
+pub(crate) fn rename_unit_uid(
+    connection: &mut Connection,
+    table: &str,
+    columns: &[&str],
+    old_id: &str,
+    new_id: &str,
+) -> Result<()> {
+    let tx = connection.savepoint()?;
+    tx.execute(
+        "INSERT OR IGNORE INTO uids(unit_id) VALUES (?1);",
+        params![new_id],
+    )?;
+    for column in columns {
+        tx.execute(
+            &format!(
+                "UPDATE {table} SET {column} = (SELECT unit_uid FROM uids WHERE unit_id = ?2)
+                    WHERE {column} = (SELECT unit_uid FROM uids WHERE unit_id = ?1);"
+            ),
+            params![old_id, new_id],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -27,21 +27,38 @@
 //! the student, that bad scores cause progress to stall, and that course and lesson filters are
 //! respected.
 
-use std::{collections::BTreeMap, sync::LazyLock};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, LazyLock},
+};
 
 use anyhow::{Ok, Result};
-use chrono::{Duration, Utc};
+use chrono::{Duration, TimeZone, Utc};
+use chrono_tz::Europe::Madrid;
+use parking_lot::RwLock;
 use tempfile::TempDir;
 use trane::{
-    Trane,
-    course_library::{CourseLibrary, LocalCourseLibrary, SerializedCourseLibrary},
+    TRANE_CONFIG_DIR_PATH, Trane, USER_DATABASE_PATH,
+    blacklist::Blacklist,
+    course_library::{
+        CourseLibrary, EXERCISE_MANIFEST_FILENAME, LocalCourseLibrary, SerializedCourseLibrary,
+    },
     data::{
-        MasteryScore, SchedulerOptions, UnitType, UserPreferences,
+        AutoReviewPolicy, AutoReviewReason, ExerciseManifest, MasteryScore, RetentionPolicy,
+        SchedulerOptions, UnitType, UserPreferences,
         filter::{ExerciseFilter, SessionPart, StudySession, StudySessionData, UnitFilter},
     },
+    graph::DotGraphOptions,
+    orphaned_data::OrphanedDataArchive,
+    practice_deltas::PracticeDeltas,
+    practice_rewards::PracticeRewards,
+    practice_stats::{LocalPracticeStats, PracticeStats},
+    preferences_manager::PreferencesManager,
+    repository_manager::RepositoryManager,
     review_list::ReviewList,
     scheduler::ExerciseScheduler,
     test_utils::*,
+    trane_builder::TraneBuilder,
 };
 use ustr::Ustr;
 
@@ -709,6 +726,165 @@ fn schedule_courses_in_review_list() -> Result<()> {
     Ok(())
 }
 
+/// Verifies that review list entries are removed after the given number of successful reviews.
+#[test]
+fn remove_review_list_entries_after_successful_reviews() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+
+    // Add a lesson that should be removed after a few successful reviews and one that should
+    // remain in the review list.
+    let removed_lesson = TestId(1, Some(0), None).to_ustr();
+    let kept_lesson = TestId(2, Some(1), None).to_ustr();
+    trane.add_to_review_list_with_details(removed_lesson, 1, Some(3))?;
+    trane.add_to_review_list(kept_lesson)?;
+
+    // Run the simulation with the review list filter.
+    let mut simulation = TraneSimulation::new(100, Box::new(|_| Some(MasteryScore::Five)));
+    simulation.run_simulation(
+        &mut trane,
+        &vec![],
+        &Some(ExerciseFilter::UnitFilter(UnitFilter::ReviewListFilter)),
+    )?;
+
+    // Only the lesson without a limit should remain in the review list.
+    assert!(trane.get_review_list_entry(removed_lesson)?.is_none());
+    let kept_entry = trane.get_review_list_entry(kept_lesson)?.unwrap();
+    assert!(kept_entry.successful_reviews > 0);
+    Ok(())
+}
+
+/// Verifies that exercises from review list entries with a higher priority are scheduled more
+/// often.
+#[test]
+fn schedule_high_priority_review_list_entries_more_often() -> Result<()> {
+    // Initialize test course library. Use a small batch size so that the candidates have to
+    // compete for a place in the batch.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+    trane.set_scheduler_options(SchedulerOptions {
+        batch_size: 5,
+        ..SchedulerOptions::default()
+    });
+
+    // Add two lessons to the review list with very different priorities.
+    let high_priority = TestId(1, Some(0), None);
+    let low_priority = TestId(2, Some(1), None);
+    trane.add_to_review_list_with_details(high_priority.to_ustr(), 100, None)?;
+    trane.add_to_review_list_with_details(low_priority.to_ustr(), 1, None)?;
+
+    // Run the simulation with the review list filter.
+    let mut simulation = TraneSimulation::new(200, Box::new(|_| Some(MasteryScore::Three)));
+    simulation.run_simulation(
+        &mut trane,
+        &vec![],
+        &Some(ExerciseFilter::UnitFilter(UnitFilter::ReviewListFilter)),
+    )?;
+
+    // Count the trials of the exercises in each lesson.
+    let count_trials = |lesson_id: &TestId| -> usize {
+        simulation
+            .answer_history
+            .iter()
+            .filter(|(exercise_id, _)| {
+                exercise_id.starts_with(&format!("{}::", lesson_id.to_ustr()))
+            })
+            .map(|(_, trials)| trials.len())
+            .sum()
+    };
+    let high_priority_trials = count_trials(&high_priority);
+    let low_priority_trials = count_trials(&low_priority);
+    assert!(
+        high_priority_trials > low_priority_trials,
+        "high priority trials: {high_priority_trials}, low priority trials: {low_priority_trials}"
+    );
+    Ok(())
+}
+
+/// Verifies that lapsed exercises are automatically added to the review list when the policy is
+/// enabled.
+#[test]
+fn auto_add_lapsed_exercises_to_review_list() -> Result<()> {
+    // Initialize test course library and enable the auto review policy.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+    trane.set_scheduler_options(SchedulerOptions {
+        auto_review: Some(AutoReviewPolicy::default()),
+        ..SchedulerOptions::default()
+    });
+
+    // Master an exercise and then fail it. The exercise should be added because it lapsed.
+    let now = Utc::now().timestamp();
+    let lapsed_exercise = TestId(0, Some(0), Some(0)).to_ustr();
+    for i in 0..3 {
+        trane.score_exercise(lapsed_exercise, MasteryScore::Five, now - 100 + i)?;
+    }
+    trane.score_exercise(lapsed_exercise, MasteryScore::One, now)?;
+
+    // Fail another exercise repeatedly. The exercise should be added after the third failure.
+    let failed_exercise = TestId(0, Some(0), Some(1)).to_ustr();
+    trane.score_exercise(failed_exercise, MasteryScore::One, now - 2)?;
+    trane.score_exercise(failed_exercise, MasteryScore::Two, now - 1)?;
+    assert!(trane.get_review_list_entry(failed_exercise)?.is_none());
+    trane.score_exercise(failed_exercise, MasteryScore::One, now)?;
+
+    // Failing an exercise that was never mastered only a few times should not add it.
+    let other_exercise = TestId(0, Some(0), Some(2)).to_ustr();
+    trane.score_exercise(other_exercise, MasteryScore::One, now)?;
+
+    // Verify the review list and the report.
+    let entries = trane.get_review_list_entries()?;
+    assert_eq!(entries, vec![lapsed_exercise, failed_exercise]);
+    let additions = trane.get_auto_review_additions()?;
+    assert_eq!(additions.len(), 2);
+    assert_eq!(additions[0].unit_id, lapsed_exercise);
+    assert!(matches!(
+        additions[0].reason,
+        AutoReviewReason::Lapse { score, .. } if score == 1.0
+    ));
+    assert_eq!(additions[1].unit_id, failed_exercise);
+    assert!(matches!(
+        additions[1].reason,
+        AutoReviewReason::RepeatedFailures {
+            num_failures: 3,
+            ..
+        }
+    ));
+    Ok(())
+}
+
+/// Verifies that the lessons of lapsed exercises are added to the review list when the policy is
+/// configured to do so, and that nothing is added when the policy is disabled.
+#[test]
+fn auto_add_lapsed_lessons_to_review_list() -> Result<()> {
+    // Initialize test course library. The policy is disabled by default.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+    let now = Utc::now().timestamp();
+    let exercise_id = TestId(1, Some(0), Some(0)).to_ustr();
+    for i in 0..3 {
+        trane.score_exercise(exercise_id, MasteryScore::One, now - 3 + i)?;
+    }
+    assert!(trane.get_review_list_entries()?.is_empty());
+
+    // Enable the policy and fail the exercise once more. Its lesson should be added.
+    trane.set_scheduler_options(SchedulerOptions {
+        auto_review: Some(AutoReviewPolicy {
+            add_lessons: true,
+            ..AutoReviewPolicy::default()
+        }),
+        ..SchedulerOptions::default()
+    });
+    trane.score_exercise(exercise_id, MasteryScore::One, now)?;
+    let lesson_id = TestId(1, Some(0), None).to_ustr();
+    assert_eq!(trane.get_review_list_entries()?, vec![lesson_id]);
+    let additions = trane.get_auto_review_additions()?;
+    assert_eq!(additions.len(), 1);
+    assert_eq!(additions[0].exercise_id, exercise_id);
+    Ok(())
+}
+
 /// Verifies scheduling exercises from the given units and their dependents.
 #[test]
 fn schedule_units_and_dependents() -> Result<()> {
@@ -1051,6 +1227,60 @@ fn get_matching_units() -> Result<()> {
     Ok(())
 }
 
+/// Verifies generating a DOT graph restricted by a filter or a neighborhood and annotated with the
+/// student's progress.
+#[test]
+fn generate_filtered_dot_graph() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+
+    // Score an exercise, blacklist a course, and add a lesson to the review list.
+    let course0_id = TestId(0, None, None).to_ustr();
+    let course1_id = TestId(1, None, None).to_ustr();
+    let lesson0_id = TestId(0, Some(0), None).to_ustr();
+    trane.score_exercise(
+        TestId(0, Some(0), Some(0)).to_ustr(),
+        MasteryScore::Five,
+        Utc::now().timestamp(),
+    )?;
+    trane.add_to_blacklist(course1_id)?;
+    trane.add_to_review_list(lesson0_id)?;
+
+    // Restrict the graph to course 0 and verify the output only contains its units.
+    let dot = trane.generate_filtered_dot_graph(&DotGraphOptions {
+        filter: Some(UnitFilter::CourseFilter {
+            course_ids: vec![course0_id],
+        }),
+        ..Default::default()
+    });
+    assert!(dot.contains(&format!("\"{course0_id}\" [color=red")));
+    assert!(dot.contains(&format!("\"{lesson0_id}\" -> \"0::1\"")));
+    assert!(!dot.contains(&format!("\"{course1_id}\"")));
+    let lesson0_line = dot
+        .lines()
+        .find(|line| line.contains(&format!("\"{lesson0_id}\" [")))
+        .unwrap();
+    assert!(lesson0_line.contains("peripheries=2"));
+    assert!(lesson0_line.contains("label=\"0::0\\n"));
+
+    // Restrict the graph to the courses one hop away from course 1.
+    let dot = trane.generate_filtered_dot_graph(&DotGraphOptions {
+        courses_only: true,
+        neighborhood: Some((course1_id, 1)),
+        ..Default::default()
+    });
+    let course1_line = dot
+        .lines()
+        .find(|line| line.contains(&format!("\"{course1_id}\" [")))
+        .unwrap();
+    assert!(course1_line.contains("fillcolor=gray"));
+    assert!(dot.contains(&format!("\"{course0_id}\" -> \"{course1_id}\"")));
+    assert!(!dot.contains("\"2\""));
+    assert!(!dot.contains("::"));
+    Ok(())
+}
+
 /// Verifies setting the scheduler options.
 #[test]
 fn set_scheduler_options() -> Result<()> {
@@ -1165,3 +1395,900 @@ fn serialized_course_library() -> Result<()> {
 
     Ok(())
 }
+
+/// Verifies undoing the last scores recorded during a session.
+#[test]
+fn undo_last_score() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+
+    // There is nothing to undo before any exercise has been scored.
+    assert!(trane.undo_last_score()?.is_none());
+
+    // Score an exercise twice. The good scores propagate rewards to the dependency lesson.
+    let exercise_id = TestId(1, Some(1), Some(0)).to_ustr();
+    let dependency_id = TestId(1, Some(0), None).to_ustr();
+    let timestamp = Utc::now().timestamp();
+    trane.score_exercise(exercise_id, MasteryScore::Three, timestamp - 2 * 86_400)?;
+    trane.score_exercise(exercise_id, MasteryScore::Five, timestamp)?;
+    assert_eq!(trane.get_scores(exercise_id, 10)?.len(), 2);
+    assert!(
+        trane
+            .get_rewards(dependency_id, 10)?
+            .iter()
+            .any(|reward| reward.timestamp == timestamp)
+    );
+
+    // Undo the last score. The trial and the rewards it produced should be removed.
+    let undone = trane.undo_last_score()?.unwrap();
+    assert_eq!(undone.exercise_id, exercise_id);
+    assert_eq!(undone.score, 5.0);
+    assert_eq!(undone.timestamp, timestamp);
+    let scores = trane.get_scores(exercise_id, 10)?;
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].score, 3.0);
+    assert!(
+        trane
+            .get_rewards(dependency_id, 10)?
+            .iter()
+            .all(|reward| reward.timestamp != timestamp)
+    );
+
+    // Undo the first score. The exercise should have no score left.
+    assert!(trane.undo_last_score()?.is_some());
+    assert!(trane.get_scores(exercise_id, 10)?.is_empty());
+    assert!(trane.get_rewards(dependency_id, 10)?.is_empty());
+    assert_eq!(trane.get_unit_score(exercise_id)?, Some(0.0));
+    assert!(trane.undo_last_score()?.is_none());
+    Ok(())
+}
+
+/// Verifies that undoing a score does not remove the rewards produced by another exercise scored in
+/// the same second.
+#[test]
+fn undo_score_in_same_second() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+
+    // Score two exercises of the same lesson at the same time. Both propagate rewards to the
+    // dependency lesson.
+    let exercise1_id = TestId(1, Some(1), Some(0)).to_ustr();
+    let exercise2_id = TestId(1, Some(1), Some(1)).to_ustr();
+    let dependency_id = TestId(1, Some(0), None).to_ustr();
+    let timestamp = Utc::now().timestamp();
+    trane.score_exercise(exercise1_id, MasteryScore::Five, timestamp)?;
+    trane.score_exercise(exercise2_id, MasteryScore::Four, timestamp)?;
+    let rewards = trane.get_rewards(dependency_id, 10)?;
+    assert_eq!(rewards.len(), 2);
+
+    // Undo the last score. Only the rewards of the second exercise should be removed.
+    assert!(trane.undo_last_score()?.is_some());
+    let remaining = trane.get_rewards(dependency_id, 10)?;
+    assert_eq!(remaining.len(), 1);
+    assert!(rewards.contains(&remaining[0]));
+    assert_eq!(trane.get_scores(exercise1_id, 10)?.len(), 1);
+    Ok(())
+}
+
+/// Verifies that undoing a score reverts the changes it made to the review list.
+#[test]
+fn undo_score_reverts_review_list() -> Result<()> {
+    // Initialize test course library and enable the auto review policy.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+    trane.set_scheduler_options(SchedulerOptions {
+        auto_review: Some(AutoReviewPolicy::default()),
+        ..SchedulerOptions::default()
+    });
+
+    // Add a lesson that is removed after one successful review and one that is kept.
+    let removed_lesson = TestId(1, Some(0), None).to_ustr();
+    let kept_lesson = TestId(0, Some(0), None).to_ustr();
+    trane.add_to_review_list_with_details(removed_lesson, 2, Some(1))?;
+    trane.add_to_review_list(kept_lesson)?;
+    let removed_entry = trane.get_review_list_entry(removed_lesson)?.unwrap();
+    let kept_entry = trane.get_review_list_entry(kept_lesson)?.unwrap();
+
+    // Review both lessons successfully and undo the scores. The entries should be restored.
+    let now = Utc::now().timestamp();
+    trane.score_exercise(
+        TestId(1, Some(0), Some(0)).to_ustr(),
+        MasteryScore::Five,
+        now,
+    )?;
+    trane.score_exercise(
+        TestId(0, Some(0), Some(0)).to_ustr(),
+        MasteryScore::Five,
+        now,
+    )?;
+    assert!(trane.get_review_list_entry(removed_lesson)?.is_none());
+    assert_eq!(
+        trane
+            .get_review_list_entry(kept_lesson)?
+            .unwrap()
+            .successful_reviews,
+        1
+    );
+    assert!(trane.undo_last_score()?.is_some());
+    assert!(trane.undo_last_score()?.is_some());
+    assert_eq!(
+        trane.get_review_list_entry(removed_lesson)?,
+        Some(removed_entry)
+    );
+    assert_eq!(trane.get_review_list_entry(kept_lesson)?, Some(kept_entry));
+
+    // Make an exercise lapse and undo the last score. The automatic addition should be removed.
+    let lapsed_exercise = TestId(2, Some(0), Some(0)).to_ustr();
+    for i in 0..3 {
+        trane.score_exercise(lapsed_exercise, MasteryScore::Five, now - 100 + i)?;
+    }
+    trane.score_exercise(lapsed_exercise, MasteryScore::One, now)?;
+    assert!(trane.get_review_list_entry(lapsed_exercise)?.is_some());
+    assert!(trane.undo_last_score()?.is_some());
+    assert!(trane.get_review_list_entry(lapsed_exercise)?.is_none());
+    assert!(trane.get_auto_review_additions()?.is_empty());
+    Ok(())
+}
+
+/// Verifies amending the score of an existing trial.
+#[test]
+fn amend_score() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+
+    // Score an exercise with a low score. The score propagates negative rewards to the dependents.
+    let exercise_id = TestId(0, Some(0), Some(0)).to_ustr();
+    let dependent_id = TestId(0, Some(1), None).to_ustr();
+    let timestamp = Utc::now().timestamp();
+    trane.score_exercise(exercise_id, MasteryScore::One, timestamp)?;
+    assert_eq!(trane.get_unit_score(exercise_id)?, Some(1.0));
+    assert!(
+        trane
+            .get_rewards(dependent_id, 10)?
+            .iter()
+            .all(|reward| reward.value < 0.0)
+    );
+
+    // Amend the score. The trial should be rewritten and the negative rewards removed.
+    trane.amend_score(exercise_id, timestamp, MasteryScore::Five)?;
+    let scores = trane.get_scores(exercise_id, 10)?;
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].score, 5.0);
+    assert_eq!(scores[0].timestamp, timestamp);
+    assert_eq!(trane.get_unit_score(exercise_id)?, Some(5.0));
+    assert!(
+        trane
+            .get_rewards(dependent_id, 10)?
+            .iter()
+            .all(|reward| reward.value >= 0.0)
+    );
+
+    // Amending a trial that does not exist fails.
+    assert!(
+        trane
+            .amend_score(exercise_id, timestamp - 1, MasteryScore::Five)
+            .is_err()
+    );
+    Ok(())
+}
+
+/// Verifies that amending a trial followed by later trials computes its delta only from the trials
+/// that came before it.
+#[test]
+fn amend_older_score() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+
+    // Score an exercise three times.
+    let exercise_id = TestId(0, Some(0), Some(0)).to_ustr();
+    let timestamp = Utc::now().timestamp();
+    trane.score_exercise(exercise_id, MasteryScore::Three, timestamp - 200)?;
+    trane.score_exercise(exercise_id, MasteryScore::Four, timestamp - 100)?;
+    trane.score_exercise(exercise_id, MasteryScore::Five, timestamp)?;
+
+    // Amend the second trial. Its delta should be computed against the score from the first trial
+    // alone, without the influence of the last trial.
+    trane.amend_score(exercise_id, timestamp - 100, MasteryScore::One)?;
+    let deltas = trane.get_deltas(exercise_id, 10)?;
+    let amended_delta = deltas
+        .iter()
+        .find(|delta| delta.timestamp == timestamp - 100)
+        .unwrap();
+    assert!((amended_delta.delta - (1.0 - 3.0)).abs() < 0.1);
+
+    // The later trial and its delta are left untouched.
+    assert_eq!(deltas.len(), 2);
+    assert_eq!(deltas[0].timestamp, timestamp);
+    let scores = trane.get_scores(exercise_id, 10)?;
+    assert_eq!(scores.len(), 3);
+    assert_eq!(scores[1].score, 1.0);
+    Ok(())
+}
+
+/// Verifies that the data of a renamed exercise is moved to its new ID when the library is opened.
+#[test]
+fn migrate_renamed_units() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+
+    // Score an exercise and add it to the blacklist and the review list.
+    let old_id = TestId(0, Some(0), Some(0)).to_ustr();
+    trane.score_exercise(old_id, MasteryScore::Four, Utc::now().timestamp())?;
+    trane.add_to_blacklist(old_id)?;
+    trane.add_to_review_list(old_id)?;
+    drop(trane);
+
+    // Rename the exercise in its manifest.
+    let new_id = Ustr::from("renamed_exercise");
+    let manifest_path = temp_dir
+        .path()
+        .join("course_0")
+        .join("lesson_0")
+        .join("exercise_0")
+        .join(EXERCISE_MANIFEST_FILENAME);
+    let mut manifest: ExerciseManifest =
+        serde_json::from_str(&std::fs::read_to_string(&manifest_path)?)?;
+    manifest.id = new_id;
+    manifest.renamed_from = vec![old_id];
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+
+    // Reopen the library and verify the data is available under the new ID.
+    let trane = Trane::new_local(temp_dir.path(), temp_dir.path())?;
+    assert_eq!(trane.get_renamed_units().get(&old_id), Some(&new_id));
+    assert!(trane.get_scores(old_id, 10)?.is_empty());
+    let scores = trane.get_scores(new_id, 10)?;
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].score, 4.0);
+    assert!(!trane.blacklisted(old_id)?);
+    assert!(trane.blacklisted(new_id)?);
+    assert_eq!(trane.get_review_list_entries()?, vec![new_id]);
+
+    // Reopening the library again does not change the migrated data.
+    drop(trane);
+    let trane = Trane::new_local(temp_dir.path(), temp_dir.path())?;
+    assert_eq!(trane.get_scores(new_id, 10)?.len(), 1);
+    assert!(trane.blacklisted(new_id)?);
+    Ok(())
+}
+
+/// Replaces the ID of the course with the given ID in all the unit IDs of the manifest.
+fn rename_course_ids(value: &mut serde_json::Value, old_id: &str, new_id: &str) {
+    match value {
+        serde_json::Value::String(unit_id) => {
+            if unit_id == old_id {
+                *unit_id = new_id.to_string();
+            } else if let Some(suffix) = unit_id.strip_prefix(&format!("{old_id}::")) {
+                *unit_id = format!("{new_id}::{suffix}");
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                rename_course_ids(value, old_id, new_id);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Verifies that the data of the exercises and lessons of a renamed course is moved to their new
+/// IDs when the library is opened.
+#[test]
+fn migrate_renamed_course() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+
+    // Score an exercise and add its lesson to the blacklist.
+    let old_exercise_id = TestId(0, Some(0), Some(0)).to_ustr();
+    let old_lesson_id = TestId(0, Some(1), None).to_ustr();
+    trane.score_exercise(old_exercise_id, MasteryScore::Four, Utc::now().timestamp())?;
+    trane.add_to_blacklist(old_lesson_id)?;
+    drop(trane);
+
+    // Rename the course and all its units in every manifest of the library, and declare the
+    // previous ID of the course.
+    for entry in walkdir::WalkDir::new(temp_dir.path()) {
+        let entry = entry?;
+        let file_name = entry.file_name().to_str().unwrap_or_default();
+        if !file_name.ends_with("_manifest.json") {
+            continue;
+        }
+        let mut manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(entry.path())?)?;
+        for key in [
+            "id",
+            "course_id",
+            "lesson_id",
+            "dependencies",
+            "encompassed",
+            "superseded",
+        ] {
+            if let Some(value) = manifest.get_mut(key) {
+                rename_course_ids(value, "0", "renamed");
+            }
+        }
+        if manifest["id"] == "renamed" {
+            manifest["renamed_from"] = serde_json::json!(["0"]);
+        }
+        std::fs::write(entry.path(), serde_json::to_string_pretty(&manifest)?)?;
+    }
+
+    // Reopen the library and verify the data of the descendants is available under their new IDs.
+    let trane = Trane::new_local(temp_dir.path(), temp_dir.path())?;
+    let new_exercise_id = Ustr::from("renamed::0::0");
+    let new_lesson_id = Ustr::from("renamed::1");
+    assert!(trane.get_exercise_manifest(new_exercise_id).is_some());
+    assert_eq!(
+        trane.get_renamed_units().get(&old_exercise_id),
+        Some(&new_exercise_id)
+    );
+    assert!(trane.get_scores(old_exercise_id, 10)?.is_empty());
+    let scores = trane.get_scores(new_exercise_id, 10)?;
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].score, 4.0);
+    assert!(!trane.blacklisted(old_lesson_id)?);
+    assert!(trane.blacklisted(new_lesson_id)?);
+    Ok(())
+}
+
+/// Verifies reporting and removing the data of units that are no longer in the course library.
+#[test]
+fn remove_orphaned_data() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+
+    // Score an exercise from a course that will be removed and another from a course that will be
+    // kept, and add the lesson of the former to the blacklist and the review list.
+    let orphaned_id = TestId(6, Some(0), Some(0)).to_ustr();
+    let kept_id = TestId(0, Some(0), Some(0)).to_ustr();
+    let orphaned_lesson = TestId(6, Some(0), None).to_ustr();
+    trane.score_exercise(orphaned_id, MasteryScore::Four, Utc::now().timestamp())?;
+    trane.score_exercise(kept_id, MasteryScore::Four, Utc::now().timestamp())?;
+    trane.add_to_blacklist(orphaned_lesson)?;
+    trane.add_to_review_list(orphaned_lesson)?;
+    //
+    // The course depends on a course that is not in the library, so the rewards propagated to it
+    // are already orphaned.
+    let report = trane.get_orphaned_data_report()?;
+    assert!(report.practice_stats.is_empty());
+    assert_eq!(report.practice_rewards, vec![Ustr::from("3")]);
+    drop(trane);
+
+    // Remove the course from the library and reopen it.
+    std::fs::remove_dir_all(temp_dir.path().join("course_6"))?;
+    let mut trane = Trane::new_local(temp_dir.path(), temp_dir.path())?;
+
+    // Verify the report lists the data of the removed course.
+    let report = trane.get_orphaned_data_report()?;
+    assert_eq!(report.practice_stats, vec![orphaned_id]);
+    assert_eq!(report.practice_rewards, vec![Ustr::from("3")]);
+    assert_eq!(report.blacklist, vec![orphaned_lesson]);
+    assert_eq!(report.review_list, vec![orphaned_lesson]);
+    assert_eq!(report.prefixes.keys().collect::<Vec<_>>(), vec!["3", "6"]);
+    assert!(report.ambiguous.is_empty());
+
+    // Remove the orphaned data and verify it was archived.
+    let archive_path = trane.remove_orphaned_data(true)?.unwrap();
+    let archive: OrphanedDataArchive =
+        serde_json::from_str(&std::fs::read_to_string(&archive_path)?)?;
+    assert_eq!(archive.report, report);
+    assert_eq!(archive.trials.len(), 1);
+    assert_eq!(archive.trials[0].exercise_id, orphaned_id);
+    assert!(!archive.rewards.is_empty());
+    assert_eq!(archive.blacklist.len(), 1);
+    assert_eq!(archive.review_list.len(), 1);
+
+    // Verify the orphaned data was removed and the rest of the data was kept.
+    assert!(trane.get_orphaned_data_report()?.is_empty());
+    assert!(trane.get_scores(orphaned_id, 10)?.is_empty());
+    assert!(trane.get_rewards(Ustr::from("3"), 10)?.is_empty());
+    assert!(!trane.blacklisted(orphaned_lesson)?);
+    assert!(trane.get_review_list_entries()?.is_empty());
+    assert_eq!(trane.get_scores(kept_id, 10)?.len(), 1);
+
+    // Removing the data again does nothing.
+    assert!(trane.remove_orphaned_data(true)?.is_none());
+
+    // A second archive does not overwrite the first, even if both are created in the same second.
+    trane.add_to_blacklist(orphaned_lesson)?;
+    let second_archive_path = trane.remove_orphaned_data(true)?.unwrap();
+    assert_ne!(second_archive_path, archive_path);
+    let archive: OrphanedDataArchive =
+        serde_json::from_str(&std::fs::read_to_string(&archive_path)?)?;
+    assert_eq!(archive.trials.len(), 1);
+    Ok(())
+}
+
+/// Verifies that Trane can run with all its user data in memory without creating the config
+/// directory.
+#[test]
+fn in_memory_trane() -> Result<()> {
+    // Initialize test course library and serialize it.
+    let temp_dir = TempDir::new()?;
+    let trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+    let serialized_library = SerializedCourseLibrary::from(&LocalCourseLibrary::new(
+        temp_dir.path(),
+        UserPreferences::default(),
+    )?);
+    drop(trane);
+
+    // Open the serialized library in a new directory with all the data in memory.
+    let empty_dir = TempDir::new()?;
+    let course_library =
+        LocalCourseLibrary::new_from_serialized(serialized_library, UserPreferences::default())?;
+    let mut trane = TraneBuilder::new(empty_dir.path())
+        .in_memory()
+        .course_library(course_library)
+        .build()?;
+    assert!(!trane.get_all_exercise_ids(None).is_empty());
+
+    // Score an exercise and add a unit to the blacklist.
+    let exercise_id = TestId(0, Some(0), Some(0)).to_ustr();
+    trane.score_exercise(exercise_id, MasteryScore::Five, Utc::now().timestamp())?;
+    trane.add_to_blacklist(TestId(1, None, None).to_ustr())?;
+    assert_eq!(trane.get_scores(exercise_id, 10)?.len(), 1);
+    assert!(trane.blacklisted(TestId(1, None, None).to_ustr())?);
+    assert!(!trane.get_exercise_batch(None)?.is_empty());
+
+    // Verify that nothing was written to the library root.
+    assert_eq!(std::fs::read_dir(empty_dir.path())?.count(), 0);
+    Ok(())
+}
+
+/// Verifies that Trane uses the stores supplied to the builder.
+#[test]
+fn custom_stores() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+    drop(trane);
+
+    // Supply a practice stats store and verify scores are recorded in it.
+    let practice_stats = Arc::new(RwLock::new(LocalPracticeStats::new_in_memory()?));
+    let trane = TraneBuilder::new(temp_dir.path())
+        .practice_stats(practice_stats.clone())
+        .build()?;
+    let exercise_id = TestId(0, Some(0), Some(0)).to_ustr();
+    trane.score_exercise(exercise_id, MasteryScore::Five, Utc::now().timestamp())?;
+    let scores = practice_stats.read().get_scores(exercise_id, 10)?;
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].score, 5.0);
+
+    // The scores are not stored in the database in the library.
+    drop(trane);
+    let trane = Trane::new_local(temp_dir.path(), temp_dir.path())?;
+    assert!(trane.get_scores(exercise_id, 10)?.is_empty());
+    Ok(())
+}
+
+/// Verifies that the data stored in separate databases is migrated to the consolidated database
+/// and that new scores are stored in it.
+#[test]
+fn consolidated_database() -> Result<()> {
+    // Initialize test course library and score an exercise with the default layout.
+    let temp_dir = TempDir::new()?;
+    let trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+    let exercise_id = TestId(0, Some(0), Some(0)).to_ustr();
+    trane.score_exercise(exercise_id, MasteryScore::Four, Utc::now().timestamp())?;
+    drop(trane);
+
+    // Open the library with the consolidated database and verify the data was migrated.
+    let trane = TraneBuilder::new(temp_dir.path())
+        .consolidated_database()
+        .build()?;
+    let config_path = temp_dir.path().join(TRANE_CONFIG_DIR_PATH);
+    assert!(config_path.join(USER_DATABASE_PATH).exists());
+    assert_eq!(trane.get_scores(exercise_id, 10)?.len(), 1);
+
+    // Score the exercise again and verify the new score is stored in the consolidated database.
+    trane.score_exercise(exercise_id, MasteryScore::Five, Utc::now().timestamp() + 1)?;
+    assert!(trane.undo_last_score()?.is_some());
+    trane.score_exercise(exercise_id, MasteryScore::Five, Utc::now().timestamp() + 2)?;
+    drop(trane);
+    let trane = TraneBuilder::new(temp_dir.path())
+        .consolidated_database()
+        .build()?;
+    let scores = trane.get_scores(exercise_id, 10)?;
+    assert_eq!(scores.len(), 2);
+    assert_eq!(scores[0].score, 5.0);
+
+    // The separate databases are not modified.
+    drop(trane);
+    let trane = Trane::new_local(temp_dir.path(), temp_dir.path())?;
+    assert_eq!(trane.get_scores(exercise_id, 10)?.len(), 1);
+    Ok(())
+}
+
+/// Verifies computing the practice statistics.
+#[test]
+fn practice_statistics() -> Result<()> {
+    // Initialize test course library and score exercises today, yesterday, and three days ago.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+    trane.set_user_preferences(UserPreferences {
+        time_zone: Some("Europe/Madrid".to_string()),
+        ..Default::default()
+    })?;
+    let today = Utc::now().with_timezone(&Madrid).date_naive();
+    let midnight = Madrid
+        .from_local_datetime(&today.and_hms_opt(0, 0, 0).unwrap())
+        .earliest()
+        .unwrap()
+        .timestamp();
+    let day = Duration::days(1).num_seconds();
+    let exercise1_id = TestId(0, Some(0), Some(0)).to_ustr();
+    let exercise2_id = TestId(1, Some(0), Some(0)).to_ustr();
+    trane.score_exercise(exercise1_id, MasteryScore::Two, midnight - 3 * day + 3600)?;
+    trane.score_exercise(exercise1_id, MasteryScore::Four, midnight - day + 3600)?;
+    trane.score_exercise(exercise1_id, MasteryScore::Five, midnight + 60)?;
+    trane.score_exercise(exercise2_id, MasteryScore::Three, midnight + 120)?;
+
+    // Verify the statistics for the last week.
+    let statistics = trane.get_practice_statistics(midnight - 7 * day, midnight + day)?;
+    assert_eq!(statistics.current_streak, 2);
+    assert_eq!(statistics.longest_streak, 2);
+    assert_eq!(statistics.num_trials, 4);
+    assert_eq!(statistics.time_on_task, 60);
+    let today_activity = statistics.daily_activity.last().unwrap();
+    assert_eq!(today_activity.date, today);
+    assert_eq!(today_activity.num_trials, 2);
+    assert_eq!(today_activity.num_exercises, 2);
+    assert_eq!(today_activity.score_counts, [0, 0, 1, 0, 1]);
+    assert_eq!(statistics.course_activity.len(), 2);
+    assert_eq!(
+        statistics.course_activity[0].course_id,
+        TestId(0, None, None).to_ustr()
+    );
+    assert_eq!(statistics.course_activity[0].num_trials, 3);
+    assert_eq!(statistics.course_activity[0].active_days, 3);
+
+    // Verify that empty and inverted ranges are reported as errors.
+    assert!(trane.get_practice_statistics(midnight, midnight).is_err());
+    assert!(trane.get_practice_statistics(midnight, i64::MIN).is_err());
+
+    // Verify that an invalid time zone is reported as an error.
+    trane.set_user_preferences(UserPreferences {
+        time_zone: Some("Invalid/Zone".to_string()),
+        ..Default::default()
+    })?;
+    assert!(
+        trane
+            .get_practice_statistics(midnight - 7 * day, midnight + day)
+            .is_err()
+    );
+    Ok(())
+}
+
+/// Verifies applying the retention policy in the user preferences to the practice history.
+#[test]
+fn enforce_retention_policy() -> Result<()> {
+    // Initialize test course library and score an exercise several times a long time ago.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+    let exercise_id = TestId(0, Some(0), Some(0)).to_ustr();
+    let day = Duration::days(1).num_seconds();
+    let long_ago = Utc::now().timestamp() - 1000 * day;
+    trane.score_exercise(exercise_id, MasteryScore::Three, long_ago)?;
+    trane.score_exercise(exercise_id, MasteryScore::One, long_ago + 60)?;
+    trane.score_exercise(exercise_id, MasteryScore::Five, long_ago + 120)?;
+    trane.score_exercise(exercise_id, MasteryScore::Two, long_ago + 180)?;
+    trane.score_exercise(exercise_id, MasteryScore::Four, long_ago + 240)?;
+    trane.score_exercise(exercise_id, MasteryScore::Five, Utc::now().timestamp())?;
+
+    // Apply a policy that doesn't downsample. The first trial, the lapse and the trial before it,
+    // and the recent trial are kept. The second trial is a failure but not a lapse because the
+    // exercise had not been mastered.
+    trane.set_user_preferences(UserPreferences {
+        retention: Some(RetentionPolicy {
+            downsample_interval: 0,
+            ..Default::default()
+        }),
+        ..Default::default()
+    })?;
+    let summary = trane.enforce_retention_policy()?;
+    assert_eq!(summary.trials, 2);
+    let scores: Vec<f32> = trane
+        .get_scores(exercise_id, 10)?
+        .iter()
+        .map(|t| t.score)
+        .collect();
+    assert_eq!(scores, vec![5.0, 2.0, 5.0, 3.0]);
+
+    // Verify that the policy can be applied to the consolidated database.
+    drop(trane);
+    let mut trane = TraneBuilder::new(temp_dir.path())
+        .consolidated_database()
+        .build()?;
+    let summary = trane.enforce_retention_policy()?;
+    assert_eq!(summary.trials, 0);
+    assert_eq!(trane.get_scores(exercise_id, 10)?.len(), 4);
+    Ok(())
+}
+
+/// Verifies opening a course whose manifests are written in YAML and TOML.
+#[test]
+fn yaml_and_toml_manifests() -> Result<()> {
+    // Write a course with a YAML course manifest, a TOML lesson manifest, and a YAML exercise
+    // manifest.
+    let temp_dir = TempDir::new()?;
+    let course_dir = temp_dir.path().join("course");
+    let exercise_dir = course_dir.join("lesson").join("exercise");
+    std::fs::create_dir_all(&exercise_dir)?;
+    std::fs::write(
+        course_dir.join("course_manifest.yaml"),
+        "# Comments are allowed.\nid: yaml_course\nname: YAML course\n",
+    )?;
+    std::fs::write(
+        course_dir.join("lesson").join("lesson_manifest.toml"),
+        "# Comments are allowed.\nid = \"yaml_course::lesson\"\ncourse_id = \"yaml_course\"\n\
+        name = \"TOML lesson\"\n",
+    )?;
+    std::fs::write(
+        exercise_dir.join("exercise_manifest.yml"),
+        "id: yaml_course::lesson::exercise\nlesson_id: yaml_course::lesson\n\
+        course_id: yaml_course\nexercise_type: Declarative\nexercise_asset:\n  \
+        InlineFlashcardAsset:\n    front_content: Front\n",
+    )?;
+
+    // Open the library and verify the units were loaded.
+    let library = LocalCourseLibrary::new(temp_dir.path(), UserPreferences::default())?;
+    assert_eq!(
+        library
+            .get_course_manifest(Ustr::from("yaml_course"))
+            .unwrap()
+            .name,
+        "YAML course"
+    );
+    assert_eq!(
+        library
+            .get_lesson_manifest(Ustr::from("yaml_course::lesson"))
+            .unwrap()
+            .name,
+        "TOML lesson"
+    );
+    assert_eq!(
+        library.get_exercise_ids(Ustr::from("yaml_course::lesson")),
+        Some(vec![Ustr::from("yaml_course::lesson::exercise")])
+    );
+
+    // Errors in the manifests report the line at which they happened.
+    std::fs::write(
+        course_dir.join("lesson").join("lesson_manifest.toml"),
+        "id = \"yaml_course::lesson\"\ncourse_id = yaml_course\n",
+    )?;
+    let error = LocalCourseLibrary::new(temp_dir.path(), UserPreferences::default())
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("at line 2"));
+
+    // The same manifest cannot be written in multiple formats.
+    std::fs::write(
+        course_dir.join("course_manifest.json"),
+        r#"{"id": "yaml_course", "name": "JSON course"}"#,
+    )?;
+    assert!(LocalCourseLibrary::new(temp_dir.path(), UserPreferences::default()).is_err());
+    Ok(())
+}
+
+/// Runs git with the given arguments and verifies that it succeeds.
+fn run_git(args: &[&str]) -> Result<()> {
+    let status = std::process::Command::new("git").args(args).status()?;
+    assert!(status.success(), "git {} failed", args.join(" "));
+    Ok(())
+}
+
+/// Verifies that courses in repositories added to Trane are loaded into the library, and that
+/// they are removed once the repository is removed.
+#[test]
+fn course_repositories() -> Result<()> {
+    // Create a git repository with a single course.
+    let remote_dir = TempDir::new()?;
+    let repo_dir = remote_dir.path().join("courses");
+    let exercise_dir = repo_dir.join("course").join("lesson").join("exercise");
+    std::fs::create_dir_all(&exercise_dir)?;
+    std::fs::write(
+        repo_dir.join("course").join("course_manifest.yaml"),
+        "id: repo_course\nname: Course\n",
+    )?;
+    std::fs::write(
+        repo_dir
+            .join("course")
+            .join("lesson")
+            .join("lesson_manifest.yaml"),
+        "id: repo_course::lesson\ncourse_id: repo_course\nname: Lesson\n",
+    )?;
+    std::fs::write(
+        exercise_dir.join("exercise_manifest.yaml"),
+        "id: repo_course::lesson::exercise\nlesson_id: repo_course::lesson\n\
+        course_id: repo_course\nexercise_type: Declarative\nexercise_asset:\n  \
+        InlineFlashcardAsset:\n    front_content: Front\n",
+    )?;
+    let repo_path = repo_dir.to_str().unwrap();
+    run_git(&["init", "--quiet", repo_path])?;
+    run_git(&["-C", repo_path, "add", "-A"])?;
+    run_git(&[
+        "-C",
+        repo_path,
+        "-c",
+        "user.name=Trane",
+        "-c",
+        "user.email=trane@example.com",
+        "commit",
+        "--quiet",
+        "-m",
+        "Add course",
+    ])?;
+
+    // Add the repository and verify the course is part of the library.
+    let library_dir = TempDir::new()?;
+    let mut trane = Trane::new_local(library_dir.path(), library_dir.path())?;
+    assert!(trane.get_course_ids().is_empty());
+    trane.add_repo(&format!("file://{repo_path}"), None)?;
+    assert_eq!(trane.list_repos()?.len(), 1);
+    assert_eq!(trane.get_course_ids(), vec![Ustr::from("repo_course")]);
+    let batch = trane.get_exercise_batch(None)?;
+    assert_eq!(batch[0].id, Ustr::from("repo_course::lesson::exercise"));
+
+    // The repository is remembered when the library is opened again.
+    let mut trane = Trane::new_local(library_dir.path(), library_dir.path())?;
+    assert_eq!(trane.get_course_ids(), vec![Ustr::from("repo_course")]);
+    trane.update_all_repos()?;
+
+    // Remove the repository and verify the course is no longer part of the library.
+    trane.remove_repo("courses")?;
+    assert!(trane.list_repos()?.is_empty());
+    assert!(trane.get_course_ids().is_empty());
+    Ok(())
+}
+
+/// Verifies grading and scoring the answers to a multiple-choice exercise.
+#[test]
+fn score_multiple_choice() -> Result<()> {
+    // Write a course with a multiple-choice exercise and a flashcard.
+    let temp_dir = TempDir::new()?;
+    let lesson_dir = temp_dir.path().join("course").join("lesson");
+    std::fs::create_dir_all(lesson_dir.join("question"))?;
+    std::fs::create_dir_all(lesson_dir.join("flashcard"))?;
+    std::fs::write(
+        temp_dir.path().join("course").join("course_manifest.yaml"),
+        "id: mc_course\nname: Course\n",
+    )?;
+    std::fs::write(
+        lesson_dir.join("lesson_manifest.yaml"),
+        "id: mc_course::lesson\ncourse_id: mc_course\nname: Lesson\n",
+    )?;
+    std::fs::write(
+        lesson_dir.join("question").join("exercise_manifest.yaml"),
+        "id: mc_course::lesson::question\nlesson_id: mc_course::lesson\ncourse_id: mc_course\n\
+        exercise_type: Declarative\nexercise_asset:\n  MultipleChoiceAsset:\n    \
+        prompt: Which are even?\n    options:\n      - text: \"1\"\n      - text: \"2\"\n        \
+        correct: true\n      - text: \"4\"\n        correct: true\n",
+    )?;
+    std::fs::write(
+        lesson_dir.join("flashcard").join("exercise_manifest.yaml"),
+        "id: mc_course::lesson::flashcard\nlesson_id: mc_course::lesson\ncourse_id: mc_course\n\
+        exercise_type: Declarative\nexercise_asset:\n  InlineFlashcardAsset:\n    \
+        front_content: Front\n",
+    )?;
+    let trane = Trane::new_local(temp_dir.path(), temp_dir.path())?;
+
+    // Grade a few answers and verify the scores were recorded.
+    let exercise_id = Ustr::from("mc_course::lesson::question");
+    assert_eq!(
+        trane.score_multiple_choice(exercise_id, &[1, 2], None, 1)?,
+        MasteryScore::Five
+    );
+    assert_eq!(
+        trane.score_multiple_choice(exercise_id, &[2, 1], Some(15.0), 2)?,
+        MasteryScore::Four
+    );
+    assert_eq!(
+        trane.score_multiple_choice(exercise_id, &[0], None, 3)?,
+        MasteryScore::One
+    );
+    let scores = trane.get_scores(exercise_id, 10)?;
+    assert_eq!(scores.len(), 3);
+    assert_eq!(scores[0].score, 1.0);
+
+    // Exercises that are not multiple-choice questions and invalid selections cannot be graded.
+    assert!(
+        trane
+            .score_multiple_choice(Ustr::from("mc_course::lesson::flashcard"), &[0], None, 4)
+            .is_err()
+    );
+    assert!(
+        trane
+            .score_multiple_choice(exercise_id, &[3], None, 4)
+            .is_err()
+    );
+    assert!(
+        trane
+            .score_multiple_choice(Ustr::from("missing"), &[0], None, 4)
+            .is_err()
+    );
+    Ok(())
+}
+
+/// Verifies grading and scoring the answers to typed-answer and dictation exercises.
+#[test]
+fn score_typed_answers() -> Result<()> {
+    // Write a course with a typed-answer exercise and a dictation exercise.
+    let temp_dir = TempDir::new()?;
+    let lesson_dir = temp_dir.path().join("course").join("lesson");
+    std::fs::create_dir_all(lesson_dir.join("typed"))?;
+    std::fs::create_dir_all(lesson_dir.join("dictation"))?;
+    std::fs::write(
+        temp_dir.path().join("course").join("course_manifest.yaml"),
+        "id: typed_course\nname: Course\n",
+    )?;
+    std::fs::write(
+        lesson_dir.join("lesson_manifest.yaml"),
+        "id: typed_course::lesson\ncourse_id: typed_course\nname: Lesson\n",
+    )?;
+    std::fs::write(
+        lesson_dir.join("typed").join("exercise_manifest.yaml"),
+        "id: typed_course::lesson::typed\nlesson_id: typed_course::lesson\n\
+        course_id: typed_course\nexercise_type: Declarative\nexercise_asset:\n  \
+        TypedAnswerAsset:\n    prompt: Song\n    accepted_answers: [Canción]\n    \
+        ignore_diacritics: true\n    tolerance: 1\n",
+    )?;
+    std::fs::write(
+        lesson_dir.join("dictation").join("exercise_manifest.yaml"),
+        "id: typed_course::lesson::dictation\nlesson_id: typed_course::lesson\n\
+        course_id: typed_course\nexercise_type: Procedural\nexercise_asset:\n  \
+        LiteracyAsset:\n    lesson_type: Dictation\n    examples: [[ca, null], [ce, se]]\n",
+    )?;
+    let trane = Trane::new_local(temp_dir.path(), temp_dir.path())?;
+
+    // Grade the typed answers and verify the scores were recorded.
+    let typed_id = Ustr::from("typed_course::lesson::typed");
+    assert_eq!(
+        trane.score_typed_answer(typed_id, "cancion", None, 1)?,
+        MasteryScore::Five
+    );
+    assert_eq!(
+        trane.score_typed_answer(typed_id, "cancio", None, 2)?,
+        MasteryScore::Four
+    );
+    assert_eq!(trane.get_scores(typed_id, 10)?.len(), 2);
+
+    // Grade the dictation answers.
+    let dictation_id = Ustr::from("typed_course::lesson::dictation");
+    assert_eq!(
+        trane.score_dictation(
+            dictation_id,
+            &[(0, "ca".to_string()), (1, "se".to_string())],
+            3
+        )?,
+        MasteryScore::Five
+    );
+    assert_eq!(
+        trane.score_dictation(dictation_id, &[(1, "se".to_string())], 4)?,
+        MasteryScore::Five
+    );
+    assert_eq!(trane.get_scores(dictation_id, 10)?.len(), 2);
+
+    // Answers to exercises of the wrong kind cannot be graded.
+    assert!(
+        trane
+            .score_typed_answer(dictation_id, "ca", None, 5)
+            .is_err()
+    );
+    assert!(
+        trane
+            .score_dictation(typed_id, &[(0, "cancion".to_string())], 5)
+            .is_err()
+    );
+    assert!(
+        trane
+            .score_dictation(dictation_id, &[(2, "ca".to_string())], 5)
+            .is_err()
+    );
+    Ok(())
+}
@@ -5,12 +5,13 @@
 //! basic_tests module.
 
 use anyhow::{Ok, Result};
+use chrono::Utc;
 use std::{collections::BTreeMap, sync::LazyLock};
 use tempfile::TempDir;
 use trane::{
     blacklist::Blacklist,
     data::{
-        MasteryScore, SchedulerOptions,
+        BlacklistReason, MasteryScore, SchedulerOptions,
         filter::{ExerciseFilter, UnitFilter},
     },
     scheduler::ExerciseScheduler,
@@ -519,3 +520,57 @@ fn invalidate_cache_on_blacklist_update() -> Result<()> {
     }
     Ok(())
 }
+
+/// Verifies that units whose blacklist entries have expired are scheduled again.
+#[test]
+fn resume_scheduling_after_blacklist_expiry() -> Result<()> {
+    // Initialize test course library.
+    let temp_dir = TempDir::new()?;
+    let mut trane = init_test_simulation(temp_dir.path(), &LIBRARY)?;
+
+    // Pause the course with ID `TestId(1, None, None)` for an hour and run the simulation. None of
+    // its exercises should be scheduled.
+    let course_id = TestId(1, None, None);
+    trane.add_to_blacklist_with_details(
+        course_id.to_ustr(),
+        BlacklistReason::TemporarilyPaused,
+        Some(Utc::now().timestamp() + 3600),
+    )?;
+    let mut simulation = TraneSimulation::new(500, Box::new(|_| Some(MasteryScore::Five)));
+    simulation.run_simulation(&mut trane, &vec![], &None)?;
+    let exercise_ids = all_test_exercises(&LIBRARY);
+    for exercise_id in &exercise_ids {
+        if exercise_id.exercise_in_course(&course_id) {
+            assert!(
+                !simulation
+                    .answer_history
+                    .contains_key(&exercise_id.to_ustr()),
+                "exercise {:?} should not have been scheduled",
+                exercise_id
+            );
+        }
+    }
+
+    // Simulate the pause running out by replacing the entry with one that has already expired.
+    // The exercises in the course should be scheduled again and the entry should be removed.
+    trane.add_to_blacklist_with_details(
+        course_id.to_ustr(),
+        BlacklistReason::TemporarilyPaused,
+        Some(Utc::now().timestamp() - 1),
+    )?;
+    let mut simulation = TraneSimulation::new(500, Box::new(|_| Some(MasteryScore::Five)));
+    simulation.run_simulation(&mut trane, &vec![], &None)?;
+    for exercise_id in &exercise_ids {
+        if exercise_id.exercise_in_course(&course_id) {
+            assert!(
+                simulation
+                    .answer_history
+                    .contains_key(&exercise_id.to_ustr()),
+                "exercise {:?} should have been scheduled",
+                exercise_id
+            );
+        }
+    }
+    assert!(trane.get_blacklist_entry(course_id.to_ustr())?.is_none());
+    Ok(())
+}
@@ -1,6 +1,6 @@
 //! End-to-end tests for the knowledge base course.
 
-use std::path::Path;
+use std::{collections::HashSet, path::Path};
 
 use anyhow::Result;
 use rand::{Rng, RngExt};
@@ -18,6 +18,7 @@ use trane::{
             KnowledgeBaseConfig, KnowledgeBaseExercise, KnowledgeBaseLesson,
         },
     },
+    scheduler::ExerciseScheduler,
     test_utils::TraneSimulation,
 };
 use ustr::Ustr;
@@ -85,6 +86,11 @@ fn knowledge_base_builder(
                             name: None,
                             description: None,
                             exercise_type: None,
+                            inline_content: None,
+                            multiple_choice: None,
+                            cloze: None,
+                            reverse: None,
+                            renamed_from: vec![],
                         },
                         asset_builders,
                     }
@@ -105,6 +111,9 @@ fn knowledge_base_builder(
                     has_instructions: false,
                     has_material: false,
                     default_exercise_type: Some(ExerciseType::Declarative),
+                    // Let even lessons generate reversed exercises.
+                    reverse: lesson_index % 2 == 0,
+                    renamed_from: vec![],
                 },
                 exercises,
                 asset_builders: vec![],
@@ -148,6 +157,7 @@ fn all_exercises_visited() -> Result<()> {
         "course1",
         CourseManifest {
             id: Ustr::from("course1"),
+            renamed_from: vec![],
             name: "Course 1".into(),
             description: None,
             dependencies: vec![],
@@ -168,6 +178,7 @@ fn all_exercises_visited() -> Result<()> {
         "course2",
         CourseManifest {
             id: Ustr::from("course2"),
+            renamed_from: vec![],
             name: "Course 2".into(),
             description: None,
             dependencies: vec!["course1".into()],
@@ -204,3 +215,57 @@ fn all_exercises_visited() -> Result<()> {
     assert_eq!(visited_exercises.len(), exercise_ids.len());
     Ok(())
 }
+
+// Verifies that exercises generated from the same cloze note are never scheduled in the same
+// batch.
+#[test]
+fn cloze_siblings_not_in_same_batch() -> Result<()> {
+    let course_builder = knowledge_base_builder(
+        "course1",
+        CourseManifest {
+            id: Ustr::from("course1"),
+            renamed_from: vec![],
+            name: "Course 1".into(),
+            description: None,
+            dependencies: vec![],
+            encompassed: vec![],
+            superseded: vec![],
+            authors: None,
+            metadata: None,
+            course_material: None,
+            course_instructions: None,
+            generator_config: Some(CourseGenerator::KnowledgeBase(KnowledgeBaseConfig {
+                inlined: false,
+            })),
+        },
+        1,
+        2,
+    );
+
+    // Add notes with multiple deletions to the lesson before opening the library.
+    let temp_dir = TempDir::new()?;
+    course_builder.build(temp_dir.path())?;
+    let lesson_dir = temp_dir.path().join("course1").join("lesson_0.lesson");
+    std::fs::write(
+        lesson_dir.join("capital.cloze.md"),
+        "The capital of {{c1::France}} is {{c2::Paris}}.",
+    )?;
+    std::fs::write(
+        lesson_dir.join("river.cloze.md"),
+        "The {{c1::Seine}} flows through {{c2::Paris}} into the {{c3::English Channel}}.",
+    )?;
+    let trane = Trane::new_local(temp_dir.path(), temp_dir.path())?;
+
+    // No batch contains two exercises from the same note.
+    for _ in 0..20 {
+        let batch = trane.get_exercise_batch(None)?;
+        assert!(!batch.is_empty());
+        let groups: Vec<Ustr> = batch.iter().filter_map(|e| e.sibling_group).collect();
+        let unique_groups: HashSet<Ustr> = groups.iter().copied().collect();
+        assert_eq!(groups.len(), unique_groups.len());
+        for exercise in batch {
+            trane.score_exercise(exercise.id, MasteryScore::Four, 0)?;
+        }
+    }
+    Ok(())
+}
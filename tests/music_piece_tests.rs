@@ -133,6 +133,7 @@ fn music_piece_builder(
         directory_name: format!("improv_course_{}", course_index),
         course_manifest: CourseManifest {
             id: course_id,
+            renamed_from: vec![],
             name: format!("Course {}", course_id),
             dependencies: vec![],
             superseded: vec![],
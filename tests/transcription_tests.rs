@@ -40,6 +40,8 @@ static USER_PREFS: LazyLock<UserPreferences> = LazyLock::new(|| UserPreferences
         ..Default::default()
     }),
     ignored_paths: vec![],
+    time_zone: None,
+    retention: None,
     scheduler: None,
 });
 
@@ -95,6 +97,7 @@ fn transcription_builder(
         directory_name: format!("transcription_course_{}", course_index),
         course_manifest: CourseManifest {
             id: course_id,
+            renamed_from: vec![],
             name: format!("Course {}", course_id),
             dependencies: vec![],
             encompassed: vec![],